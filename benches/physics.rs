@@ -0,0 +1,99 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use double_pendulum::batch;
+use double_pendulum::color::Palette;
+use double_pendulum::integrator::IntegratorKind;
+use double_pendulum::pendulum::{DoublePendulum, InitialConditions, Physics};
+use double_pendulum::pendulums::Pendulums;
+use double_pendulum::simulation::DT;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::hint::black_box;
+
+/// The length given to the pendulums, the same as the simulations of the library
+const LENGTH: f32 = 400.0;
+/// The numbers of pendulums the batched updates are measured with
+const COUNTS: [usize; 4] = [8, 100, 1_000, 10_000];
+
+/// Returns `count` random pendulums, the same ones on every run
+fn pendulums(count: usize) -> Vec<DoublePendulum> {
+    let mut rng = StdRng::seed_from_u64(0);
+    (0..count)
+        .map(|_| {
+            DoublePendulum::new(
+                &mut rng,
+                LENGTH,
+                Physics::default(),
+                &Default::default(),
+                Palette::default(),
+            )
+        })
+        .collect()
+}
+
+fn compute_acceleration(c: &mut Criterion) {
+    let p = pendulums(1).remove(0);
+    let [theta1, theta2] = p.angles();
+    let [omega1, omega2] = p.angular_velocities();
+    let state = [theta1, theta2, omega1, omega2];
+    c.bench_function("compute_acceleration", |b| {
+        b.iter(|| p.compute_acceleration(black_box(state), black_box(0.0)))
+    });
+}
+
+fn forward(c: &mut Criterion) {
+    let mut group = c.benchmark_group("forward");
+    for integrator in IntegratorKind::ALL {
+        let initial = InitialConditions {
+            theta1: Some(2.0),
+            theta2: Some(2.5),
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut p = DoublePendulum::new(
+            &mut rng,
+            LENGTH,
+            Physics::default(),
+            &initial,
+            Palette::default(),
+        );
+        p.set_integrator(integrator);
+        let mut time = 0.0;
+        group.bench_function(integrator.name(), |b| {
+            b.iter(|| {
+                p.forward(black_box(DT), 0.0, time);
+                time += DT;
+            })
+        });
+    }
+    group.finish();
+}
+
+fn update(c: &mut Criterion) {
+    let mut group = c.benchmark_group("update");
+    for count in COUNTS {
+        group.throughput(Throughput::Elements(count as u64));
+
+        let mut scalar = pendulums(count);
+        group.bench_with_input(BenchmarkId::new("scalar", count), &count, |b, _| {
+            b.iter(|| {
+                for p in &mut scalar {
+                    p.update(black_box(DT), 0.0, 0.0);
+                }
+            })
+        });
+
+        let mut batched = pendulums(count);
+        group.bench_with_input(BenchmarkId::new("batch", count), &count, |b, _| {
+            b.iter(|| batch::update(&mut batched, black_box(DT), 0.0, 0.0))
+        });
+
+        let mut columns = Pendulums::from(pendulums(count));
+        group.bench_with_input(BenchmarkId::new("columns", count), &count, |b, _| {
+            b.iter(|| columns.update(black_box(DT), 0.0, 0.0))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, compute_acceleration, forward, update);
+criterion_main!(benches);
@@ -0,0 +1,142 @@
+use crate::recorder::{read_frame, timestamp, RECORDINGS_DIR};
+use double_pendulum::sink::{ExportSettings, ExportSink, Metadata};
+use ggez::{Context, GameError, GameResult};
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::time::Instant;
+
+/// The speed of the color quantization, from 1 for the best colors to 30 for the fastest encoding
+const QUANTIZATION_SPEED: i32 = 10;
+
+/// A frame of the window, in RGBA
+struct Frame {
+    pixels: Vec<u8>,
+    /// How long this frame stays on screen, in hundredths of a second
+    delay: u16,
+}
+
+/// Records every few frames of the window for a set duration,
+/// and encodes them into an animated GIF once stopped
+///
+/// GIF only has 256 colors per frame, so the gradients of the trails are banded,
+/// but it can be shared almost anywhere. The APNG recorder keeps the full colors.
+pub struct GifRecorder {
+    /// Where the file is written, a name is picked from the current time if it is missing
+    path: Option<PathBuf>,
+    frames: Vec<Frame>,
+    /// Stored in a comment of the file
+    metadata: Metadata,
+    /// The size of the first frame, the frames of a different size are skipped
+    size: Option<(u32, u32)>,
+    /// Only one frame every `frame_step` frames is recorded
+    frame_step: u32,
+    /// The recording stops by itself after this many seconds
+    duration: f32,
+    /// The number of frames drawn since the start of the recording
+    counter: u32,
+    start: Instant,
+    last_capture: Instant,
+}
+
+impl GifRecorder {
+    pub fn new(path: Option<&str>, metadata: &Metadata, settings: &ExportSettings) -> Self {
+        Self {
+            path: path.map(PathBuf::from),
+            frames: Vec::new(),
+            metadata: metadata.clone(),
+            size: None,
+            frame_step: settings.gif_frame_step.max(1),
+            duration: settings.gif_duration,
+            counter: 0,
+            start: Instant::now(),
+            last_capture: Instant::now(),
+        }
+    }
+}
+
+impl ExportSink for GifRecorder {
+    fn name(&self) -> &str {
+        "gif"
+    }
+
+    /// Returns true once the recording reached its duration
+    fn is_done(&self) -> bool {
+        self.start.elapsed().as_secs_f32() >= self.duration
+    }
+
+    /// Read back the last frame of the window, if it is one of the recorded ones
+    fn write_frame(&mut self, ctx: &Context) -> GameResult {
+        self.counter += 1;
+        if !self.counter.is_multiple_of(self.frame_step) {
+            return Ok(());
+        }
+
+        let frame = ctx.gfx.frame();
+        let size = (frame.width(), frame.height());
+        if *self.size.get_or_insert(size) != size {
+            return Ok(());
+        }
+        let pixels = read_frame(ctx)?;
+
+        // The delay of the previous frame is only known now,
+        // and the new frame gets the same delay until the next one is captured
+        let elapsed = self.last_capture.elapsed().as_millis() / 10;
+        self.last_capture = Instant::now();
+        if let Some(previous) = self.frames.last_mut() {
+            previous.delay = elapsed.clamp(2, u16::MAX as u128) as u16;
+        }
+
+        let delay = self.frames.last().map_or(100 / 30, |f| f.delay);
+        self.frames.push(Frame { pixels, delay });
+        Ok(())
+    }
+
+    /// Quantize every recorded frame to 256 colors and encode them into an animated GIF
+    fn finish(mut self: Box<Self>) -> GameResult {
+        let Some((width, height)) = self.size else {
+            return Ok(());
+        };
+        if self.frames.is_empty() {
+            return Ok(());
+        }
+        let (Ok(width), Ok(height)) = (u16::try_from(width), u16::try_from(height)) else {
+            return Err(encoding_error("the window is too large for a GIF"));
+        };
+
+        let path = match &self.path {
+            Some(path) => path.clone(),
+            None => {
+                fs::create_dir_all(RECORDINGS_DIR)?;
+                PathBuf::from(RECORDINGS_DIR).join(format!("{}.gif", timestamp()))
+            }
+        };
+        let writer = BufWriter::new(File::create(&path)?);
+
+        let mut encoder = gif::Encoder::new(writer, width, height, &[]).map_err(encoding_error)?;
+        encoder
+            .set_repeat(gif::Repeat::Infinite)
+            .map_err(encoding_error)?;
+        let comment: Vec<String> = (self.metadata.entries().iter())
+            .map(|(key, value)| format!("{key}: {value}"))
+            .collect();
+        encoder.write_raw_extension(
+            gif::Extension::Comment.into(),
+            &[comment.join("\n").as_bytes()],
+        )?;
+
+        for frame in &mut self.frames {
+            let mut image =
+                gif::Frame::from_rgba_speed(width, height, &mut frame.pixels, QUANTIZATION_SPEED);
+            image.delay = frame.delay;
+            encoder.write_frame(&image).map_err(encoding_error)?;
+        }
+
+        println!("Saved the GIF to {}", path.display());
+        Ok(())
+    }
+}
+
+fn encoding_error(e: impl ToString) -> GameError {
+    GameError::CustomError(format!("couldn't encode the GIF: {}", e.to_string()))
+}
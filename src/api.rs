@@ -0,0 +1,120 @@
+use crate::ramp::Ramp;
+use ggez::{GameError, GameResult};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+/// Something asked for through the HTTP API
+pub enum Command {
+    /// Spawn a random pendulum
+    Spawn,
+    /// Replace every pendulum with the ones created at startup
+    Reset,
+    /// Pause or resume the simulation
+    Pause,
+    /// Change the gravity of every pendulum
+    SetGravity(f32),
+    /// Start changing a parameter over time
+    Ramp(Ramp),
+    /// Returns the state of the simulation
+    GetState,
+}
+
+/// A request to the HTTP API waiting for its answer
+pub struct ApiRequest {
+    pub command: Command,
+    request: Request,
+}
+
+impl ApiRequest {
+    /// Answer the request with a JSON body
+    pub fn reply(self, json: String) {
+        respond(self.request, 200, json);
+    }
+
+    /// Answer the request with an error
+    pub fn fail(self, status: u16, message: &str) {
+        respond(self.request, status, error_json(message));
+    }
+}
+
+/// A small HTTP server controlling the running simulation from other tools, like a classroom demo
+///
+/// The requests are read without waiting between two frames, and every answer is JSON:
+/// - `POST /spawn`, `POST /reset` and `POST /pause` do the same as the actions with the same names
+/// - `POST /set-gravity?value=G` sets the gravity of every pendulum to `G`
+/// - `POST /ramp?value=PARAMETER:FROM:TO:SECONDS` starts a ramp written like `--ramp`
+/// - `GET /get-state` returns the state of the simulation in the format of the saves
+pub struct ApiServer {
+    server: Server,
+}
+
+impl ApiServer {
+    /// Listen on `address`, written as `HOST:PORT`
+    pub fn new(address: &str) -> GameResult<Self> {
+        let server = Server::http(address)
+            .map_err(|e| GameError::CustomError(format!("couldn't listen on {address}: {e}")))?;
+        println!("Serving the HTTP API on http://{}", server.server_addr());
+        Ok(Self { server })
+    }
+
+    /// Returns the next request waiting for an answer, the invalid ones are answered right away
+    pub fn next_request(&self) -> Option<ApiRequest> {
+        loop {
+            let request = self.server.try_recv().ok()??;
+            match parse(request.method(), request.url()) {
+                Ok(command) => return Some(ApiRequest { command, request }),
+                Err((status, message)) => respond(request, status, error_json(&message)),
+            }
+        }
+    }
+}
+
+/// Returns the command asked for by a request with `method` to `url`, or the status and the message of the error
+fn parse(method: &Method, url: &str) -> Result<Command, (u16, String)> {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let expected = match path {
+        "/get-state" => Method::Get,
+        "/spawn" | "/reset" | "/pause" | "/set-gravity" | "/ramp" => Method::Post,
+        _ => return Err((404, format!("unknown endpoint {path}"))),
+    };
+    if *method != expected {
+        return Err((405, format!("{path} expects {expected}")));
+    }
+    let value = query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("value="));
+
+    Ok(match path {
+        "/spawn" => Command::Spawn,
+        "/reset" => Command::Reset,
+        "/pause" => Command::Pause,
+        "/set-gravity" => {
+            let value = value
+                .and_then(|value| value.parse::<f32>().ok())
+                .filter(|value| value.is_finite())
+                .ok_or_else(|| (400, "expected a number as ?value=".to_owned()))?;
+            Command::SetGravity(value)
+        }
+        "/ramp" => {
+            let ramp = value.and_then(|value| value.parse().ok()).ok_or_else(|| {
+                let expected = "expected PARAMETER:FROM:TO:SECONDS as ?value=";
+                (400, expected.to_owned())
+            })?;
+            Command::Ramp(ramp)
+        }
+        _ => Command::GetState,
+    })
+}
+
+/// Returns the JSON body of an error
+fn error_json(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
+
+fn respond(request: Request, status: u16, json: String) {
+    let mut response = Response::from_string(json).with_status_code(status);
+    if let Ok(header) = Header::from_bytes("Content-Type", "application/json") {
+        response.add_header(header);
+    }
+    // The client may be gone already, there is no one to tell then
+    let _ = request.respond(response);
+}
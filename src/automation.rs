@@ -0,0 +1,65 @@
+use crate::api::{ApiRequest, ApiServer};
+use crate::config::Config;
+use crate::script::Script;
+use double_pendulum::pendulum::InitialConditions;
+use double_pendulum::pendulums::Pendulums;
+use ggez::GameResult;
+use std::path::Path;
+
+/// The automation system, which lets the user script and other tools through the HTTP API drive the simulation
+///
+/// The requests of the API are only read here, they are answered like the inputs of the user
+pub struct AutomationSystem {
+    /// The HTTP server controlling the simulation, if it is served
+    api: Option<ApiServer>,
+    /// The user script run after every physics update, if any
+    script: Option<Script>,
+}
+
+impl AutomationSystem {
+    /// Load the script and start serving the API, if they are configured
+    pub fn new(config: &Config) -> GameResult<Self> {
+        Ok(Self {
+            api: match &config.api {
+                Some(address) => Some(ApiServer::new(address)?),
+                None => None,
+            },
+            script: match &config.script {
+                Some(path) => Some(Script::load(Path::new(path))?),
+                None => None,
+            },
+        })
+    }
+
+    /// Returns the file the script was loaded from, if there is a script
+    pub fn script_path(&self) -> Option<&Path> {
+        self.script.as_ref().map(Script::path)
+    }
+
+    /// Run the script on `pendulums` after a physics update lasting `dt` that ended at `time`,
+    /// and returns the initial conditions of the pendulums it spawned
+    pub fn update(
+        &mut self,
+        pendulums: &mut Pendulums,
+        time: f32,
+        dt: f32,
+    ) -> Vec<InitialConditions> {
+        let Some(script) = &mut self.script else {
+            return Vec::new();
+        };
+        match script.run(pendulums, time, dt) {
+            Ok(spawned) => spawned,
+            // A broken script would fail again after every update
+            Err(e) => {
+                eprintln!("Stopping the script: {e}");
+                self.script = None;
+                Vec::new()
+            }
+        }
+    }
+
+    /// Returns the next request of the API waiting for an answer, if any
+    pub fn next_request(&self) -> Option<ApiRequest> {
+        self.api.as_ref().and_then(ApiServer::next_request)
+    }
+}
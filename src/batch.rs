@@ -0,0 +1,268 @@
+use crate::integrator::{IntegratorKind, VELOCITY_SCALE};
+use crate::pendulum::DoublePendulum;
+use std::f32::consts::PI;
+use wide::f32x8;
+
+/// The number of pendulums advanced together, one in each lane of the SIMD registers
+pub const LANES: usize = 8;
+
+/// The angles, angular velocities and constants of up to `LANES` pendulums, stored quantity by quantity
+///
+/// The unused lanes hold copies of the first pendulum, so they never produce NaN
+struct Lanes {
+    theta: [f32x8; 2],
+    omega: [f32x8; 2],
+    mass: [f32x8; 2],
+    length: [f32x8; 2],
+    gravity: f32x8,
+    damping: f32x8,
+    drive_amplitude: f32x8,
+    drive_frequency: f32x8,
+}
+
+impl Lanes {
+    /// Gather the pendulums into the lanes, `pendulums` must not be empty
+    fn load(pendulums: &[&mut DoublePendulum]) -> Self {
+        // Each pendulum is read once, into a column of every quantity
+        let mut columns = [[0.0; LANES]; 12];
+        for lane in 0..LANES {
+            let p = pendulums.get(lane).unwrap_or(&pendulums[0]);
+            let [theta1, theta2] = p.angles();
+            let [omega1, omega2] = p.angular_velocities();
+            let [m1, m2] = p.masses();
+            let [l1, l2] = p.lengths();
+            let physics = p.physics();
+            let values = [
+                theta1,
+                theta2,
+                omega1,
+                omega2,
+                m1,
+                m2,
+                l1,
+                l2,
+                physics.gravity,
+                physics.damping,
+                physics.drive_amplitude,
+                physics.drive_frequency,
+            ];
+            for (column, value) in columns.iter_mut().zip(values) {
+                column[lane] = value;
+            }
+        }
+        Self::from_columns(columns)
+    }
+
+    /// Load the lanes from the values of each quantity, in the order of `step_columns`
+    fn from_columns(columns: [[f32; LANES]; 12]) -> Self {
+        let [theta1, theta2, omega1, omega2, m1, m2, l1, l2, gravity, damping, drive_amplitude, drive_frequency] =
+            columns.map(f32x8::new);
+        Self {
+            theta: [theta1, theta2],
+            omega: [omega1, omega2],
+            mass: [m1, m2],
+            length: [l1, l2],
+            gravity,
+            damping,
+            drive_amplitude,
+            drive_frequency,
+        }
+    }
+
+    /// Scatter the angles and the angular velocities back into the pendulums
+    fn store(&self, pendulums: &mut [&mut DoublePendulum]) {
+        let theta = self.theta.map(f32x8::to_array);
+        let omega = self.omega.map(f32x8::to_array);
+        for (i, p) in pendulums.iter_mut().enumerate() {
+            p.set_angles([theta[0][i], theta[1][i]]);
+            p.set_angular_velocities([omega[0][i], omega[1][i]]);
+        }
+    }
+
+    /// Returns how much the angular velocities change per second, the same equations as `DoublePendulum`
+    /// with the friction and the driving force, for every lane at once
+    fn acceleration(&self, wind: f32, time: f32) -> [f32x8; 2] {
+        let wind = f32x8::splat(wind);
+        let two = f32x8::splat(2.0);
+        let tilt = wind.atan2(self.gravity);
+        let g = (self.gravity * self.gravity + wind * wind).sqrt();
+
+        let [m1, m2] = self.mass;
+        let [l1, l2] = self.length;
+        let [omega1, omega2] = self.omega;
+        let t1 = self.theta[0] - tilt;
+        let t2 = self.theta[1] - tilt;
+        let s1sq = omega1 * omega1;
+        let s2sq = omega2 * omega2;
+        let (sin_t1, cos_t1) = t1.sin_cos();
+        let (sin_d, cos_d) = (t1 - t2).sin_cos();
+
+        let num1 = -(g * (two * m1 + m2) * sin_t1)
+            - m2 * g * (t1 - two * t2).sin()
+            - two * sin_d * m2 * (s2sq * l2 + s1sq * l1 * cos_d);
+        let num2 = two
+            * sin_d
+            * (s1sq * l1 * (m1 + m2) + g * (m1 + m2) * cos_t1 + two * s2sq * l2 * m2 * cos_d);
+        let denom = two * m1 + m2 - m2 * (two * (t1 - t2)).cos();
+
+        let drive = (f32x8::splat(2.0 * PI * time) * self.drive_frequency).sin();
+        let a1 = num1 / (l1 * denom) - self.damping * omega1 + self.drive_amplitude * drive;
+        let a2 = num2 / (l2 * denom) - self.damping * omega2;
+        let scale = f32x8::splat(VELOCITY_SCALE);
+        [scale * a1, scale * a2]
+    }
+
+    /// Advance every lane by `dt` seconds with the symplectic Euler method
+    fn step(&mut self, dt: f32, wind: f32, time: f32) {
+        let acceleration = self.acceleration(wind, time);
+        let dt = f32x8::splat(dt);
+        for (rod, acceleration) in acceleration.into_iter().enumerate() {
+            self.omega[rod] += dt * acceleration;
+            self.theta[rod] += dt * f32x8::splat(VELOCITY_SCALE) * self.omega[rod];
+        }
+    }
+}
+
+/// Advance up to `LANES` pendulums by one step of `dt` seconds with the symplectic Euler method,
+/// and returns their new angles and angular velocities as `[θ1, θ2, ω1, ω2]`
+///
+/// `columns` holds each quantity for every lane: `θ1`, `θ2`, `ω1`, `ω2`, `m1`, `m2`, `l1`, `l2`,
+/// then the gravity, the damping, the drive amplitude and the drive frequency.
+/// The unused lanes should hold copies of a used one, so they never produce NaN
+pub(crate) fn step_columns(
+    columns: [[f32; LANES]; 12],
+    dt: f32,
+    wind: f32,
+    time: f32,
+) -> [[f32; LANES]; 4] {
+    let mut lanes = Lanes::from_columns(columns);
+    lanes.step(dt, wind, time);
+    let [theta1, theta2] = lanes.theta.map(f32x8::to_array);
+    let [omega1, omega2] = lanes.omega.map(f32x8::to_array);
+    [theta1, theta2, omega1, omega2]
+}
+
+/// Advance `pendulums` by one step of `dt` seconds like `DoublePendulum::update`, including the trails
+///
+/// The pendulums using the symplectic Euler method, the default one, are advanced `LANES` at a time with SIMD,
+/// which is much faster with many pendulums. The results differ from `DoublePendulum::update`
+/// in the last bits since the trigonometric functions aren't the same, but they are as deterministic
+pub fn update<'a>(
+    pendulums: impl IntoIterator<Item = &'a mut DoublePendulum>,
+    dt: f32,
+    wind: f32,
+    time: f32,
+) {
+    advance(pendulums, dt, wind, time, true);
+}
+
+/// Advance `pendulums` by one step of `dt` seconds like `DoublePendulum::forward`, without the trails
+pub fn forward<'a>(
+    pendulums: impl IntoIterator<Item = &'a mut DoublePendulum>,
+    dt: f32,
+    wind: f32,
+    time: f32,
+) {
+    advance(pendulums, dt, wind, time, false);
+}
+
+fn advance<'a>(
+    pendulums: impl IntoIterator<Item = &'a mut DoublePendulum>,
+    dt: f32,
+    wind: f32,
+    time: f32,
+    trails: bool,
+) {
+    let mut batch: Vec<&mut DoublePendulum> = Vec::with_capacity(LANES);
+    let flush = |batch: &mut Vec<&mut DoublePendulum>| {
+        if batch.is_empty() {
+            return;
+        }
+        let mut lanes = Lanes::load(batch);
+        lanes.step(dt, wind, time);
+        lanes.store(batch);
+        for p in batch.drain(..) {
+            if trails {
+                p.update_trail();
+            }
+        }
+    };
+
+    for p in pendulums {
+        if p.integrator() != IntegratorKind::Symplectic {
+            if trails {
+                p.update(dt, wind, time);
+            } else {
+                p.forward(dt, wind, time);
+            }
+            continue;
+        }
+        batch.push(p);
+        if batch.len() == LANES {
+            flush(&mut batch);
+        }
+    }
+    flush(&mut batch);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Palette;
+    use crate::pendulum::{InitialConditions, Physics};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    /// Returns `count` random pendulums, some of them driven and damped, and one using RK4
+    fn pendulums(count: usize) -> Vec<DoublePendulum> {
+        let mut rng = StdRng::seed_from_u64(7);
+        (0..count)
+            .map(|i| {
+                let physics = Physics {
+                    damping: if i % 3 == 0 { 0.01 } else { 0.0 },
+                    drive_amplitude: if i % 4 == 0 { 0.02 } else { 0.0 },
+                    drive_frequency: 0.8,
+                    ..Physics::default()
+                };
+                let initial = InitialConditions::default();
+                let mut p =
+                    DoublePendulum::new(&mut rng, 200.0, physics, &initial, Palette::default());
+                if i == 5 {
+                    p.set_integrator(IntegratorKind::Rk4);
+                }
+                p
+            })
+            .collect()
+    }
+
+    #[test]
+    fn batches_match_the_scalar_update() {
+        // More pendulums than lanes, so the last batch is partly filled
+        let mut batched = pendulums(LANES * 2 + 3);
+        let mut scalar = batched.clone();
+        let dt = 1.0 / 240.0;
+        for step in 0..120 {
+            let time = step as f32 * dt;
+            update(batched.iter_mut(), dt, 0.1, time);
+            for p in &mut scalar {
+                p.update(dt, 0.1, time);
+            }
+        }
+
+        for (index, (b, s)) in batched.iter().zip(&scalar).enumerate() {
+            // The trigonometric functions differ in the last bits, which the chaos slowly amplifies
+            let divergence = b.divergence(s);
+            assert!(divergence < 1e-3, "pendulum {index}: {divergence}");
+            assert_eq!(b.trail().len(), s.trail().len());
+        }
+    }
+
+    #[test]
+    fn forward_leaves_the_trails() {
+        let mut batched = pendulums(LANES + 1);
+        let before: Vec<_> = batched.iter().map(|p| p.trail().len()).collect();
+        forward(batched.iter_mut(), 1.0 / 240.0, 0.0, 0.0);
+        let after: Vec<_> = batched.iter().map(|p| p.trail().len()).collect();
+        assert_eq!(before, after);
+    }
+}
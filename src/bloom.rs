@@ -0,0 +1,30 @@
+use ggez::graphics::{Canvas, Image, Shader, ShaderBuilder, ShaderParams, ShaderParamsBuilder};
+use ggez::{Context, GameResult};
+
+/// A post-process pass making the bright bobs and trails glow, for a more striking screensaver
+///
+/// The simulation is drawn in an image first, then the image is drawn on the frame with a shader
+/// adding a blurred copy of its bright parts, so the dark background stays as it is.
+pub struct Bloom {
+    shader: Shader,
+    /// How bright the glow is compared to the average of the bright colors around a pixel
+    params: ShaderParams<f32>,
+}
+
+impl Bloom {
+    pub fn new(ctx: &mut Context, strength: f32) -> GameResult<Self> {
+        let shader = ShaderBuilder::new_wgsl()
+            .fragment_code(include_str!("bloom.wgsl"))
+            .build(&ctx.gfx)?;
+        let params = ShaderParamsBuilder::new(&strength).build(ctx);
+        Ok(Self { shader, params })
+    }
+
+    /// Draw `image` at the top left corner of the canvas with its bright parts glowing
+    pub fn draw(&self, canvas: &mut Canvas, image: &Image) {
+        canvas.set_shader(&self.shader);
+        canvas.set_shader_params(&self.params);
+        canvas.draw(image, [0.0, 0.0]);
+        canvas.set_default_shader();
+    }
+}
@@ -0,0 +1,45 @@
+// The image with a glow around its bright parts, drawn by the default vertex shader of ggez
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+@group(1) @binding(0) var t: texture_2d<f32>;
+@group(1) @binding(1) var s: sampler;
+// How bright the glow is compared to the average of the bright colors around a pixel
+@group(3) @binding(0) var<uniform> strength: f32;
+
+// The part of a color brighter than the threshold, so the background doesn't glow
+fn bright(uv: vec2<f32>) -> vec3<f32> {
+    let threshold = 0.45;
+    let color = textureSampleLevel(t, s, uv, 0.0).rgb;
+    let luma = dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+    return color * max(luma - threshold, 0.0) / max(luma, 0.0001);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // The bright colors are sampled on rings around the pixel, the outer ones weighing less
+    let rings = 4;
+    let taps = 12;
+    // The distance in pixels between two rings
+    let spacing = 3.0;
+    let texel = 1.0 / vec2<f32>(textureDimensions(t));
+    var glow = vec3<f32>(0.0);
+    var total = 0.0;
+    for (var ring = 1; ring <= rings; ring = ring + 1) {
+        let radius = f32(ring) * spacing;
+        let weight = exp(-f32(ring * ring) / f32(rings));
+        for (var tap = 0; tap < taps; tap = tap + 1) {
+            // The rings are turned by half a tap from each other to cover the gaps
+            let angle = 6.2831853 * (f32(tap) + 0.5 * f32(ring)) / f32(taps);
+            let offset = vec2<f32>(cos(angle), sin(angle)) * radius * texel;
+            glow = glow + bright(in.uv + offset) * weight;
+            total = total + weight;
+        }
+    }
+    let color = textureSampleLevel(t, s, in.uv, 0.0);
+    return in.color * vec4<f32>(color.rgb + glow / total * strength, color.a);
+}
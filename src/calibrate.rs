@@ -0,0 +1,115 @@
+use crate::preset::PRESETS;
+use double_pendulum::color::Palette;
+use double_pendulum::integrator::IntegratorKind;
+use double_pendulum::pendulum::{DoublePendulum, InitialConditions, Physics};
+use ggez::{GameError, GameResult};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Instant;
+
+/// The simulated duration of the calibration trajectory, in seconds
+///
+/// The double pendulum is chaotic, so a longer trajectory would only measure the chaos
+const DURATION: f32 = 2.0;
+/// The physics rate of the reference trajectory, which is assumed to be exact
+const REFERENCE_RATE: u32 = 15360;
+/// The integrator of the reference trajectory, the most accurate one
+const REFERENCE_INTEGRATOR: IntegratorKind = IntegratorKind::Rk4;
+/// The physics rates that are compared, in updates per second
+const RATES: [u32; 7] = [60, 120, 240, 480, 960, 1920, 3840];
+/// The maximum error in radians used when none is given
+pub const DEFAULT_TOLERANCE: f32 = 0.05;
+
+/// Run a short trajectory with every integrator at several physics rates and print the error and the cost
+/// of each, then save the cheapest pair whose error is below `tolerance` into the configuration file at `path`
+pub fn run(tolerance: f32, path: &Path) -> GameResult {
+    let initial = (PRESETS[0].pendulums)()[0];
+    let (reference, _) = simulate(&initial, REFERENCE_INTEGRATOR, REFERENCE_RATE);
+
+    println!(
+        "Comparing a {DURATION} s trajectory with a {REFERENCE_RATE} Hz {} reference",
+        REFERENCE_INTEGRATOR.name(),
+    );
+    println!(
+        "{:>18} {:>10} {:>14} {:>14}",
+        "integrator", "rate (Hz)", "error (rad)", "cost (us)"
+    );
+
+    let mut recommendation: Option<(IntegratorKind, u32, f32)> = None;
+    for integrator in IntegratorKind::ALL {
+        for rate in RATES {
+            let (pendulum, cost) = simulate(&initial, integrator, rate);
+            let error = pendulum.divergence(&reference);
+            println!(
+                "{:>18} {rate:>10} {error:>14.3e} {:>14.1}",
+                integrator.name(),
+                cost * 1e6,
+            );
+
+            if error <= tolerance && recommendation.is_none_or(|(_, _, best)| cost < best) {
+                recommendation = Some((integrator, rate, cost));
+            }
+        }
+    }
+
+    let Some((integrator, rate, _)) = recommendation else {
+        println!("No integrator reaches an error below {tolerance:e} rad");
+        return Ok(());
+    };
+    println!(
+        "Recommended for an error below {tolerance:e} rad: {} at {rate} Hz \
+         (--integrator {integrator} --physics-hz {rate})",
+        integrator.name(),
+    );
+    save(path, integrator, rate)?;
+    println!("Saved them into {}", path.display());
+    Ok(())
+}
+
+/// Simulate one pendulum for `DURATION` seconds with `integrator` at `rate` updates per second
+///
+/// Returns the final state of the pendulum and the time it took in seconds
+fn simulate(
+    initial: &InitialConditions,
+    integrator: IntegratorKind,
+    rate: u32,
+) -> (DoublePendulum, f32) {
+    // Every initial condition is given, so the random number generator isn't actually used
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut pendulum = DoublePendulum::new(
+        &mut rng,
+        0.0,
+        Physics::default(),
+        initial,
+        Palette::default(),
+    );
+    pendulum.set_integrator(integrator);
+
+    let steps = (DURATION * rate as f32).round() as u32;
+    let start = Instant::now();
+    for step in 0..steps {
+        pendulum.update(1.0 / rate as f32, 0.0, step as f32 / rate as f32);
+    }
+    (pendulum, start.elapsed().as_secs_f32())
+}
+
+/// Write `integrator` and `rate` into the configuration file at `path`, which is created if it doesn't exist
+///
+/// The other settings and the comments of the file are kept as they are
+fn save(path: &Path, integrator: IntegratorKind, rate: u32) -> GameResult {
+    let error = |e: &dyn std::fmt::Display| {
+        GameError::CustomError(format!("couldn't save into {}: {e}", path.display()))
+    };
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(error(&e)),
+    };
+    let mut document: toml_edit::DocumentMut = content.parse().map_err(|e| error(&e))?;
+    document["physics-hz"] = toml_edit::value(i64::from(rate));
+    document["integrator"] = toml_edit::value(integrator.to_string());
+    fs::write(path, document.to_string()).map_err(|e| error(&e))
+}
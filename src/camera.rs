@@ -0,0 +1,158 @@
+use ggez::graphics::{Canvas, Rect};
+use ggez::input::mouse::MouseButton;
+use ggez::{Context, GameResult};
+
+/// The smallest and the largest zoom
+const ZOOM_RANGE: [f32; 2] = [0.1, 20.0];
+/// How much the zoom changes with each notch of the mouse wheel
+const ZOOM_STEP: f32 = 1.1;
+
+/// A view over the scenes, which can be zoomed and moved around
+///
+/// Points in the window are called screen points, and the points of the scenes are called world points.
+/// Without zoom nor pan, both are the same.
+#[derive(Clone, Copy)]
+pub struct Camera {
+    /// How many screen pixels a world pixel covers
+    zoom: f32,
+    /// The world point at the top left corner of the window
+    offset: [f32; 2],
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            zoom: 1.0,
+            offset: [0.0, 0.0],
+        }
+    }
+}
+
+impl Camera {
+    /// Returns the zoom, 1 being the default one
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    /// Returns the world point under a screen point
+    pub fn world_point(&self, point: [f32; 2]) -> [f32; 2] {
+        [
+            self.offset[0] + point[0] / self.zoom,
+            self.offset[1] + point[1] / self.zoom,
+        ]
+    }
+
+    /// Multiply the zoom by `factor`, the world point under the screen point `fixed` doesn't move
+    pub fn zoom_at(&mut self, fixed: [f32; 2], factor: f32) {
+        let world = self.world_point(fixed);
+        self.zoom = (self.zoom * factor).clamp(ZOOM_RANGE[0], ZOOM_RANGE[1]);
+        self.offset = [
+            world[0] - fixed[0] / self.zoom,
+            world[1] - fixed[1] / self.zoom,
+        ];
+    }
+
+    /// Move the view so the world follows the mouse, which moved by `delta` screen pixels
+    pub fn pan(&mut self, delta: [f32; 2]) {
+        self.offset[0] -= delta[0] / self.zoom;
+        self.offset[1] -= delta[1] / self.zoom;
+    }
+
+    /// Returns the area of the world shown in a window whose screen coordinates are `screen`,
+    /// to be given to `Canvas::set_screen_coordinates`
+    pub fn view(&self, screen: Rect) -> Rect {
+        Rect::new(
+            self.offset[0],
+            self.offset[1],
+            screen.w / self.zoom,
+            screen.h / self.zoom,
+        )
+    }
+
+    /// Returns the screen area covered by the world area `world`, clipped to `screen`,
+    /// or None if it is outside of the window
+    pub fn screen_rect(&self, world: Rect, screen: Rect) -> Option<Rect> {
+        let left = ((world.x - self.offset[0]) * self.zoom).max(screen.x);
+        let top = ((world.y - self.offset[1]) * self.zoom).max(screen.y);
+        let right = ((world.right() - self.offset[0]) * self.zoom).min(screen.right());
+        let bottom = ((world.bottom() - self.offset[1]) * self.zoom).min(screen.bottom());
+        // The scissor rectangle must be at least one pixel wide
+        (right - left >= 1.0 && bottom - top >= 1.0)
+            .then(|| Rect::new(left, top, right - left, bottom - top))
+    }
+}
+
+/// The camera system, which moves the camera with the mouse and draws the scenes through it
+#[derive(Default)]
+pub struct CameraSystem {
+    camera: Camera,
+    /// Whether the view is being moved with the middle mouse button
+    panning: bool,
+}
+
+impl CameraSystem {
+    /// Returns the camera the scenes are seen through
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    /// Go back to the view without zoom nor pan
+    pub fn reset(&mut self) {
+        self.camera = Camera::default();
+    }
+
+    /// Start moving the view if `button` is the middle mouse button
+    pub fn mouse_down(&mut self, button: MouseButton) {
+        if button == MouseButton::Middle {
+            self.panning = true;
+        }
+    }
+
+    /// Stop moving the view if `button` is the middle mouse button
+    pub fn mouse_up(&mut self, button: MouseButton) {
+        if button == MouseButton::Middle {
+            self.panning = false;
+        }
+    }
+
+    /// Zoom in around the screen point `fixed` by one notch of the mouse wheel, or out if `notches` is negative
+    pub fn scroll(&mut self, fixed: [f32; 2], notches: f32) {
+        self.camera.zoom_at(fixed, ZOOM_STEP.powf(notches.signum()));
+    }
+
+    /// Move the view so the world follows the mouse while it is being moved
+    pub fn update(&mut self, ctx: &Context) {
+        if self.panning {
+            let delta = ctx.mouse.delta();
+            self.camera.pan([delta.x, delta.y]);
+        }
+    }
+
+    /// Run `draw` with `canvas` showing the world through the camera, then show the window coordinates again
+    ///
+    /// `draw` is given the window area in screen coordinates
+    pub fn draw(
+        &self,
+        canvas: &mut Canvas,
+        window: [f32; 2],
+        draw: impl FnOnce(&mut Canvas, Rect) -> GameResult,
+    ) -> GameResult {
+        let screen = canvas
+            .screen_coordinates()
+            .unwrap_or_else(|| Rect::new(0.0, 0.0, window[0], window[1]));
+        canvas.set_screen_coordinates(self.camera.view(screen));
+        let result = draw(canvas, screen);
+        // The overlays are drawn in the window coordinates
+        canvas.set_screen_coordinates(screen);
+        result
+    }
+
+    /// Returns the lines of the HUD describing the view, each starting with a newline
+    pub fn hud(&self) -> String {
+        if self.camera.zoom() == 1.0 {
+            String::new()
+        } else {
+            format!("\nZoom: {:.1}x", self.camera.zoom())
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use crate::phase::add_run;
+use double_pendulum::pendulum::{wrap_angle, Rods};
+use ggez::graphics::{self, Canvas, Color, DrawMode, DrawParam, Mesh, MeshBuilder, Rect};
+use ggez::Context;
+use ggez::GameResult;
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+
+/// The number of samples of simulated time per second
+const SAMPLE_RATE: f32 = 60.0;
+/// The simulated time covered by the chart, in seconds, the older samples are dropped
+const DURATION: f32 = 10.0;
+/// The color of the axes of the chart
+const AXIS_COLOR: Color = Color::new(1.0, 1.0, 1.0, 0.3);
+/// The colors of the curves of the first and the second rod
+const ROD_COLORS: [Color; 2] = [
+    Color::new(1.0, 0.5, 0.3, 1.0),
+    Color::new(0.4, 0.8, 1.0, 1.0),
+];
+
+/// A scrolling chart of the angle of each rod over the last `DURATION` seconds of simulated time
+///
+/// A periodic motion draws the same waves again and again, a chaotic one never repeats itself.
+/// Only one pendulum is recorded, and the chart is cleared when another one is recorded instead
+pub struct AngleChart {
+    /// The index of the recorded pendulum
+    index: usize,
+    /// The simulated time since the last sample, in seconds
+    since_sample: f32,
+    /// The wrapped angles of both rods at each sample, from the oldest to the newest
+    samples: VecDeque<[f32; 2]>,
+    /// The number of samples taken since the chart was cleared, to place the ticks of the time axis
+    taken: u64,
+}
+
+impl AngleChart {
+    pub fn new() -> Self {
+        Self {
+            index: 0,
+            since_sample: 0.0,
+            samples: VecDeque::with_capacity(capacity()),
+            taken: 0,
+        }
+    }
+
+    /// Sample `pendulum`, which is at `index` in the list of pendulums, if it is time to,
+    /// `dt` seconds after the last physics update
+    pub fn record(&mut self, index: usize, pendulum: &Rods, dt: f32) {
+        if index != self.index {
+            self.index = index;
+            self.since_sample = 0.0;
+            self.samples.clear();
+            self.taken = 0;
+        }
+        self.since_sample += dt;
+        if self.since_sample < 1.0 / SAMPLE_RATE {
+            return;
+        }
+        self.since_sample -= 1.0 / SAMPLE_RATE;
+        if self.samples.len() >= capacity() {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(pendulum.angles().map(wrap_angle));
+        self.taken += 1;
+    }
+
+    /// Draw the chart in `area`, the newest sample on the right edge and the time flowing to the left
+    ///
+    /// The angles go from -PI at the bottom to PI at the top
+    pub fn draw(&self, ctx: &mut Context, canvas: &mut Canvas, area: Rect) -> GameResult {
+        let panel =
+            Mesh::new_rectangle(ctx, DrawMode::fill(), area, Color::new(0.0, 0.0, 0.0, 0.6))?;
+        canvas.draw(&panel, [0.0, 0.0]);
+
+        let plot = Rect::new(area.x + 10.0, area.y + 25.0, area.w - 20.0, area.h - 35.0);
+        let middle = plot.y + plot.h / 2.0;
+        let mut builder = MeshBuilder::new();
+        builder.line(&[[plot.x, middle], [plot.right(), middle]], 1.0, AXIS_COLOR)?;
+        let newest = self.samples.len() as f32 - 1.0;
+        let x = |i: usize| plot.right() - (newest - i as f32) / SAMPLE_RATE / DURATION * plot.w;
+        // A tick every second of simulated time, scrolling with the curves
+        let first = self.taken - self.samples.len() as u64;
+        for i in 0..self.samples.len() {
+            if (first + i as u64).is_multiple_of(SAMPLE_RATE as u64) {
+                builder.line(
+                    &[[x(i), middle - 3.0], [x(i), middle + 3.0]],
+                    1.0,
+                    AXIS_COLOR,
+                )?;
+            }
+        }
+
+        for (rod, color) in ROD_COLORS.into_iter().enumerate() {
+            let label = format!("θ{}(t)", rod + 1);
+            canvas.draw(
+                &graphics::Text::new(label),
+                DrawParam::new()
+                    .dest([plot.x + rod as f32 * 60.0, area.y + 6.0])
+                    .color(color),
+            );
+
+            // The line is cut where the angle wraps around, so it doesn't cross the whole chart
+            let mut run: Vec<[f32; 2]> = Vec::new();
+            let mut previous: Option<f32> = None;
+            for (i, angles) in self.samples.iter().enumerate() {
+                let theta = angles[rod];
+                if previous.is_some_and(|p| (theta - p).abs() > PI) {
+                    add_run(&mut builder, &run, color)?;
+                    run.clear();
+                }
+                run.push([x(i), middle - theta / PI * plot.h / 2.0]);
+                previous = Some(theta);
+            }
+            add_run(&mut builder, &run, color)?;
+        }
+        canvas.draw(&Mesh::from_data(ctx, builder.build()), [0.0, 0.0]);
+        Ok(())
+    }
+}
+
+/// Returns the number of samples covering `DURATION` seconds
+fn capacity() -> usize {
+    (DURATION * SAMPLE_RATE) as usize
+}
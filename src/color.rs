@@ -0,0 +1,195 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// The viridis colormap sampled every eighth of its range, from dark purple to yellow
+const VIRIDIS: [Color; 9] = [
+    Color::new(0.267, 0.005, 0.329, 1.0),
+    Color::new(0.283, 0.141, 0.458, 1.0),
+    Color::new(0.254, 0.265, 0.530, 1.0),
+    Color::new(0.207, 0.372, 0.553, 1.0),
+    Color::new(0.164, 0.471, 0.558, 1.0),
+    Color::new(0.128, 0.567, 0.551, 1.0),
+    Color::new(0.135, 0.659, 0.518, 1.0),
+    Color::new(0.267, 0.749, 0.441, 1.0),
+    Color::new(0.993, 0.906, 0.144, 1.0),
+];
+
+/// A color with its red, green, blue and alpha components between 0 and 1
+///
+/// It is written in the files the same way as the colors of the window
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Color {
+    pub const WHITE: Color = Color::new(1.0, 1.0, 1.0, 1.0);
+
+    pub const fn new(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// Returns the red, green and blue components between 0 and 255, without the alpha
+    pub fn to_rgb(self) -> (u8, u8, u8) {
+        let byte = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        (byte(self.r), byte(self.g), byte(self.b))
+    }
+
+    /// Returns the hue in degrees, the saturation and the value of the color, the opposite of `hsv`
+    ///
+    /// The hue of a gray is 0, since any hue gives the same color
+    pub fn to_hsv(self) -> (f32, f32, f32) {
+        let max = self.r.max(self.g).max(self.b);
+        let min = self.r.min(self.g).min(self.b);
+        let chroma = max - min;
+        let hue = if chroma == 0.0 {
+            0.0
+        } else if max == self.r {
+            60.0 * ((self.g - self.b) / chroma)
+        } else if max == self.g {
+            60.0 * ((self.b - self.r) / chroma + 2.0)
+        } else {
+            60.0 * ((self.r - self.g) / chroma + 4.0)
+        };
+        let saturation = if max == 0.0 { 0.0 } else { chroma / max };
+        (hue.rem_euclid(360.0), saturation, max)
+    }
+
+    /// Returns the color with the same hue, value and alpha, and its saturation scaled by `factor`
+    pub fn desaturated(self, factor: f32) -> Color {
+        let (hue, saturation, value) = self.to_hsv();
+        Color {
+            a: self.a,
+            ..hsv(hue, saturation * factor.clamp(0.0, 1.0), value)
+        }
+    }
+}
+
+/// The colors given to new pendulums, with a background that goes with them
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum Palette {
+    /// Any color, each component picked uniformly
+    #[default]
+    Random,
+    /// Light and soft colors of any hue
+    Pastel,
+    /// Saturated and bright colors of any hue, on a black background
+    Neon,
+    /// The colors of the viridis colormap, from blue to yellow
+    Viridis,
+    /// Shades of gray on a black background
+    Monochrome,
+}
+
+impl Palette {
+    /// Every palette, in the order they are cycled through
+    pub const ALL: [Palette; 5] = [
+        Palette::Random,
+        Palette::Pastel,
+        Palette::Neon,
+        Palette::Viridis,
+        Palette::Monochrome,
+    ];
+
+    /// Returns the name of the palette, as written in the configuration
+    pub fn name(self) -> &'static str {
+        match self {
+            Palette::Random => "random",
+            Palette::Pastel => "pastel",
+            Palette::Neon => "neon",
+            Palette::Viridis => "viridis",
+            Palette::Monochrome => "monochrome",
+        }
+    }
+
+    /// Returns the palette after this one, going back to the first one after the last one
+    pub fn next(self) -> Palette {
+        let index = Self::ALL.iter().position(|&p| p == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// Returns the color of the background going with the palette
+    pub fn background(self) -> Color {
+        match self {
+            Palette::Random => Color::new(0.1, 0.2, 0.3, 1.0),
+            Palette::Pastel => Color::new(0.22, 0.22, 0.28, 1.0),
+            Palette::Neon => Color::new(0.02, 0.0, 0.05, 1.0),
+            Palette::Viridis => Color::new(0.08, 0.08, 0.1, 1.0),
+            Palette::Monochrome => Color::new(0.0, 0.0, 0.0, 1.0),
+        }
+    }
+
+    /// Pick a random color of the palette
+    pub fn color(self, rng: &mut impl Rng) -> Color {
+        match self {
+            Palette::Random => {
+                let r = rng.gen_range(0.0..=1.0);
+                let g = rng.gen_range(0.0..=1.0);
+                let b = rng.gen_range(0.0..=1.0);
+                Color::new(r, g, b, 1.0)
+            }
+            Palette::Pastel => hsv(rng.gen_range(0.0..360.0), 0.35, 1.0),
+            Palette::Neon => hsv(rng.gen_range(0.0..360.0), 1.0, 1.0),
+            // The darkest purples would vanish into the background
+            Palette::Viridis => viridis(rng.gen_range(0.15..=1.0)),
+            Palette::Monochrome => {
+                let value = rng.gen_range(0.4..=1.0);
+                Color::new(value, value, value, 1.0)
+            }
+        }
+    }
+}
+
+impl FromStr for Palette {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter().find(|p| p.name() == s).ok_or(())
+    }
+}
+
+impl fmt::Display for Palette {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl From<Color> for [f32; 4] {
+    fn from(color: Color) -> Self {
+        [color.r, color.g, color.b, color.a]
+    }
+}
+
+/// Returns the color of the viridis colormap at `t`, between 0 and 1
+pub fn viridis(t: f32) -> Color {
+    let position = t.clamp(0.0, 1.0) * (VIRIDIS.len() - 1) as f32;
+    let index = (position as usize).min(VIRIDIS.len() - 2);
+    let (a, b) = (VIRIDIS[index], VIRIDIS[index + 1]);
+    let t = position - index as f32;
+    let mix = |a: f32, b: f32| a + (b - a) * t;
+    Color::new(mix(a.r, b.r), mix(a.g, b.g), mix(a.b, b.b), 1.0)
+}
+
+/// Convert a color from HSV to RGB
+///
+/// `hue` is in degrees, `saturation` and `value` are between 0 and 1
+pub fn hsv(hue: f32, saturation: f32, value: f32) -> Color {
+    let hue = hue.rem_euclid(360.0) / 60.0;
+    let chroma = value * saturation;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = value - chroma;
+    Color::new(r + m, g + m, b + m, 1.0)
+}
@@ -0,0 +1,1313 @@
+use crate::calibrate;
+use crate::headless;
+use crate::keybindings::{self, Keybindings};
+use crate::preset::{self, PRESETS};
+use crate::ramp::Ramp;
+use crate::randomize::{Constraints, Invariant};
+use clap::{Args, Parser, Subcommand};
+use double_pendulum::color::Palette;
+use double_pendulum::integrator::IntegratorKind;
+use double_pendulum::pendulum::{InitialConditions, Physics, DEFAULT_TRAIL_LENGTH};
+use double_pendulum::sink::ExportSettings;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// The file read at startup if no other path is given with `--config`
+const DEFAULT_CONFIG_PATH: &str = "double_pendulum.toml";
+/// The width and the height of the screen at startup
+const INITIAL_SCREEN_SIZE: (f32, f32) = (400.0, 400.0);
+/// How saturated the trails taking the color of their pendulum are when it isn't configured
+pub const DEFAULT_TRAIL_SATURATION: f32 = 0.5;
+/// The number of physics updates per second when it isn't configured
+pub const DEFAULT_PHYSICS_HZ: u32 = 240;
+/// The bounds of the physics rate: below, the pendulums visibly drift,
+/// above, a single pendulum doesn't fit in the frame budget anymore
+const PHYSICS_HZ_RANGE: (u32, u32) = (30, 4000);
+/// The bounds of the frame cap, in frames per second
+const MAX_FPS_RANGE: (u32, u32) = (10, 1000);
+
+/// What the simulation does while the window is minimized
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum HiddenPolicy {
+    /// Stop the simulation until the window is back
+    Pause,
+    /// Keep simulating, the missed updates are all done at once when the window is back
+    Keep,
+    /// Keep simulating, the missed updates are spread over the next frames as a fast-forward
+    CatchUp,
+}
+
+impl FromStr for HiddenPolicy {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pause" => Ok(Self::Pause),
+            "keep" => Ok(Self::Keep),
+            "catch-up" => Ok(Self::CatchUp),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for HiddenPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Pause => "pause",
+            Self::Keep => "keep",
+            Self::CatchUp => "catch-up",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A pendulum created at startup and after a reset
+#[derive(Clone, Copy)]
+pub struct ScenePendulum {
+    /// The scene the pendulum belongs to, or None to create it in every scene
+    pub scene: Option<usize>,
+    /// The initial state of the pendulum
+    pub initial: InitialConditions,
+    /// The physics of the pendulum that differ from the ones of the simulation
+    pub physics: PhysicsOverrides,
+}
+
+/// The physical constants of a single pendulum replacing the ones of the simulation
+///
+/// They are applied when the pendulum spawns, changing the physics of the whole simulation later replaces them
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct PhysicsOverrides {
+    pub gravity: Option<f32>,
+    pub damping: Option<f32>,
+    pub drive_amplitude: Option<f32>,
+    pub drive_frequency: Option<f32>,
+}
+
+impl PhysicsOverrides {
+    /// Returns the values of `physics` that differ from the ones of the simulation, `global`
+    pub fn between(global: &Physics, physics: &Physics) -> Self {
+        let differs = |global: f32, value: f32| (value != global).then_some(value);
+        Self {
+            gravity: differs(global.gravity, physics.gravity),
+            damping: differs(global.damping, physics.damping),
+            drive_amplitude: differs(global.drive_amplitude, physics.drive_amplitude),
+            drive_frequency: differs(global.drive_frequency, physics.drive_frequency),
+        }
+    }
+
+    /// Returns `physics` with the overridden values replaced
+    pub fn apply(&self, physics: Physics) -> Physics {
+        Physics {
+            gravity: self.gravity.unwrap_or(physics.gravity),
+            damping: self.damping.unwrap_or(physics.damping),
+            drive_amplitude: self.drive_amplitude.unwrap_or(physics.drive_amplitude),
+            drive_frequency: self.drive_frequency.unwrap_or(physics.drive_frequency),
+        }
+    }
+}
+
+/// A little struct that helps to parse the configuration file and the command line arguments
+///
+/// The command line arguments override the values of the configuration file.
+pub struct Config {
+    /// The width and the height of the screen at startup
+    pub window_size: (f32, f32),
+    /// Open the window maximized instead of at `window_size`
+    pub maximized: bool,
+    /// The monitor the window opens on, counting from 0, or None for the one chosen by the system
+    pub monitor: Option<usize>,
+    /// The color of the background
+    pub background: [f32; 4],
+    /// The color of the trails, or None if each trail takes the color of its pendulum
+    pub trail_color: Option<[f32; 4]>,
+    /// How saturated the trails taking the color of their pendulum are, from gray at 0 to as saturated at 1
+    pub trail_saturation: f32,
+    /// The color of the trails of the first circles
+    pub joint_trail_color: [f32; 4],
+    /// The number of positions kept in the trails
+    pub trail_length: usize,
+    /// Draw everything in white on black, with patterns instead of colors
+    pub monochrome: bool,
+    /// The colors given to new pendulums
+    pub palette: Palette,
+    /// Clear the window before each frame, otherwise the frames pile up into a drawing
+    pub clear: bool,
+    /// Draw on a pure black background without the HUD, to project the pendulums on a wall
+    pub projector: bool,
+    /// Fade the previous frames instead of clearing them, keeping this fraction of each one, if set
+    pub motion_blur: Option<f32>,
+    /// Make the bright bobs and trails glow with this strength, if set
+    pub bloom: Option<f32>,
+    pub size: usize,
+    /// The position of the origin of the pendulums in each scene, as a fraction of its width and height
+    pub anchor: [f32; 2],
+    /// The number of independent scenes tiled across the window, each with `size` pendulums
+    pub scenes: usize,
+    pub show_trail: bool,
+    /// Spawn a new random pendulum every `spawn_interval` seconds
+    pub spawn_interval: Option<f32>,
+    /// The maximum number of pendulums on the screen, the oldest ones are removed past it
+    pub max_pendulums: Option<usize>,
+    /// The number of nearly identical copies spawned by the clone action
+    pub clones: usize,
+    /// How fast the simulation runs compared to real time, between 0.1 and 10
+    pub speed: f32,
+    /// The number of physics updates per second, the more the more accurate and the slower
+    pub physics_hz: u32,
+    /// The numerical method advancing the new pendulums
+    pub integrator: IntegratorKind,
+    /// Wait for the screen to refresh before showing each frame
+    pub vsync: bool,
+    /// The most frames drawn per second, or None to draw as many as the screen or the machine allows
+    pub max_fps: Option<u32>,
+    /// What the simulation does while the window is hidden
+    pub on_hidden: HiddenPolicy,
+    /// Merge several physics updates into longer ones when they don't fit in a frame
+    pub adaptive_substeps: bool,
+    /// Fast-forward the simulation to this time in seconds at startup
+    pub seek: Option<f32>,
+    /// The constant horizontal wind, positive values blow to the right
+    pub wind: f32,
+    /// The amplitude of the random gusts of wind
+    pub gust: f32,
+    /// The physical constants given to every new pendulum
+    pub physics: Physics,
+    /// The initial state of the pendulums created at startup
+    pub initial: InitialConditions,
+    /// The exact pendulums created at startup, replacing `size` and `initial` if there are any
+    pub pendulums: Vec<ScenePendulum>,
+    /// The seed of the random number generator, a random one is picked if it is missing
+    pub seed: Option<u64>,
+    /// The parameters changing smoothly over time since startup
+    pub ramps: Vec<Ramp>,
+    /// The index of the preset to load at startup instead of the other initial conditions
+    pub preset: Option<usize>,
+    /// The export sinks started at startup, written as `NAME` or `NAME:PATH`
+    pub exports: Vec<String>,
+    /// Whether the simulation runs without any window, and writes its results into `output`
+    pub headless: bool,
+    /// Compare the integrators and the physics rates with this tolerance in radians instead of running the simulation
+    pub calibrate: Option<f32>,
+    /// The number of physics updates done in headless mode
+    pub steps: u64,
+    /// The directory the results of the headless mode are written in
+    pub output: String,
+    /// The resolution of the flip fractal written in headless mode, if any
+    pub fractal: Option<u32>,
+    /// The saved state the simulation resumes from, if any
+    pub load: Option<String>,
+    /// The file the inputs are recorded into, to replay the run later
+    pub record_replay: Option<String>,
+    /// The replay played instead of the inputs of the user, if any
+    pub replay: Option<String>,
+    /// The Rhai script run after every physics update, if any
+    pub script: Option<String>,
+    /// The address the HTTP API listens on, if it is served
+    pub api: Option<String>,
+    /// Play the motion of the pendulums as sound from startup
+    pub sound: bool,
+    /// The volume of the sound, between 0 and 1
+    pub volume: f32,
+    /// Advance the pendulums with a compute shader on the graphics card, experimental
+    pub gpu: bool,
+    /// Start the stress test from startup
+    pub stress_test: bool,
+    /// The options of the export sinks
+    pub export_settings: ExportSettings,
+    /// Which action each key triggers
+    pub keybindings: Keybindings,
+    /// The bounds of the parameters picked when a pendulum is randomized
+    pub constraints: Constraints,
+    /// The configuration file read at startup, watched to apply its changes while running
+    pub path: PathBuf,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            window_size: INITIAL_SCREEN_SIZE,
+            maximized: false,
+            monitor: None,
+            background: Palette::default().background().into(),
+            trail_color: None,
+            trail_saturation: DEFAULT_TRAIL_SATURATION,
+            joint_trail_color: [0.6, 0.3, 0.7, 1.0],
+            trail_length: DEFAULT_TRAIL_LENGTH,
+            monochrome: false,
+            palette: Palette::default(),
+            clear: true,
+            projector: false,
+            motion_blur: None,
+            bloom: None,
+            size: 1,
+            anchor: [0.5, 0.5],
+            scenes: 1,
+            show_trail: false,
+            spawn_interval: None,
+            max_pendulums: None,
+            clones: 8,
+            speed: 1.0,
+            physics_hz: DEFAULT_PHYSICS_HZ,
+            integrator: IntegratorKind::default(),
+            vsync: true,
+            max_fps: None,
+            on_hidden: HiddenPolicy::Keep,
+            adaptive_substeps: false,
+            seek: None,
+            wind: 0.0,
+            gust: 0.0,
+            physics: Physics::default(),
+            initial: InitialConditions::default(),
+            pendulums: Vec::new(),
+            seed: None,
+            ramps: Vec::new(),
+            preset: None,
+            exports: Vec::new(),
+            headless: false,
+            calibrate: None,
+            steps: headless::DEFAULT_STEPS,
+            output: headless::DEFAULT_OUTPUT_DIR.to_owned(),
+            fractal: None,
+            load: None,
+            record_replay: None,
+            replay: None,
+            script: None,
+            api: None,
+            sound: false,
+            volume: 0.5,
+            gpu: false,
+            stress_test: false,
+            export_settings: ExportSettings::default(),
+            keybindings: Keybindings::default(),
+            constraints: Constraints::default(),
+            path: PathBuf::from(DEFAULT_CONFIG_PATH),
+        }
+    }
+}
+
+impl Config {
+    /// Read the configuration file, then the command line arguments over it
+    ///
+    /// The invalid arguments and `--help` print their message and exit the program
+    pub fn new(args: env::Args) -> Self {
+        let cli = Cli::parse_from(args);
+        let mut config = Self::default();
+
+        // The configuration file is loaded first, so the command line arguments can override it
+        let explicit = cli.config.is_some();
+        if let Some(path) = &cli.config {
+            config.path = PathBuf::from(path);
+        }
+        let path = config.path.clone();
+        match config.apply_file(&path) {
+            Ok(()) => (),
+            // Not having the default configuration file is perfectly fine
+            Err(e) if e.kind() == io::ErrorKind::NotFound && !explicit => (),
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                eprintln!(
+                    "Ignoring the invalid configuration file {}: {e}",
+                    path.display()
+                );
+            }
+            Err(e) => eprintln!(
+                "Couldn't read the configuration file {}: {e}",
+                path.display()
+            ),
+        }
+
+        cli.apply(&mut config);
+        config.clamp();
+        config
+    }
+
+    /// Overwrite the settings with every value of the configuration file at `path`
+    ///
+    /// An invalid file is an `InvalidData` error, and it doesn't change any setting
+    pub fn apply_file(&mut self, path: &Path) -> io::Result<()> {
+        let content = fs::read_to_string(path)?;
+        let file: FileConfig =
+            toml::from_str(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        file.apply(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        self.clamp();
+        Ok(())
+    }
+
+    /// Bring the settings that would break the simulation back into their bounds
+    fn clamp(&mut self) {
+        // A zero or negative interval would spawn pendulums in an infinite loop
+        self.spawn_interval = self.spawn_interval.filter(|&s| s > 0.0);
+        self.speed = self.speed.clamp(0.1, 10.0);
+        let (min, max) = PHYSICS_HZ_RANGE;
+        self.physics_hz = self.physics_hz.clamp(min, max);
+        let (min, max) = MAX_FPS_RANGE;
+        self.max_fps = self.max_fps.map(|fps| fps.clamp(min, max));
+        self.volume = self.volume.clamp(0.0, 1.0);
+        self.trail_saturation = self.trail_saturation.clamp(0.0, 1.0);
+        // Keeping the whole previous frame would never fade it
+        self.motion_blur = self.motion_blur.map(|kept| kept.clamp(0.0, 0.99));
+        self.bloom = self.bloom.map(|strength| strength.max(0.0));
+        self.scenes = self.scenes.max(1);
+    }
+
+    /// Give the colors of `palette` to new pendulums, on the background going with it
+    fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+        self.background = palette.background().into();
+    }
+
+    /// Returns the configuration file reproducing these settings
+    ///
+    /// Only the settings that change the simulation are written, the keys and the exports are left out
+    pub fn to_toml(&self) -> Result<String, toml::ser::Error> {
+        let physics = &self.physics;
+        let file = FileConfig {
+            width: Some(self.window_size.0),
+            height: Some(self.window_size.1),
+            background: Some(self.background),
+            trail_color: self.trail_color,
+            trail_saturation: Some(self.trail_saturation),
+            joint_trail_color: Some(self.joint_trail_color),
+            trail_length: Some(self.trail_length),
+            monochrome: Some(self.monochrome),
+            palette: Some(self.palette.to_string()),
+            clear: Some(self.clear),
+            projector: Some(self.projector),
+            motion_blur: self.motion_blur,
+            bloom: self.bloom,
+            count: Some(self.size),
+            anchor: Some(self.anchor),
+            scenes: Some(self.scenes),
+            show_trail: Some(self.show_trail),
+            spawn_every: self.spawn_interval,
+            max_pendulums: self.max_pendulums,
+            speed: Some(self.speed),
+            physics_hz: Some(self.physics_hz),
+            integrator: Some(self.integrator.to_string()),
+            on_hidden: Some(self.on_hidden.to_string()),
+            adaptive_substeps: Some(self.adaptive_substeps),
+            wind: Some(self.wind),
+            gust: Some(self.gust),
+            gravity: Some(physics.gravity),
+            damping: Some(physics.damping),
+            drive_amplitude: Some(physics.drive_amplitude),
+            drive_frequency: Some(physics.drive_frequency),
+            seed: self.seed,
+            script: self.script.clone(),
+            ramps: (!self.ramps.is_empty())
+                .then(|| self.ramps.iter().map(Ramp::to_string).collect()),
+            pendulums: (!self.pendulums.is_empty())
+                .then(|| self.pendulums.iter().map(FilePendulum::from).collect()),
+            ..FileConfig::default()
+        };
+        toml::to_string(&file)
+    }
+
+    /// Returns the command line arguments reproducing these settings, without the program name
+    ///
+    /// Every pendulum gets the same initial conditions, so only the first of `pendulums` is used.
+    /// The settings that only exist in the configuration file, like the colors of the joint trails,
+    /// are left out.
+    pub fn to_args(&self) -> Vec<String> {
+        let initial = self.pendulums.first().map_or(self.initial, |p| p.initial);
+        let mut args = Vec::new();
+        let mut push = |flag: &str, value: Option<String>| {
+            if let Some(value) = value {
+                args.push(format!("--{flag}"));
+                args.push(value);
+            }
+        };
+        let physics = &self.physics;
+        push("count", Some(self.size.to_string()));
+        push("width", Some(self.window_size.0.to_string()));
+        push("height", Some(self.window_size.1.to_string()));
+        push("seed", self.seed.map(|seed| seed.to_string()));
+        push("script", self.script.clone());
+        push(
+            "anchor",
+            Some(format!("{},{}", self.anchor[0], self.anchor[1])),
+        );
+        push("scenes", Some(self.scenes.to_string()));
+        push("spawn-every", self.spawn_interval.map(|s| s.to_string()));
+        push("max-pendulums", self.max_pendulums.map(|m| m.to_string()));
+        push("speed", Some(self.speed.to_string()));
+        push("physics-hz", Some(self.physics_hz.to_string()));
+        push("integrator", Some(self.integrator.to_string()));
+        push("on-hidden", Some(self.on_hidden.to_string()));
+        push("wind", Some(self.wind.to_string()));
+        push("gust", Some(self.gust.to_string()));
+        push("gravity", Some(physics.gravity.to_string()));
+        push("damping", Some(physics.damping.to_string()));
+        push("drive-amplitude", Some(physics.drive_amplitude.to_string()));
+        push("drive-frequency", Some(physics.drive_frequency.to_string()));
+        for ramp in &self.ramps {
+            push("ramp", Some(ramp.to_string()));
+        }
+        push("m1", initial.m1.map(|v| v.to_string()));
+        push("m2", initial.m2.map(|v| v.to_string()));
+        push("l1", initial.l1.map(|v| v.to_string()));
+        push("l2", initial.l2.map(|v| v.to_string()));
+        push("theta1", initial.theta1.map(|v| v.to_string()));
+        push("theta2", initial.theta2.map(|v| v.to_string()));
+        push("v1", initial.v1.map(|v| v.to_string()));
+        push("v2", initial.v2.map(|v| v.to_string()));
+        if self.palette != Palette::default() {
+            push("palette", Some(self.palette.to_string()));
+        }
+        let [r, g, b, a] = self.background;
+        push("background", Some(format!("{r},{g},{b},{a}")));
+        if let Some([r, g, b, a]) = self.trail_color {
+            push("trail-color", Some(format!("{r},{g},{b},{a}")));
+        }
+        push("trail-saturation", Some(self.trail_saturation.to_string()));
+        push("trail-length", Some(self.trail_length.to_string()));
+        push("motion-blur", self.motion_blur.map(|kept| kept.to_string()));
+        push("bloom", self.bloom.map(|strength| strength.to_string()));
+        if self.show_trail {
+            args.push("--trail".to_owned());
+        }
+        if self.monochrome {
+            args.push("--monochrome".to_owned());
+        }
+        if !self.clear {
+            args.push("--no-clear".to_owned());
+        }
+        if self.projector {
+            args.push("--projector".to_owned());
+        }
+        if self.adaptive_substeps {
+            args.push("--adaptive-substeps".to_owned());
+        }
+        args
+    }
+}
+
+/// The command line arguments, every one of them is optional and overrides the configuration file
+#[derive(Parser)]
+#[command(
+    name = "double_pendulum",
+    version,
+    about = "An optimized double pendulum simulator"
+)]
+struct Cli {
+    /// The number of pendulums at startup, like `--count`
+    #[arg(value_name = "COUNT", conflicts_with = "count_flag")]
+    count: Option<usize>,
+    /// Whether the trails are drawn at startup, like `--trail`
+    #[arg(value_name = "SHOW_TRAIL", conflicts_with = "trail")]
+    show_trail: Option<bool>,
+    /// The configuration file read before the arguments [default: double_pendulum.toml]
+    #[arg(long, value_name = "PATH")]
+    config: Option<String>,
+    #[command(flatten)]
+    display: DisplayArgs,
+    #[command(flatten)]
+    simulation: SimulationArgs,
+    #[command(flatten)]
+    pendulums: PendulumArgs,
+    #[command(flatten)]
+    exports: ExportArgs,
+    #[command(flatten)]
+    headless: HeadlessArgs,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// The tools run instead of the simulation
+#[derive(Subcommand)]
+enum Command {
+    /// Compare the integrators at several physics rates, and save the cheapest pair below the tolerance
+    /// into the configuration file
+    Calibrate {
+        /// The largest error allowed at the end of the calibration trajectory, in radians
+        #[arg(value_name = "TOLERANCE", value_parser = positive, default_value_t = calibrate::DEFAULT_TOLERANCE)]
+        tolerance: f32,
+    },
+}
+
+#[derive(Args)]
+#[command(next_help_heading = "Display")]
+struct DisplayArgs {
+    /// The width of the window at startup
+    #[arg(long, value_name = "PIXELS", value_parser = positive)]
+    width: Option<f32>,
+    /// The height of the window at startup
+    #[arg(long, value_name = "PIXELS", value_parser = positive)]
+    height: Option<f32>,
+    /// The width and the height of the window at startup, like 1920x1080
+    #[arg(long, value_name = "WIDTHxHEIGHT", value_parser = size_arg, conflicts_with_all = ["width", "height"])]
+    size: Option<(f32, f32)>,
+    /// Open the window maximized
+    #[arg(long)]
+    maximized: bool,
+    /// Open the window on this monitor, counting from 0
+    #[arg(long, value_name = "N")]
+    monitor: Option<usize>,
+    /// Draw the trails at startup
+    #[arg(long)]
+    trail: bool,
+    /// Draw everything in white on black, with patterns instead of colors
+    #[arg(long)]
+    monochrome: bool,
+    /// The colors of the new pendulums, with a background going with them
+    #[arg(long, value_name = "NAME", value_parser = palette_arg)]
+    palette: Option<Palette>,
+    /// The color of the background, with components between 0 and 1
+    #[arg(long, value_name = "R,G,B[,A]", value_parser = color_arg)]
+    background: Option<[f32; 4]>,
+    /// Draw every trail in this color, instead of the color of its pendulum
+    #[arg(long, value_name = "R,G,B[,A]", value_parser = color_arg)]
+    trail_color: Option<[f32; 4]>,
+    /// How saturated the trails are compared to their pendulum, from gray at 0 to the same color at 1
+    #[arg(long, value_name = "S", value_parser = between(0.0, 1.0))]
+    trail_saturation: Option<f32>,
+    /// The number of positions kept in the trails
+    #[arg(long, value_name = "N")]
+    trail_length: Option<usize>,
+    /// Don't clear the window between frames, so the pendulums pile up into a drawing
+    #[arg(long)]
+    no_clear: bool,
+    /// Draw on a pure black background and hide the HUD
+    #[arg(long)]
+    projector: bool,
+    /// Fade the previous frames out, keeping this fraction of each one
+    #[arg(long, value_name = "KEPT", value_parser = between(0.0, 0.99))]
+    motion_blur: Option<f32>,
+    /// Make the bright bobs and trails glow with this strength
+    #[arg(long, value_name = "STRENGTH", value_parser = positive)]
+    bloom: Option<f32>,
+    /// The position of the origin, as a fraction of the width and the height of each scene
+    #[arg(long, value_name = "X,Y", value_parser = pair_arg)]
+    anchor: Option<[f32; 2]>,
+    /// The number of independent scenes tiled across the window
+    #[arg(long, value_name = "N", value_parser = at_least_one::<usize>)]
+    scenes: Option<usize>,
+    /// Show the frames as soon as they are drawn instead of waiting for the screen to refresh
+    #[arg(long)]
+    no_vsync: bool,
+    /// Draw at most this many frames per second
+    #[arg(long, value_name = "FPS", value_parser = whole_between(MAX_FPS_RANGE))]
+    max_fps: Option<u32>,
+}
+
+#[derive(Args)]
+#[command(next_help_heading = "Simulation")]
+struct SimulationArgs {
+    /// The number of pendulums at startup
+    #[arg(long = "count", id = "count_flag", value_name = "N")]
+    count: Option<usize>,
+    /// Spawn a new random pendulum every few seconds
+    #[arg(long, value_name = "SECONDS", value_parser = positive)]
+    spawn_every: Option<f32>,
+    /// Keep at most N pendulums, the oldest ones are removed first
+    #[arg(long, value_name = "N")]
+    max_pendulums: Option<usize>,
+    /// The number of copies spawned by the clone action
+    #[arg(long, value_name = "N")]
+    clones: Option<usize>,
+    /// How fast the simulation runs compared to real time
+    #[arg(long, value_name = "FACTOR", value_parser = between(0.1, 10.0))]
+    speed: Option<f32>,
+    /// The number of physics updates per second, the more the more accurate [default: 240]
+    #[arg(long, value_name = "HZ", value_parser = whole_between(PHYSICS_HZ_RANGE))]
+    physics_hz: Option<u32>,
+    /// The numerical method advancing the pendulums: symplectic, euler, rk4 or verlet [default: symplectic]
+    #[arg(long, value_name = "NAME", value_parser = integrator_arg)]
+    integrator: Option<IntegratorKind>,
+    /// What happens while the window is hidden: pause, keep or catch-up
+    #[arg(long, value_name = "POLICY", value_parser = hidden_policy_arg)]
+    on_hidden: Option<HiddenPolicy>,
+    /// Merge the physics updates into longer ones when they don't fit in a frame
+    #[arg(long)]
+    adaptive_substeps: bool,
+    /// Fast-forward the simulation to this time at startup
+    #[arg(long, value_name = "TIME", value_parser = time_arg)]
+    seek: Option<f32>,
+    /// Blow a constant horizontal wind, relative to the gravity
+    #[arg(long, value_name = "STRENGTH", value_parser = finite, allow_negative_numbers = true)]
+    wind: Option<f32>,
+    /// Add random gusts of wind on top of the constant wind
+    #[arg(long, value_name = "AMPLITUDE", value_parser = non_negative)]
+    gust: Option<f32>,
+    /// The gravity pulling the masses down
+    #[arg(long, value_name = "G", value_parser = finite, allow_negative_numbers = true)]
+    gravity: Option<f32>,
+    /// The friction slowing down the rods
+    #[arg(long, value_name = "D", value_parser = non_negative)]
+    damping: Option<f32>,
+    /// The amplitude of the periodic force driving the first rod
+    #[arg(long, value_name = "A", value_parser = finite, allow_negative_numbers = true)]
+    drive_amplitude: Option<f32>,
+    /// The frequency of the periodic force driving the first rod
+    #[arg(long, value_name = "HZ", value_parser = non_negative)]
+    drive_frequency: Option<f32>,
+    /// Smoothly change a parameter over time, written as PARAMETER:FROM:TO:SECONDS, it can be repeated
+    #[arg(long, value_name = "RAMP", value_parser = ramp_arg)]
+    ramp: Vec<Ramp>,
+    /// The seed of the random number generator
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Start with one of the built-in presets
+    #[arg(long, value_name = "NAME", value_parser = preset_arg)]
+    preset: Option<usize>,
+    /// Resume the simulation from a saved state
+    #[arg(long, value_name = "PATH")]
+    load: Option<String>,
+    /// Run a Rhai script after every physics update
+    #[arg(long, value_name = "PATH")]
+    script: Option<String>,
+    /// Serve the HTTP API on this address
+    #[arg(long, value_name = "HOST:PORT")]
+    api: Option<String>,
+    /// Play the motion of the pendulums as sound from startup
+    #[arg(long)]
+    sound: bool,
+    /// The volume of the sound
+    #[arg(long, value_name = "LEVEL", value_parser = between(0.0, 1.0))]
+    volume: Option<f32>,
+    /// Advance the pendulums on the graphics card, experimental
+    #[arg(long)]
+    gpu: bool,
+    /// Start the stress test from startup
+    #[arg(long)]
+    stress_test: bool,
+}
+
+#[derive(Args)]
+#[command(next_help_heading = "Initial conditions")]
+struct PendulumArgs {
+    /// The mass of the first circle, chosen randomly if it is missing
+    #[arg(long, value_parser = positive)]
+    m1: Option<f32>,
+    /// The mass of the second circle
+    #[arg(long, value_parser = positive)]
+    m2: Option<f32>,
+    /// The length in pixels of the first rod
+    #[arg(long, value_parser = positive)]
+    l1: Option<f32>,
+    /// The length in pixels of the second rod
+    #[arg(long, value_parser = positive)]
+    l2: Option<f32>,
+    /// The angle in radians of the first rod
+    #[arg(long, value_parser = finite, allow_negative_numbers = true)]
+    theta1: Option<f32>,
+    /// The angle in radians of the second rod
+    #[arg(long, value_parser = finite, allow_negative_numbers = true)]
+    theta2: Option<f32>,
+    /// The angular velocity of the first rod
+    #[arg(long, value_parser = finite, allow_negative_numbers = true)]
+    v1: Option<f32>,
+    /// The angular velocity of the second rod
+    #[arg(long, value_parser = finite, allow_negative_numbers = true)]
+    v2: Option<f32>,
+    /// The bounds of the masses picked when a pendulum is randomized
+    #[arg(long, value_name = "MIN,MAX", value_parser = pair_arg)]
+    randomize_mass: Option<[f32; 2]>,
+    /// The bounds of the rod lengths picked when a pendulum is randomized
+    #[arg(long, value_name = "MIN,MAX", value_parser = pair_arg)]
+    randomize_length: Option<[f32; 2]>,
+    /// Keep the total length and/or the energy when a pendulum is randomized
+    #[arg(long, value_name = "length,energy", value_delimiter = ',', value_parser = invariant_arg)]
+    randomize_keep: Option<Vec<Invariant>>,
+}
+
+#[derive(Args)]
+#[command(next_help_heading = "Exports and replays")]
+struct ExportArgs {
+    /// Start an export sink, it can be repeated
+    #[arg(long, value_name = "NAME[:PATH]")]
+    export: Vec<String>,
+    /// Record the pendulums into a CSV file, like `--export csv`
+    #[arg(long)]
+    record: bool,
+    /// Log the energy of the pendulums into a CSV file
+    #[arg(long, value_name = "PATH")]
+    log_energy: Vec<String>,
+    /// The number of physics updates between two rows of the energy log
+    #[arg(long, value_name = "UPDATES", value_parser = at_least_one::<u32>)]
+    log_energy_every: Option<u32>,
+    /// Record a video of the window
+    #[arg(long, value_name = "PATH")]
+    record_video: Vec<String>,
+    /// Stream the pendulums over a WebSocket
+    #[arg(long, value_name = "ws://HOST:PORT")]
+    serve: Vec<String>,
+    /// The frame rate of the videos
+    #[arg(long, value_name = "FPS", value_parser = at_least_one::<u32>)]
+    video_fps: Option<u32>,
+    /// Only record one frame every N frames in the GIFs
+    #[arg(long, value_name = "N", value_parser = at_least_one::<u32>)]
+    gif_frame_step: Option<u32>,
+    /// The duration of the GIFs
+    #[arg(long, value_name = "SECONDS", value_parser = positive)]
+    gif_duration: Option<f32>,
+    /// The largest number of OSC messages sent per second
+    #[arg(long, value_name = "RATE", value_parser = positive)]
+    osc_rate: Option<f32>,
+    /// Record every input into a replay file written when the window is closed
+    #[arg(long, value_name = "PATH")]
+    record_replay: Option<String>,
+    /// Play a replay recorded with `--record-replay`
+    #[arg(long, value_name = "PATH")]
+    replay: Option<String>,
+}
+
+#[derive(Args)]
+#[command(next_help_heading = "Headless")]
+struct HeadlessArgs {
+    /// Run the simulation without any window, and write its results into `--output`
+    #[arg(long)]
+    headless: bool,
+    /// The number of physics updates done in headless mode
+    #[arg(long, value_name = "UPDATES")]
+    steps: Option<u64>,
+    /// The directory the results of the headless mode are written in [default: results]
+    #[arg(long, value_name = "DIR")]
+    output: Option<String>,
+    /// Also write a flip fractal of this resolution in headless mode
+    #[arg(long, value_name = "SIZE")]
+    fractal: Option<u32>,
+}
+
+impl Cli {
+    /// Overwrite the settings of `config` with every argument present
+    fn apply(self, config: &mut Config) {
+        let Self {
+            count,
+            show_trail,
+            config: _,
+            display,
+            simulation,
+            pendulums,
+            exports,
+            headless,
+            command,
+        } = self;
+
+        let (width, height) = &mut config.window_size;
+        set(width, display.width);
+        set(height, display.height);
+        set(&mut config.window_size, display.size);
+        config.maximized |= display.maximized;
+        config.monitor = display.monitor.or(config.monitor);
+        // The background of the palette is replaced by the one of the arguments, if there is one
+        if let Some(palette) = display.palette {
+            config.set_palette(palette);
+        }
+        set(&mut config.background, display.background);
+        config.trail_color = display.trail_color.or(config.trail_color);
+        set(&mut config.trail_saturation, display.trail_saturation);
+        set(&mut config.trail_length, display.trail_length);
+        config.monochrome |= display.monochrome;
+        config.clear &= !display.no_clear;
+        config.projector |= display.projector;
+        config.motion_blur = display.motion_blur.or(config.motion_blur);
+        config.bloom = display.bloom.or(config.bloom);
+        set(&mut config.anchor, display.anchor);
+        set(&mut config.scenes, display.scenes);
+        config.vsync &= !display.no_vsync;
+        config.max_fps = display.max_fps.or(config.max_fps);
+        set(&mut config.show_trail, show_trail);
+        config.show_trail |= display.trail;
+
+        set(&mut config.size, count.or(simulation.count));
+        config.spawn_interval = simulation.spawn_every.or(config.spawn_interval);
+        config.max_pendulums = simulation.max_pendulums.or(config.max_pendulums);
+        set(&mut config.clones, simulation.clones);
+        set(&mut config.speed, simulation.speed);
+        set(&mut config.physics_hz, simulation.physics_hz);
+        set(&mut config.integrator, simulation.integrator);
+        set(&mut config.on_hidden, simulation.on_hidden);
+        config.adaptive_substeps |= simulation.adaptive_substeps;
+        config.seek = simulation.seek.or(config.seek);
+        set(&mut config.wind, simulation.wind);
+        set(&mut config.gust, simulation.gust);
+        set(&mut config.physics.gravity, simulation.gravity);
+        set(&mut config.physics.damping, simulation.damping);
+        set(
+            &mut config.physics.drive_amplitude,
+            simulation.drive_amplitude,
+        );
+        set(
+            &mut config.physics.drive_frequency,
+            simulation.drive_frequency,
+        );
+        config.ramps.extend(simulation.ramp);
+        config.seed = simulation.seed.or(config.seed);
+        config.preset = simulation.preset.or(config.preset);
+        config.load = simulation.load.or(config.load.take());
+        config.script = simulation.script.or(config.script.take());
+        config.api = simulation.api.or(config.api.take());
+        config.sound |= simulation.sound;
+        set(&mut config.volume, simulation.volume);
+        config.gpu |= simulation.gpu;
+        config.stress_test |= simulation.stress_test;
+
+        let initial = &mut config.initial;
+        initial.m1 = pendulums.m1.or(initial.m1);
+        initial.m2 = pendulums.m2.or(initial.m2);
+        initial.l1 = pendulums.l1.or(initial.l1);
+        initial.l2 = pendulums.l2.or(initial.l2);
+        initial.theta1 = pendulums.theta1.or(initial.theta1);
+        initial.theta2 = pendulums.theta2.or(initial.theta2);
+        initial.v1 = pendulums.v1.or(initial.v1);
+        initial.v2 = pendulums.v2.or(initial.v2);
+        set(&mut config.constraints.mass, pendulums.randomize_mass);
+        set(&mut config.constraints.length, pendulums.randomize_length);
+        set(&mut config.constraints.keep, pendulums.randomize_keep);
+
+        config.exports.extend(exports.export);
+        if exports.record {
+            config.exports.push("csv".to_owned());
+        }
+        let energy = exports.log_energy.into_iter();
+        config
+            .exports
+            .extend(energy.map(|path| format!("energy:{path}")));
+        let video = exports.record_video.into_iter();
+        config
+            .exports
+            .extend(video.map(|path| format!("video:{path}")));
+        // The address is written as a WebSocket URL, the scheme is implied by the sink
+        let serve = exports.serve.into_iter();
+        config.exports.extend(serve.map(|url| {
+            let address = url.trim_start_matches("ws://");
+            format!("websocket:{address}")
+        }));
+        let settings = &mut config.export_settings;
+        set(&mut settings.energy_interval, exports.log_energy_every);
+        set(&mut settings.video_fps, exports.video_fps);
+        set(&mut settings.gif_frame_step, exports.gif_frame_step);
+        set(&mut settings.gif_duration, exports.gif_duration);
+        set(&mut settings.osc_rate, exports.osc_rate);
+        config.record_replay = exports.record_replay.or(config.record_replay.take());
+        config.replay = exports.replay.or(config.replay.take());
+
+        config.headless |= headless.headless;
+        set(&mut config.steps, headless.steps);
+        set(&mut config.output, headless.output);
+        config.fractal = headless.fractal.or(config.fractal);
+        config.calibrate = command.map(|Command::Calibrate { tolerance }| tolerance);
+    }
+}
+
+/// The settings that can be written in the configuration file, every one of them is optional
+///
+/// ```toml
+/// width = 800
+/// height = 600
+/// count = 3
+/// show-trail = true
+/// background = [0.0, 0.0, 0.0, 1.0]
+/// gravity = 2.0
+///
+/// [keys]
+/// spawn = "n"
+///
+/// [[pendulums]]
+/// m1 = 2.0
+/// theta1 = 1.57
+/// ```
+#[derive(Deserialize, Serialize, Default)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+struct FileConfig {
+    width: Option<f32>,
+    height: Option<f32>,
+    maximized: Option<bool>,
+    monitor: Option<usize>,
+    background: Option<[f32; 4]>,
+    trail_color: Option<[f32; 4]>,
+    trail_saturation: Option<f32>,
+    joint_trail_color: Option<[f32; 4]>,
+    trail_length: Option<usize>,
+    monochrome: Option<bool>,
+    palette: Option<String>,
+    clear: Option<bool>,
+    projector: Option<bool>,
+    motion_blur: Option<f32>,
+    bloom: Option<f32>,
+    count: Option<usize>,
+    anchor: Option<[f32; 2]>,
+    scenes: Option<usize>,
+    show_trail: Option<bool>,
+    spawn_every: Option<f32>,
+    max_pendulums: Option<usize>,
+    clones: Option<usize>,
+    speed: Option<f32>,
+    physics_hz: Option<u32>,
+    integrator: Option<String>,
+    vsync: Option<bool>,
+    max_fps: Option<u32>,
+    on_hidden: Option<String>,
+    adaptive_substeps: Option<bool>,
+    wind: Option<f32>,
+    gust: Option<f32>,
+    gravity: Option<f32>,
+    damping: Option<f32>,
+    drive_amplitude: Option<f32>,
+    drive_frequency: Option<f32>,
+    seed: Option<u64>,
+    script: Option<String>,
+    preset: Option<String>,
+    randomize_mass: Option<[f32; 2]>,
+    randomize_length: Option<[f32; 2]>,
+    randomize_keep: Option<Vec<String>>,
+    /// The ramps started at startup, written like `--ramp`
+    ramps: Option<Vec<String>>,
+    /// The keys replacing the default ones, by action name
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    keys: BTreeMap<String, String>,
+    /// The exact pendulums created at startup, replacing `count`
+    pendulums: Option<Vec<FilePendulum>>,
+}
+
+/// A pendulum written in the configuration file, the missing values are chosen randomly
+#[derive(Deserialize, Serialize, Default)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+struct FilePendulum {
+    /// The scene of the pendulum, it is created in every scene if it is missing
+    scene: Option<usize>,
+    m1: Option<f32>,
+    m2: Option<f32>,
+    l1: Option<f32>,
+    l2: Option<f32>,
+    theta1: Option<f32>,
+    theta2: Option<f32>,
+    v1: Option<f32>,
+    v2: Option<f32>,
+    /// The physics of this pendulum replacing the ones of the simulation
+    gravity: Option<f32>,
+    damping: Option<f32>,
+    drive_amplitude: Option<f32>,
+    drive_frequency: Option<f32>,
+}
+
+impl From<&ScenePendulum> for FilePendulum {
+    fn from(pendulum: &ScenePendulum) -> Self {
+        let initial = &pendulum.initial;
+        Self {
+            scene: pendulum.scene,
+            m1: initial.m1,
+            m2: initial.m2,
+            l1: initial.l1,
+            l2: initial.l2,
+            theta1: initial.theta1,
+            theta2: initial.theta2,
+            v1: initial.v1,
+            v2: initial.v2,
+            gravity: pendulum.physics.gravity,
+            damping: pendulum.physics.damping,
+            drive_amplitude: pendulum.physics.drive_amplitude,
+            drive_frequency: pendulum.physics.drive_frequency,
+        }
+    }
+}
+
+impl From<FilePendulum> for ScenePendulum {
+    fn from(pendulum: FilePendulum) -> Self {
+        Self {
+            scene: pendulum.scene,
+            initial: InitialConditions {
+                m1: pendulum.m1,
+                m2: pendulum.m2,
+                l1: pendulum.l1,
+                l2: pendulum.l2,
+                theta1: pendulum.theta1,
+                theta2: pendulum.theta2,
+                v1: pendulum.v1,
+                v2: pendulum.v2,
+            },
+            physics: PhysicsOverrides {
+                gravity: pendulum.gravity,
+                damping: pendulum.damping,
+                drive_amplitude: pendulum.drive_amplitude,
+                drive_frequency: pendulum.drive_frequency,
+            },
+        }
+    }
+}
+
+impl FileConfig {
+    /// Overwrite the settings of `config` with every value present in the file
+    ///
+    /// A value written as text that can't be parsed is an error naming its key, like the command line arguments
+    fn apply(self, config: &mut Config) -> Result<(), String> {
+        // They are parsed first, so an invalid one doesn't change any setting
+        let palette = (self.palette.as_deref())
+            .map(|s| parse_value("palette", s, palette_arg))
+            .transpose()?;
+        let integrator = (self.integrator.as_deref())
+            .map(|s| parse_value("integrator", s, integrator_arg))
+            .transpose()?;
+        let on_hidden = (self.on_hidden.as_deref())
+            .map(|s| parse_value("on-hidden", s, hidden_policy_arg))
+            .transpose()?;
+        let preset = (self.preset.as_deref())
+            .map(|s| parse_value("preset", s, preset_arg))
+            .transpose()?;
+        let keep = (self.randomize_keep.as_deref())
+            .map(|names| {
+                names
+                    .iter()
+                    .map(|s| parse_value("randomize-keep", s, invariant_arg))
+                    .collect()
+            })
+            .transpose()?;
+        let ramps: Vec<_> = (self.ramps.iter().flatten())
+            .map(|s| parse_value("ramps", s, ramp_arg))
+            .collect::<Result<_, _>>()?;
+
+        let (width, height) = &mut config.window_size;
+        set(width, self.width);
+        set(height, self.height);
+        set(&mut config.maximized, self.maximized);
+        config.monitor = self.monitor.or(config.monitor);
+        // The background of the palette is replaced by the one of the file, if there is one
+        if let Some(palette) = palette {
+            config.set_palette(palette);
+        }
+        set(&mut config.background, self.background);
+        config.trail_color = self.trail_color.or(config.trail_color);
+        set(&mut config.trail_saturation, self.trail_saturation);
+        set(&mut config.joint_trail_color, self.joint_trail_color);
+        set(&mut config.trail_length, self.trail_length);
+        set(&mut config.monochrome, self.monochrome);
+        set(&mut config.clear, self.clear);
+        set(&mut config.projector, self.projector);
+        config.motion_blur = self.motion_blur.or(config.motion_blur);
+        config.bloom = self.bloom.or(config.bloom);
+        set(&mut config.size, self.count);
+        set(&mut config.anchor, self.anchor);
+        set(&mut config.scenes, self.scenes);
+        set(&mut config.show_trail, self.show_trail);
+        config.spawn_interval = self.spawn_every.or(config.spawn_interval);
+        config.max_pendulums = self.max_pendulums.or(config.max_pendulums);
+        set(&mut config.clones, self.clones);
+        set(&mut config.speed, self.speed);
+        set(&mut config.physics_hz, self.physics_hz);
+        set(&mut config.integrator, integrator);
+        set(&mut config.vsync, self.vsync);
+        config.max_fps = self.max_fps.or(config.max_fps);
+        set(&mut config.on_hidden, on_hidden);
+        set(&mut config.adaptive_substeps, self.adaptive_substeps);
+        set(&mut config.wind, self.wind);
+        set(&mut config.gust, self.gust);
+        set(&mut config.physics.gravity, self.gravity);
+        set(&mut config.physics.damping, self.damping);
+        set(&mut config.physics.drive_amplitude, self.drive_amplitude);
+        set(&mut config.physics.drive_frequency, self.drive_frequency);
+        config.seed = self.seed.or(config.seed);
+        config.script = self.script.or(config.script.take());
+        config.preset = preset.or(config.preset);
+        set(&mut config.constraints.mass, self.randomize_mass);
+        set(&mut config.constraints.length, self.randomize_length);
+        set(&mut config.constraints.keep, keep);
+        config.ramps.extend(ramps);
+        for (action, key) in self.keys {
+            match (action.parse(), keybindings::parse_key(&key)) {
+                (Ok(action), Some(key)) => config.keybindings.bind(action, key),
+                _ => eprintln!("Ignoring the invalid keybinding {action} = {key:?}"),
+            }
+        }
+        if let Some(pendulums) = self.pendulums {
+            config.pendulums = pendulums.into_iter().map(ScenePendulum::from).collect();
+        }
+        Ok(())
+    }
+}
+
+/// Parse `value`, written as text for `key` in the file, with the parser of the command line argument
+fn parse_value<T>(
+    key: &str,
+    value: &str,
+    parser: impl Fn(&str) -> Result<T, String>,
+) -> Result<T, String> {
+    parser(value).map_err(|e| format!("invalid value {value:?} for {key}: {e}"))
+}
+
+/// Overwrite `value` if the file contains a new one
+fn set<T>(value: &mut T, new: Option<T>) {
+    if let Some(new) = new {
+        *value = new;
+    }
+}
+
+/// Parse two numbers separated by a comma, like `0.5,0.25`
+fn parse_pair(s: &str) -> Option<[f32; 2]> {
+    let (x, y) = s.split_once(',')?;
+    Some([x.trim().parse().ok()?, y.trim().parse().ok()?])
+}
+
+/// Parse the size of the window written as two numbers separated by an x, like `1920x1080`
+fn parse_size(s: &str) -> Option<(f32, f32)> {
+    let (width, height) = s.split_once('x')?;
+    let size = (width.trim().parse().ok()?, height.trim().parse().ok()?);
+    Some(size).filter(|&(width, height)| width > 0.0 && height > 0.0)
+}
+
+/// Parse a color written as three or four numbers between 0 and 1 separated by commas, like `0,0,0`,
+/// the alpha is 1 if it is missing
+fn parse_color(s: &str) -> Option<[f32; 4]> {
+    let components: Vec<f32> = (s.split(','))
+        .map(|c| c.trim().parse().ok())
+        .collect::<Option<_>>()?;
+    match components[..] {
+        [r, g, b] => Some([r, g, b, 1.0]),
+        [r, g, b, a] => Some([r, g, b, a]),
+        _ => None,
+    }
+}
+
+/// Parse a time written as `SECONDS`, `MM:SS` or `HH:MM:SS` into seconds
+///
+/// The last component can have a fractional part, like `01:23.5`
+fn parse_time(s: &str) -> Option<f32> {
+    let mut seconds = 0.0;
+    for part in s.split(':') {
+        let value: f32 = part.parse().ok()?;
+        if value < 0.0 {
+            return None;
+        }
+        seconds = seconds * 60.0 + value;
+    }
+    Some(seconds)
+}
+
+/// Returns the value of an argument, or the error shown by the command line parser if it is invalid,
+/// `expected` describing the valid values
+fn or_expected<T>(value: Option<T>, expected: &str) -> Result<T, String> {
+    value.ok_or_else(|| format!("expected {expected}"))
+}
+
+/// Returns a parser of the numbers between `min` and `max` included, for the command line arguments
+fn between(min: f32, max: f32) -> impl Fn(&str) -> Result<f32, String> + Clone + Send + Sync {
+    move |s| {
+        let value = s.parse().ok().filter(|value| (min..=max).contains(value));
+        or_expected(value, &format!("a number between {min} and {max}"))
+    }
+}
+
+/// Returns a parser of the whole numbers between the bounds of `range` included, for the command line arguments
+fn whole_between(
+    (min, max): (u32, u32),
+) -> impl Fn(&str) -> Result<u32, String> + Clone + Send + Sync {
+    move |s| {
+        let value = s.parse().ok().filter(|value| (min..=max).contains(value));
+        or_expected(value, &format!("a whole number between {min} and {max}"))
+    }
+}
+
+/// Parse a finite number greater than 0
+fn positive(s: &str) -> Result<f32, String> {
+    let value = s
+        .parse()
+        .ok()
+        .filter(|&value: &f32| value > 0.0 && value.is_finite());
+    or_expected(value, "a number greater than 0")
+}
+
+/// Parse a number that is neither infinite nor NaN
+fn finite(s: &str) -> Result<f32, String> {
+    let value = s.parse().ok().filter(|value: &f32| value.is_finite());
+    or_expected(value, "a finite number")
+}
+
+/// Parse a finite number of at least 0
+fn non_negative(s: &str) -> Result<f32, String> {
+    let value = s
+        .parse()
+        .ok()
+        .filter(|&value: &f32| (0.0..f32::INFINITY).contains(&value));
+    or_expected(value, "a finite number of at least 0")
+}
+
+/// Parse a whole number of at least 1
+fn at_least_one<T: FromStr + PartialOrd + From<u8>>(s: &str) -> Result<T, String> {
+    or_expected(
+        s.parse().ok().filter(|n| *n >= T::from(1)),
+        "a whole number of at least 1",
+    )
+}
+
+fn color_arg(s: &str) -> Result<[f32; 4], String> {
+    or_expected(
+        parse_color(s),
+        "three or four numbers between 0 and 1 separated by commas",
+    )
+}
+
+fn pair_arg(s: &str) -> Result<[f32; 2], String> {
+    or_expected(
+        parse_pair(s),
+        "two numbers separated by a comma, like 0.5,0.25",
+    )
+}
+
+fn size_arg(s: &str) -> Result<(f32, f32), String> {
+    or_expected(
+        parse_size(s),
+        "a width and a height greater than 0 separated by an x, like 1920x1080",
+    )
+}
+
+fn time_arg(s: &str) -> Result<f32, String> {
+    or_expected(parse_time(s), "SECONDS, MM:SS or HH:MM:SS")
+}
+
+fn palette_arg(s: &str) -> Result<Palette, String> {
+    let names: Vec<_> = Palette::ALL.iter().map(|palette| palette.name()).collect();
+    or_expected(s.parse().ok(), &format!("one of {}", names.join(", ")))
+}
+
+fn preset_arg(s: &str) -> Result<usize, String> {
+    let names: Vec<_> = PRESETS.iter().map(|preset| preset.name).collect();
+    or_expected(preset::find(s), &format!("one of {}", names.join(", ")))
+}
+
+fn hidden_policy_arg(s: &str) -> Result<HiddenPolicy, String> {
+    or_expected(s.parse().ok(), "pause, keep or catch-up")
+}
+
+fn integrator_arg(s: &str) -> Result<IntegratorKind, String> {
+    or_expected(s.parse().ok(), "symplectic, euler, rk4 or verlet")
+}
+
+fn ramp_arg(s: &str) -> Result<Ramp, String> {
+    or_expected(
+        s.parse().ok(),
+        "PARAMETER:FROM:TO:SECONDS, like gravity:1.0:3.0:60",
+    )
+}
+
+fn invariant_arg(s: &str) -> Result<Invariant, String> {
+    or_expected(s.trim().parse().ok(), "length or energy")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn times_are_parsed_into_seconds() {
+        assert_eq!(parse_time("0"), Some(0.0));
+        assert_eq!(parse_time("90"), Some(90.0));
+        assert_eq!(parse_time("2.5"), Some(2.5));
+        assert_eq!(parse_time("01:30"), Some(90.0));
+        assert_eq!(parse_time("01:23.5"), Some(83.5));
+        assert_eq!(parse_time("1:00:00"), Some(3600.0));
+        assert_eq!(parse_time("1:02:03"), Some(3723.0));
+    }
+
+    #[test]
+    fn invalid_times_are_rejected() {
+        for s in ["", ":", "1:", ":30", "-5", "01:-30", "1:2:3:x", "one"] {
+            assert_eq!(parse_time(s), None, "{s:?}");
+        }
+    }
+}
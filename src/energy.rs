@@ -0,0 +1,79 @@
+use crate::recorder::{timestamp, RECORDINGS_DIR};
+use double_pendulum::sink::{ExportSink, Metadata, Sample};
+use ggez::GameResult;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+/// Appends the energy of every pendulum to a CSV file every few physics updates,
+/// to measure how well the integrators keep the energy offline
+///
+/// The file is appended to, so several runs can be logged into the same file.
+/// The header is only written if the file is empty, and the metadata of every run is written
+/// as comments before its rows.
+pub struct EnergyLog {
+    writer: BufWriter<File>,
+    /// The number of physics updates between two rows
+    interval: u32,
+    /// The number of physics updates since the last row
+    since_row: u32,
+}
+
+impl EnergyLog {
+    pub fn new(path: Option<&str>, interval: u32, metadata: &Metadata) -> GameResult<Self> {
+        let path = match path {
+            Some(path) => PathBuf::from(path),
+            None => {
+                fs::create_dir_all(RECORDINGS_DIR)?;
+                PathBuf::from(RECORDINGS_DIR).join(format!("{}-energy.csv", timestamp()))
+            }
+        };
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let empty = file.metadata()?.len() == 0;
+        let mut writer = BufWriter::new(file);
+        for (key, value) in metadata.entries() {
+            writeln!(writer, "# {key}: {value}")?;
+        }
+        if empty {
+            writeln!(writer, "time,pendulum,kinetic,potential,total")?;
+        }
+        println!("Logging the energy to {}", path.display());
+        Ok(Self {
+            writer,
+            interval: interval.max(1),
+            // The first row is written right away
+            since_row: interval.max(1) - 1,
+        })
+    }
+}
+
+impl ExportSink for EnergyLog {
+    fn name(&self) -> &str {
+        "energy"
+    }
+
+    fn write_sample(&mut self, sample: &Sample) -> GameResult {
+        self.since_row += 1;
+        if self.since_row < self.interval {
+            return Ok(());
+        }
+        self.since_row = 0;
+
+        for (index, pendulum) in sample.pendulums.iter().enumerate() {
+            let kinetic = pendulum.kinetic_energy();
+            let potential = pendulum.potential_energy();
+            writeln!(
+                self.writer,
+                "{},{index},{kinetic},{potential},{}",
+                sample.time,
+                kinetic + potential,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> GameResult {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,117 @@
+use crate::animation::GifRecorder;
+use crate::energy::EnergyLog;
+use crate::midi::MidiSender;
+use crate::orientation::OrientationExporter;
+use crate::osc::OscSender;
+use crate::recorder::Recorder;
+use crate::screenshot::Screenshot;
+use crate::trajectory::TrajectoryExporter;
+use crate::video::VideoRecorder;
+use crate::websocket::WebSocketServer;
+use double_pendulum::sink::{ExportSettings, ExportSink, Metadata, Sample, SinkRegistry};
+use ggez::{Context, GameResult};
+
+/// Create a registry containing every built-in sink, configured with `settings`
+pub fn registry(settings: ExportSettings) -> SinkRegistry {
+    let mut registry = SinkRegistry::new(settings);
+    registry.register("apng", |path, metadata, _| {
+        Ok(Box::new(Recorder::new(path, metadata)))
+    });
+    registry.register("gif", |path, metadata, settings| {
+        Ok(Box::new(GifRecorder::new(path, metadata, settings)))
+    });
+    registry.register("video", |path, metadata, settings| {
+        Ok(Box::new(VideoRecorder::new(path, metadata, settings)?))
+    });
+    registry.register("screenshot", |path, metadata, _| {
+        Ok(Box::new(Screenshot::new(path, metadata)))
+    });
+    registry.register("orientation", |path, metadata, _| {
+        Ok(Box::new(OrientationExporter::new(path, metadata)?))
+    });
+    registry.register("csv", |path, metadata, _| {
+        Ok(Box::new(TrajectoryExporter::new(path, metadata)?))
+    });
+    registry.register("osc", |address, _, settings| {
+        Ok(Box::new(OscSender::new(address, settings.osc_rate)?))
+    });
+    registry.register("midi", |port, _, _| Ok(Box::new(MidiSender::new(port)?)));
+    registry.register("websocket", |address, _, _| {
+        Ok(Box::new(WebSocketServer::new(address)?))
+    });
+    registry.register("energy", |path, metadata, settings| {
+        let interval = settings.energy_interval;
+        Ok(Box::new(EnergyLog::new(path, interval, metadata)?))
+    });
+    registry
+}
+
+/// The export system, which starts the sinks and gives them the simulation data
+pub struct ExportSystem {
+    /// Every kind of export sink that can be started
+    registry: SinkRegistry,
+    /// The export sinks currently receiving the simulation data
+    sinks: Vec<Box<dyn ExportSink>>,
+}
+
+impl ExportSystem {
+    pub fn new(settings: ExportSettings) -> Self {
+        Self {
+            registry: registry(settings),
+            sinks: Vec::new(),
+        }
+    }
+
+    /// Start the export sink described by `spec`, which receives `metadata` to reproduce the simulation
+    pub fn start(&mut self, spec: &str, metadata: &Metadata) -> GameResult {
+        let sink = self.registry.create(spec, metadata)?;
+        self.sinks.push(sink);
+        Ok(())
+    }
+
+    /// Start the export sink called `name`, or finish it if it is already running
+    pub fn toggle(&mut self, name: &str, metadata: &Metadata) -> GameResult {
+        match self.sinks.iter().position(|sink| sink.name() == name) {
+            Some(index) => self.sinks.remove(index).finish(),
+            None => self.start(name, metadata),
+        }
+    }
+
+    /// Give the state of the simulation after a physics update to every sink
+    pub fn update(&mut self, sample: &Sample) -> GameResult {
+        for sink in &mut self.sinks {
+            sink.write_sample(sample)?;
+        }
+        Ok(())
+    }
+
+    /// Give the frame that was just drawn to every sink, then finish and remove the ones that are done
+    pub fn draw(&mut self, ctx: &Context) -> GameResult {
+        for sink in &mut self.sinks {
+            sink.write_frame(ctx)?;
+        }
+        let (done, running) = self.sinks.drain(..).partition(|sink| sink.is_done());
+        self.sinks = running;
+        for sink in done {
+            sink.finish()?;
+        }
+        Ok(())
+    }
+
+    /// Finish every sink, so the recordings in progress are saved rather than lost
+    pub fn finish(&mut self) -> GameResult {
+        for sink in self.sinks.drain(..) {
+            sink.finish()?;
+        }
+        Ok(())
+    }
+
+    /// Returns the lines of the HUD listing the running sinks, each starting with a newline
+    pub fn hud(&self) -> String {
+        // The screenshots only last one frame, and they would capture their own line
+        (self.sinks.iter())
+            .filter(|sink| sink.name() != "screenshot")
+            .map(|sink| format!("\nExporting: {}", sink.name()))
+            .collect()
+    }
+}
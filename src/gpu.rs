@@ -0,0 +1,290 @@
+use crate::integrator::{IntegratorKind, VELOCITY_SCALE};
+use crate::pendulum::Physics;
+use crate::pendulums::Pendulums;
+use bytemuck::{Pod, Zeroable};
+use std::error::Error;
+use std::fmt;
+use std::mem;
+use std::sync::mpsc;
+
+/// The number of pendulums advanced by each workgroup of the shader
+const WORKGROUP_SIZE: u32 = 64;
+/// The number of pendulums the buffers hold at first, they grow when more are uploaded
+const INITIAL_CAPACITY: usize = 1024;
+
+/// Why the GPU can't be used for the physics
+#[derive(Debug)]
+pub enum GpuError {
+    /// No graphics card, nor software renderer, is available
+    NoAdapter,
+    /// The graphics card refused to be used
+    Device(wgpu::RequestDeviceError),
+}
+
+impl fmt::Display for GpuError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NoAdapter => f.write_str("no graphics adapter was found"),
+            Self::Device(e) => write!(f, "the graphics device couldn't be opened: {e}"),
+        }
+    }
+}
+
+impl Error for GpuError {}
+
+/// The uniforms of one dispatch, laid out like the `Step` struct of `gpu.wgsl`
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Step {
+    /// The duration of each step times `VELOCITY_SCALE`
+    dt: f32,
+    /// The duration of each step in seconds
+    seconds: f32,
+    wind: f32,
+    time: f32,
+    count: u32,
+    substeps: u32,
+    /// The distance between two columns of the storage, which is its capacity
+    stride: u32,
+    padding: u32,
+}
+
+/// The number of columns of `Pendulums` sent to the shader: θ1, θ2, ω1, ω2, m1, m2, l1 and l2
+const COLUMNS: usize = 8;
+/// The number of those columns written by the shader and read back, the angles and the angular velocities
+const STATE_COLUMNS: usize = 4;
+
+/// The buffers holding the pendulums, sized for `capacity` of them
+struct Buffers {
+    capacity: usize,
+    /// The columns of the pendulums one after the other, read and written by the shader
+    columns: wgpu::Buffer,
+    /// The physical constants of the pendulums
+    physics: wgpu::Buffer,
+    /// Where the state columns are copied to be read back
+    staging: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+/// Advances the pendulums with a compute shader instead of the CPU, for hundreds of thousands of them
+///
+/// This is experimental: every update uploads the columns of the pendulums, runs the shader for several steps
+/// and waits for the results, so it only pays off with a lot of pendulums. The shader does the same steps
+/// as `batch::forward`, but the trigonometric functions of the graphics card are less accurate,
+/// so the results drift apart sooner
+pub struct GpuPhysics {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+    uniforms: wgpu::Buffer,
+    buffers: Buffers,
+}
+
+impl GpuPhysics {
+    /// Open the graphics card preferred for heavy work and compile the shader
+    pub fn new() -> Result<Self, GpuError> {
+        let instance = wgpu::Instance::new(wgpu::Backends::all());
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            force_fallback_adapter: false,
+            compatible_surface: None,
+        }))
+        .ok_or(GpuError::NoAdapter)?;
+        let (device, queue) = pollster::block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                label: Some("physics"),
+                features: wgpu::Features::empty(),
+                limits: wgpu::Limits::downlevel_defaults(),
+            },
+            None,
+        ))
+        .map_err(GpuError::Device)?;
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("physics"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("gpu.wgsl").into()),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("physics"),
+            layout: None,
+            module: &shader,
+            entry_point: "main",
+        });
+        let uniforms = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("step"),
+            size: mem::size_of::<Step>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let buffers = Self::create_buffers(&device, &pipeline, &uniforms, INITIAL_CAPACITY);
+        Ok(Self {
+            device,
+            queue,
+            pipeline,
+            uniforms,
+            buffers,
+        })
+    }
+
+    fn create_buffers(
+        device: &wgpu::Device,
+        pipeline: &wgpu::ComputePipeline,
+        uniforms: &wgpu::Buffer,
+        capacity: usize,
+    ) -> Buffers {
+        let column = (capacity * mem::size_of::<f32>()) as u64;
+        let columns = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("pendulums"),
+            size: COLUMNS as u64 * column,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let physics = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("physics"),
+            size: (capacity * mem::size_of::<Physics>()) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let staging = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback"),
+            size: STATE_COLUMNS as u64 * column,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("physics"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniforms.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: columns.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: physics.as_entire_binding(),
+                },
+            ],
+        });
+        Buffers {
+            capacity,
+            columns,
+            physics,
+            staging,
+            bind_group,
+        }
+    }
+
+    /// Advance `pendulums` by `substeps` steps of `dt` seconds like `Pendulums::forward`, without the trails
+    ///
+    /// Every step is done by a single submission to the graphics card, whose results are read back once.
+    /// The pendulums not using the symplectic Euler method are advanced on the CPU
+    pub fn forward(
+        &mut self,
+        pendulums: &mut Pendulums,
+        dt: f32,
+        substeps: u32,
+        wind: f32,
+        time: f32,
+    ) {
+        // The shader advances every pendulum, so the other ones are taken before it overwrites them
+        let others: Vec<_> = (pendulums.integrators().iter().enumerate())
+            .filter(|(_, &integrator)| integrator != IntegratorKind::Symplectic)
+            .map(|(index, _)| (index, pendulums.at(index)))
+            .collect();
+        if others.len() < pendulums.len() && substeps > 0 {
+            self.run(pendulums, dt, substeps, wind, time);
+        }
+        for (index, mut rods) in others {
+            for substep in 0..substeps {
+                rods.forward(dt, wind, time + substep as f32 * dt);
+            }
+            pendulums.set(index, rods);
+        }
+    }
+
+    /// Run the shader on the columns of `pendulums`, and write the angles and the angular velocities back into them
+    fn run(&mut self, pendulums: &mut Pendulums, dt: f32, substeps: u32, wind: f32, time: f32) {
+        let count = pendulums.len();
+        if count > self.buffers.capacity {
+            let capacity = count.next_power_of_two();
+            self.buffers =
+                Self::create_buffers(&self.device, &self.pipeline, &self.uniforms, capacity);
+        }
+        let step = Step {
+            dt: dt * VELOCITY_SCALE,
+            seconds: dt,
+            wind,
+            time,
+            count: count as u32,
+            substeps,
+            stride: self.buffers.capacity as u32,
+            padding: 0,
+        };
+        self.queue
+            .write_buffer(&self.uniforms, 0, bytemuck::bytes_of(&step));
+        // The columns are sent straight from the vectors of the pendulums
+        let stride = (self.buffers.capacity * mem::size_of::<f32>()) as u64;
+        let state = pendulums.state_columns_mut().map(|column| &*column);
+        for (index, column) in state.into_iter().enumerate() {
+            let data = bytemuck::cast_slice(column);
+            (self.queue).write_buffer(&self.buffers.columns, index as u64 * stride, data);
+        }
+        let constants = pendulums.constant_columns();
+        for (index, column) in (STATE_COLUMNS..).zip(constants) {
+            let data = bytemuck::cast_slice(column);
+            (self.queue).write_buffer(&self.buffers.columns, index as u64 * stride, data);
+        }
+        let physics = bytemuck::cast_slice(pendulums.physics());
+        self.queue.write_buffer(&self.buffers.physics, 0, physics);
+
+        let mut encoder = (self.device).create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("physics"),
+        });
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("physics"),
+            });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &self.buffers.bind_group, &[]);
+            pass.dispatch_workgroups((count as u32).div_ceil(WORKGROUP_SIZE), 1, 1);
+        }
+        // Only the used part of each state column is read back, packed one after the other
+        let column = (count * mem::size_of::<f32>()) as u64;
+        for index in 0..STATE_COLUMNS as u64 {
+            encoder.copy_buffer_to_buffer(
+                &self.buffers.columns,
+                index * stride,
+                &self.buffers.staging,
+                index * column,
+                column,
+            );
+        }
+        self.queue.submit(Some(encoder.finish()));
+
+        let slice = self.buffers.staging.slice(..STATE_COLUMNS as u64 * column);
+        let (sender, receiver) = mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            // The receiver waits below, so it is still there
+            let _ = sender.send(result);
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+        // If the results can't be read, the pendulums keep their state until the next update
+        if let Ok(Ok(())) = receiver.recv() {
+            let mapped = slice.get_mapped_range();
+            let results: &[f32] = bytemuck::cast_slice(&mapped);
+            let columns = pendulums.state_columns_mut();
+            for (column, result) in columns.into_iter().zip(results.chunks_exact(count)) {
+                column.copy_from_slice(result);
+            }
+            drop(mapped);
+            self.buffers.staging.unmap();
+            pendulums.wrap_angles();
+        }
+    }
+}
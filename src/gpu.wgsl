@@ -0,0 +1,73 @@
+// Several steps of the symplectic Euler method for every pendulum, the same equations as `DoublePendulum`
+
+struct Physics {
+    gravity: f32,
+    damping: f32,
+    drive_amplitude: f32,
+    drive_frequency: f32,
+}
+
+struct Step {
+    // The duration of each step times the velocity scale of the integrators
+    dt: f32,
+    // The duration of each step in seconds, to advance the time of the driving force
+    seconds: f32,
+    wind: f32,
+    time: f32,
+    count: u32,
+    substeps: u32,
+    // The distance between two columns of `columns`
+    stride: u32,
+    padding: u32,
+}
+
+@group(0) @binding(0) var<uniform> step: Step;
+// The columns θ1, θ2, ω1, ω2, m1, m2, l1 and l2 one after the other, each with a value for every pendulum
+@group(0) @binding(1) var<storage, read_write> columns: array<f32>;
+@group(0) @binding(2) var<storage, read> physics: array<Physics>;
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x >= step.count) {
+        return;
+    }
+    let i = id.x;
+    let stride = step.stride;
+    let p = physics[i];
+    let m1 = columns[4u * stride + i];
+    let m2 = columns[5u * stride + i];
+    let l1 = columns[6u * stride + i];
+    let l2 = columns[7u * stride + i];
+    var theta = vec2<f32>(columns[i], columns[stride + i]);
+    var omega = vec2<f32>(columns[2u * stride + i], columns[3u * stride + i]);
+
+    let tilt = atan2(step.wind, p.gravity);
+    let g = sqrt(p.gravity * p.gravity + step.wind * step.wind);
+    for (var substep = 0u; substep < step.substeps; substep = substep + 1u) {
+        let t1 = theta.x - tilt;
+        let t2 = theta.y - tilt;
+        let s1sq = omega.x * omega.x;
+        let s2sq = omega.y * omega.y;
+        let sin_d = sin(t1 - t2);
+        let cos_d = cos(t1 - t2);
+
+        let num1 = -g * (2.0 * m1 + m2) * sin(t1)
+            - m2 * g * sin(t1 - 2.0 * t2)
+            - 2.0 * sin_d * m2 * (s2sq * l2 + s1sq * l1 * cos_d);
+        let num2 = 2.0 * sin_d
+            * (s1sq * l1 * (m1 + m2) + g * (m1 + m2) * cos(t1) + 2.0 * s2sq * l2 * m2 * cos_d);
+        let denom = 2.0 * m1 + m2 - m2 * cos(2.0 * (t1 - t2));
+
+        let time = step.time + f32(substep) * step.seconds;
+        let drive = sin(6.28318530718 * time * p.drive_frequency);
+        let a1 = num1 / (l1 * denom) - p.damping * omega.x + p.drive_amplitude * drive;
+        let a2 = num2 / (l2 * denom) - p.damping * omega.y;
+
+        omega += step.dt * vec2<f32>(a1, a2);
+        theta += step.dt * omega;
+    }
+    columns[i] = theta.x;
+    columns[stride + i] = theta.y;
+    columns[2u * stride + i] = omega.x;
+    columns[3u * stride + i] = omega.y;
+}
@@ -0,0 +1,123 @@
+use crate::recorder::{add_text_chunks, encoding_error};
+use double_pendulum::color::{self, Palette};
+use double_pendulum::pendulum::{DoublePendulum, InitialConditions, Physics};
+use double_pendulum::pendulums::Pendulums;
+use double_pendulum::sink::Metadata;
+use ggez::GameResult;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::f32::consts::PI;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// The directory the results are written in when none is given
+pub const DEFAULT_OUTPUT_DIR: &str = "results";
+/// The number of physics updates when none is given, a minute of simulated time
+pub const DEFAULT_STEPS: u64 = 240 * 60;
+
+/// Write the final state and the energy drift of every pendulum into a CSV file
+///
+/// `initial_energies` are the total energies of the pendulums before the first physics update
+pub fn write_statistics(
+    path: &Path,
+    pendulums: &Pendulums,
+    initial_energies: &[f32],
+    metadata: &Metadata,
+) -> GameResult {
+    let mut writer = BufWriter::new(File::create(path)?);
+    for (key, value) in metadata.entries() {
+        writeln!(writer, "# {key}: {value}")?;
+    }
+    writeln!(
+        writer,
+        "pendulum,theta1,theta2,omega1,omega2,initial_energy,final_energy,relative_drift"
+    )?;
+    for (index, (p, initial)) in pendulums.iter().zip(initial_energies).enumerate() {
+        let [theta1, theta2] = p.angles();
+        let [omega1, omega2] = p.angular_velocities();
+        let energy = p.kinetic_energy() + p.potential_energy();
+        // The energy is measured from the rest position, so it is never 0 unless the pendulum is at rest
+        let reference = initial - p.rest_potential_energy();
+        let drift = (energy - initial) / reference.abs().max(f32::EPSILON);
+        writeln!(
+            writer,
+            "{index},{theta1},{theta2},{omega1},{omega2},{initial},{energy},{drift}"
+        )?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Returns the flip fractal of the pendulum, with `resolution` pixels on each side, in RGBA
+///
+/// Each pixel starts a pendulum at rest from the angles of its position, from -PI to PI on both axes
+/// with the first angle going right and the second one going up, and is colored by how long it takes
+/// for one of its rods to flip over. The pixels that don't flip within `steps` physics updates of `dt` seconds are black.
+/// Every pendulum is created from a random number generator seeded with `seed`,
+/// so the missing masses and lengths of `initial` are the same for every pixel.
+pub fn flip_fractal(
+    seed: u64,
+    length: f32,
+    physics: Physics,
+    initial: &InitialConditions,
+    resolution: u32,
+    steps: u64,
+    dt: f32,
+) -> GameResult<Vec<u8>> {
+    let angle = |i: u32| -PI + 2.0 * PI * (i as f32 + 0.5) / resolution as f32;
+    let mut pixels = Vec::with_capacity((resolution * resolution * 4) as usize);
+    for row in 0..resolution {
+        for column in 0..resolution {
+            let initial = InitialConditions {
+                theta1: Some(angle(column)),
+                theta2: Some(-angle(row)),
+                v1: Some(0.0),
+                v2: Some(0.0),
+                ..*initial
+            };
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut p =
+                DoublePendulum::new(&mut rng, length, physics, &initial, Palette::default());
+
+            let mut flip = None;
+            for step in 1..=steps {
+                p.update(dt, 0.0, step as f32 * dt);
+                if p.has_flipped() {
+                    flip = Some(step);
+                    break;
+                }
+            }
+
+            // The times are on a logarithmic scale, so the quick flips can be told apart
+            let pixel = match flip {
+                Some(step) => {
+                    let t = (step as f32).ln() / (steps as f32).ln().max(f32::EPSILON);
+                    let (r, g, b) = color::hsv(300.0 * t, 1.0, 1.0 - 0.6 * t).to_rgb();
+                    [r, g, b, 255]
+                }
+                None => [0, 0, 0, 255],
+            };
+            pixels.extend(pixel);
+        }
+    }
+    Ok(pixels)
+}
+
+/// Write RGBA `pixels` of `width` by `height` into a PNG file, with `metadata` in its text chunks
+pub fn write_png(
+    path: &Path,
+    width: u32,
+    height: u32,
+    pixels: &[u8],
+    metadata: &Metadata,
+) -> GameResult {
+    let writer = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(writer, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    add_text_chunks(&mut encoder, metadata)?;
+    let mut writer = encoder.write_header().map_err(encoding_error)?;
+    writer.write_image_data(pixels).map_err(encoding_error)?;
+    writer.finish().map_err(encoding_error)
+}
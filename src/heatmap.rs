@@ -0,0 +1,78 @@
+use double_pendulum::color;
+use ggez::graphics::{Image, ImageFormat};
+use ggez::Context;
+
+/// The number of cells along each side of the grid
+pub const RESOLUTION: usize = 160;
+
+/// A grid counting how often the tips of the pendulums visit each region around their origin
+///
+/// The grid is a square centered on the origin, going from `-extent` to `extent` on both axes,
+/// in the same units as the lengths of the rods
+pub struct Heatmap {
+    /// The half of the width of the grid
+    extent: f32,
+    /// The number of visits of each cell, row by row
+    counts: Vec<u32>,
+    /// The largest number of visits of a cell
+    max: u32,
+}
+
+impl Heatmap {
+    pub fn new(extent: f32) -> Self {
+        Self {
+            extent,
+            counts: vec![0; RESOLUTION * RESOLUTION],
+            max: 0,
+        }
+    }
+
+    /// Returns the half of the width of the grid
+    pub fn extent(&self) -> f32 {
+        self.extent
+    }
+
+    /// Count a visit of `point`, relative to the origin, the points outside of the grid are ignored
+    pub fn add(&mut self, point: [f32; 2]) {
+        let cell = |x: f32| ((x + self.extent) / (2.0 * self.extent) * RESOLUTION as f32).floor();
+        let (column, row) = (cell(point[0]), cell(point[1]));
+        let range = 0.0..RESOLUTION as f32;
+        if !range.contains(&column) || !range.contains(&row) {
+            return;
+        }
+
+        let count = &mut self.counts[row as usize * RESOLUTION + column as usize];
+        *count += 1;
+        self.max = self.max.max(*count);
+    }
+
+    /// Returns the grid as an image with one pixel per cell
+    pub fn image(&self, ctx: &Context) -> Image {
+        let size = RESOLUTION as u32;
+        Image::from_pixels(ctx, &self.pixels(), ImageFormat::Rgba8UnormSrgb, size, size)
+    }
+
+    /// Returns the RGBA pixels of the grid, one per cell, row by row
+    ///
+    /// The cells that were never visited are transparent, and the others go from blue for the rarely visited ones
+    /// to red for the most visited ones, on a logarithmic scale so the rare visits are still visible
+    pub fn pixels(&self) -> Vec<u8> {
+        let max = (self.max as f32).ln_1p().max(f32::EPSILON);
+        (self.counts.iter())
+            .flat_map(|&count| {
+                if count == 0 {
+                    return [0; 4];
+                }
+                let t = (count as f32).ln_1p() / max;
+                let color = color::hsv(240.0 * (1.0 - t), 1.0, 1.0);
+                let (r, g, b) = color.to_rgb();
+                [r, g, b, (96.0 + 159.0 * t) as u8]
+            })
+            .collect()
+    }
+
+    /// Returns the width of a cell, in the units of the lengths of the rods
+    pub fn cell_size(&self) -> f32 {
+        2.0 * self.extent / RESOLUTION as f32
+    }
+}
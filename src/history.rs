@@ -0,0 +1,97 @@
+use double_pendulum::pendulums::Pendulums;
+use std::collections::VecDeque;
+
+/// The simulated time between two snapshots, in seconds
+const SNAPSHOT_INTERVAL: f32 = 0.1;
+/// The simulated time covered by the snapshots, in seconds, the older ones are dropped
+const HISTORY_DURATION: f32 = 30.0;
+
+/// The state of the simulation at some point in the past
+#[derive(Clone)]
+pub struct Snapshot {
+    /// The simulated time, in seconds
+    pub time: f64,
+    pub pendulums: Pendulums,
+    /// The scene of each pendulum, in the same order as `pendulums`
+    pub pendulum_scenes: Vec<usize>,
+    /// The simulated time since the last automatic spawn, in seconds
+    pub spawn_timer: f32,
+}
+
+/// A rolling buffer of the last `HISTORY_DURATION` seconds of the simulation, to rewind it
+///
+/// While rewinding, the snapshots newer than the current one are kept so the rewind can go forward again,
+/// and they are dropped as soon as the simulation resumes from the current one
+pub struct History {
+    /// The snapshots from the oldest to the newest
+    snapshots: VecDeque<Snapshot>,
+    /// The simulated time since the last snapshot, in seconds
+    since_snapshot: f32,
+    /// The index of the snapshot shown while rewinding, if the simulation was rewound
+    cursor: Option<usize>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self {
+            snapshots: VecDeque::new(),
+            since_snapshot: 0.0,
+            cursor: None,
+        }
+    }
+
+    /// Take a snapshot with `snapshot` if it is time to, `dt` seconds after the last physics update
+    ///
+    /// The simulation goes on from the current snapshot if it was rewound
+    pub fn record(&mut self, dt: f32, snapshot: impl FnOnce() -> Snapshot) {
+        if let Some(cursor) = self.cursor.take() {
+            self.snapshots.truncate(cursor + 1);
+            self.since_snapshot = 0.0;
+        }
+        self.since_snapshot += dt;
+        if self.since_snapshot < SNAPSHOT_INTERVAL {
+            return;
+        }
+        self.since_snapshot -= SNAPSHOT_INTERVAL;
+        if self.snapshots.len() as f32 >= HISTORY_DURATION / SNAPSHOT_INTERVAL {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(snapshot());
+    }
+
+    /// Move one snapshot back in time, or forward if `back` is false, and return it
+    ///
+    /// `current` is the state of the simulation, kept as the newest snapshot when the rewind starts
+    /// so the rewind can go forward up to it again
+    pub fn rewind(&mut self, back: bool, current: impl FnOnce() -> Snapshot) -> Option<&Snapshot> {
+        let cursor = match self.cursor {
+            Some(cursor) => cursor,
+            None if !back => return None,
+            None => {
+                self.snapshots.push_back(current());
+                self.snapshots.len() - 1
+            }
+        };
+        let cursor = if back {
+            cursor.saturating_sub(1)
+        } else {
+            (cursor + 1).min(self.snapshots.len() - 1)
+        };
+        self.cursor = Some(cursor);
+        self.snapshots.get(cursor)
+    }
+
+    /// Returns how far back in time the simulation was rewound, in seconds, if it was
+    pub fn rewound(&self) -> Option<f64> {
+        let cursor = self.cursor?;
+        let newest = self.snapshots.back()?;
+        Some(newest.time - self.snapshots[cursor].time)
+    }
+
+    /// Forget every snapshot, when the simulation jumps to an unrelated state
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+        self.since_snapshot = 0.0;
+        self.cursor = None;
+    }
+}
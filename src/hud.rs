@@ -0,0 +1,261 @@
+use crate::config::Config;
+use crate::panel::Panel;
+use crate::profiler::Profiler;
+use crate::render;
+use double_pendulum::pendulums::Pendulums;
+use ggez::graphics::{self, Canvas, Color, DrawMode, Drawable, Mesh, MeshBuilder, Rect};
+use ggez::{Context, GameResult};
+
+/// The length in pixels of the wind indicator for a wind as strong as the gravity
+const WIND_ARROW_SCALE: f32 = 40.0;
+/// The width in pixels of the inspector panel on the right of the screen
+const INSPECTOR_WIDTH: f32 = 220.0;
+/// The width and the height in pixels of the graph of the frame timings
+const PROFILER_SIZE: [f32; 2] = [290.0, 120.0];
+/// The width in pixels of the longest energy bar
+const ENERGY_BAR_WIDTH: f32 = 150.0;
+/// The height in pixels of each row of the energy panel
+const ENERGY_ROW_HEIGHT: f32 = 12.0;
+/// The color of the kinetic energy in the energy bars
+const KINETIC_COLOR: Color = Color::new(0.9, 0.4, 0.2, 1.0);
+/// The color of the potential energy in the energy bars
+const POTENTIAL_COLOR: Color = Color::new(0.3, 0.5, 0.9, 1.0);
+
+/// What the HUD shows about the simulation in a frame
+pub struct HudView<'a> {
+    /// The lines describing the simulation, written in the top left corner
+    pub text: String,
+    pub pendulums: &'a Pendulums,
+    /// The index of the pendulum shown in the inspector panel, if any
+    pub selected: Option<usize>,
+    /// The size of the window
+    pub window: [f32; 2],
+    /// The wind blowing at the moment, if there is wind at all
+    pub wind: Option<f32>,
+    /// Whether everything is drawn in white on black, with patterns instead of colors
+    pub monochrome: bool,
+}
+
+/// The HUD system, which writes the state of the simulation over it and holds the control panel
+pub struct HudSystem {
+    /// The sliders and the buttons controlling the simulation with the mouse
+    pub panel: Panel,
+    /// Whether the background is pure black and the HUD is hidden, to project the pendulums on a wall
+    pub projector: bool,
+    /// Whether the kinetic and the potential energy of each pendulum are shown as bars
+    pub show_energy: bool,
+    /// Whether the state of the selected pendulum, or of the first one, is written in the HUD
+    pub show_readout: bool,
+    /// Whether the timings of the last frames are written in the HUD and drawn as a graph
+    pub show_profiler: bool,
+    /// The time taken by the physics, the trails and the drawing in the last frames
+    pub profiler: Profiler,
+}
+
+impl HudSystem {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            panel: Panel::new(),
+            projector: config.projector,
+            show_energy: false,
+            show_readout: false,
+            show_profiler: false,
+            profiler: Profiler::new(),
+        }
+    }
+
+    /// Returns the lines of the HUD with the state of the selected pendulum, or of the first one,
+    /// and the timings of the last frames, each starting with a newline
+    pub fn lines(&self, pendulums: &Pendulums, selected: Option<usize>) -> String {
+        let mut hud = String::new();
+        if self.show_readout {
+            let index = selected.unwrap_or(0);
+            if let Some(p) = pendulums.get(index) {
+                let [theta1, theta2] = p.angles();
+                let [turns1, turns2] = p.revolutions();
+                let [omega1, omega2] = p.angular_velocities();
+                let energy = p.kinetic_energy() + p.potential_energy();
+                hud += &format!(
+                    "\nPendulum {}\nθ1: {theta1:+.4} rad  θ2: {theta2:+.4} rad\n\
+                     Turns: {turns1:+}  {turns2:+}\n\
+                     ω1: {omega1:+.4}  ω2: {omega2:+.4}\nEnergy: {energy:.3}",
+                    index + 1,
+                );
+            }
+        }
+        if self.show_profiler {
+            hud += &format!("\n{}", self.profiler.hud());
+        }
+        hud
+    }
+
+    /// Draw the wind indicator, the lines of the HUD with the graph of the frame timings,
+    /// the inspector panel and the energy bars
+    pub fn draw(&self, ctx: &mut Context, canvas: &mut Canvas, view: &HudView) -> GameResult {
+        if let Some(wind) = view.wind {
+            self.draw_wind(ctx, canvas, view.window, wind)?;
+        }
+
+        // The projector mode only lights up the pendulums and what was turned on explicitly
+        let text = graphics::Text::new(if self.projector {
+            ""
+        } else {
+            view.text.as_str()
+        });
+        let dest_point = [10.0, 10.0];
+        canvas.draw(&text, dest_point);
+        if self.show_profiler {
+            // Right under the HUD
+            let height = text.dimensions(ctx).map_or(0.0, |rect| rect.h);
+            let area = Rect::new(
+                0.0,
+                dest_point[1] + height + 10.0,
+                PROFILER_SIZE[0],
+                PROFILER_SIZE[1],
+            );
+            self.profiler.draw(ctx, canvas, area)?;
+        }
+
+        self.draw_inspector(ctx, canvas, view)?;
+        if self.show_energy {
+            self.draw_energy(ctx, canvas, view)?;
+        }
+        Ok(())
+    }
+
+    /// Draw an arrow in the bottom left corner of `window` showing the direction and the strength of `wind`
+    fn draw_wind(
+        &self,
+        ctx: &mut Context,
+        canvas: &mut Canvas,
+        window: [f32; 2],
+        wind: f32,
+    ) -> GameResult {
+        let wind = wind * WIND_ARROW_SCALE;
+        // The arrow would be degenerate if there is no wind at the moment
+        if wind.abs() < 1.0 {
+            return Ok(());
+        }
+
+        let tip = [wind, 0.0];
+        let head = -wind.signum() * 8.0;
+        let mut builder = MeshBuilder::new();
+        builder
+            .line(&[[0.0, 0.0], tip], 2.0, Color::WHITE)?
+            .polygon(
+                DrawMode::fill(),
+                &[tip, [wind + head, -5.0], [wind + head, 5.0]],
+                Color::WHITE,
+            )?;
+        let arrow = Mesh::from_data(ctx, builder.build());
+
+        let dest = [20.0 + WIND_ARROW_SCALE * 2.0, window[1] - 20.0];
+        canvas.draw(&arrow, dest);
+        Ok(())
+    }
+
+    /// Draw a stacked bar of the kinetic and the potential energy of each pendulum,
+    /// in a panel on the right of the screen under the inspector
+    ///
+    /// The potential energy is measured from the rest position, so both parts are positive,
+    /// and the longest bar is the pendulum with the most energy.
+    /// The pendulums that don't fit in the window are left out
+    fn draw_energy(&self, ctx: &mut Context, canvas: &mut Canvas, view: &HudView) -> GameResult {
+        let energies: Vec<_> = (view.pendulums.iter())
+            .map(|p| {
+                let kinetic = p.kinetic_energy().max(0.0);
+                let potential = (p.potential_energy() - p.rest_potential_energy()).max(0.0);
+                (render::to_graphics(p.color()), kinetic, potential)
+            })
+            .collect();
+        let largest = (energies.iter())
+            .map(|(_, kinetic, potential)| kinetic + potential)
+            .fold(f32::EPSILON, f32::max);
+
+        let left = view.window[0] - INSPECTOR_WIDTH;
+        let top = 180.0;
+        let rows = ((view.window[1] - top - 30.0) / ENERGY_ROW_HEIGHT).max(0.0) as usize;
+        let height = 30.0 + ENERGY_ROW_HEIGHT * energies.len().min(rows) as f32;
+
+        let mut builder = MeshBuilder::new();
+        builder.rectangle(
+            DrawMode::fill(),
+            Rect::new(left, top, INSPECTOR_WIDTH, height),
+            Color::new(0.0, 0.0, 0.0, 0.6),
+        )?;
+        for (row, (color, kinetic, potential)) in energies.into_iter().take(rows).enumerate() {
+            let y = top + 25.0 + row as f32 * ENERGY_ROW_HEIGHT;
+            let bar = ENERGY_ROW_HEIGHT - 4.0;
+            // A square of the color of the pendulum tells which one it is
+            let swatch = if view.monochrome { Color::WHITE } else { color };
+            builder.rectangle(
+                DrawMode::fill(),
+                Rect::new(left + 10.0, y, bar, bar),
+                swatch,
+            )?;
+
+            let kinetic = kinetic / largest * ENERGY_BAR_WIDTH;
+            let potential = potential / largest * ENERGY_BAR_WIDTH;
+            let x = left + 10.0 + bar + 6.0;
+            if kinetic >= 0.5 {
+                let rect = Rect::new(x, y, kinetic, bar);
+                builder.rectangle(DrawMode::fill(), rect, KINETIC_COLOR)?;
+            }
+            if potential >= 0.5 {
+                let rect = Rect::new(x + kinetic, y, potential, bar);
+                builder.rectangle(DrawMode::fill(), rect, POTENTIAL_COLOR)?;
+            }
+        }
+        canvas.draw(&Mesh::from_data(ctx, builder.build()), [0.0, 0.0]);
+
+        let mut legend =
+            graphics::Text::new(graphics::TextFragment::new("Kinetic").color(KINETIC_COLOR));
+        legend
+            .add(" / ")
+            .add(graphics::TextFragment::new("Potential").color(POTENTIAL_COLOR));
+        canvas.draw(&legend, [left + 10.0, top + 6.0]);
+        Ok(())
+    }
+
+    /// Draw the properties of the selected pendulum in a panel on the right of the screen
+    fn draw_inspector(&self, ctx: &mut Context, canvas: &mut Canvas, view: &HudView) -> GameResult {
+        let Some(index) = view.selected else {
+            return Ok(());
+        };
+        let p = view.pendulums.at(index);
+
+        let [m1, m2] = p.masses();
+        let [l1, l2] = p.lengths();
+        let [theta1, theta2] = p.angles();
+        let [v1, v2] = p.angular_velocities();
+        let [r1, r2] = p.revolutions();
+        let kinetic = p.kinetic_energy();
+        let potential = p.potential_energy();
+        let text = format!(
+            "Pendulum {}\n\
+             Masses: {m1:.2}, {m2:.2}\n\
+             Lengths: {l1:.1}, {l2:.1}\n\
+             Angles: {theta1:.3}, {theta2:.3}\n\
+             Revolutions: {r1}, {r2}\n\
+             Velocities: {v1:.4}, {v2:.4}\n\
+             Integrator: {}\n\
+             Kinetic energy: {kinetic:.2}\n\
+             Potential energy: {potential:.2}\n\
+             Total energy: {:.2}",
+            index + 1,
+            p.integrator().name(),
+            kinetic + potential,
+        );
+
+        let left = view.window[0] - INSPECTOR_WIDTH;
+        let panel = Mesh::new_rectangle(
+            ctx,
+            DrawMode::fill(),
+            Rect::new(left, 0.0, INSPECTOR_WIDTH, 170.0),
+            Color::new(0.0, 0.0, 0.0, 0.6),
+        )?;
+        canvas.draw(&panel, [0.0, 0.0]);
+        canvas.draw(&graphics::Text::new(text), [left + 10.0, 10.0]);
+        Ok(())
+    }
+}
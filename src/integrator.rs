@@ -0,0 +1,287 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// How many radians per second an angular velocity of 1 is
+///
+/// The constants of the simulation were tuned for one step of 1.0 at 60 updates per second,
+/// so the angular velocities are in radians per 1/60 of a second
+pub const VELOCITY_SCALE: f32 = 60.0;
+
+/// The angles and angular velocities of both rods of a double pendulum at some point in time
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PendulumState {
+    /// The angles of both rods, in radians
+    pub theta: [f32; 2],
+    /// The angular velocities of both rods, in radians per 1/60 of a second
+    pub omega: [f32; 2],
+    /// The simulated time, in seconds
+    pub time: f32,
+}
+
+impl PendulumState {
+    /// Returns the state `h` seconds later if the angular velocities kept changing by `acceleration` per second,
+    /// the angles moving with the angular velocities `omega`
+    fn advanced(&self, omega: [f32; 2], acceleration: [f32; 2], h: f32) -> Self {
+        Self {
+            theta: std::array::from_fn(|i| self.theta[i] + h * VELOCITY_SCALE * omega[i]),
+            omega: std::array::from_fn(|i| self.omega[i] + h * acceleration[i]),
+            time: self.time + h,
+        }
+    }
+}
+
+/// The equations of motion of a pendulum, given to the integrators
+pub trait Dynamics {
+    /// Returns how much the angular velocities of `state` change per second
+    fn acceleration(&self, state: &PendulumState) -> [f32; 2];
+}
+
+/// A numerical method advancing a pendulum from one physics update to the next
+///
+/// The integrators only see the state and the equations of motion,
+/// so a new method can be added without knowing anything about the pendulums
+pub trait Integrator {
+    /// Advance `state` by `dt` seconds following `dynamics`
+    fn step(&self, state: &mut PendulumState, dt: f32, dynamics: &dyn Dynamics);
+}
+
+/// The explicit Euler method, the angles move with the velocities of the start of the step,
+/// so the energy keeps growing
+pub struct Euler;
+
+impl Integrator for Euler {
+    fn step(&self, state: &mut PendulumState, dt: f32, dynamics: &dyn Dynamics) {
+        *state = state.advanced(state.omega, dynamics.acceleration(state), dt);
+    }
+}
+
+/// The semi-implicit Euler method, the angles move with the velocities of the end of the step,
+/// which keeps the energy close to its initial value
+pub struct SymplecticEuler;
+
+impl Integrator for SymplecticEuler {
+    fn step(&self, state: &mut PendulumState, dt: f32, dynamics: &dyn Dynamics) {
+        let acceleration = dynamics.acceleration(state);
+        let omega = std::array::from_fn(|i| state.omega[i] + dt * acceleration[i]);
+        *state = state.advanced(omega, acceleration, dt);
+    }
+}
+
+/// The classic fourth order Runge-Kutta method, much more accurate but slowly losing energy
+pub struct Rk4;
+
+impl Integrator for Rk4 {
+    fn step(&self, state: &mut PendulumState, dt: f32, dynamics: &dyn Dynamics) {
+        let s1 = *state;
+        let a1 = dynamics.acceleration(&s1);
+        let s2 = s1.advanced(s1.omega, a1, dt / 2.0);
+        let a2 = dynamics.acceleration(&s2);
+        let s3 = s1.advanced(s2.omega, a2, dt / 2.0);
+        let a3 = dynamics.acceleration(&s3);
+        let s4 = s1.advanced(s3.omega, a3, dt);
+        let a4 = dynamics.acceleration(&s4);
+
+        let average = |k: [[f32; 2]; 4]| -> [f32; 2] {
+            std::array::from_fn(|i| (k[0][i] + 2.0 * k[1][i] + 2.0 * k[2][i] + k[3][i]) / 6.0)
+        };
+        let omega = average([s1.omega, s2.omega, s3.omega, s4.omega]);
+        let acceleration = average([a1, a2, a3, a4]);
+        *state = s1.advanced(omega, acceleration, dt);
+    }
+}
+
+/// The velocity Verlet method, second order and keeping the energy like the symplectic Euler method
+///
+/// The accelerations depend on the velocities through the friction, so the velocities at the end of the step
+/// are predicted with the accelerations at its start
+pub struct Verlet;
+
+impl Integrator for Verlet {
+    fn step(&self, state: &mut PendulumState, dt: f32, dynamics: &dyn Dynamics) {
+        let a1 = dynamics.acceleration(state);
+        // The angles move with the velocities halfway through the step
+        let half = std::array::from_fn(|i| state.omega[i] + dt / 2.0 * a1[i]);
+        let predicted = state.advanced(half, a1, dt);
+        let a2 = dynamics.acceleration(&predicted);
+        let acceleration = std::array::from_fn(|i| (a1[i] + a2[i]) / 2.0);
+        *state = state.advanced(half, acceleration, dt);
+    }
+}
+
+/// Which integrator advances a pendulum, picked at runtime and saved with it
+#[derive(Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum IntegratorKind {
+    Euler,
+    #[default]
+    Symplectic,
+    Rk4,
+    Verlet,
+}
+
+impl IntegratorKind {
+    /// Every integrator, in the order they are compared, starting with the default one
+    pub const ALL: [IntegratorKind; 4] = [
+        IntegratorKind::Symplectic,
+        IntegratorKind::Euler,
+        IntegratorKind::Rk4,
+        IntegratorKind::Verlet,
+    ];
+
+    /// Returns the name of the integrator, as shown on the screen
+    pub fn name(self) -> &'static str {
+        match self {
+            IntegratorKind::Euler => "Euler",
+            IntegratorKind::Symplectic => "symplectic Euler",
+            IntegratorKind::Rk4 => "RK4",
+            IntegratorKind::Verlet => "Verlet",
+        }
+    }
+
+    /// Returns the name of the integrator in the arguments and the configuration files
+    pub fn id(self) -> &'static str {
+        match self {
+            IntegratorKind::Euler => "euler",
+            IntegratorKind::Symplectic => "symplectic",
+            IntegratorKind::Rk4 => "rk4",
+            IntegratorKind::Verlet => "verlet",
+        }
+    }
+
+    /// Returns the implementation of the integrator
+    pub fn integrator(self) -> &'static dyn Integrator {
+        match self {
+            IntegratorKind::Euler => &Euler,
+            IntegratorKind::Symplectic => &SymplecticEuler,
+            IntegratorKind::Rk4 => &Rk4,
+            IntegratorKind::Verlet => &Verlet,
+        }
+    }
+}
+
+impl FromStr for IntegratorKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter().find(|i| i.id() == s).ok_or(())
+    }
+}
+
+impl fmt::Display for IntegratorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.id())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Palette;
+    use crate::pendulum::{DoublePendulum, InitialConditions, Physics};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    /// The physics rate of the tests, in updates per second
+    const RATE: f32 = 240.0;
+
+    /// Returns a pendulum released from a moderate angle, whose motion stays regular, advanced by `integrator`
+    fn pendulum(integrator: IntegratorKind) -> DoublePendulum {
+        let initial = InitialConditions {
+            m1: Some(3.0),
+            m2: Some(2.0),
+            l1: Some(100.0),
+            l2: Some(80.0),
+            theta1: Some(0.4),
+            theta2: Some(0.6),
+            v1: Some(0.0),
+            v2: Some(0.0),
+        };
+        // Every initial condition is given, so the random number generator isn't used
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut p = DoublePendulum::new(
+            &mut rng,
+            0.0,
+            Physics::default(),
+            &initial,
+            Palette::default(),
+        );
+        p.set_integrator(integrator);
+        p
+    }
+
+    /// Returns the total energy of `p`
+    fn energy(p: &DoublePendulum) -> f32 {
+        p.kinetic_energy() + p.potential_energy()
+    }
+
+    /// Advance `p` for `seconds` of simulated time at `rate` updates per second
+    fn run(p: &mut DoublePendulum, seconds: f32, rate: f32) {
+        let steps = (seconds * rate).round() as u32;
+        for step in 0..steps {
+            p.forward(1.0 / rate, 0.0, step as f32 / rate);
+        }
+    }
+
+    /// Returns the largest difference between the energy and the initial one over `seconds`,
+    /// relative to the initial energy above the rest position, for a pendulum advanced by `integrator`
+    fn drift(integrator: IntegratorKind, seconds: f32) -> f32 {
+        let mut p = pendulum(integrator);
+        let initial = energy(&p);
+        let scale = (initial - p.rest_potential_energy()).abs();
+        let mut largest: f32 = 0.0;
+        for _ in 0..(seconds as u32) {
+            run(&mut p, 1.0, RATE);
+            largest = largest.max((energy(&p) - initial).abs() / scale);
+        }
+        largest
+    }
+
+    /// Returns how far a pendulum advanced by `integrator` is after `seconds`
+    /// from the same pendulum advanced by RK4 at 8 times the rate, which is close to exact
+    fn error(integrator: IntegratorKind, seconds: f32) -> f32 {
+        let mut reference = pendulum(IntegratorKind::Rk4);
+        run(&mut reference, seconds, 8.0 * RATE);
+        let mut p = pendulum(integrator);
+        run(&mut p, seconds, RATE);
+        p.divergence(&reference)
+    }
+
+    #[test]
+    fn euler_gains_energy() {
+        assert!(drift(IntegratorKind::Euler, 60.0) > 1.0);
+    }
+
+    #[test]
+    fn other_integrators_keep_the_energy() {
+        for integrator in [
+            IntegratorKind::Symplectic,
+            IntegratorKind::Rk4,
+            IntegratorKind::Verlet,
+        ] {
+            let drift = drift(integrator, 60.0);
+            assert!(drift < 0.05, "{}: {drift}", integrator.name());
+        }
+    }
+
+    #[test]
+    fn integrators_agree() {
+        for integrator in IntegratorKind::ALL {
+            let error = error(integrator, 1.0);
+            assert!(error < 0.02, "{}: {error}", integrator.name());
+        }
+    }
+
+    #[test]
+    fn higher_orders_are_more_accurate() {
+        assert!(error(IntegratorKind::Verlet, 1.0) < 1e-3);
+        assert!(error(IntegratorKind::Rk4, 1.0) < 1e-5);
+    }
+
+    #[test]
+    fn ids_are_parsed_back() {
+        for integrator in IntegratorKind::ALL {
+            assert!(integrator.to_string().parse() == Ok(integrator));
+        }
+        assert!("leapfrog".parse::<IntegratorKind>().is_err());
+    }
+}
@@ -0,0 +1,343 @@
+use ggez::event::Button;
+use ggez::winit::event::VirtualKeyCode;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Something the user can do with a key
+///
+/// The actions are serialized with their names in the configuration file
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    Pause,
+    Step,
+    SpeedUp,
+    SlowDown,
+    Record,
+    RecordCsv,
+    RecordGif,
+    RecordVideo,
+    ExportSvg,
+    Screenshot,
+    Reverse,
+    Spawn,
+    Delete,
+    Monochrome,
+    Palette,
+    MotionBlur,
+    Bloom,
+    SlowMotion,
+    Snapshot,
+    Compare,
+    Preset,
+    Reset,
+    Trail,
+    JointTrail,
+    SpeedTrail,
+    RainbowTrail,
+    Ink,
+    Heatmap,
+    Velocities,
+    Energy,
+    Readout,
+    Profiler,
+    StressTest,
+    PhaseSpace,
+    AngleChart,
+    Poincare,
+    Lyapunov,
+    Spectrum,
+    Clones,
+    Integrators,
+    Wizard,
+    Scenarios,
+    Sound,
+    Louder,
+    Quieter,
+    ControlPanel,
+    LongerRod,
+    ShorterRod,
+    Heavier,
+    Lighter,
+    Randomize,
+    ResetCamera,
+    Deselect,
+    Repro,
+    Rewind,
+    Save,
+    Quit,
+}
+
+/// The name of every action in the configuration file
+const ACTION_NAMES: [(&str, Action); 57] = [
+    ("pause", Action::Pause),
+    ("step", Action::Step),
+    ("speed-up", Action::SpeedUp),
+    ("slow-down", Action::SlowDown),
+    ("record", Action::Record),
+    ("record-csv", Action::RecordCsv),
+    ("record-gif", Action::RecordGif),
+    ("record-video", Action::RecordVideo),
+    ("export-svg", Action::ExportSvg),
+    ("screenshot", Action::Screenshot),
+    ("reverse", Action::Reverse),
+    ("spawn", Action::Spawn),
+    ("delete", Action::Delete),
+    ("monochrome", Action::Monochrome),
+    ("palette", Action::Palette),
+    ("motion-blur", Action::MotionBlur),
+    ("bloom", Action::Bloom),
+    ("slow-motion", Action::SlowMotion),
+    ("snapshot", Action::Snapshot),
+    ("compare", Action::Compare),
+    ("preset", Action::Preset),
+    ("reset", Action::Reset),
+    ("trail", Action::Trail),
+    ("joint-trail", Action::JointTrail),
+    ("speed-trail", Action::SpeedTrail),
+    ("rainbow-trail", Action::RainbowTrail),
+    ("ink", Action::Ink),
+    ("heatmap", Action::Heatmap),
+    ("velocities", Action::Velocities),
+    ("energy", Action::Energy),
+    ("readout", Action::Readout),
+    ("profiler", Action::Profiler),
+    ("stress-test", Action::StressTest),
+    ("phase-space", Action::PhaseSpace),
+    ("angle-chart", Action::AngleChart),
+    ("poincare", Action::Poincare),
+    ("lyapunov", Action::Lyapunov),
+    ("spectrum", Action::Spectrum),
+    ("clones", Action::Clones),
+    ("integrators", Action::Integrators),
+    ("wizard", Action::Wizard),
+    ("scenarios", Action::Scenarios),
+    ("sound", Action::Sound),
+    ("louder", Action::Louder),
+    ("quieter", Action::Quieter),
+    ("control-panel", Action::ControlPanel),
+    ("longer-rod", Action::LongerRod),
+    ("shorter-rod", Action::ShorterRod),
+    ("heavier", Action::Heavier),
+    ("lighter", Action::Lighter),
+    ("randomize", Action::Randomize),
+    ("reset-camera", Action::ResetCamera),
+    ("deselect", Action::Deselect),
+    ("repro", Action::Repro),
+    ("rewind", Action::Rewind),
+    ("save", Action::Save),
+    ("quit", Action::Quit),
+];
+
+impl FromStr for Action {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ACTION_NAMES
+            .iter()
+            .find(|(name, _)| *name == s)
+            .map(|&(_, action)| action)
+            .ok_or(())
+    }
+}
+
+/// The keys bound at startup, before the configuration file is applied
+const DEFAULT_BINDINGS: [(VirtualKeyCode, Action); 60] = [
+    (VirtualKeyCode::Space, Action::Pause),
+    (VirtualKeyCode::Period, Action::Step),
+    (VirtualKeyCode::Equals, Action::SpeedUp),
+    (VirtualKeyCode::Plus, Action::SpeedUp),
+    (VirtualKeyCode::NumpadAdd, Action::SpeedUp),
+    (VirtualKeyCode::Minus, Action::SlowDown),
+    (VirtualKeyCode::NumpadSubtract, Action::SlowDown),
+    (VirtualKeyCode::A, Action::Record),
+    (VirtualKeyCode::Key3, Action::RecordCsv),
+    (VirtualKeyCode::Key4, Action::RecordGif),
+    (VirtualKeyCode::Key5, Action::RecordVideo),
+    (VirtualKeyCode::Key6, Action::ExportSvg),
+    (VirtualKeyCode::F12, Action::Screenshot),
+    (VirtualKeyCode::B, Action::Reverse),
+    (VirtualKeyCode::C, Action::Spawn),
+    (VirtualKeyCode::D, Action::Delete),
+    (VirtualKeyCode::M, Action::Monochrome),
+    (VirtualKeyCode::Comma, Action::Palette),
+    (VirtualKeyCode::Slash, Action::MotionBlur),
+    (VirtualKeyCode::F6, Action::Bloom),
+    (VirtualKeyCode::L, Action::SlowMotion),
+    (VirtualKeyCode::K, Action::Snapshot),
+    (VirtualKeyCode::V, Action::Compare),
+    (VirtualKeyCode::P, Action::Preset),
+    (VirtualKeyCode::R, Action::Reset),
+    (VirtualKeyCode::T, Action::Trail),
+    (VirtualKeyCode::J, Action::JointTrail),
+    (VirtualKeyCode::G, Action::SpeedTrail),
+    (VirtualKeyCode::Semicolon, Action::RainbowTrail),
+    (VirtualKeyCode::I, Action::Ink),
+    (VirtualKeyCode::H, Action::Heatmap),
+    (VirtualKeyCode::U, Action::Velocities),
+    (VirtualKeyCode::Y, Action::Energy),
+    (VirtualKeyCode::S, Action::Readout),
+    (VirtualKeyCode::F3, Action::Profiler),
+    (VirtualKeyCode::F4, Action::StressTest),
+    (VirtualKeyCode::F, Action::PhaseSpace),
+    (VirtualKeyCode::Key7, Action::AngleChart),
+    (VirtualKeyCode::X, Action::Poincare),
+    (VirtualKeyCode::O, Action::Lyapunov),
+    (VirtualKeyCode::Key1, Action::Spectrum),
+    (VirtualKeyCode::Z, Action::Clones),
+    (VirtualKeyCode::Key2, Action::Integrators),
+    (VirtualKeyCode::W, Action::Wizard),
+    (VirtualKeyCode::Key8, Action::Scenarios),
+    (VirtualKeyCode::Key9, Action::Sound),
+    (VirtualKeyCode::RBracket, Action::Louder),
+    (VirtualKeyCode::LBracket, Action::Quieter),
+    (VirtualKeyCode::Tab, Action::ControlPanel),
+    (VirtualKeyCode::Up, Action::LongerRod),
+    (VirtualKeyCode::Down, Action::ShorterRod),
+    (VirtualKeyCode::Right, Action::Heavier),
+    (VirtualKeyCode::Left, Action::Lighter),
+    (VirtualKeyCode::N, Action::Randomize),
+    (VirtualKeyCode::Key0, Action::ResetCamera),
+    (VirtualKeyCode::Escape, Action::Deselect),
+    (VirtualKeyCode::E, Action::Repro),
+    (VirtualKeyCode::Back, Action::Rewind),
+    (VirtualKeyCode::F5, Action::Save),
+    (VirtualKeyCode::Q, Action::Quit),
+];
+
+/// The names of the keys that can be bound in the configuration file, the case is ignored
+const KEY_NAMES: [(&str, VirtualKeyCode); 74] = [
+    ("a", VirtualKeyCode::A),
+    ("b", VirtualKeyCode::B),
+    ("c", VirtualKeyCode::C),
+    ("d", VirtualKeyCode::D),
+    ("e", VirtualKeyCode::E),
+    ("f", VirtualKeyCode::F),
+    ("g", VirtualKeyCode::G),
+    ("h", VirtualKeyCode::H),
+    ("i", VirtualKeyCode::I),
+    ("j", VirtualKeyCode::J),
+    ("k", VirtualKeyCode::K),
+    ("l", VirtualKeyCode::L),
+    ("m", VirtualKeyCode::M),
+    ("n", VirtualKeyCode::N),
+    ("o", VirtualKeyCode::O),
+    ("p", VirtualKeyCode::P),
+    ("q", VirtualKeyCode::Q),
+    ("r", VirtualKeyCode::R),
+    ("s", VirtualKeyCode::S),
+    ("t", VirtualKeyCode::T),
+    ("u", VirtualKeyCode::U),
+    ("v", VirtualKeyCode::V),
+    ("w", VirtualKeyCode::W),
+    ("x", VirtualKeyCode::X),
+    ("y", VirtualKeyCode::Y),
+    ("z", VirtualKeyCode::Z),
+    ("0", VirtualKeyCode::Key0),
+    ("1", VirtualKeyCode::Key1),
+    ("2", VirtualKeyCode::Key2),
+    ("3", VirtualKeyCode::Key3),
+    ("4", VirtualKeyCode::Key4),
+    ("5", VirtualKeyCode::Key5),
+    ("6", VirtualKeyCode::Key6),
+    ("7", VirtualKeyCode::Key7),
+    ("8", VirtualKeyCode::Key8),
+    ("9", VirtualKeyCode::Key9),
+    ("f1", VirtualKeyCode::F1),
+    ("f2", VirtualKeyCode::F2),
+    ("f3", VirtualKeyCode::F3),
+    ("f4", VirtualKeyCode::F4),
+    ("f5", VirtualKeyCode::F5),
+    ("f6", VirtualKeyCode::F6),
+    ("f7", VirtualKeyCode::F7),
+    ("f8", VirtualKeyCode::F8),
+    ("f9", VirtualKeyCode::F9),
+    ("f10", VirtualKeyCode::F10),
+    ("f11", VirtualKeyCode::F11),
+    ("f12", VirtualKeyCode::F12),
+    ("space", VirtualKeyCode::Space),
+    ("enter", VirtualKeyCode::Return),
+    ("backspace", VirtualKeyCode::Back),
+    ("tab", VirtualKeyCode::Tab),
+    ("escape", VirtualKeyCode::Escape),
+    ("delete", VirtualKeyCode::Delete),
+    ("insert", VirtualKeyCode::Insert),
+    ("home", VirtualKeyCode::Home),
+    ("end", VirtualKeyCode::End),
+    ("page-up", VirtualKeyCode::PageUp),
+    ("page-down", VirtualKeyCode::PageDown),
+    ("up", VirtualKeyCode::Up),
+    ("down", VirtualKeyCode::Down),
+    ("left", VirtualKeyCode::Left),
+    ("right", VirtualKeyCode::Right),
+    ("period", VirtualKeyCode::Period),
+    ("comma", VirtualKeyCode::Comma),
+    ("minus", VirtualKeyCode::Minus),
+    ("equals", VirtualKeyCode::Equals),
+    ("plus", VirtualKeyCode::Plus),
+    ("slash", VirtualKeyCode::Slash),
+    ("semicolon", VirtualKeyCode::Semicolon),
+    ("left-bracket", VirtualKeyCode::LBracket),
+    ("right-bracket", VirtualKeyCode::RBracket),
+    ("numpad-add", VirtualKeyCode::NumpadAdd),
+    ("numpad-subtract", VirtualKeyCode::NumpadSubtract),
+];
+
+/// The actions triggered by the buttons of a gamepad
+const GAMEPAD_BINDINGS: [(Button, Action); 9] = [
+    (Button::South, Action::Spawn),
+    (Button::East, Action::Reset),
+    (Button::West, Action::Delete),
+    (Button::North, Action::Trail),
+    (Button::Start, Action::Pause),
+    (Button::RightTrigger, Action::SpeedUp),
+    (Button::RightTrigger2, Action::SpeedUp),
+    (Button::LeftTrigger, Action::SlowDown),
+    (Button::LeftTrigger2, Action::SlowDown),
+];
+
+/// Returns the action triggered by a button of a gamepad, if any
+pub fn gamepad_action(button: Button) -> Option<Action> {
+    GAMEPAD_BINDINGS
+        .iter()
+        .find(|(b, _)| *b == button)
+        .map(|&(_, action)| action)
+}
+
+/// Returns the key called `name` in the configuration file
+pub fn parse_key(name: &str) -> Option<VirtualKeyCode> {
+    KEY_NAMES
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|&(_, key)| key)
+}
+
+/// Which action each key triggers, a single action can have several keys
+#[derive(Clone)]
+pub struct Keybindings {
+    bindings: Vec<(VirtualKeyCode, Action)>,
+}
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        Self {
+            bindings: DEFAULT_BINDINGS.to_vec(),
+        }
+    }
+}
+
+impl Keybindings {
+    /// Make `key` the only key triggering `action`, the key is unbound from any other action
+    pub fn bind(&mut self, action: Action, key: VirtualKeyCode) {
+        self.bindings.retain(|&(k, a)| k != key && a != action);
+        self.bindings.push((key, action));
+    }
+
+    /// Returns the action triggered by `key`, if any
+    pub fn action(&self, key: VirtualKeyCode) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|&(_, action)| action)
+    }
+}
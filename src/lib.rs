@@ -0,0 +1,14 @@
+pub mod batch;
+pub mod color;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+pub mod integrator;
+pub mod notebook;
+pub mod pendulum;
+pub mod pendulums;
+#[cfg(feature = "python")]
+mod python;
+pub mod simplify;
+pub mod simulation;
+#[cfg(feature = "window")]
+pub mod sink;
@@ -0,0 +1,116 @@
+use double_pendulum::pendulum::{wrap_angle, Rods};
+use ggez::GameResult;
+
+/// The distance between the pendulum and its shadow after each renormalization
+const SEPARATION: f32 = 1e-5;
+/// The simulated time between two renormalizations of the separation, in seconds
+const RENORMALIZE_INTERVAL: f64 = 0.5;
+/// The simulated time between two estimates printed to stdout, in seconds
+const PRINT_INTERVAL: f64 = 10.0;
+
+/// An estimate of the largest Lyapunov exponent of a pendulum, with the method of Benettin et al.
+///
+/// A shadow copy of the pendulum starts a tiny distance away and is simulated alongside it.
+/// The separation is measured and scaled back to its initial size at regular intervals,
+/// and the exponent is the average growth rate of the logarithm of the separation.
+/// The distance is measured in the space of the angles and the angular velocities
+pub struct Lyapunov {
+    /// The index of the studied pendulum
+    index: usize,
+    /// The perturbed copy of the studied pendulum
+    shadow: Rods,
+    /// The sum of the logarithms of the growth of the separation over each interval
+    sum: f64,
+    /// The simulated time since the estimation started, in seconds
+    elapsed: f64,
+    /// The simulated time since the last renormalization, in seconds
+    since_renormalization: f64,
+    /// The number of estimates printed so far
+    printed: u32,
+}
+
+impl Lyapunov {
+    /// Start estimating the exponent of `pendulum`, which is at `index` in the list of pendulums
+    pub fn new(index: usize, pendulum: &Rods) -> Self {
+        let mut shadow = *pendulum;
+        let [theta1, theta2] = shadow.angles();
+        shadow.set_angles([theta1 + SEPARATION, theta2]);
+        Self {
+            index,
+            shadow,
+            sum: 0.0,
+            elapsed: 0.0,
+            since_renormalization: 0.0,
+            printed: 0,
+        }
+    }
+
+    /// Returns the current estimate, in units per simulated second, once a first interval is over
+    pub fn exponent(&self) -> Option<f64> {
+        let time = self.elapsed - self.since_renormalization;
+        (time > 0.0).then(|| self.sum / time)
+    }
+
+    /// Advance the shadow like `pendulum` was just advanced, `pendulum` being at `index` in the list of pendulums
+    ///
+    /// The estimation starts over if another pendulum is studied
+    pub fn update(
+        &mut self,
+        index: usize,
+        pendulum: &Rods,
+        dt: f32,
+        wind: f32,
+        time: f32,
+    ) -> GameResult {
+        if index != self.index {
+            *self = Self::new(index, pendulum);
+            return Ok(());
+        }
+
+        self.shadow.forward(dt, wind, time);
+        self.elapsed += dt as f64;
+        self.since_renormalization += dt as f64;
+        if self.since_renormalization < RENORMALIZE_INTERVAL {
+            return Ok(());
+        }
+
+        // Move the shadow back along the direction of the separation
+        let reference = state(pendulum);
+        let mut delta = state(&self.shadow);
+        for (d, r) in delta.iter_mut().zip(reference) {
+            *d -= r;
+        }
+        delta[0] = wrap_angle(delta[0]);
+        delta[1] = wrap_angle(delta[1]);
+        let distance = delta.iter().map(|d| d * d).sum::<f32>().sqrt();
+        if distance > 0.0 {
+            self.sum += (distance / SEPARATION).ln() as f64;
+            let scale = SEPARATION / distance;
+            let [theta1, theta2, omega1, omega2] = reference;
+            self.shadow
+                .set_angles([theta1 + delta[0] * scale, theta2 + delta[1] * scale]);
+            self.shadow
+                .set_angular_velocities([omega1 + delta[2] * scale, omega2 + delta[3] * scale]);
+        }
+        self.since_renormalization = 0.0;
+
+        if self.elapsed >= PRINT_INTERVAL * (self.printed + 1) as f64 {
+            self.printed += 1;
+            if let Some(exponent) = self.exponent() {
+                println!(
+                    "Largest Lyapunov exponent of pendulum {} after {:.0} s: {exponent:.4} per second",
+                    self.index + 1,
+                    self.elapsed,
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the angles and the angular velocities of `pendulum`, as `[θ1, θ2, ω1, ω2]`
+fn state(pendulum: &Rods) -> [f32; 4] {
+    let [theta1, theta2] = pendulum.angles();
+    let [omega1, omega2] = pendulum.angular_velocities();
+    [theta1, theta2, omega1, omega2]
+}
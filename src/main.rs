@@ -1,45 +1,116 @@
+mod animation;
+mod api;
+mod automation;
+mod bloom;
+mod calibrate;
+mod camera;
+mod chart;
+mod config;
+mod energy;
+mod export;
+mod headless;
+mod heatmap;
+mod history;
+mod hud;
+mod keybindings;
+mod lyapunov;
 mod mainstate;
-mod pendulum;
+mod midi;
+mod orientation;
+mod osc;
+mod overlay;
+mod panel;
+mod phase;
+mod poincare;
+mod postprocess;
+mod preset;
+mod profiler;
+mod ramp;
+mod randomize;
+mod recorder;
+mod reload;
+mod render;
+mod replay;
+mod save;
+mod scenario;
+mod screenshot;
+mod script;
+mod seekbar;
+mod spectrum;
+mod stress;
+mod svg;
+mod synth;
+mod trails;
+mod trajectory;
+mod video;
+mod websocket;
+mod wind;
+mod wizard;
+mod world;
 
+use config::Config;
 use ggez::conf::{WindowMode, WindowSetup};
 use ggez::event;
-use ggez::GameResult;
+use ggez::winit::dpi::PhysicalPosition;
+use ggez::{Context, GameResult};
 use mainstate::MainState;
 use std::env;
+use std::path::Path;
 
-/// The width and the height of the screen at startup
-const INITIAL_SCREEN_SIZE: (f32, f32) = (400.0, 400.0);
-
-/// A little struct that helps to parse the command line arguments
-struct Config {
-    size: usize,
-    show_trail: bool,
-}
-
-impl Config {
-    pub fn new(mut args: env::Args) -> Self {
-        args.next();
-
-        let size = args.next().unwrap_or(String::new()).parse().unwrap_or(1);
-        let show_trail = args.next().unwrap_or(String::new()) == "true";
+fn main() -> GameResult {
+    let config = Config::new(env::args());
+    if let Some(tolerance) = config.calibrate {
+        return calibrate::run(tolerance, &config.path);
+    }
+    let (width, height) = config.window_size;
 
-        Self { size, show_trail }
+    if config.headless {
+        let mut state = MainState::new(&config, [width / 2.0, height / 2.0])?;
+        let output = Path::new(&config.output);
+        return state.run_headless(config.steps, output, config.fractal);
     }
-}
 
-fn main() -> GameResult {
-    let window_setup = WindowSetup::default().title("Double Pendulum");
+    let window_setup = WindowSetup::default()
+        .title("Double Pendulum")
+        .vsync(config.vsync);
     let window_mode = WindowMode::default()
-        .dimensions(INITIAL_SCREEN_SIZE.0, INITIAL_SCREEN_SIZE.1)
+        .dimensions(width, height)
         .min_dimensions(200.0, 200.0)
-        .resizable(true);
+        .resizable(true)
+        .maximized(config.maximized);
     let cb = ggez::ContextBuilder::new("double_pendulum", "kugiyasan")
         .window_setup(window_setup)
         .window_mode(window_mode);
     let (ctx, event_loop) = cb.build()?;
+    if let Some(monitor) = config.monitor {
+        move_to_monitor(&ctx, monitor, config.maximized);
+    }
 
-    let config = Config::new(env::args());
-    let center = [INITIAL_SCREEN_SIZE.0 / 2.0, INITIAL_SCREEN_SIZE.1 / 2.0];
-    let state = MainState::new(config.size, config.show_trail, center)?;
+    // The window manager can give the window another size than the one asked for
+    let (width, height) = ctx.gfx.drawable_size();
+    let state = MainState::new(&config, [width / 2.0, height / 2.0])?;
     event::run(ctx, event_loop, state)
 }
+
+/// Center the window on the monitor with the index `monitor`, and maximize it there if `maximized`
+///
+/// The window stays where the system put it if there isn't such a monitor
+fn move_to_monitor(ctx: &Context, monitor: usize, maximized: bool) {
+    let window = ctx.gfx.window();
+    let Some(handle) = window.available_monitors().nth(monitor) else {
+        let count = window.available_monitors().count();
+        eprintln!(
+            "There is no monitor {monitor}, only {count} from 0, the window stays where it is"
+        );
+        return;
+    };
+    // A maximized window can't be moved, it is maximized again on the new monitor
+    window.set_maximized(false);
+    let (position, size) = (handle.position(), handle.size());
+    let window_size = window.outer_size();
+    window.set_outer_position(PhysicalPosition::new(
+        position.x + (size.width as i32 - window_size.width as i32).max(0) / 2,
+        position.y + (size.height as i32 - window_size.height as i32).max(0) / 2,
+    ));
+    window.set_maximized(maximized);
+}
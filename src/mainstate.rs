@@ -1,79 +1,2183 @@
-use crate::pendulum::DoublePendulum;
-use ggez::event::EventHandler;
-use ggez::graphics::{self, Color, DrawMode, Mesh};
-use ggez::input::keyboard::KeyInput;
+use crate::api::Command;
+use crate::automation::AutomationSystem;
+use crate::camera::CameraSystem;
+use crate::config::{Config, HiddenPolicy, PhysicsOverrides, ScenePendulum};
+use crate::export::ExportSystem;
+use crate::headless;
+use crate::heatmap::{self, Heatmap};
+use crate::history::Snapshot;
+use crate::hud::{HudSystem, HudView};
+use crate::keybindings::{self, Action, Keybindings};
+use crate::overlay::{self, Followed, Overlays};
+use crate::panel::{Page, PanelInput, Setting, Slider, Widget};
+use crate::postprocess::PostProcessSystem;
+use crate::preset::PRESETS;
+use crate::ramp::{Parameter, Ramp};
+use crate::randomize::{self, Constraints};
+use crate::recorder::timestamp;
+use crate::reload::ConfigWatcher;
+use crate::render::{self, Renderer, Style};
+use crate::replay::{self, Event, Keyframe, Replay, ReplaySystem};
+use crate::save::{json_error, SavedState, SAVES_DIR};
+use crate::scenario::{MenuAction, Scenario, ScenarioMenu, Stage, SCENARIOS};
+use crate::stress::StressTest;
+use crate::svg::{Svg, DRAWINGS_DIR};
+use crate::synth::SoundSystem;
+use crate::trails::TrailSystem;
+use crate::wind::Wind;
+use crate::wizard::{Wizard, WizardAction};
+use crate::world::{DrawOptions, Layout, PendulumSystem};
+use double_pendulum::color::{self, Palette};
+use double_pendulum::gpu::GpuPhysics;
+use double_pendulum::integrator::IntegratorKind;
+use double_pendulum::pendulum::{wrap_angle, DoublePendulum, InitialConditions, Physics};
+use double_pendulum::pendulums::Pendulums;
+use double_pendulum::sink::{Metadata, Sample};
+use ggez::event::{Axis, Button, EventHandler, GamepadId};
+use ggez::graphics::{self, Canvas, Color, DrawMode};
+use ggez::input::keyboard::{KeyInput, KeyMods};
+use ggez::input::mouse::MouseButton;
 use ggez::winit::event::VirtualKeyCode;
 use ggez::Context;
-use ggez::GameResult;
+use ggez::{GameError, GameResult};
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
+use serde_json::json;
+use std::cmp::Ordering;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
-/// This value controls the number of physics updates per second
-const DESIRED_FPS: u32 = 240;
+/// The maximum number of physics updates per frame when catching up after the window was hidden
+const MAX_CATCH_UP_TICKS: u32 = 16;
+/// The simulation speeds cycled through with the speed keys
+const SPEEDS: [f32; 9] = [0.1, 0.25, 0.5, 1.0, 1.5, 2.0, 3.0, 5.0, 10.0];
+/// The longest time spent on the physics updates of a single frame, the updates that don't fit are dropped
+const FRAME_BUDGET: Duration = Duration::from_millis(12);
+/// The largest number of physics updates merged into a single one with adaptive substeps or on the graphics card
+const MAX_STEP_SCALE: u32 = 8;
+/// How fast the displayed ratio between the simulated and the requested time follows its actual value
+const RATIO_SMOOTHING: f32 = 0.05;
+/// The time scale applied to the physics in slow motion
+const SLOW_MOTION_SCALE: f32 = 0.1;
+/// The number of physics updates done when stepping with Shift held
+const SHIFT_STEP_TICKS: u32 = 10;
+/// The largest gravity that can be picked with the control panel
+const MAX_PANEL_GRAVITY: f32 = 5.0;
+/// The largest damping that can be picked with the control panel
+const MAX_PANEL_DAMPING: f32 = 0.05;
+/// The longest trail that can be picked with the control panel, the shortest one stands for unlimited trails
+const MAX_PANEL_TRAIL_LENGTH: usize = 1000;
+/// How much the volume changes for each key press
+const VOLUME_STEP: f32 = 0.1;
+/// The largest mass of a circle that can be picked with the control panel
+const MAX_PANEL_MASS: f32 = 10.0;
+/// The longest rod that can be picked with the control panel, in pixels
+const MAX_PANEL_LENGTH: f32 = 300.0;
+/// How much the mass of a circle changes with each key press or wheel notch
+const MASS_STEP: f32 = 0.25;
+/// The smallest mass a circle can be given while editing
+const MIN_MASS: f32 = 0.5;
+/// How much the length of a rod changes with each key press or wheel notch, in pixels
+const LENGTH_STEP: f32 = 5.0;
+/// The shortest length a rod can be given while editing, in pixels
+const MIN_LENGTH: f32 = 10.0;
+/// The stick positions closer to the center than this are ignored, since sticks rarely rest at 0
+const STICK_DEAD_ZONE: f32 = 0.15;
+/// The difference in radians between the first angles of two consecutive clones
+const CLONE_OFFSET: f32 = 1e-6;
+/// The color of the pendulums compared with each integrator, in the order of `IntegratorKind::ALL`
+const INTEGRATOR_COLORS: [color::Color; 4] = [
+    color::Color::new(0.3, 0.9, 0.3, 1.0),
+    color::Color::new(0.95, 0.3, 0.3, 1.0),
+    color::Color::new(0.3, 0.5, 1.0, 1.0),
+    color::Color::new(0.95, 0.85, 0.2, 1.0),
+];
+
+/// A circle of a pendulum held with the mouse
+struct Drag {
+    /// The index of the pendulum in `PendulumSystem::pendulums`
+    index: usize,
+    /// 0 for the first circle, 1 for the second one
+    bob: usize,
+    /// The position of the mouse, relative to the origin of the pendulums
+    target: [f32; 2],
+}
 
 pub struct MainState {
-    /// A vector of every double pendulum on the screen
-    pendulums: Vec<DoublePendulum>,
-    /// Stores whether the trail of each pendulum should be drawn or not
-    ///
-    /// Note that the trail is still updated at each frame
-    show_trail: bool,
-    /// The coordinates of the center of the screen
-    center: [f32; 2],
+    /// Every double pendulum on the screen with its scene
+    world: PendulumSystem,
+    /// Where the scenes are in the window
+    layout: Layout,
+    /// How the trails of the pendulums are drawn
+    trails: TrailSystem,
+    /// The zoom and the pan applied to the scenes
+    camera: CameraSystem,
+    /// How the frames pile up, blur and glow before they are shown
+    post: PostProcessSystem,
+    /// The recorded or replayed inputs, and the history to rewind
+    replay: ReplaySystem,
+    /// The export sinks receiving the simulation data
+    exports: ExportSystem,
+    /// The sound played from the motion of the selected pendulum, or of the first one
+    sound: SoundSystem,
+    /// The user script and the HTTP API driving the simulation
+    automation: AutomationSystem,
+    /// The state of the simulation written over it, and the control panel
+    hud: HudSystem,
+    /// Spawn a new pendulum every `spawn_interval` seconds, if set
+    spawn_interval: Option<f32>,
+    /// The simulated time elapsed since the last automatic spawn, in seconds
+    spawn_timer: f32,
+    /// The maximum number of pendulums, the oldest ones are evicted when it is exceeded
+    max_pendulums: Option<usize>,
+    /// The number of copies spawned by the clone action
+    clones: usize,
+    /// The simulated time since the start of the simulation, in seconds
+    time: f64,
+    /// The horizontal force field blowing on the pendulums
+    wind: Wind,
+    /// The physical constants given to every new pendulum
+    physics: Physics,
+    /// Every pendulum created at startup and after a reset
+    initial: Vec<ScenePendulum>,
+    /// The index of the current preset in `PRESETS`, if any
+    preset: Option<usize>,
+    /// The seed of `rng`, stored in the metadata of the exports
+    seed: u64,
+    /// The random number generator shared by every spawn, so a run can be reproduced with its seed
+    ///
+    /// It is the generator behind `StdRng`, which can be saved with the state
+    rng: ChaCha12Rng,
+    /// The parameters changing over time with the simulated time they started at,
+    /// removed once they reach their final value
+    ramps: Vec<(f64, Ramp)>,
+    /// A copy of the pendulums taken at some point, to compare it with the live simulation
+    snapshot: Option<Pendulums>,
+    /// Whether the snapshot is drawn side-by-side with the live simulation
+    show_snapshot: bool,
+    /// The color of the background
+    background: Color,
+    /// The colors given to new pendulums
+    palette: Palette,
+    /// Whether an arrow shows the velocity of each circle
+    show_velocities: bool,
+    /// The stress test spawning pendulums until the frames get too slow, if it is running
+    stress_test: Option<StressTest>,
+    /// The analyses of the selected pendulum, or of the first one, that are turned on
+    overlays: Overlays,
+    /// The places visited by the tips of the pendulums since the heatmap was turned on, if it is on
+    heatmap: Option<Heatmap>,
+    /// Whether everything is drawn in white on black, with patterns instead of colors
+    monochrome: bool,
+    /// Whether the physics updates are frozen
+    paused: bool,
+    /// The overlay used to build a new pendulum step by step, if it is open
+    wizard: Option<Wizard>,
+    /// The running scenario with its index in `SCENARIOS`, if any
+    scenario: Option<(usize, Box<dyn Scenario>)>,
+    /// The list of the scenarios, if it is open
+    scenario_menu: Option<ScenarioMenu>,
+    /// Tells when the configuration file changes, so it is applied without restarting
+    config_watcher: Option<ConfigWatcher>,
+    /// The compute shader advancing the pendulums instead of the CPU, if it is used
+    gpu: Option<GpuPhysics>,
+    /// How many physics updates are done for each tick of the update timer
+    speed: f32,
+    /// The fraction of a physics update left over from the previous ticks when the speed isn't whole
+    pending_steps: f32,
+    /// The number of physics updates per second
+    physics_hz: u32,
+    /// The numerical method advancing the new pendulums
+    integrator: IntegratorKind,
+    /// The shortest time between two frames, if the frames are capped
+    frame_period: Option<Duration>,
+    /// When the previous frame started, to wait for the end of `frame_period`
+    frame_start: Instant,
+    /// Whether the duration of each physics update is scaled down by `SLOW_MOTION_SCALE`
+    slow_motion: bool,
+    /// The circle being dragged with the mouse, if any
+    drag: Option<Drag>,
+    /// The index of the pendulum shown in the inspector panel, if any
+    selected: Option<usize>,
+    /// Which action each key triggers
+    keybindings: Keybindings,
+    /// The bounds of the parameters picked when the selected pendulum is randomized
+    constraints: Constraints,
+    /// The position of the left stick of the gamepad, with y pointing down
+    stick: [f32; 2],
+    /// What the simulation does while the window is hidden
+    on_hidden: HiddenPolicy,
+    /// Whether the window is currently minimized
+    hidden: bool,
+    /// The physics updates that are late and still have to be done to catch up
+    backlog: u32,
+    /// Whether several physics updates are merged into longer ones when they don't fit in the frame budget
+    adaptive_substeps: bool,
+    /// How many physics updates each update covers, always 1 without adaptive substeps
+    step_scale: u32,
+    /// The smoothed fraction of the requested physics updates that were done
+    realtime_ratio: f32,
 }
 
 impl MainState {
-    pub fn new(size: usize, show_trail: bool, center: [f32; 2]) -> GameResult<Self> {
-        let mut pendulums = Vec::with_capacity(size);
-        for _ in 0..size {
-            pendulums.push(DoublePendulum::new(center[1]));
+    pub fn new(config: &Config, center: [f32; 2]) -> GameResult<Self> {
+        let seed = config.seed.unwrap_or_else(rand::random);
+        println!("Seed: {seed}");
+        let mut rng = ChaCha12Rng::seed_from_u64(seed);
+
+        let mut s = Self {
+            world: PendulumSystem::with_capacity(config.size),
+            layout: Layout {
+                center,
+                reference_center: center,
+                anchor: config.anchor,
+                scenes: config.scenes,
+            },
+            trails: TrailSystem::new(config),
+            camera: CameraSystem::default(),
+            post: PostProcessSystem::new(config),
+            replay: ReplaySystem::new(),
+            exports: ExportSystem::new(config.export_settings.clone()),
+            sound: SoundSystem::new(config.volume),
+            automation: AutomationSystem::new(config)?,
+            hud: HudSystem::new(config),
+            spawn_interval: config.spawn_interval,
+            spawn_timer: 0.0,
+            max_pendulums: config.max_pendulums,
+            clones: config.clones,
+            time: 0.0,
+            wind: Wind::new(&mut rng, config.wind, config.gust),
+            physics: config.physics,
+            initial: if config.pendulums.is_empty() {
+                let pendulum = ScenePendulum {
+                    scene: None,
+                    initial: config.initial,
+                    physics: PhysicsOverrides::default(),
+                };
+                vec![pendulum; config.size]
+            } else {
+                config.pendulums.clone()
+            },
+            preset: None,
+            seed,
+            rng,
+            ramps: Vec::new(),
+            snapshot: None,
+            show_snapshot: false,
+            background: config.background.into(),
+            palette: config.palette,
+            heatmap: None,
+            show_velocities: false,
+            stress_test: config.stress_test.then(StressTest::new),
+            overlays: Overlays::new(),
+            monochrome: config.monochrome,
+            paused: false,
+            wizard: None,
+            scenario: None,
+            scenario_menu: None,
+            // A headless run is over before anyone could edit the file
+            config_watcher: if config.headless {
+                None
+            } else {
+                ConfigWatcher::new(&config.path)
+                    .map_err(|e| eprintln!("The configuration file won't be reloaded: {e}"))
+                    .ok()
+            },
+            gpu: None,
+            speed: config.speed,
+            pending_steps: 0.0,
+            physics_hz: config.physics_hz,
+            integrator: config.integrator,
+            frame_period: config
+                .max_fps
+                .map(|fps| Duration::from_secs_f32(1.0 / fps as f32)),
+            frame_start: Instant::now(),
+            slow_motion: false,
+            drag: None,
+            selected: None,
+            keybindings: config.keybindings.clone(),
+            constraints: config.constraints.clone(),
+            stick: [0.0, 0.0],
+            on_hidden: config.on_hidden,
+            hidden: false,
+            backlog: 0,
+            adaptive_substeps: config.adaptive_substeps,
+            step_scale: 1,
+            realtime_ratio: 1.0,
+        };
+        match config.preset {
+            Some(index) => s.load_preset(index),
+            None => s.reset(),
+        }
+        if config.sound {
+            s.sound.toggle();
+        }
+        if config.gpu {
+            match GpuPhysics::new() {
+                Ok(gpu) => s.gpu = Some(gpu),
+                Err(e) => eprintln!("Couldn't use the GPU, the physics stay on the CPU: {e}"),
+            }
+        }
+        for spec in &config.exports {
+            s.exports.start(spec, &s.metadata())?;
+        }
+        if let Some(path) = &config.load {
+            // A state that can't be loaded leaves the simulation as it was configured
+            match SavedState::load(Path::new(path)) {
+                Ok(state) => s.restore(state),
+                Err(e) => eprintln!("Couldn't load the state from {path}: {e}"),
+            }
+        }
+        if let Some(path) = &config.replay {
+            match Replay::load(Path::new(path)) {
+                Ok(replay) => s.play(replay),
+                Err(e) => eprintln!("Couldn't load the replay from {path}: {e}"),
+            }
+        }
+        // A replay starts its own ramps
+        if !s.is_playing() {
+            for &ramp in &config.ramps {
+                s.start_ramp(ramp);
+            }
+        }
+        if let Some(time) = config.seek.map(f64::from) {
+            if s.is_playing() {
+                s.replay.pending_seek = Some(time);
+            } else {
+                s.fast_forward(time)?;
+            }
+        }
+        if let (None, Some(path)) = (&config.replay, &config.record_replay) {
+            s.start_replay(PathBuf::from(path));
+        }
+        Ok(s)
+    }
+
+    /// Run `steps` physics updates as fast as possible without any window, then write the results into `output`
+    ///
+    /// The final state, the statistics of every pendulum and the heatmap of their tips are always written,
+    /// and the flip fractal of the first pendulum too if its resolution is given.
+    /// The export sinks receive every physics update but no frame.
+    pub fn run_headless(&mut self, steps: u64, output: &Path, fractal: Option<u32>) -> GameResult {
+        fs::create_dir_all(output)?;
+        let initial_energies: Vec<f32> = (self.world.pendulums.iter())
+            .map(|p| p.kinetic_energy() + p.potential_energy())
+            .collect();
+        let extent = self.layout.reference_center[0].max(self.layout.reference_center[1]);
+        self.heatmap = Some(Heatmap::new(extent));
+
+        let start = Instant::now();
+        for _ in 0..steps {
+            self.tick(1)?;
+        }
+        let elapsed = start.elapsed().as_secs_f32();
+        println!(
+            "Simulated {:.1} s in {elapsed:.2} s ({:.0} updates per second)",
+            self.time,
+            steps as f32 / elapsed.max(f32::EPSILON),
+        );
+        self.exports.finish()?;
+
+        let metadata = self.metadata();
+        self.saved_state().save(&output.join("state.json"))?;
+        let path = output.join("statistics.csv");
+        headless::write_statistics(&path, &self.world.pendulums, &initial_energies, &metadata)?;
+        let heatmap = self.heatmap.take().expect("the heatmap should exist");
+        let size = heatmap::RESOLUTION as u32;
+        let path = output.join("heatmap.png");
+        headless::write_png(&path, size, size, &heatmap.pixels(), &metadata)?;
+
+        if let Some(resolution) = fractal {
+            let initial = self.initial.first().map(|p| p.initial).unwrap_or_default();
+            let length = self.layout.scene_rect(0).h / 2.0 / self.layout.scale();
+            let dt = 1.0 / self.physics_hz as f32;
+            let pixels = headless::flip_fractal(
+                self.seed,
+                length,
+                self.physics,
+                &initial,
+                resolution,
+                steps,
+                dt,
+            )?;
+            let path = output.join("fractal.png");
+            headless::write_png(&path, resolution, resolution, &pixels, &metadata)?;
+        }
+        println!("Saved the results to {}", output.display());
+        Ok(())
+    }
+
+    /// Advance the whole simulation by one physics update, lasting `scale` times the usual duration
+    fn tick(&mut self, scale: u32) -> GameResult {
+        self.update_ramps();
+        let time_scale = if self.slow_motion {
+            SLOW_MOTION_SCALE
+        } else {
+            1.0
+        };
+        let dt = time_scale * scale as f32 / self.physics_hz as f32;
+        let keyframe = self.keyframe(scale);
+        self.replay.update(scale, keyframe);
+        let time = self.time();
+        let wind = self.wind.at(time);
+        // The dragged pendulum follows the mouse instead of the physics
+        let held = self.drag.as_ref().map(|drag| drag.index);
+        // The CPU merges the updates into a longer step, while the graphics card does all of them
+        // in a single submission, which costs about the same as one since it waits for its results
+        let (step, substeps) = match self.gpu {
+            Some(_) => (dt / scale as f32, scale),
+            None => (dt, 1),
+        };
+        let start = Instant::now();
+        self.world
+            .update(self.gpu.as_mut(), step, substeps, wind, time, held);
+        self.hud.profiler.add_physics(start.elapsed());
+        let start = Instant::now();
+        self.trails.update(&mut self.world.pendulums);
+        self.hud.profiler.add_trails(start.elapsed());
+        let spawned = (self.automation).update(&mut self.world.pendulums, time + dt, dt);
+        for initial in spawned {
+            self.spawn_with(&initial, self.world.emptiest_scene(&self.layout));
+        }
+        if let Some((_, scenario)) = &mut self.scenario {
+            let mut stage = Stage {
+                pendulums: &mut self.world.pendulums,
+                time: time + dt,
+                dt,
+                restart: None,
+            };
+            scenario.update(&mut stage);
+            if let Some(initial) = stage.restart {
+                self.load_conditions(initial);
+            }
+        }
+        if let Some(heatmap) = &mut self.heatmap {
+            for p in self.world.pendulums.iter() {
+                heatmap.add(p.bobs()[1]);
+            }
+        }
+        let index = self.selected.unwrap_or(0);
+        if let Some(pendulum) = self.world.pendulums.get(index) {
+            self.overlays.update(&Followed {
+                index,
+                pendulum: &pendulum,
+                dt,
+                wind,
+                time,
+            })?;
+        }
+        self.update_spawn_timer(dt);
+        self.time += dt as f64;
+        self.replay.history.record(dt, || Snapshot {
+            time: self.time,
+            pendulums: self.world.pendulums.clone(),
+            pendulum_scenes: self.world.pendulum_scenes.clone(),
+            spawn_timer: self.spawn_timer,
+        });
+
+        self.exports.update(&Sample {
+            time: self.time(),
+            pendulums: &self.world.pendulums,
+        })
+    }
+
+    /// Do `steps` physics updates, as long as they fit in `FRAME_BUDGET`
+    ///
+    /// The updates that don't fit are dropped, so a slow machine runs the simulation slower than real time
+    /// instead of freezing. With adaptive substeps, several updates are merged into longer ones first,
+    /// which is less accurate but keeps up with real time.
+    fn run_steps(&mut self, ctx: &mut Context, steps: u32) -> GameResult {
+        if steps == 0 {
+            return Ok(());
+        }
+
+        let start = Instant::now();
+        let mut done = 0;
+        while done < steps && start.elapsed() < FRAME_BUDGET {
+            // A replay uses the scales of the recorded run, so the updates are the same whatever the frame rate
+            self.play_due_events(ctx)?;
+            let scale = match self.replay.scale() {
+                Some(scale) => scale,
+                // The graphics card does every update of the frame at once, instead of waiting for each one
+                None if self.gpu.is_some() => MAX_STEP_SCALE.min(steps - done),
+                None => self.step_scale.min(steps - done),
+            };
+            self.tick(scale)?;
+            done += scale;
+        }
+
+        if self.adaptive_substeps {
+            if done < steps {
+                self.step_scale = (self.step_scale * 2).min(MAX_STEP_SCALE);
+            } else if self.step_scale > 1 && start.elapsed() < FRAME_BUDGET / 4 {
+                // Halving the scale doubles the cost, which still fits in the budget
+                self.step_scale /= 2;
+            }
+        }
+
+        let ratio = done as f32 / steps as f32;
+        self.realtime_ratio += (ratio - self.realtime_ratio) * RATIO_SMOOTHING;
+        Ok(())
+    }
+
+    /// Advance the simulation by `ticks` physics updates while it is paused
+    ///
+    /// This bypasses the update timer, so the motion can be examined step by step
+    fn step(&mut self, ticks: u32) -> GameResult {
+        if self.paused {
+            for _ in 0..ticks {
+                self.tick(1)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Change the speed to the next faster (`faster` is true) or slower preset speed
+    fn change_speed(&mut self, faster: bool) {
+        let next = if faster {
+            SPEEDS.iter().find(|&&s| s > self.speed)
+        } else {
+            SPEEDS.iter().rev().find(|&&s| s < self.speed)
+        };
+        if let Some(&speed) = next {
+            self.speed = speed;
+        }
+    }
+
+    /// Returns the widgets of the current page of the control panel, with the current value of each setting
+    fn panel_widgets(&self) -> Vec<Widget> {
+        let slider = |setting, label, min, max, value| {
+            Widget::Slider(Slider {
+                setting,
+                label,
+                min,
+                max,
+                value,
+            })
+        };
+        match self.hud.panel.page() {
+            Page::Simulation => {
+                let trail_length = match self.trails.length() {
+                    0 => "unlimited".to_string(),
+                    length => length.to_string(),
+                };
+                vec![
+                    slider(
+                        Setting::Gravity,
+                        format!("Gravity: {:.2}", self.physics.gravity),
+                        0.0,
+                        MAX_PANEL_GRAVITY,
+                        self.physics.gravity,
+                    ),
+                    slider(
+                        Setting::Damping,
+                        format!("Damping: {:.4}", self.physics.damping),
+                        0.0,
+                        MAX_PANEL_DAMPING,
+                        self.physics.damping,
+                    ),
+                    slider(
+                        Setting::Speed,
+                        format!("Speed: {:.2}x", self.speed),
+                        SPEEDS[0],
+                        SPEEDS[SPEEDS.len() - 1],
+                        self.speed,
+                    ),
+                    slider(
+                        Setting::TrailLength,
+                        format!("Trail length: {trail_length}"),
+                        0.0,
+                        MAX_PANEL_TRAIL_LENGTH as f32,
+                        self.trails.length() as f32,
+                    ),
+                    slider(
+                        Setting::Volume,
+                        format!("Volume: {:.0}%", self.sound.volume() * 100.0),
+                        0.0,
+                        1.0,
+                        self.sound.volume(),
+                    ),
+                    Widget::Buttons,
+                ]
+            }
+            Page::Pendulums => {
+                let Some(index) = self.panel_pendulum() else {
+                    return vec![Widget::Label {
+                        text: "No pendulum".to_string(),
+                        color: None,
+                    }];
+                };
+                let p = self.world.pendulums.at(index);
+                let [m1, m2] = p.masses();
+                let [l1, l2] = p.lengths();
+                let (hue, saturation, brightness) = p.color().to_hsv();
+                let colors = self.world.pendulums.iter();
+                vec![
+                    Widget::Pendulums {
+                        colors: colors.map(|p| render::to_graphics(p.color())).collect(),
+                        selected: Some(index),
+                    },
+                    Widget::Label {
+                        text: format!("Pendulum {}", index + 1),
+                        color: Some(render::to_graphics(p.color())),
+                    },
+                    slider(
+                        Setting::Mass(0),
+                        format!("m1: {m1:.2}"),
+                        MIN_MASS,
+                        MAX_PANEL_MASS,
+                        m1,
+                    ),
+                    slider(
+                        Setting::Mass(1),
+                        format!("m2: {m2:.2}"),
+                        MIN_MASS,
+                        MAX_PANEL_MASS,
+                        m2,
+                    ),
+                    slider(
+                        Setting::Length(0),
+                        format!("l1: {l1:.0}"),
+                        MIN_LENGTH,
+                        MAX_PANEL_LENGTH,
+                        l1,
+                    ),
+                    slider(
+                        Setting::Length(1),
+                        format!("l2: {l2:.0}"),
+                        MIN_LENGTH,
+                        MAX_PANEL_LENGTH,
+                        l2,
+                    ),
+                    slider(Setting::Hue, format!("Hue: {hue:.0}°"), 0.0, 360.0, hue),
+                    slider(
+                        Setting::Saturation,
+                        format!("Saturation: {saturation:.2}"),
+                        0.0,
+                        1.0,
+                        saturation,
+                    ),
+                    slider(
+                        Setting::Brightness,
+                        format!("Brightness: {brightness:.2}"),
+                        0.0,
+                        1.0,
+                        brightness,
+                    ),
+                ]
+            }
+        }
+    }
+
+    /// Returns the index of the pendulum edited with the control panel, the selected one or the first one
+    fn panel_pendulum(&self) -> Option<usize> {
+        self.selected.or(if self.world.pendulums.is_empty() {
+            None
+        } else {
+            Some(0)
+        })
+    }
+
+    /// Do what the user asked for with the control panel
+    fn apply_panel(&mut self, ctx: &mut Context, input: PanelInput) -> GameResult {
+        let (setting, value) = match input {
+            PanelInput::Press(action) => return self.perform(ctx, action, false),
+            PanelInput::Select(index) => {
+                self.apply(Event::Select(index));
+                self.record(Event::Select(index));
+                return Ok(());
+            }
+            PanelInput::Set(setting, value) => (setting, value),
+        };
+        let event = match setting {
+            Setting::Speed => {
+                self.speed = value;
+                return Ok(());
+            }
+            Setting::TrailLength => {
+                self.trails
+                    .set_length(&mut self.world.pendulums, value.round() as usize);
+                return Ok(());
+            }
+            Setting::Volume => {
+                self.sound.set_volume(value);
+                return Ok(());
+            }
+            // The physics changes through a parameter event, so it is replayed the same way
+            Setting::Gravity => Event::Parameter {
+                parameter: Parameter::Gravity,
+                value,
+            },
+            Setting::Damping => Event::Parameter {
+                parameter: Parameter::Damping,
+                value,
+            },
+            Setting::Mass(bob) | Setting::Length(bob) => {
+                let Some(index) = self.panel_pendulum() else {
+                    return Ok(());
+                };
+                let length = matches!(setting, Setting::Length(_));
+                Event::Tune {
+                    index,
+                    bob,
+                    value,
+                    length,
+                }
+            }
+            Setting::Hue | Setting::Saturation | Setting::Brightness => {
+                let Some(index) = self.panel_pendulum() else {
+                    return Ok(());
+                };
+                let old = self.world.pendulums.at(index).color();
+                let (mut hue, mut saturation, mut brightness) = old.to_hsv();
+                match setting {
+                    Setting::Hue => hue = value,
+                    Setting::Saturation => saturation = value,
+                    _ => brightness = value,
+                }
+                let color = color::Color {
+                    a: old.a,
+                    ..color::hsv(hue, saturation, brightness)
+                };
+                Event::Recolor { index, color }
+            }
+        };
+        self.apply(event.clone());
+        self.record(event);
+        Ok(())
+    }
+
+    /// Fast-forward the simulation until it reaches `time` seconds since its start
+    ///
+    /// The simulation runs as fast as possible without drawing anything,
+    /// so it ends up in the exact same state as if it was watched in real time
+    fn fast_forward(&mut self, time: f64) -> GameResult {
+        if time < self.time {
+            eprintln!(
+                "The simulation can't seek back to {time:.2} s, it is already at {:.2} s",
+                self.time,
+            );
+        }
+        while self.time < time {
+            self.tick(1)?;
+        }
+        Ok(())
+    }
+
+    /// Move the replay being played to `time` seconds since its start, forward or backward
+    ///
+    /// The replay starts over from the last keyframe before `time` when seeking backward or when it skips
+    /// a part of the replay, and is then played as fast as possible up to `time`.
+    fn seek(&mut self, ctx: &mut Context, time: f64) -> GameResult {
+        if !self.is_playing() {
+            return self.fast_forward(time);
+        }
+        if let Some(keyframe) = self.replay.rewind_to(time, self.time) {
+            self.restore(keyframe.state);
+            self.ramps = keyframe.ramps;
+        }
+        while self.time < time {
+            self.play_due_events(ctx)?;
+            // A paused replay doesn't go any further until it ends
+            let Some(scale) = self.replay.scale() else {
+                return self.fast_forward(time);
+            };
+            if self.paused {
+                break;
+            }
+            self.tick(scale)?;
+        }
+        Ok(())
+    }
+
+    /// Change a global parameter for the whole simulation, including the existing pendulums
+    fn set_parameter(&mut self, parameter: Parameter, value: f32) {
+        if let Parameter::Wind = parameter {
+            self.wind.set_strength(value);
+            return;
+        }
+
+        let physics = self.world.pendulums.physics_mut();
+        for physics in std::iter::once(&mut self.physics).chain(physics) {
+            match parameter {
+                Parameter::Gravity => physics.gravity = value,
+                Parameter::Damping => physics.damping = value,
+                Parameter::DriveAmplitude => physics.drive_amplitude = value,
+                Parameter::DriveFrequency => physics.drive_frequency = value,
+                Parameter::Wind => unreachable!(),
+            }
+        }
+    }
+
+    /// Start changing a parameter over time with `ramp`
+    ///
+    /// Only the start of the ramp is recorded in the replay, its values follow the simulated time
+    fn start_ramp(&mut self, ramp: Ramp) {
+        let event = Event::Ramp(ramp);
+        self.apply(event.clone());
+        self.record(event);
+    }
+
+    /// Apply the value of every ramp at the current simulated time, and drop the ones that are done
+    fn update_ramps(&mut self) {
+        for i in 0..self.ramps.len() {
+            let (start, ramp) = self.ramps[i];
+            let elapsed = (self.time - start) as f32;
+            self.set_parameter(ramp.parameter, ramp.value_at(elapsed));
+        }
+        let time = self.time;
+        self.ramps
+            .retain(|(start, ramp)| ramp.progress((time - start) as f32) < 1.0);
+    }
+
+    /// Change the mass of a circle of the pendulum at `index` by `steps` times `MASS_STEP`,
+    /// or the length of its rod by `steps` times `LENGTH_STEP` if `length` is true
+    fn edit(&mut self, index: usize, bob: usize, steps: f32, length: bool) {
+        let mut p = self.world.pendulums.at_mut(index);
+        if length {
+            let value = p.lengths()[bob] + steps * LENGTH_STEP;
+            p.set_length(bob, value.max(MIN_LENGTH));
+        } else {
+            let value = p.masses()[bob] + steps * MASS_STEP;
+            p.set_mass(bob, value.max(MIN_MASS));
+        }
+    }
+
+    /// Returns the simulated time in seconds
+    fn time(&self) -> f32 {
+        self.time as f32
+    }
+
+    /// Returns the average divergence between the live pendulums and the snapshot
+    fn snapshot_divergence(&self) -> Option<f32> {
+        let snapshot = self.snapshot.as_ref()?;
+        let count = self.world.pendulums.len().min(snapshot.len());
+        if count == 0 {
+            return None;
+        }
+
+        let total: f32 = (self.world.pendulums.iter().zip(snapshot.iter()))
+            .map(|(live, old)| live.divergence(&old))
+            .sum();
+        Some(total / count as f32)
+    }
+
+    /// Draw the pendulums of every scene, or the comparison with the snapshot, with the heatmap and the ink
+    fn draw_simulation(&mut self, ctx: &mut Context, canvas: &mut Canvas) -> GameResult {
+        // Once the stress test lowers the level of detail, the older pendulums lose their trails
+        // and their velocity arrows
+        let detailed = (self.stress_test.as_ref().and_then(StressTest::detailed))
+            .map(|detailed| self.world.pendulums.len().saturating_sub(detailed));
+        let options = DrawOptions {
+            trails: self.trails.styles(self.monochrome),
+            progress: self.interpolation_progress(ctx),
+            detailed,
+            heatmap: self.heatmap.as_ref(),
+            velocities: self.show_velocities,
+            selected: self.selected,
+            monochrome: self.monochrome,
+        };
+        if let Some(snapshot) = self.snapshot.as_mut().filter(|_| self.show_snapshot) {
+            return self
+                .world
+                .draw_comparison(ctx, canvas, &self.layout, snapshot, &options);
+        }
+        let camera = self.camera.camera();
+        self.camera
+            .draw(canvas, self.layout.window(), |canvas, screen| {
+                self.trails.draw(canvas);
+                self.world
+                    .draw(ctx, canvas, &self.layout, camera, screen, &options)
+            })
+    }
+
+    /// Returns how far the next physics update is, to draw the rods between the last two updates,
+    /// or None if they are drawn as they are
+    ///
+    /// The rods are drawn as they are while the simulation stands still,
+    /// and after pendulums were added or removed since the last update
+    fn interpolation_progress(&self, ctx: &Context) -> Option<f32> {
+        let still = self.paused || (self.hidden && self.on_hidden == HiddenPolicy::Pause);
+        if still || !self.world.can_interpolate() {
+            return None;
+        }
+        // The next physics update is due once the timer and the speed add up to a whole update,
+        // a merged update with adaptive substeps taking as many of them as it covers
+        let remaining = ctx.time.remaining_update_time().as_secs_f32();
+        let pending = self.pending_steps + remaining * self.physics_hz as f32 * self.speed;
+        Some((pending / self.step_scale as f32).min(1.0))
+    }
+
+    /// Replace every pendulum with the pendulums created at startup, in every scene
+    fn reset(&mut self) {
+        self.drag = None;
+        self.selected = None;
+        self.world.clear();
+        for scene in 0..self.layout.scenes {
+            for pendulum in self.initial.clone() {
+                // The pendulums of a missing scene go to the last one
+                if pendulum
+                    .scene
+                    .is_none_or(|s| s.min(self.layout.scenes - 1) == scene)
+                {
+                    self.spawn_overridden(&pendulum.initial, &pendulum.physics, scene);
+                }
+            }
+        }
+    }
+
+    /// Replace every pendulum with the ones of the preset at `index` in `PRESETS`
+    fn load_preset(&mut self, index: usize) {
+        self.preset = Some(index);
+        self.scenario = None;
+        self.load_conditions((PRESETS[index].pendulums)());
+    }
+
+    /// Start the scenario at `index` in `SCENARIOS` from the beginning, with the default physics
+    fn start_scenario(&mut self, index: usize) {
+        let mut scenario = (SCENARIOS[index].create)();
+        self.physics = Physics::default();
+        let initial = scenario.setup(&mut self.physics);
+        self.preset = None;
+        self.scenario = Some((index, scenario));
+        self.load_conditions(initial);
+    }
+
+    /// Replace every pendulum with new ones, which are also the ones created by the next resets
+    fn load_conditions(&mut self, initial: Vec<InitialConditions>) {
+        self.initial = initial
+            .into_iter()
+            .map(|initial| ScenePendulum {
+                scene: None,
+                initial,
+                physics: PhysicsOverrides::default(),
+            })
+            .collect();
+        self.reset();
+    }
+
+    /// Returns the state of the simulation, to resume it later with `restore`
+    fn saved_state(&self) -> SavedState {
+        SavedState {
+            metadata: self.metadata(),
+            seed: self.seed,
+            rng: Some(self.rng.clone()),
+            time: self.time,
+            pendulums: self.world.pendulums.to_vec(),
+            pendulum_scenes: self.world.pendulum_scenes.clone(),
+            scenes: self.layout.scenes,
+            anchor: self.layout.anchor,
+            reference_center: self.layout.reference_center,
+            physics: self.physics,
+            wind: self.wind.strength(),
+            gust: self.wind.gust(),
+            spawn_timer: self.spawn_timer,
+            speed: self.speed,
+            paused: self.paused,
+            slow_motion: self.slow_motion,
+            monochrome: self.monochrome,
+            show_trail: self.trails.show,
+            show_joint_trail: self.trails.show_joint,
+            speed_trail: self.trails.speed,
+            rainbow_trail: self.trails.rainbow,
+            background: self.background,
+            trail_color: self.trails.color,
+            trail_saturation: self.trails.saturation,
+            joint_trail_color: self.trails.joint_color,
+            selected: self.selected,
+        }
+    }
+
+    /// Save the state of the simulation into a new JSON file in `SAVES_DIR`
+    fn save(&self) -> GameResult {
+        fs::create_dir_all(SAVES_DIR)?;
+        let path = PathBuf::from(SAVES_DIR).join(format!("{}.json", timestamp()));
+        self.saved_state().save(&path)?;
+        println!("Saved the state to {}", path.display());
+        Ok(())
+    }
+
+    /// Write the trails of every pendulum as they are shown, and their rods if `rods` is true,
+    /// into an SVG file in `DRAWINGS_DIR`
+    fn export_svg(&mut self, rods: bool) -> GameResult {
+        let background = if self.monochrome {
+            Color::BLACK
+        } else {
+            self.background
+        };
+        let [width, height] = self.layout.center.map(|c| c * 2.0);
+        let mut svg = Svg::new(width, height, background);
+
+        let trails = self.trails.styles(self.monochrome);
+        let scale = self.layout.scale();
+        for index in 0..self.world.pendulums.len() {
+            let anchor = self.layout.origin(self.world.pendulum_scenes[index]);
+            let style = if self.monochrome {
+                Style::Monochrome { index }
+            } else {
+                Style::Color
+            };
+            let mut p = self.world.pendulums.at_mut(index);
+            render::draw_trails(&mut p, &mut svg, anchor, scale, trails)?;
+            if rods {
+                render::draw_rods(&p, &mut svg, anchor, scale, style)?;
+            }
+        }
+        if rods {
+            for scene in 0..self.layout.scenes {
+                svg.circle(
+                    self.layout.origin(scene),
+                    10.0,
+                    DrawMode::fill(),
+                    Color::WHITE,
+                )?;
+            }
+        }
+
+        fs::create_dir_all(DRAWINGS_DIR)?;
+        let path = PathBuf::from(DRAWINGS_DIR).join(format!("{}.svg", timestamp()));
+        svg.save(&path, &self.metadata())?;
+        println!("Saved the drawing to {}", path.display());
+        Ok(())
+    }
+
+    /// Resume the simulation from `state`, the pendulums it contains become the ones created by a reset
+    fn restore(&mut self, state: SavedState) {
+        self.seed = state.seed;
+        self.rng = ChaCha12Rng::seed_from_u64(state.seed);
+        self.wind = Wind::new(&mut self.rng, state.wind, state.gust);
+        // The gusts were the first thing drawn from the generator, the spawns go on where they were saved
+        if let Some(rng) = state.rng {
+            self.rng = rng;
+        }
+        self.time = state.time;
+        self.initial = (state.pendulums.iter().zip(&state.pendulum_scenes))
+            .map(|(p, &scene)| ScenePendulum {
+                scene: Some(scene),
+                initial: p.conditions(),
+                physics: PhysicsOverrides::between(&state.physics, p.physics()),
+            })
+            .collect();
+        self.world.pendulums = state.pendulums.into();
+        self.world.pendulum_scenes = state.pendulum_scenes;
+        self.layout.scenes = state.scenes.max(1);
+        self.layout.anchor = state.anchor;
+        self.layout.reference_center = state.reference_center;
+        self.physics = state.physics;
+        self.spawn_timer = state.spawn_timer;
+        self.speed = state.speed;
+        self.paused = state.paused;
+        self.slow_motion = state.slow_motion;
+        self.monochrome = state.monochrome;
+        self.trails.show = state.show_trail;
+        self.trails.show_joint = state.show_joint_trail;
+        self.trails.speed = state.speed_trail;
+        self.trails.rainbow = state.rainbow_trail;
+        self.background = state.background;
+        self.trails.color = state.trail_color;
+        self.trails.saturation = state.trail_saturation;
+        self.trails.joint_color = state.joint_trail_color;
+        self.selected = state.selected.filter(|&i| i < self.world.pendulums.len());
+        self.preset = None;
+        self.scenario = None;
+        self.drag = None;
+        self.snapshot = None;
+        // The analyses of the previous pendulums start over
+        self.trails.clear_ink();
+        self.post.discard();
+        self.overlays.restart();
+        self.replay.history.clear();
+    }
+
+    /// Go one snapshot back in time, or forward if `back` is false, and pause the simulation there
+    ///
+    /// The simulation resumes from that snapshot, and the newer ones are forgotten
+    fn rewind(&mut self, back: bool) {
+        let current = || Snapshot {
+            time: self.time,
+            pendulums: self.world.pendulums.clone(),
+            pendulum_scenes: self.world.pendulum_scenes.clone(),
+            spawn_timer: self.spawn_timer,
+        };
+        let Some(snapshot) = self.replay.history.rewind(back, current) else {
+            return;
+        };
+        let snapshot = snapshot.clone();
+        self.time = snapshot.time;
+        self.world.pendulums = snapshot.pendulums;
+        self.world.pendulum_scenes = snapshot.pendulum_scenes;
+        self.spawn_timer = snapshot.spawn_timer;
+        self.paused = true;
+        self.drag = None;
+        self.selected = self.selected.filter(|&i| i < self.world.pendulums.len());
+        // The shadow pendulum can't be rewound with the others
+        self.overlays.remove(Action::Lyapunov);
+    }
+
+    /// Start recording the inputs into a replay written to `path` when the window is closed
+    ///
+    /// The simulation is restored from its own state first, so the analyses and the history
+    /// start over like they do when the replay is played
+    fn start_replay(&mut self, path: PathBuf) {
+        let mut state = self.saved_state();
+        let metadata = std::mem::take(&mut state.metadata);
+        self.restore(self.saved_state());
+        let replay = Replay {
+            metadata,
+            state,
+            spawn_interval: self.spawn_interval,
+            max_pendulums: self.max_pendulums,
+            clones: self.clones,
+            constraints: self.constraints.clone(),
+            physics_hz: self.physics_hz,
+            integrator: self.integrator,
+            events: Vec::new(),
+            keyframes: Vec::new(),
+            end_time: None,
+        };
+        self.replay.start_recording(path, replay);
+        // The ramps in progress start over in the replay from where they are
+        for (start, ramp) in std::mem::take(&mut self.ramps) {
+            let elapsed = (self.time - start) as f32;
+            self.start_ramp(Ramp {
+                from: ramp.value_at(elapsed),
+                duration: ramp.duration - elapsed,
+                ..ramp
+            });
+        }
+    }
+
+    /// Returns the keyframe of the replay being recorded if it is due, before a physics update lasting `scale`
+    ///
+    /// There is none while a scenario runs or a pendulum is dragged, since their state isn't saved
+    fn keyframe(&self, scale: u32) -> Option<Keyframe> {
+        if !self.replay.keyframe_due(self.physics_hz) {
+            return None;
+        }
+        if self.scenario.is_some() || self.drag.is_some() {
+            return None;
+        }
+        Some(Keyframe {
+            tick: self.replay.ticks(),
+            scale,
+            // The metadata of the replay is enough
+            state: SavedState {
+                metadata: Metadata::default(),
+                ..self.saved_state()
+            },
+            ramps: self.ramps.clone(),
+        })
+    }
+
+    /// Start playing `replay`, the ramps in progress are dropped since the replay starts its own
+    fn play(&mut self, replay: Replay) {
+        self.restore(replay.state.clone());
+        self.spawn_interval = replay.spawn_interval;
+        self.max_pendulums = replay.max_pendulums;
+        self.clones = replay.clones;
+        self.constraints = replay.constraints.clone();
+        self.physics_hz = replay.physics_hz;
+        self.integrator = replay.integrator;
+        self.ramps.clear();
+        self.replay.start_playing(replay);
+    }
+
+    /// Returns true while a replay is played
+    fn is_playing(&self) -> bool {
+        self.replay.is_playing()
+    }
+
+    /// Add `event` to the replay if it is recorded
+    fn record(&mut self, event: Event) {
+        self.replay.record(event);
+    }
+
+    /// Apply every event of the replay due before the next physics update
+    fn play_due_events(&mut self, ctx: &mut Context) -> GameResult {
+        while let Some(event) = self.replay.next_event() {
+            match event {
+                Event::Action { action, shift } => self.perform(ctx, action, shift)?,
+                event => self.apply(event),
+            }
+        }
+        Ok(())
+    }
+
+    /// Do what an event from the mouse, the wizard, the panel or a ramp asks for
+    ///
+    /// The actions are handled by the caller since they need the context, and the scales by the replay
+    fn apply(&mut self, event: Event) {
+        match event {
+            Event::Scenario(index) => self.start_scenario(index),
+            Event::Select(index) => self.selected = Some(index),
+            Event::SpawnAt { scene, target } => {
+                self.spawn_with(&InitialConditions::default(), scene);
+                if let Some(mut p) = self.world.pendulums.last_mut() {
+                    p.place(target);
+                }
+            }
+            Event::SpawnWith {
+                initial,
+                scene,
+                color,
+            } => {
+                self.spawn_with(&initial, scene);
+                if let Some(mut p) = self.world.pendulums.last_mut() {
+                    p.set_color(color);
+                }
+            }
+            Event::Remove(index) => self.remove(index),
+            Event::Edit {
+                index,
+                bob,
+                steps,
+                length,
+            } => {
+                self.edit(index, bob, steps, length);
+                self.selected = Some(index);
+            }
+            Event::Drag {
+                index,
+                bob,
+                target,
+                dt,
+            } => self.world.pendulums.at_mut(index).drag(bob, target, dt),
+            Event::Tune {
+                index,
+                bob,
+                value,
+                length,
+            } => {
+                let mut p = self.world.pendulums.at_mut(index);
+                if length {
+                    p.set_length(bob, value.max(MIN_LENGTH));
+                } else {
+                    p.set_mass(bob, value.max(MIN_MASS));
+                }
+            }
+            Event::Recolor { index, color } => self.world.pendulums.at_mut(index).set_color(color),
+            Event::Parameter { parameter, value } => self.set_parameter(parameter, value),
+            Event::Ramp(ramp) => {
+                self.set_parameter(ramp.parameter, ramp.from);
+                self.ramps.push((self.time, ramp));
+            }
+            Event::Action { .. } | Event::Scale(_) => (),
+        }
+    }
+
+    /// Remove the pendulum at `index`, the dragged one is released
+    /// and the selected one is deselected if it is removed
+    fn remove(&mut self, index: usize) {
+        self.world.remove(index);
+
+        if let Some(drag) = &mut self.drag {
+            match drag.index.cmp(&index) {
+                Ordering::Less => (),
+                Ordering::Equal => self.drag = None,
+                Ordering::Greater => drag.index -= 1,
+            }
+        }
+        self.selected = self
+            .selected
+            .and_then(|selected| match selected.cmp(&index) {
+                Ordering::Less => Some(selected),
+                Ordering::Equal => None,
+                Ordering::Greater => Some(selected - 1),
+            });
+    }
+
+    /// Add a new random pendulum to the scene with the fewest pendulums
+    fn spawn(&mut self) {
+        self.spawn_with(
+            &InitialConditions::default(),
+            self.world.emptiest_scene(&self.layout),
+        );
+    }
+
+    /// Start the export sink called `name`, or finish it if it is already running
+    fn toggle_sink(&mut self, name: &str) -> GameResult {
+        let metadata = self.metadata();
+        self.exports.toggle(name, &metadata)
+    }
+
+    /// Returns everything needed to reproduce the simulation, given to the sinks when they start
+    ///
+    /// The physical constants are the ones given to new pendulums,
+    /// the ones of existing pendulums might have been edited
+    fn metadata(&self) -> Metadata {
+        let mut metadata = Metadata::default();
+        metadata.push("version", env!("CARGO_PKG_VERSION"));
+        metadata.push("command", env::args().collect::<Vec<_>>().join(" "));
+        metadata.push("seed", self.seed);
+        metadata.push("dt", 1.0 / self.physics_hz as f32);
+        // The integrators in use, there can be several of them while they are compared
+        let integrators: Vec<_> = (IntegratorKind::ALL.into_iter())
+            .filter(|&i| self.world.pendulums.iter().any(|p| p.integrator() == i))
+            .map(IntegratorKind::name)
+            .collect();
+        metadata.push("integrator", integrators.join(", "));
+        metadata.push("start-time", self.time());
+        metadata.push("pendulums", self.world.pendulums.len());
+        if let Some(index) = self.preset {
+            metadata.push("preset", PRESETS[index].name);
+        }
+        if let Some((index, _)) = &self.scenario {
+            metadata.push("scenario", SCENARIOS[*index].name);
         }
+        metadata.push("gravity", self.physics.gravity);
+        metadata.push("damping", self.physics.damping);
+        metadata.push("drive-amplitude", self.physics.drive_amplitude);
+        metadata.push("drive-frequency", self.physics.drive_frequency);
+        metadata.push("wind", self.wind.strength());
+        metadata.push("gust", self.wind.gust());
+        metadata.push(
+            "platform",
+            format!("{} {}", env::consts::OS, env::consts::ARCH),
+        );
+        metadata
+    }
 
-        let s = Self {
+    /// Returns the settings that create the current pendulums from scratch
+    ///
+    /// The clock starts over, so a driving force starts back at the beginning of its period
+    fn reproduction(&self) -> Config {
+        let pendulums = (self.world.pendulums.iter())
+            .zip(&self.world.pendulum_scenes)
+            .map(|(p, &scene)| ScenePendulum {
+                scene: (self.layout.scenes > 1).then_some(scene),
+                initial: p.conditions(),
+                physics: PhysicsOverrides::between(&self.physics, p.physics()),
+            })
+            .collect::<Vec<_>>();
+        Config {
+            // The lengths of the pendulums are in pixels of the window at startup
+            window_size: (
+                self.layout.reference_center[0] * 2.0,
+                self.layout.reference_center[1] * 2.0,
+            ),
+            background: self.background.into(),
+            trail_color: self.trails.color.map(Color::into),
+            trail_saturation: self.trails.saturation,
+            joint_trail_color: self.trails.joint_color.into(),
+            monochrome: self.monochrome,
+            palette: self.palette,
+            clear: self.post.clear(),
+            projector: self.hud.projector,
+            motion_blur: self.post.motion_blur(),
+            bloom: self.post.bloom(),
+            size: pendulums.len(),
+            anchor: self.layout.anchor,
+            scenes: self.layout.scenes,
+            show_trail: self.trails.show,
+            trail_length: self.trails.length(),
+            spawn_interval: self.spawn_interval,
+            max_pendulums: self.max_pendulums,
+            clones: self.clones,
+            speed: self.speed,
+            physics_hz: self.physics_hz,
+            integrator: self.integrator,
+            on_hidden: self.on_hidden,
+            adaptive_substeps: self.adaptive_substeps,
+            wind: self.wind.strength(),
+            gust: self.wind.gust(),
+            physics: self.physics,
             pendulums,
-            show_trail,
-            center,
+            seed: Some(self.seed),
+            script: (self.automation.script_path()).map(|path| path.display().to_string()),
+            ..Config::default()
+        }
+    }
+
+    /// Apply the settings of the configuration file that was just changed to the running simulation
+    ///
+    /// The settings start from the current ones, so only what the file sets changes.
+    /// The physics and the colors of a new palette change through events, so they are recorded in the replay,
+    /// while the number of pendulums, the keys and the pendulums themselves keep their value until the restart.
+    fn reload_config(&mut self) {
+        let Some(path) = self.config_watcher.as_ref().map(|w| w.path().to_owned()) else {
+            return;
         };
-        Ok(s)
+        let mut config = self.reproduction();
+        if let Err(e) = config.apply_file(&path) {
+            eprintln!(
+                "Couldn't reload the configuration file {}: {e}",
+                path.display()
+            );
+            return;
+        }
+
+        // The inputs of a replay being played include its own physics
+        if !self.is_playing() {
+            let physics = [
+                (
+                    Parameter::Gravity,
+                    self.physics.gravity,
+                    config.physics.gravity,
+                ),
+                (
+                    Parameter::Damping,
+                    self.physics.damping,
+                    config.physics.damping,
+                ),
+                (
+                    Parameter::DriveAmplitude,
+                    self.physics.drive_amplitude,
+                    config.physics.drive_amplitude,
+                ),
+                (
+                    Parameter::DriveFrequency,
+                    self.physics.drive_frequency,
+                    config.physics.drive_frequency,
+                ),
+                (Parameter::Wind, self.wind.strength(), config.wind),
+            ];
+            for (parameter, old, value) in physics {
+                if old != value {
+                    let event = Event::Parameter { parameter, value };
+                    self.apply(event.clone());
+                    self.record(event);
+                }
+            }
+            // The pendulums are recolored like with the palette action, the colors are recorded
+            // so the random number generator of the simulation isn't used outside of the replay
+            if config.palette != self.palette {
+                self.palette = config.palette;
+                for index in 0..self.world.pendulums.len() {
+                    let color = self.palette.color(&mut rand::thread_rng());
+                    let event = Event::Recolor { index, color };
+                    self.apply(event.clone());
+                    self.record(event);
+                }
+            }
+        }
+
+        self.background = config.background.into();
+        self.trails.color = config.trail_color.map(Color::from);
+        self.trails.saturation = config.trail_saturation;
+        self.trails.joint_color = config.joint_trail_color.into();
+        self.monochrome = config.monochrome;
+        self.hud.projector = config.projector;
+        self.trails.show = config.show_trail;
+        self.speed = config.speed;
+        self.layout.anchor = config.anchor;
+        self.on_hidden = config.on_hidden;
+        self.adaptive_substeps = config.adaptive_substeps;
+        self.post.reload(&config);
+        if config.trail_length != self.trails.length() {
+            self.trails
+                .set_length(&mut self.world.pendulums, config.trail_length);
+        }
+        println!("Reloaded the configuration file {}", path.display());
+    }
+
+    /// Print the command that reproduces the current scene
+    ///
+    /// A single pendulum fits in the command line arguments,
+    /// otherwise the scene is saved to a configuration file in the temporary directory
+    fn print_reproduction(&self) -> GameResult {
+        let config = self.reproduction();
+        let program = env::args()
+            .next()
+            .unwrap_or_else(|| "double_pendulum".to_owned());
+        let args = if config.pendulums.len() == 1 && config.scenes == 1 {
+            config.to_args()
+        } else {
+            let content = config
+                .to_toml()
+                .map_err(|e| GameError::CustomError(format!("couldn't write the scene: {e}")))?;
+            let path = env::temp_dir().join(format!("double_pendulum-{}.toml", timestamp()));
+            fs::write(&path, content)?;
+            vec!["--config".to_owned(), path.display().to_string()]
+        };
+        println!("Reproduce this scene with: {program} {}", args.join(" "));
+        Ok(())
+    }
+
+    /// Handle a key press while the wizard is open
+    fn wizard_key_down(&mut self, key: VirtualKeyCode) {
+        let Some(wizard) = &mut self.wizard else {
+            return;
+        };
+
+        match wizard.key_down(key) {
+            WizardAction::Continue => (),
+            WizardAction::Cancel => self.wizard = None,
+            WizardAction::Done => {
+                let initial = wizard.initial_conditions();
+                let color = wizard.color();
+                self.wizard = None;
+
+                let scene = self.world.emptiest_scene(&self.layout);
+                self.spawn_with(&initial, scene);
+                if let Some(mut p) = self.world.pendulums.last_mut() {
+                    p.set_color(color);
+                }
+                self.record(Event::SpawnWith {
+                    initial,
+                    scene,
+                    color,
+                });
+            }
+        }
+    }
+
+    /// Do what every request waiting in the HTTP API asks for, like the inputs of the user
+    fn answer_api(&mut self, ctx: &mut Context) -> GameResult {
+        while let Some(request) = self.automation.next_request() {
+            if self.is_playing() && !matches!(request.command, Command::GetState) {
+                request.fail(409, "the inputs come from a replay");
+                continue;
+            }
+            let json = match request.command {
+                Command::Spawn => {
+                    self.perform(ctx, Action::Spawn, false)?;
+                    json!({ "pendulums": self.world.pendulums.len() })
+                }
+                Command::Reset => {
+                    self.perform(ctx, Action::Reset, false)?;
+                    json!({ "pendulums": self.world.pendulums.len() })
+                }
+                Command::Pause => {
+                    self.perform(ctx, Action::Pause, false)?;
+                    json!({ "paused": self.paused })
+                }
+                Command::SetGravity(value) => {
+                    let event = Event::Parameter {
+                        parameter: Parameter::Gravity,
+                        value,
+                    };
+                    self.apply(event.clone());
+                    self.record(event);
+                    json!({ "gravity": self.physics.gravity })
+                }
+                Command::Ramp(ramp) => {
+                    self.start_ramp(ramp);
+                    json!({ "ramps": self.ramps.len() })
+                }
+                Command::GetState => {
+                    serde_json::to_value(self.saved_state()).map_err(json_error)?
+                }
+            };
+            request.reply(json.to_string());
+        }
+        Ok(())
+    }
+
+    /// Handle a key press while the menu of the scenarios is open
+    fn scenario_menu_key_down(&mut self, key: VirtualKeyCode) {
+        let Some(menu) = &mut self.scenario_menu else {
+            return;
+        };
+
+        match menu.key_down(key) {
+            MenuAction::Continue => (),
+            MenuAction::Cancel => self.scenario_menu = None,
+            MenuAction::Start(index) => {
+                self.scenario_menu = None;
+                self.apply(Event::Scenario(index));
+                self.record(Event::Scenario(index));
+            }
+        }
+    }
+
+    /// Do what a key or a gamepad button asks for, `shift` changes the behavior of some actions
+    fn perform(&mut self, ctx: &mut Context, action: Action, shift: bool) -> GameResult {
+        if replay::is_replayed(action) {
+            self.record(Event::Action { action, shift });
+        }
+        match action {
+            Action::Pause => self.paused = !self.paused,
+            Action::Step => self.step(if shift { SHIFT_STEP_TICKS } else { 1 })?,
+            Action::SpeedUp => self.change_speed(true),
+            Action::SlowDown => self.change_speed(false),
+            Action::Record => self.toggle_sink("apng")?,
+            Action::RecordCsv => self.toggle_sink("csv")?,
+            Action::RecordGif => self.toggle_sink("gif")?,
+            Action::RecordVideo => self.toggle_sink("video")?,
+            Action::Screenshot => self.exports.start("screenshot", &self.metadata())?,
+            Action::Reverse => self.world.pendulums.reverse(),
+            Action::Spawn => self.spawn(),
+            Action::Delete => {
+                if let Some(last) = self.world.pendulums.len().checked_sub(1) {
+                    self.remove(last);
+                }
+            }
+            Action::Monochrome => self.monochrome = !self.monochrome,
+            Action::MotionBlur => self.post.toggle_motion_blur(),
+            Action::Bloom => self.post.toggle_bloom(),
+            Action::Palette => self.cycle_palette(),
+            Action::SlowMotion => self.slow_motion = !self.slow_motion,
+            Action::Snapshot => self.snapshot = Some(self.world.pendulums.clone()),
+            Action::Preset => {
+                let next = self.preset.map_or(0, |i| (i + 1) % PRESETS.len());
+                self.load_preset(next);
+            }
+            // A scenario starts over, so its own state matches its pendulums
+            Action::Reset => match &self.scenario {
+                Some((index, _)) => self.start_scenario(*index),
+                None => self.reset(),
+            },
+            Action::Trail => self.trails.show = !self.trails.show,
+            Action::JointTrail => self.trails.show_joint = !self.trails.show_joint,
+            Action::SpeedTrail => self.trails.speed = !self.trails.speed,
+            Action::RainbowTrail if shift => {
+                if let Some(mut p) = self.world.pendulums.get_mut(self.selected.unwrap_or(0)) {
+                    let rainbow = p.rainbow_trail();
+                    p.set_rainbow_trail(!rainbow);
+                }
+            }
+            Action::RainbowTrail => self.trails.rainbow = !self.trails.rainbow,
+            Action::Velocities => self.show_velocities = !self.show_velocities,
+            Action::Energy => self.hud.show_energy = !self.hud.show_energy,
+            Action::Readout => self.hud.show_readout = !self.hud.show_readout,
+            Action::Profiler => self.hud.show_profiler = !self.hud.show_profiler,
+            Action::StressTest => {
+                self.stress_test = match self.stress_test {
+                    Some(_) => None,
+                    None => Some(StressTest::new()),
+                }
+            }
+            Action::Clones => self.spawn_clones(),
+            Action::Integrators => self.spawn_integrators(),
+            action @ (Action::PhaseSpace
+            | Action::AngleChart
+            | Action::Poincare
+            | Action::Spectrum
+            | Action::Lyapunov) => {
+                let index = self.selected.unwrap_or(0);
+                let pendulum = self.world.pendulums.get(index);
+                self.overlays
+                    .toggle(action, || overlay::create(action, index, pendulum.as_ref()));
+            }
+            Action::Heatmap => {
+                self.heatmap = match self.heatmap {
+                    // It covers the window at startup when the origin is at its center
+                    None => Some(Heatmap::new(
+                        self.layout.reference_center[0].max(self.layout.reference_center[1]),
+                    )),
+                    Some(_) => None,
+                };
+            }
+            // The ink is discarded when it is cleared or turned off
+            Action::Ink if shift => self.trails.clear_ink(),
+            Action::Ink => self.trails.toggle_ink(),
+            Action::ControlPanel => self.hud.panel.toggle(),
+            Action::Wizard => self.wizard = Some(Wizard::new()),
+            Action::Scenarios => self.scenario_menu = Some(ScenarioMenu::new()),
+            Action::Sound => self.sound.toggle(),
+            Action::Louder => self.sound.set_volume(self.sound.volume() + VOLUME_STEP),
+            Action::Quieter => self.sound.set_volume(self.sound.volume() - VOLUME_STEP),
+            Action::Compare => self.show_snapshot = !self.show_snapshot,
+            action @ (Action::LongerRod
+            | Action::ShorterRod
+            | Action::Heavier
+            | Action::Lighter) => {
+                // The second circle of the selected pendulum is edited, or the first one with Shift held
+                if let Some(index) = self.selected {
+                    let bob = if shift { 0 } else { 1 };
+                    let (steps, length) = match action {
+                        Action::LongerRod => (1.0, true),
+                        Action::ShorterRod => (-1.0, true),
+                        Action::Heavier => (1.0, false),
+                        _ => (-1.0, false),
+                    };
+                    self.edit(index, bob, steps, length);
+                }
+            }
+            Action::Randomize => {
+                if let Some(index) = self.selected {
+                    let mut p = self.world.pendulums.at(index);
+                    if randomize::randomize(&mut p, &mut self.rng, &self.constraints) {
+                        self.world.pendulums.set(index, p);
+                    } else {
+                        eprintln!(
+                            "Couldn't find parameters satisfying the randomization constraints"
+                        );
+                    }
+                }
+            }
+            Action::ResetCamera => self.camera.reset(),
+            Action::Deselect => self.selected = None,
+            Action::Repro => self.print_reproduction()?,
+            Action::Rewind => self.rewind(!shift),
+            Action::Save => self.save()?,
+            Action::ExportSvg => self.export_svg(shift)?,
+            Action::Quit => ctx.request_quit(),
+        }
+        Ok(())
+    }
+
+    /// Add a new pendulum to `scene`, evicting the oldest ones if there are too many
+    fn spawn_with(&mut self, initial: &InitialConditions, scene: usize) {
+        self.spawn_overridden(initial, &PhysicsOverrides::default(), scene);
+    }
+
+    /// Spawn a pendulum like `spawn_with`, whose physics replace the ones of the simulation with `overrides`
+    fn spawn_overridden(
+        &mut self,
+        initial: &InitialConditions,
+        overrides: &PhysicsOverrides,
+        scene: usize,
+    ) {
+        let length = self.layout.scene_rect(scene).h / 2.0 / self.layout.scale();
+        let physics = overrides.apply(self.physics);
+        let mut pendulum =
+            DoublePendulum::new(&mut self.rng, length, physics, initial, self.palette);
+        pendulum.set_trail_length(self.trails.length());
+        pendulum.set_integrator(self.integrator);
+        self.world.push(pendulum, scene);
+        self.evict_excess();
+    }
+
+    /// Switch to the next palette with its background, and give every pendulum a new color from it
+    fn cycle_palette(&mut self) {
+        self.palette = self.palette.next();
+        self.background = render::to_graphics(self.palette.background());
+        for mut p in self.world.pendulums.iter_mut() {
+            p.set_color(self.palette.color(&mut self.rng));
+        }
+    }
+
+    /// Spawn `clones` copies of the selected pendulum, or of the first one, in its scene
+    ///
+    /// The first angle of the i-th copy is offset by `CLONE_OFFSET * i`, and the original and its copies
+    /// get hues spread around the color wheel, so they can be told apart once they diverge
+    fn spawn_clones(&mut self) {
+        let count = self.clones + 1;
+        self.spawn_variants(count, |i, p| {
+            // The angle is wrapped first, otherwise the offsets are lost in the rounding after a few turns
+            let [theta1, theta2] = p.angles();
+            p.set_angles([wrap_angle(theta1) + CLONE_OFFSET * i as f32, theta2]);
+            p.set_color(color::hsv(360.0 * i as f32 / count as f32, 0.8, 1.0));
+        });
+    }
+
+    /// Spawn a copy of the selected pendulum, or of the first one, for every other integrator
+    ///
+    /// The original and its copies are colored by integrator, so the numerical errors can be compared
+    fn spawn_integrators(&mut self) {
+        self.spawn_variants(IntegratorKind::ALL.len(), |i, p| {
+            p.set_integrator(IntegratorKind::ALL[i]);
+            p.set_color(INTEGRATOR_COLORS[i]);
+        });
+    }
+
+    /// Turn the selected pendulum, or the first one, into `count` variants in its scene,
+    /// the original being changed by `vary(0, ..)` and the copies by `vary(i, ..)` before being spawned
+    fn spawn_variants(&mut self, count: usize, vary: impl Fn(usize, &mut DoublePendulum)) {
+        let index = self.selected.unwrap_or(0);
+        let Some(mut original) = self.world.pendulums.get_mut(index) else {
+            return;
+        };
+        let copies = original.clone();
+        vary(0, &mut original);
+        drop(original);
+        let scene = self.world.pendulum_scenes[index];
+
+        for i in 1..count {
+            let mut copy = copies.clone();
+            vary(i, &mut copy);
+            self.world.push(copy, scene);
+        }
+        self.evict_excess();
+    }
+
+    /// Remove the oldest pendulums while there are more than `max_pendulums`
+    ///
+    /// The stress test is looking for the largest count, so nothing is evicted while it runs
+    fn evict_excess(&mut self) {
+        if self.stress_test.is_some() {
+            return;
+        }
+        if let Some(max) = self.max_pendulums {
+            if self.world.pendulums.len() > max {
+                let excess = self.world.pendulums.len() - max;
+                self.world.remove_first(excess);
+                // The indices changed, so the dragged pendulum might be gone
+                self.drag = None;
+                self.selected = self
+                    .selected
+                    .and_then(|selected| selected.checked_sub(excess));
+            }
+        }
+    }
+
+    /// Spawn a pendulum whenever the spawn interval has elapsed, `dt` seconds after the last update
+    fn update_spawn_timer(&mut self, dt: f32) {
+        if let Some(interval) = self.spawn_interval {
+            self.spawn_timer += dt;
+            if self.spawn_timer >= interval {
+                self.spawn_timer -= interval;
+                self.spawn();
+            }
+        }
     }
 }
 
 impl EventHandler for MainState {
     fn update(&mut self, ctx: &mut Context) -> GameResult {
-        // Update every pendulum `DESIRED_FPS` number of times per second
-        while ctx.time.check_update_time(DESIRED_FPS) {
-            for p in &mut self.pendulums {
-                p.update(DESIRED_FPS)?;
+        // Capping the frames leaves the CPU idle instead of drawing frames faster than needed
+        if let Some(period) = self.frame_period {
+            let elapsed = self.frame_start.elapsed();
+            if elapsed < period {
+                std::thread::sleep(period - elapsed);
+            }
+            self.frame_start = Instant::now();
+        }
+        if let Some(time) = self.replay.pending_seek.take() {
+            self.seek(ctx, time)?;
+        }
+        self.play_due_events(ctx)?;
+        self.answer_api(ctx)?;
+        if self
+            .config_watcher
+            .as_ref()
+            .is_some_and(ConfigWatcher::changed)
+        {
+            self.reload_config();
+        }
+
+        // The left stick of the gamepad moves the origin of the pendulums around
+        let dt = ctx.time.delta().as_secs_f32();
+        self.layout.move_anchor(self.stick, dt);
+        self.camera.update(ctx);
+
+        // The sound follows the selected pendulum, or the first one, and fades out while paused
+        let pendulum = self.world.pendulums.get(self.selected.unwrap_or(0));
+        (self.sound).update(pendulum.as_ref().filter(|_| !self.paused));
+
+        if let Some(drag) = &self.drag {
+            let (index, bob, target) = (drag.index, drag.bob, drag.target);
+            self.world.pendulums.at_mut(index).drag(bob, target, dt);
+            self.record(Event::Drag {
+                index,
+                bob,
+                target,
+                dt,
+            });
+        }
+
+        // Update every pendulum `physics_hz` number of times per second, scaled by the speed
+        // The timer keeps running while paused, so there is no burst of updates when resuming
+        let mut ticks = 0;
+        while ctx.time.check_update_time(self.physics_hz) {
+            ticks += 1;
+        }
+        if self.paused || (self.hidden && self.on_hidden == HiddenPolicy::Pause) {
+            return Ok(());
+        }
+
+        // Spread the late updates over several frames, so the pendulums don't teleport
+        if self.on_hidden == HiddenPolicy::CatchUp {
+            self.backlog += ticks;
+            ticks = self.backlog.min(MAX_CATCH_UP_TICKS);
+            self.backlog -= ticks;
+        }
+
+        let mut steps = 0;
+        for _ in 0..ticks {
+            self.pending_steps += self.speed;
+            while self.pending_steps >= 1.0 {
+                self.pending_steps -= 1.0;
+                steps += 1;
+            }
+        }
+        self.run_steps(ctx, steps)?;
+
+        if let Some(stress_test) = &mut self.stress_test {
+            let count = self.world.pendulums.len();
+            // The spawns are recorded like the spawn action, so a replay has the same pendulums
+            for _ in 0..stress_test.frame(dt, count, self.realtime_ratio) {
+                self.record(Event::Action {
+                    action: Action::Spawn,
+                    shift: false,
+                });
+                self.spawn();
             }
         }
         Ok(())
     }
 
     fn draw(&mut self, ctx: &mut Context) -> GameResult {
-        let mut canvas =
-            graphics::Canvas::from_frame(ctx, Some([0.1, 0.2, 0.3, 1.0].into()));
+        let start = Instant::now();
+        let background = if self.monochrome || self.hud.projector {
+            Color::BLACK
+        } else {
+            self.background
+        };
+        self.trails
+            .draw_ink(ctx, &mut self.world, &self.layout, self.monochrome)?;
+        let mut canvas = graphics::Canvas::from_frame(ctx, Some(background));
+        let window = self.layout.window();
+
+        let mut layer = self.post.layer(ctx, window, background)?;
+        self.draw_simulation(ctx, layer.as_mut().unwrap_or(&mut canvas))?;
+        self.post.draw(ctx, &mut canvas, layer)?;
+
+        // Write the fps, the number of pendulums and the simulated time in the top left corner
+        let time = self.time();
+        let mut hud = format!(
+            "FPS: {}\nPendulums count: {}\nTime: {:02}:{:05.2}",
+            ctx.time.fps().round(),
+            self.world.pendulums.len(),
+            (time / 60.0).floor(),
+            time % 60.0,
+        );
+
+        if self.speed != 1.0 {
+            hud += &format!("\nSpeed: {}x", self.speed);
+        }
+        if self.slow_motion {
+            hud += "\nSlow motion";
+        }
+        if self.realtime_ratio < 0.95 {
+            hud += &format!("\nSimulation running at {:.2}x", self.realtime_ratio);
+        }
+        hud += &self.camera.hud();
+        if self.step_scale > 1 {
+            hud += &format!("\nTime step: {}x longer", self.step_scale);
+        }
+        if self.paused {
+            hud += "\nPAUSED";
+        }
+        if let Some(rewound) = self.replay.history.rewound() {
+            hud += &format!("\nRewound: {rewound:.1} s");
+        }
+        hud += &self.exports.hud();
+        if let Some(index) = self.preset {
+            hud += &format!("\nPreset: {}", PRESETS[index].name);
+        }
+        if self.palette != Palette::default() {
+            hud += &format!("\nPalette: {}", self.palette);
+        }
+        if let Some((index, _)) = &self.scenario {
+            hud += &format!("\nScenario: {}", SCENARIOS[*index].name);
+        }
+        hud += &self.sound.hud();
+        hud += &self.hud.lines(&self.world.pendulums, self.selected);
+        if let Some(stress_test) = &self.stress_test {
+            hud += &format!("\n{}", stress_test.hud());
+        }
+        hud += &self.overlays.hud();
+        if let Some(divergence) = self.snapshot_divergence() {
+            hud += &format!("\nDivergence from snapshot: {divergence:.3} rad");
+        }
 
-        for p in &mut self.pendulums {
-            p.draw(ctx, &mut canvas, self.center, self.show_trail)?;
+        // Followed by the progress of every ramp
+        for (start, ramp) in &self.ramps {
+            let elapsed = (self.time - start) as f32;
+            hud += &format!(
+                "\n{}: {:.2} -> {:.2} ({:.0}%)",
+                ramp.parameter,
+                ramp.value_at(elapsed),
+                ramp.to,
+                ramp.progress(elapsed) * 100.0,
+            );
         }
+        let view = HudView {
+            text: hud,
+            pendulums: &self.world.pendulums,
+            selected: self.selected,
+            window,
+            wind: self.wind.is_enabled().then(|| self.wind.at(time)),
+            monochrome: self.monochrome,
+        };
+        self.hud.draw(ctx, &mut canvas, &view)?;
 
-        // Draw a white circle in the center of the screen
-        let origin = [0.0, 0.0];
-        let circle = Mesh::new_circle(ctx, DrawMode::fill(), origin, 10.0, 2.0, Color::WHITE)?;
-        canvas.draw(&circle, self.center);
+        self.overlays.draw(ctx, &mut canvas, window)?;
+        if let Some((_, scenario)) = &self.scenario {
+            scenario.draw(ctx, &mut canvas, window)?;
+        }
+        if !self.hud.projector {
+            (self.replay).draw(ctx, &mut canvas, window, self.time)?;
+        }
 
-        // Write the fps and the number of pendulums in the top left corner
-        let text = graphics::Text::new(format!(
-            "FPS: {}\nPendulums count: {}",
-            ctx.time.fps().round(),
-            self.pendulums.len(),
-        ));
-        let dest_point = [10.0, 10.0];
-        canvas.draw(&text, dest_point);
+        self.hud
+            .panel
+            .draw(ctx, &mut canvas, &self.panel_widgets())?;
+        if let Some(wizard) = &self.wizard {
+            wizard.draw(ctx, &mut canvas, self.layout.center)?;
+        }
+        if let Some(menu) = &self.scenario_menu {
+            menu.draw(ctx, &mut canvas, window)?;
+        }
+
+        canvas.finish(ctx)?;
+        self.hud.profiler.end_frame(start.elapsed());
+        self.exports.draw(ctx)
+    }
+
+    fn mouse_button_down_event(
+        &mut self,
+        ctx: &mut Context,
+        button: MouseButton,
+        x: f32,
+        y: f32,
+    ) -> GameResult {
+        let window = self.layout.window();
+        // The projector mode hides the seek bar
+        let seek = (self.replay.seek_bar_time(window, [x, y])).filter(|_| !self.hud.projector);
+        if let Some(time) = seek {
+            if button == MouseButton::Left {
+                self.seek(ctx, time)?;
+            }
+            return Ok(());
+        }
+        let widgets = self.panel_widgets();
+        if self.hud.panel.contains([x, y], &widgets) {
+            // The replays can't be changed with the panel either, but it can still be opened and closed
+            if button == MouseButton::Left {
+                let input = self.hud.panel.mouse_down([x, y], &widgets);
+                match input {
+                    Some(input) if !self.is_playing() => self.apply_panel(ctx, input)?,
+                    _ => (),
+                }
+            }
+            return Ok(());
+        }
+        self.camera.mouse_down(button);
+        // The split screen doesn't match the coordinates of the pendulums
+        if self.show_snapshot {
+            return Ok(());
+        }
+
+        // The replays can't be changed with the mouse
+        if self.is_playing() {
+            return Ok(());
+        }
+
+        let [x, y] = self.camera.camera().world_point([x, y]);
+        let scene = self.layout.scene_at(x, y);
+        let target = self.layout.to_origin(x, y, scene);
+        let event = match (button, self.world.bob_at(&self.layout, x, y)) {
+            (MouseButton::Left, Some((index, bob))) => {
+                self.drag = Some(Drag { index, bob, target });
+                Event::Select(index)
+            }
+            // Clicking on empty space spawns a pendulum whose tip is under the mouse
+            (MouseButton::Left, None) => Event::SpawnAt { scene, target },
+            (MouseButton::Right, Some((index, _))) => Event::Remove(index),
+            _ => return Ok(()),
+        };
+        self.apply(event.clone());
+        self.record(event);
+        Ok(())
+    }
 
-        canvas.finish(ctx)
+    fn mouse_button_up_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: MouseButton,
+        _x: f32,
+        _y: f32,
+    ) -> GameResult {
+        if button == MouseButton::Left {
+            self.drag = None;
+            self.hud.panel.mouse_up();
+        }
+        self.camera.mouse_up(button);
+        Ok(())
+    }
+
+    fn mouse_motion_event(
+        &mut self,
+        ctx: &mut Context,
+        x: f32,
+        y: f32,
+        _dx: f32,
+        _dy: f32,
+    ) -> GameResult {
+        let widgets = self.panel_widgets();
+        if let Some(input) = self.hud.panel.mouse_motion([x, y], &widgets) {
+            if !self.is_playing() {
+                self.apply_panel(ctx, input)?;
+            }
+            return Ok(());
+        }
+
+        let Some(index) = self.drag.as_ref().map(|drag| drag.index) else {
+            return Ok(());
+        };
+        let [x, y] = self.camera.camera().world_point([x, y]);
+        let target = self
+            .layout
+            .to_origin(x, y, self.world.pendulum_scenes[index]);
+        if let Some(drag) = &mut self.drag {
+            drag.target = target;
+        }
+        Ok(())
+    }
+
+    fn mouse_wheel_event(&mut self, ctx: &mut Context, _x: f32, y: f32) -> GameResult {
+        // Scrolling over a circle selects its pendulum and changes its mass, or its rod with Shift,
+        // and scrolling anywhere else zooms around the mouse
+        if self.show_snapshot || y == 0.0 {
+            return Ok(());
+        }
+        let position = [ctx.mouse.position().x, ctx.mouse.position().y];
+        let [x, y_world] = self.camera.camera().world_point(position);
+        match self.world.bob_at(&self.layout, x, y_world) {
+            Some((index, bob)) if !self.is_playing() => {
+                let length = ctx.keyboard.active_mods().contains(KeyMods::SHIFT);
+                let event = Event::Edit {
+                    index,
+                    bob,
+                    steps: y.signum(),
+                    length,
+                };
+                self.apply(event.clone());
+                self.record(event);
+            }
+            _ => self.camera.scroll(position, y),
+        }
+        Ok(())
+    }
+
+    fn gamepad_button_down_event(
+        &mut self,
+        ctx: &mut Context,
+        button: Button,
+        _id: GamepadId,
+    ) -> GameResult {
+        if self.wizard.is_some() || self.scenario_menu.is_some() || self.is_playing() {
+            return Ok(());
+        }
+        match keybindings::gamepad_action(button) {
+            Some(action) => self.perform(ctx, action, false),
+            None => Ok(()),
+        }
+    }
+
+    fn gamepad_axis_event(
+        &mut self,
+        _ctx: &mut Context,
+        axis: Axis,
+        value: f32,
+        _id: GamepadId,
+    ) -> GameResult {
+        let value = if value.abs() < STICK_DEAD_ZONE {
+            0.0
+        } else {
+            value
+        };
+        match axis {
+            Axis::LeftStickX => self.stick[0] = value,
+            // The y axis of the stick points up, unlike the one of the window
+            Axis::LeftStickY => self.stick[1] = -value,
+            _ => (),
+        }
+        Ok(())
     }
 
     fn resize_event(&mut self, _ctx: &mut Context, width: f32, height: f32) -> GameResult {
-        self.center = [width / 2.0, height / 2.0];
+        // A minimized window shrinks to nothing, its layout is kept for when it is restored.
+        // Losing the focus doesn't hide it, the pendulums are still visible behind other windows.
+        self.hidden = width == 0.0 || height == 0.0;
+        if !self.hidden {
+            self.layout.center = [width / 2.0, height / 2.0];
+        }
         Ok(())
     }
 
@@ -83,13 +2187,37 @@ impl EventHandler for MainState {
         input: KeyInput,
         _repeated: bool,
     ) -> GameResult {
-        match input.keycode {
-            Some(VirtualKeyCode::C) => self.pendulums.push(DoublePendulum::new(self.center[1])),
-            Some(VirtualKeyCode::R) => self.pendulums = vec![DoublePendulum::new(self.center[1])],
-            Some(VirtualKeyCode::T) => self.show_trail = !self.show_trail,
-            Some(VirtualKeyCode::Q) => ctx.request_quit(),
-            _ => (),
+        if self.wizard.is_some() {
+            if let Some(key) = input.keycode {
+                self.wizard_key_down(key);
+            }
+            return Ok(());
+        }
+        if self.scenario_menu.is_some() {
+            if let Some(key) = input.keycode {
+                self.scenario_menu_key_down(key);
+            }
+            return Ok(());
+        }
+
+        let Some(key) = input.keycode else {
+            return Ok(());
         };
+        let shift = input.mods.contains(KeyMods::SHIFT);
+        match self.keybindings.action(key) {
+            // Only quitting works while a replay is played, the other actions come from the replay
+            Some(action) if !self.is_playing() || action == Action::Quit => {
+                self.perform(ctx, action, shift)?;
+            }
+            _ => (),
+        }
         Ok(())
     }
+
+    fn quit_event(&mut self, _ctx: &mut Context) -> GameResult<bool> {
+        // The recordings in progress are saved rather than lost
+        self.exports.finish()?;
+        self.replay.save(self.time)?;
+        Ok(false)
+    }
 }
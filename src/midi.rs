@@ -0,0 +1,158 @@
+use double_pendulum::pendulum::wrap_angle;
+use double_pendulum::sink::{ExportSink, Sample};
+use ggez::{GameError, GameResult};
+use midir::{MidiOutput, MidiOutputConnection};
+use std::f32::consts::PI;
+
+/// The notes of the pentatonic scale played, as semitones above the root
+const SCALE: [u8; 5] = [0, 2, 4, 7, 9];
+/// The lowest note played, a C
+const ROOT_NOTE: u8 = 48;
+/// The number of octaves covered by the notes, from the root
+const OCTAVES: usize = 3;
+/// The simulated time in seconds a note is held before it is released
+const NOTE_DURATION: f32 = 0.25;
+/// The angular velocity of the second rod played at full velocity, the slower crossings are softer
+const FULL_VELOCITY_OMEGA: f32 = 0.3;
+/// The controllers receiving the angles of the first and the second rod
+const CONTROLLERS: [u8; 2] = [1, 2];
+/// The number of MIDI channels, the pendulums after the 16th share the channels of the first ones
+const CHANNELS: usize = 16;
+
+/// A note being played, released once the simulated time reaches `until`
+struct HeldNote {
+    channel: u8,
+    note: u8,
+    until: f32,
+}
+
+/// Turns the pendulums into a generative instrument by sending MIDI events to an output port
+///
+/// A note is played each time the second rod of a pendulum passes through the vertical,
+/// its pitch on a pentatonic scale comes from the angle of the first rod and its velocity
+/// from the speed of the second one. The angles of both rods are also sent as the controllers 1 and 2.
+/// The pendulum `N` plays on the channel `N` modulo 16, and only the first 16 pendulums send controllers
+pub struct MidiSender {
+    connection: MidiOutputConnection,
+    /// The wrapped angle of the second rod of each pendulum at the previous sample
+    previous: Vec<f32>,
+    /// The values last sent to the controllers of each channel
+    controls: Vec<[u8; 2]>,
+    held: Vec<HeldNote>,
+}
+
+impl MidiSender {
+    /// Connect to the first output port whose name contains `port`, or to the first port if it is `None`
+    pub fn new(port: Option<&str>) -> GameResult<Self> {
+        let output = MidiOutput::new("double_pendulum").map_err(midi_error)?;
+        let ports = output.ports();
+        let names: Vec<_> = (ports.iter())
+            .map(|p| output.port_name(p).unwrap_or_default())
+            .collect();
+        let index = match port {
+            Some(port) => names.iter().position(|name| name.contains(port)),
+            None => (!names.is_empty()).then_some(0),
+        }
+        .ok_or_else(|| {
+            GameError::CustomError(format!(
+                "no MIDI output port matches {}, the available ones are: {}",
+                port.unwrap_or("anything"),
+                names.join(", ")
+            ))
+        })?;
+        let connection = output
+            .connect(&ports[index], "double_pendulum")
+            .map_err(midi_error)?;
+        println!("Sending MIDI events to {}", names[index]);
+        Ok(Self {
+            connection,
+            previous: Vec::new(),
+            controls: Vec::new(),
+            held: Vec::new(),
+        })
+    }
+
+    fn send(&mut self, message: [u8; 3]) -> GameResult {
+        self.connection.send(&message).map_err(midi_error)
+    }
+}
+
+impl ExportSink for MidiSender {
+    fn name(&self) -> &str {
+        "midi"
+    }
+
+    fn write_sample(&mut self, sample: &Sample) -> GameResult {
+        // The notes are released first, so a note played again right away isn't cut short
+        let (released, held): (Vec<_>, _) = std::mem::take(&mut self.held)
+            .into_iter()
+            .partition(|held| held.until <= sample.time);
+        self.held = held;
+        for held in released {
+            self.send([0x80 | held.channel, held.note, 0])?;
+        }
+
+        self.previous.truncate(sample.pendulums.len());
+        for (index, pendulum) in sample.pendulums.iter().enumerate() {
+            let channel = (index % CHANNELS) as u8;
+            let angles = pendulum.angles().map(wrap_angle);
+            let theta2 = angles[1];
+            let previous = self.previous.get(index).copied();
+            match self.previous.get_mut(index) {
+                Some(previous) => *previous = theta2,
+                None => self.previous.push(theta2),
+            }
+
+            // A jump between -PI and PI is the rod passing over the pivot, not through the vertical
+            let crossed = previous
+                .is_some_and(|previous| previous * theta2 < 0.0 && (previous - theta2).abs() < PI);
+            if crossed {
+                let note = note(angles[0]);
+                let omega2 = pendulum.angular_velocities()[1];
+                let velocity = (omega2.abs() / FULL_VELOCITY_OMEGA * 127.0).clamp(1.0, 127.0);
+                self.send([0x90 | channel, note, velocity as u8])?;
+                self.held.push(HeldNote {
+                    channel,
+                    note,
+                    until: sample.time + NOTE_DURATION,
+                });
+            }
+
+            if index < CHANNELS {
+                let values = angles.map(|theta| ((theta + PI) / (2.0 * PI) * 127.0) as u8);
+                if self.controls.len() <= index {
+                    self.controls.push([u8::MAX; 2]);
+                }
+                // Only the values that changed are sent, to spare the receiver
+                for (bob, value) in values.into_iter().enumerate() {
+                    if self.controls[index][bob] != value {
+                        self.controls[index][bob] = value;
+                        self.send([0xB0 | channel, CONTROLLERS[bob], value])?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> GameResult {
+        for held in std::mem::take(&mut self.held) {
+            self.send([0x80 | held.channel, held.note, 0])?;
+        }
+        println!("Stopped sending MIDI events");
+        Ok(())
+    }
+}
+
+/// Returns the note of the scale played when the first rod is at `theta1`, from the lowest note
+/// when it points up on the left to the highest one when it points up on the right
+fn note(theta1: f32) -> u8 {
+    let steps = SCALE.len() * OCTAVES;
+    let step = (((theta1 + PI) / (2.0 * PI) * steps as f32) as usize).min(steps - 1);
+    ROOT_NOTE + 12 * (step / SCALE.len()) as u8 + SCALE[step % SCALE.len()]
+}
+
+/// Returns the error of a MIDI port that couldn't be opened or written to
+fn midi_error(error: impl std::error::Error) -> GameError {
+    GameError::CustomError(format!("MIDI error: {error}"))
+}
@@ -0,0 +1,303 @@
+use crate::color::Palette;
+use crate::pendulum::{DoublePendulum, InitialConditions, Physics};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::f32::consts::PI;
+use std::fmt::Write;
+
+/// The number of rows shown when a trajectory is displayed in a notebook
+const PREVIEW_ROWS: usize = 10;
+/// The empty space around the drawing of a plot, in pixels
+const PLOT_MARGIN: f32 = 10.0;
+
+/// The complete initial state and constants of a double pendulum
+///
+/// Unlike `InitialConditions`, nothing is chosen randomly, so the same parameters always
+/// give the same trajectory
+#[derive(Clone, Copy)]
+pub struct Params {
+    /// The masses of both circles
+    pub m1: f32,
+    pub m2: f32,
+    /// The lengths of both rods, in pixels
+    pub l1: f32,
+    pub l2: f32,
+    /// The angles of both rods, in radians
+    pub theta1: f32,
+    pub theta2: f32,
+    /// The angular velocities of both rods
+    pub omega1: f32,
+    pub omega2: f32,
+    pub physics: Physics,
+}
+
+impl Default for Params {
+    /// Both rods horizontal, like the `classic` preset
+    fn default() -> Self {
+        Self {
+            m1: 3.0,
+            m2: 3.0,
+            l1: 100.0,
+            l2: 100.0,
+            theta1: PI / 2.0,
+            theta2: PI / 2.0,
+            omega1: 0.0,
+            omega2: 0.0,
+            physics: Physics::default(),
+        }
+    }
+}
+
+impl Params {
+    /// Returns a double pendulum starting in this state
+    pub fn pendulum(&self) -> DoublePendulum {
+        let initial = InitialConditions {
+            m1: Some(self.m1),
+            m2: Some(self.m2),
+            l1: Some(self.l1),
+            l2: Some(self.l2),
+            theta1: Some(self.theta1),
+            theta2: Some(self.theta2),
+            v1: Some(self.omega1),
+            v2: Some(self.omega2),
+        };
+        // Every value is given, so the generator only picks the color which is never used
+        let mut rng = StdRng::seed_from_u64(0);
+        let length = self.l1 + self.l2;
+        DoublePendulum::new(&mut rng, length, self.physics, &initial, Palette::default())
+    }
+}
+
+/// The state of a double pendulum at every step of a simulation, stored column by column
+///
+/// The positions are relative to the origin, in pixels, with the y axis pointing down
+#[derive(Clone, Default)]
+pub struct Trajectory {
+    pub time: Vec<f32>,
+    pub theta1: Vec<f32>,
+    pub theta2: Vec<f32>,
+    pub omega1: Vec<f32>,
+    pub omega2: Vec<f32>,
+    pub x1: Vec<f32>,
+    pub y1: Vec<f32>,
+    pub x2: Vec<f32>,
+    pub y2: Vec<f32>,
+    /// The lengths of both rods, used to frame the plots
+    lengths: [f32; 2],
+}
+
+/// Simulate a double pendulum for `steps` steps of `dt` seconds, without wind
+///
+/// The initial state is the first row, so the trajectory has `steps + 1` rows.
+/// In a Jupyter notebook running the evcxr kernel:
+///
+/// ```text
+/// :dep double_pendulum = { path = "path/to/double_pendulum", default-features = false }
+/// use double_pendulum::notebook::{simulate, Params};
+/// let trajectory = simulate(Params::default(), 1.0 / 240.0, 2400);
+/// trajectory.plot(400, 400)?
+/// ```
+pub fn simulate(params: Params, dt: f32, steps: usize) -> Trajectory {
+    let mut pendulum = params.pendulum();
+
+    let mut trajectory = Trajectory {
+        lengths: [params.l1, params.l2],
+        ..Default::default()
+    };
+    trajectory.push(0.0, &pendulum);
+    for i in 0..steps {
+        let time = i as f32 * dt;
+        pendulum.update(dt, 0.0, time);
+        trajectory.push(time + dt, &pendulum);
+    }
+    trajectory
+}
+
+impl Trajectory {
+    /// Add a row with the current state of `pendulum`
+    fn push(&mut self, time: f32, pendulum: &DoublePendulum) {
+        let [theta1, theta2] = pendulum.angles();
+        let [omega1, omega2] = pendulum.angular_velocities();
+        let [[x1, y1], [x2, y2]] = pendulum.bobs();
+        self.time.push(time);
+        self.theta1.push(theta1);
+        self.theta2.push(theta2);
+        self.omega1.push(omega1);
+        self.omega2.push(omega2);
+        self.x1.push(x1);
+        self.y1.push(y1);
+        self.x2.push(x2);
+        self.y2.push(y2);
+    }
+
+    /// Returns the number of rows
+    pub fn len(&self) -> usize {
+        self.time.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.time.is_empty()
+    }
+
+    /// Returns the name and the values of every column
+    pub fn columns(&self) -> [(&'static str, &[f32]); 9] {
+        [
+            ("time", &self.time),
+            ("theta1", &self.theta1),
+            ("theta2", &self.theta2),
+            ("omega1", &self.omega1),
+            ("omega2", &self.omega2),
+            ("x1", &self.x1),
+            ("y1", &self.y1),
+            ("x2", &self.x2),
+            ("y2", &self.y2),
+        ]
+    }
+
+    /// Returns the trajectory as CSV, with a header row
+    pub fn to_csv(&self) -> String {
+        let columns = self.columns();
+        let mut csv = columns.map(|(name, _)| name).join(",");
+        csv.push('\n');
+        for row in 0..self.len() {
+            let values: Vec<String> = columns.iter().map(|(_, c)| c[row].to_string()).collect();
+            csv.push_str(&values.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+
+    /// Draw the paths of both circles on a white image of `width` by `height` pixels
+    ///
+    /// The path of the first circle is gray, the one of the second circle is blue,
+    /// and the rods are drawn in black at their final position
+    pub fn plot(&self, width: u32, height: u32) -> Result<Plot, png::EncodingError> {
+        let mut image = Image::new(width, height);
+        let reach = (self.lengths[0] + self.lengths[1]).max(f32::EPSILON);
+        let scale = ((width.min(height) as f32 / 2.0 - PLOT_MARGIN) / reach).max(0.0);
+        let center = [width as f32 / 2.0, height as f32 / 2.0];
+        let to_pixel = |x: f32, y: f32| [center[0] + x * scale, center[1] + y * scale];
+
+        let paths = [
+            (&self.x1, &self.y1, [170, 170, 170]),
+            (&self.x2, &self.y2, [30, 90, 200]),
+        ];
+        for (xs, ys, color) in paths {
+            let points: Vec<_> = xs.iter().zip(ys).map(|(&x, &y)| to_pixel(x, y)).collect();
+            for segment in points.windows(2) {
+                image.line(segment[0], segment[1], color);
+            }
+        }
+
+        if let Some(last) = self.len().checked_sub(1) {
+            let origin = to_pixel(0.0, 0.0);
+            let p1 = to_pixel(self.x1[last], self.y1[last]);
+            let p2 = to_pixel(self.x2[last], self.y2[last]);
+            image.line(origin, p1, [0, 0, 0]);
+            image.line(p1, p2, [0, 0, 0]);
+        }
+
+        image.encode().map(|png| Plot { png })
+    }
+
+    /// Show the first rows as a table, called by evcxr when a trajectory is displayed
+    pub fn evcxr_display(&self) {
+        let columns = self.columns();
+        let mut html = String::from("<table><tr>");
+        for (name, _) in &columns {
+            let _ = write!(html, "<th>{name}</th>");
+        }
+        html.push_str("</tr>");
+        for row in 0..self.len().min(PREVIEW_ROWS) {
+            html.push_str("<tr>");
+            for (_, column) in &columns {
+                let _ = write!(html, "<td>{:.4}</td>", column[row]);
+            }
+            html.push_str("</tr>");
+        }
+        html.push_str("</table>");
+        let _ = write!(html, "<p>{} rows</p>", self.len());
+        println!("EVCXR_BEGIN_CONTENT text/html\n{html}\nEVCXR_END_CONTENT");
+    }
+}
+
+/// A PNG image, displayed inline by evcxr
+pub struct Plot {
+    /// The encoded PNG file, which can also be written to the disk
+    pub png: Vec<u8>,
+}
+
+impl Plot {
+    /// Show the image, called by evcxr when a plot is displayed
+    pub fn evcxr_display(&self) {
+        println!(
+            "EVCXR_BEGIN_CONTENT image/png\n{}\nEVCXR_END_CONTENT",
+            base64(&self.png)
+        );
+    }
+}
+
+/// A tiny RGB image to draw the plots without a window
+struct Image {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Image {
+    /// Create a white image
+    fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![255; width as usize * height as usize * 3],
+        }
+    }
+
+    /// Draw a one pixel wide line from `a` to `b`, the pixels outside of the image are skipped
+    fn line(&mut self, a: [f32; 2], b: [f32; 2], color: [u8; 3]) {
+        let steps = (b[0] - a[0]).abs().max((b[1] - a[1]).abs()).ceil().max(1.0);
+        for i in 0..=steps as usize {
+            let t = i as f32 / steps;
+            let x = a[0] + (b[0] - a[0]) * t;
+            let y = a[1] + (b[1] - a[1]) * t;
+            if x < 0.0 || y < 0.0 || x >= self.width as f32 || y >= self.height as f32 {
+                continue;
+            }
+            let index = (y as usize * self.width as usize + x as usize) * 3;
+            self.pixels[index..index + 3].copy_from_slice(&color);
+        }
+    }
+
+    /// Returns the image encoded as a PNG file
+    fn encode(&self) -> Result<Vec<u8>, png::EncodingError> {
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&self.pixels))?;
+        Ok(png)
+    }
+}
+
+/// Encode `bytes` in base64, the format evcxr expects for images
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
@@ -0,0 +1,66 @@
+use crate::recorder::{timestamp, RECORDINGS_DIR};
+use double_pendulum::sink::{ExportSink, Metadata, Sample};
+use ggez::GameResult;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+/// Writes the orientation of every rod after each physics update into a CSV file,
+/// as an angle, a quaternion and a rotation matrix, for 3D tools
+///
+/// The orientations are rotations about the pivot of each rod, in a right-handed frame
+/// where x points right, y points up and z points out of the screen.
+/// The rest orientation is a rod hanging straight down, so every rotation is about the z axis.
+pub struct OrientationExporter {
+    writer: BufWriter<File>,
+}
+
+impl OrientationExporter {
+    pub fn new(path: Option<&str>, metadata: &Metadata) -> GameResult<Self> {
+        let path = match path {
+            Some(path) => PathBuf::from(path),
+            None => {
+                fs::create_dir_all(RECORDINGS_DIR)?;
+                PathBuf::from(RECORDINGS_DIR).join(format!("{}-orientation.csv", timestamp()))
+            }
+        };
+        let mut writer = BufWriter::new(File::create(&path)?);
+        // The metadata is written as comments before the header
+        for (key, value) in metadata.entries() {
+            writeln!(writer, "# {key}: {value}")?;
+        }
+        writeln!(
+            writer,
+            "time,pendulum,rod,theta,qw,qx,qy,qz,m00,m01,m02,m10,m11,m12,m20,m21,m22"
+        )?;
+        println!("Exporting the orientations to {}", path.display());
+        Ok(Self { writer })
+    }
+}
+
+impl ExportSink for OrientationExporter {
+    fn name(&self) -> &str {
+        "orientation"
+    }
+
+    fn write_sample(&mut self, sample: &Sample) -> GameResult {
+        for (index, pendulum) in sample.pendulums.iter().enumerate() {
+            for (rod, theta) in pendulum.angles().into_iter().enumerate() {
+                let (sin, cos) = theta.sin_cos();
+                let (half_sin, half_cos) = (theta / 2.0).sin_cos();
+                writeln!(
+                    self.writer,
+                    "{},{index},{rod},{theta},{half_cos},0,0,{half_sin},\
+                     {cos},{},0,{sin},{cos},0,0,0,1",
+                    sample.time, -sin,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> GameResult {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,114 @@
+use double_pendulum::pendulum::wrap_angle;
+use double_pendulum::sink::{ExportSink, Sample};
+use ggez::{GameError, GameResult};
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+
+/// Where the messages are sent when no address is given
+const DEFAULT_ADDRESS: &str = "127.0.0.1:9000";
+
+/// Sends the state of every pendulum as OSC messages over UDP, to drive visuals or synths
+/// in tools like TouchDesigner or Max
+///
+/// At most `rate` times per second of wall-clock time, a `/time` message holds the simulated time
+/// and a `/pendulum/N` message for the pendulum `N` holds the angles wrapped between -π and π,
+/// the angular velocities and the positions of both circles relative to the origin,
+/// in the same order as the columns of the CSV export.
+/// The messages are sent whether someone listens or not
+pub struct OscSender {
+    socket: UdpSocket,
+    target: SocketAddr,
+    /// The wall-clock time between two sends
+    interval: Duration,
+    /// When the last messages were sent, if they were
+    last_sent: Option<Instant>,
+}
+
+impl OscSender {
+    /// Send the messages to `address`, written as `HOST:PORT`, at most `rate` times per second
+    pub fn new(address: Option<&str>, rate: f32) -> GameResult<Self> {
+        let address = address.unwrap_or(DEFAULT_ADDRESS);
+        let target = (address.to_socket_addrs()?.next()).ok_or_else(|| {
+            GameError::CustomError(format!("{address} isn't the address of any host"))
+        })?;
+        // Any free port of the same kind of address is fine, nothing is received
+        let local = if target.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        };
+        let socket = UdpSocket::bind(local)?;
+        println!("Sending OSC messages to {target}");
+        Ok(Self {
+            socket,
+            target,
+            interval: Duration::from_secs_f32(1.0 / rate.max(f32::EPSILON)),
+            last_sent: None,
+        })
+    }
+
+    fn send(&self, address: &str, arguments: &[f32]) -> GameResult {
+        self.socket
+            .send_to(&encode(address, arguments), self.target)?;
+        Ok(())
+    }
+}
+
+impl ExportSink for OscSender {
+    fn name(&self) -> &str {
+        "osc"
+    }
+
+    fn write_sample(&mut self, sample: &Sample) -> GameResult {
+        let now = Instant::now();
+        if self
+            .last_sent
+            .is_some_and(|last| now.duration_since(last) < self.interval)
+        {
+            return Ok(());
+        }
+        self.last_sent = Some(now);
+
+        self.send("/time", &[sample.time])?;
+        for (index, pendulum) in sample.pendulums.iter().enumerate() {
+            let [theta1, theta2] = pendulum.angles().map(wrap_angle);
+            let [omega1, omega2] = pendulum.angular_velocities();
+            let [[x1, y1], [x2, y2]] = pendulum.bobs();
+            self.send(
+                &format!("/pendulum/{index}"),
+                &[theta1, theta2, omega1, omega2, x1, y1, x2, y2],
+            )?;
+        }
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> GameResult {
+        println!("Stopped sending OSC messages to {}", self.target);
+        Ok(())
+    }
+}
+
+/// Returns the OSC message sent to `address` with the float `arguments`
+///
+/// The strings are null-terminated and padded to a multiple of 4 bytes,
+/// and the numbers are big-endian
+fn encode(address: &str, arguments: &[f32]) -> Vec<u8> {
+    let mut packet = Vec::new();
+    push_string(&mut packet, address);
+    let tags: String = std::iter::once(',')
+        .chain(arguments.iter().map(|_| 'f'))
+        .collect();
+    push_string(&mut packet, &tags);
+    for argument in arguments {
+        packet.extend_from_slice(&argument.to_be_bytes());
+    }
+    packet
+}
+
+/// Append `string` to `packet` as an OSC string
+fn push_string(packet: &mut Vec<u8>, string: &str) {
+    packet.extend_from_slice(string.as_bytes());
+    // There is always at least one null byte
+    let padding = 4 - string.len() % 4;
+    packet.extend(std::iter::repeat_n(0, padding));
+}
@@ -0,0 +1,221 @@
+use crate::chart::AngleChart;
+use crate::keybindings::Action;
+use crate::lyapunov::Lyapunov;
+use crate::phase::PhasePlot;
+use crate::poincare::PoincareSection;
+use crate::spectrum::Spectrum;
+use double_pendulum::pendulum::Rods;
+use ggez::graphics::{Canvas, Rect};
+use ggez::{Context, GameResult};
+
+/// The width and the height in pixels of the phase space panel
+const PHASE_PANEL_SIZE: [f32; 2] = [340.0, 190.0];
+/// The width and the height in pixels of the Poincaré section panel
+const POINCARE_PANEL_SIZE: [f32; 2] = [240.0, 220.0];
+/// The width and the height in pixels of the spectrum panel
+const SPECTRUM_PANEL_SIZE: [f32; 2] = [300.0, 150.0];
+/// The width and the height in pixels of the angle chart
+const ANGLE_CHART_SIZE: [f32; 2] = [340.0, 150.0];
+
+/// The pendulum followed by the overlays, right after a physics update
+pub struct Followed<'a> {
+    /// The index of the pendulum in the list of pendulums
+    pub index: usize,
+    pub pendulum: &'a Rods,
+    /// The duration of the physics update, in seconds
+    pub dt: f32,
+    /// The wind blowing during the physics update
+    pub wind: f32,
+    /// The simulated time at the start of the physics update, in seconds
+    pub time: f32,
+}
+
+/// An analysis of one pendulum, updated after every physics update and drawn on top of the scenes
+///
+/// Every overlay follows the selected pendulum, or the first one, and starts over when another one is followed
+pub trait Overlay {
+    /// Take the new state of the followed pendulum into account
+    fn update(&mut self, followed: &Followed) -> GameResult;
+
+    /// Draw the overlay in its own part of a window of size `window`
+    fn draw(&self, _ctx: &mut Context, _canvas: &mut Canvas, _window: [f32; 2]) -> GameResult {
+        Ok(())
+    }
+
+    /// Returns the text added to the HUD, if any
+    fn hud(&self) -> Option<String> {
+        None
+    }
+
+    /// Returns an empty overlay of the same kind, when the pendulums are replaced by unrelated ones,
+    /// or `None` if it can't start without a pendulum
+    fn restart(&self) -> Option<Box<dyn Overlay>>;
+}
+
+impl Overlay for PhasePlot {
+    fn update(&mut self, followed: &Followed) -> GameResult {
+        self.record(followed.index, followed.pendulum);
+        Ok(())
+    }
+
+    fn draw(&self, ctx: &mut Context, canvas: &mut Canvas, window: [f32; 2]) -> GameResult {
+        // In the bottom right corner of the window
+        let [width, height] = PHASE_PANEL_SIZE;
+        let area = Rect::new(window[0] - width, window[1] - height, width, height);
+        PhasePlot::draw(self, ctx, canvas, area)
+    }
+
+    fn restart(&self) -> Option<Box<dyn Overlay>> {
+        Some(Box::new(PhasePlot::new()))
+    }
+}
+
+impl Overlay for AngleChart {
+    fn update(&mut self, followed: &Followed) -> GameResult {
+        self.record(followed.index, followed.pendulum, followed.dt);
+        Ok(())
+    }
+
+    fn draw(&self, ctx: &mut Context, canvas: &mut Canvas, window: [f32; 2]) -> GameResult {
+        // In the middle of the bottom of the window
+        let [width, height] = ANGLE_CHART_SIZE;
+        let area = Rect::new((window[0] - width) / 2.0, window[1] - height, width, height);
+        AngleChart::draw(self, ctx, canvas, area)
+    }
+
+    fn restart(&self) -> Option<Box<dyn Overlay>> {
+        Some(Box::new(AngleChart::new()))
+    }
+}
+
+impl Overlay for PoincareSection {
+    fn update(&mut self, followed: &Followed) -> GameResult {
+        self.record(followed.index, followed.pendulum);
+        Ok(())
+    }
+
+    fn draw(&self, ctx: &mut Context, canvas: &mut Canvas, window: [f32; 2]) -> GameResult {
+        // In the bottom left corner of the window, above the wind indicator
+        let [width, height] = POINCARE_PANEL_SIZE;
+        let area = Rect::new(0.0, window[1] - height - 40.0, width, height);
+        PoincareSection::draw(self, ctx, canvas, area)
+    }
+
+    fn restart(&self) -> Option<Box<dyn Overlay>> {
+        Some(Box::new(PoincareSection::new()))
+    }
+}
+
+impl Overlay for Spectrum {
+    fn update(&mut self, followed: &Followed) -> GameResult {
+        self.record(followed.index, followed.pendulum, followed.dt);
+        Ok(())
+    }
+
+    fn draw(&self, ctx: &mut Context, canvas: &mut Canvas, window: [f32; 2]) -> GameResult {
+        // In the middle of the top of the window
+        let [width, height] = SPECTRUM_PANEL_SIZE;
+        let area = Rect::new((window[0] - width) / 2.0, 0.0, width, height);
+        Spectrum::draw(self, ctx, canvas, area)
+    }
+
+    fn restart(&self) -> Option<Box<dyn Overlay>> {
+        Some(Box::new(Spectrum::new()))
+    }
+}
+
+impl Overlay for Lyapunov {
+    fn update(&mut self, followed: &Followed) -> GameResult {
+        let Followed {
+            index,
+            pendulum,
+            dt,
+            wind,
+            time,
+        } = *followed;
+        Lyapunov::update(self, index, pendulum, dt, wind, time)
+    }
+
+    fn hud(&self) -> Option<String> {
+        Some(match self.exponent() {
+            Some(exponent) => format!("Lyapunov exponent: {exponent:.4} /s"),
+            None => "Lyapunov exponent: estimating...".to_string(),
+        })
+    }
+
+    // The shadow pendulum is a copy of the studied one, so it can't outlive it
+    fn restart(&self) -> Option<Box<dyn Overlay>> {
+        None
+    }
+}
+
+/// Returns a new overlay of the kind toggled by `action`, following `pendulum` which is at `index`
+/// in the list of pendulums, or `None` if `action` doesn't toggle an overlay or there is no pendulum to follow
+pub fn create(action: Action, index: usize, pendulum: Option<&Rods>) -> Option<Box<dyn Overlay>> {
+    Some(match action {
+        Action::PhaseSpace => Box::new(PhasePlot::new()),
+        Action::AngleChart => Box::new(AngleChart::new()),
+        Action::Poincare => Box::new(PoincareSection::new()),
+        Action::Spectrum => Box::new(Spectrum::new()),
+        Action::Lyapunov => Box::new(Lyapunov::new(index, pendulum?)),
+        _ => return None,
+    })
+}
+
+/// Every overlay turned on, each one with the action toggling it
+///
+/// They are updated and drawn in the order they were turned on, so the newest one is on top
+pub struct Overlays {
+    active: Vec<(Action, Box<dyn Overlay>)>,
+}
+
+impl Overlays {
+    pub fn new() -> Self {
+        Self { active: Vec::new() }
+    }
+
+    /// Turn off the overlay of `action` if it is on, otherwise turn on the one built by `create`, if any
+    pub fn toggle(&mut self, action: Action, create: impl FnOnce() -> Option<Box<dyn Overlay>>) {
+        if self.active.iter().any(|(a, _)| *a == action) {
+            self.remove(action);
+        } else if let Some(overlay) = create() {
+            self.active.push((action, overlay));
+        }
+    }
+
+    /// Turn off the overlay of `action`, if it is on
+    pub fn remove(&mut self, action: Action) {
+        self.active.retain(|(a, _)| *a != action);
+    }
+
+    /// Empty every overlay, or turn it off if it can't start over, when the pendulums are replaced
+    pub fn restart(&mut self) {
+        self.active = (self.active.drain(..))
+            .filter_map(|(action, overlay)| Some((action, overlay.restart()?)))
+            .collect();
+    }
+
+    /// Update every overlay with the followed pendulum, after a physics update
+    pub fn update(&mut self, followed: &Followed) -> GameResult {
+        for (_, overlay) in &mut self.active {
+            overlay.update(followed)?;
+        }
+        Ok(())
+    }
+
+    /// Draw every overlay into a window of size `window`
+    pub fn draw(&self, ctx: &mut Context, canvas: &mut Canvas, window: [f32; 2]) -> GameResult {
+        for (_, overlay) in &self.active {
+            overlay.draw(ctx, canvas, window)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the text added to the HUD by the overlays, each one on its own line
+    pub fn hud(&self) -> String {
+        (self.active.iter())
+            .filter_map(|(_, overlay)| overlay.hud())
+            .map(|text| format!("\n{text}"))
+            .collect()
+    }
+}
@@ -0,0 +1,400 @@
+use crate::keybindings::Action;
+use ggez::graphics::{self, Canvas, Color, DrawMode, Mesh, Rect};
+use ggez::{Context, GameResult};
+
+/// The width in pixels of the panel
+const PANEL_WIDTH: f32 = 220.0;
+/// The distance in pixels between the top of the window and the panel, below the text in the corner
+const PANEL_TOP: f32 = 200.0;
+/// The height in pixels of the title of the panel, which opens and closes it
+const HEADER_HEIGHT: f32 = 24.0;
+/// The width in pixels of the arrow on the left of the title, which opens and closes the panel
+const ARROW_WIDTH: f32 = 24.0;
+/// The vertical space in pixels taken by each slider and its label
+const SLIDER_SPACING: f32 = 36.0;
+/// The height in pixels of the buttons
+const BUTTON_HEIGHT: f32 = 24.0;
+/// The height in pixels of a line of text
+const LABEL_HEIGHT: f32 = 20.0;
+/// The size in pixels of the square standing for each pendulum in the list of pendulums
+const SWATCH_SIZE: f32 = 20.0;
+/// The largest number of pendulums listed, the others can still be selected by clicking on them
+const MAX_SWATCHES: usize = 40;
+/// The space in pixels around the content of the panel
+const MARGIN: f32 = 10.0;
+/// The buttons at the bottom of the panel, with the action they perform
+const BUTTONS: [(&str, Action); 3] = [
+    ("Spawn", Action::Spawn),
+    ("Reset", Action::Reset),
+    ("Pause", Action::Pause),
+];
+
+/// A value of the simulation that can be changed with a slider of the panel
+#[derive(Clone, Copy, PartialEq)]
+pub enum Setting {
+    Gravity,
+    Damping,
+    Speed,
+    TrailLength,
+    Volume,
+    /// The mass of a circle of the listed pendulum (0 for the first one, 1 for the second one)
+    Mass(usize),
+    /// The length of a rod of the listed pendulum (0 for the first one, 1 for the second one)
+    Length(usize),
+    /// The hue of the color of the listed pendulum, in degrees
+    Hue,
+    Saturation,
+    Brightness,
+}
+
+/// The group of widgets shown in the panel, picked with the tabs of its title
+#[derive(Clone, Copy, PartialEq)]
+pub enum Page {
+    /// The settings of the whole simulation
+    Simulation,
+    /// The list of pendulums and the settings of the selected one
+    Pendulums,
+}
+
+/// A slider of the panel, with the current value of its setting
+pub struct Slider {
+    pub setting: Setting,
+    /// The text above the slider, with the value
+    pub label: String,
+    pub min: f32,
+    pub max: f32,
+    pub value: f32,
+}
+
+impl Slider {
+    /// Returns where the value is in the range of the slider, between 0 and 1
+    fn fraction(&self) -> f32 {
+        ((self.value - self.min) / (self.max - self.min)).clamp(0.0, 1.0)
+    }
+}
+
+/// Something shown in the panel, from the top to the bottom of the page
+pub enum Widget {
+    Slider(Slider),
+    /// A line of text, with a square of `color` on its right if it is given
+    Label {
+        text: String,
+        color: Option<Color>,
+    },
+    /// The buttons performing the most common actions
+    Buttons,
+    /// A square of the color of each pendulum, to select it
+    Pendulums {
+        colors: Vec<Color>,
+        /// The index of the pendulum whose settings are shown, it is outlined
+        selected: Option<usize>,
+    },
+}
+
+impl Widget {
+    /// Returns the vertical space in pixels taken by the widget
+    fn height(&self) -> f32 {
+        match self {
+            Widget::Slider(_) => SLIDER_SPACING,
+            Widget::Label { .. } => LABEL_HEIGHT,
+            Widget::Buttons => BUTTON_HEIGHT + 8.0,
+            Widget::Pendulums { colors, .. } => {
+                let rows = colors.len().min(MAX_SWATCHES).div_ceil(swatches_per_row());
+                rows as f32 * SWATCH_SIZE + 8.0
+            }
+        }
+    }
+}
+
+/// What the user asked for with the panel
+pub enum PanelInput {
+    /// A slider was moved to a new value
+    Set(Setting, f32),
+    /// A button was clicked
+    Press(Action),
+    /// A pendulum of the list was clicked
+    Select(usize),
+}
+
+/// A collapsible panel on the left of the window with sliders for the main settings
+/// and buttons for the most common actions, so the simulation can be controlled with the mouse
+///
+/// Only its title is shown at startup, so it doesn't hide the pendulums of a small window
+///
+/// The widgets of the current page are given by the simulation every time, so they always show the current values
+pub struct Panel {
+    open: bool,
+    page: Page,
+    /// The setting of the slider being dragged, if any
+    dragging: Option<Setting>,
+}
+
+impl Panel {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            page: Page::Simulation,
+            dragging: None,
+        }
+    }
+
+    /// Open the panel if it is closed, or close it
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.dragging = None;
+    }
+
+    /// Returns the page shown by the panel, whose widgets have to be given to the other methods
+    pub fn page(&self) -> Page {
+        self.page
+    }
+
+    /// Returns true if `point` is over the panel, so the clicks there don't reach the simulation
+    pub fn contains(&self, point: [f32; 2], widgets: &[Widget]) -> bool {
+        self.rect(widgets).contains(point)
+    }
+
+    /// Handle a click at `point`, and returns what it asks for
+    pub fn mouse_down(&mut self, point: [f32; 2], widgets: &[Widget]) -> Option<PanelInput> {
+        if header_rect().contains(point) {
+            match tab_at(point[0]) {
+                Some(page) if self.open => {
+                    self.page = page;
+                    self.dragging = None;
+                }
+                _ => self.toggle(),
+            }
+            return None;
+        }
+        if !self.open {
+            return None;
+        }
+
+        let (widget, area) = layout(widgets).find(|(_, area)| area.contains(point))?;
+        match widget {
+            Widget::Slider(slider) => {
+                self.dragging = Some(slider.setting);
+                self.mouse_motion(point, widgets)
+            }
+            Widget::Label { .. } => None,
+            Widget::Buttons => (0..BUTTONS.len())
+                .find(|&i| button_rect(area, i).contains(point))
+                .map(|i| PanelInput::Press(BUTTONS[i].1)),
+            Widget::Pendulums { colors, .. } => (0..colors.len().min(MAX_SWATCHES))
+                .find(|&i| swatch_rect(area, i).contains(point))
+                .map(PanelInput::Select),
+        }
+    }
+
+    /// Handle a move of the mouse to `point`, which moves the slider being dragged
+    pub fn mouse_motion(&mut self, point: [f32; 2], widgets: &[Widget]) -> Option<PanelInput> {
+        let setting = self.dragging?;
+        let slider = widgets.iter().find_map(|widget| match widget {
+            Widget::Slider(slider) if slider.setting == setting => Some(slider),
+            _ => None,
+        })?;
+        let track = track_rect(header_rect());
+        let fraction = ((point[0] - track.x) / track.w).clamp(0.0, 1.0);
+        let value = slider.min + fraction * (slider.max - slider.min);
+        Some(PanelInput::Set(setting, value))
+    }
+
+    /// Stop dragging the slider, when the mouse button is released
+    pub fn mouse_up(&mut self) {
+        self.dragging = None;
+    }
+
+    /// Draw the panel with `widgets`, or only its title if it is closed
+    pub fn draw(&self, ctx: &mut Context, canvas: &mut Canvas, widgets: &[Widget]) -> GameResult {
+        let background = Mesh::new_rectangle(
+            ctx,
+            DrawMode::fill(),
+            self.rect(widgets),
+            Color::new(0.0, 0.0, 0.0, 0.6),
+        )?;
+        canvas.draw(&background, [0.0, 0.0]);
+
+        let arrow = if self.open { "v" } else { ">" };
+        canvas.draw(&graphics::Text::new(arrow), [MARGIN, PANEL_TOP + 4.0]);
+        if !self.open {
+            let title = graphics::Text::new("Controls");
+            canvas.draw(&title, [ARROW_WIDTH + 6.0, PANEL_TOP + 4.0]);
+            return Ok(());
+        }
+        for (page, name) in [
+            (Page::Simulation, "Simulation"),
+            (Page::Pendulums, "Pendulums"),
+        ] {
+            let rect = tab_rect(page);
+            let color = if page == self.page {
+                Color::YELLOW
+            } else {
+                Color::new(0.6, 0.6, 0.6, 1.0)
+            };
+            canvas.draw(
+                &graphics::Text::new(name),
+                graphics::DrawParam::new()
+                    .dest([rect.x + 6.0, rect.y + 4.0])
+                    .color(color),
+            );
+        }
+
+        for (widget, area) in layout(widgets) {
+            match widget {
+                Widget::Slider(slider) => self.draw_slider(ctx, canvas, slider, area)?,
+                Widget::Label { text, color } => {
+                    let label = graphics::Text::new(text.as_str());
+                    canvas.draw(&label, [MARGIN, area.y + 2.0]);
+                    if let Some(color) = color {
+                        let swatch = Rect::new(
+                            PANEL_WIDTH - MARGIN - SWATCH_SIZE,
+                            area.y + 2.0,
+                            SWATCH_SIZE,
+                            LABEL_HEIGHT - 4.0,
+                        );
+                        let swatch = Mesh::new_rectangle(ctx, DrawMode::fill(), swatch, *color)?;
+                        canvas.draw(&swatch, [0.0, 0.0]);
+                    }
+                }
+                Widget::Buttons => {
+                    for (i, (name, _)) in BUTTONS.iter().enumerate() {
+                        let rect = button_rect(area, i);
+                        let button =
+                            Mesh::new_rectangle(ctx, DrawMode::stroke(1.0), rect, Color::WHITE)?;
+                        canvas.draw(&button, [0.0, 0.0]);
+                        let text = graphics::Text::new(*name);
+                        canvas.draw(&text, [rect.x + 6.0, rect.y + 4.0]);
+                    }
+                }
+                Widget::Pendulums { colors, selected } => {
+                    for (i, &color) in colors.iter().take(MAX_SWATCHES).enumerate() {
+                        let rect = swatch_rect(area, i);
+                        let inner =
+                            Rect::new(rect.x + 2.0, rect.y + 2.0, rect.w - 4.0, rect.h - 4.0);
+                        let swatch = Mesh::new_rectangle(ctx, DrawMode::fill(), inner, color)?;
+                        canvas.draw(&swatch, [0.0, 0.0]);
+                        if *selected == Some(i) {
+                            let outline = Mesh::new_rectangle(
+                                ctx,
+                                DrawMode::stroke(2.0),
+                                rect,
+                                Color::WHITE,
+                            )?;
+                            canvas.draw(&outline, [0.0, 0.0]);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Draw `slider` with its label in `area`, highlighted while it is dragged
+    fn draw_slider(
+        &self,
+        ctx: &mut Context,
+        canvas: &mut Canvas,
+        slider: &Slider,
+        area: Rect,
+    ) -> GameResult {
+        let highlight = if self.dragging == Some(slider.setting) {
+            Color::YELLOW
+        } else {
+            Color::WHITE
+        };
+        let label = graphics::Text::new(slider.label.as_str());
+        canvas.draw(
+            &label,
+            graphics::DrawParam::new()
+                .dest([MARGIN, area.y])
+                .color(highlight),
+        );
+
+        let track = track_rect(area);
+        let outline = Mesh::new_rectangle(ctx, DrawMode::stroke(1.0), track, highlight)?;
+        canvas.draw(&outline, [0.0, 0.0]);
+        let filled = Rect::new(track.x, track.y, track.w * slider.fraction(), track.h);
+        if filled.w > 0.0 {
+            let filled = Mesh::new_rectangle(ctx, DrawMode::fill(), filled, highlight)?;
+            canvas.draw(&filled, [0.0, 0.0]);
+        }
+        Ok(())
+    }
+
+    /// Returns the area covered by the panel with `widgets`, or by its title if it is closed
+    fn rect(&self, widgets: &[Widget]) -> Rect {
+        if !self.open {
+            return header_rect();
+        }
+        let bottom = layout(widgets)
+            .last()
+            .map_or(header_rect().bottom(), |(_, area)| area.bottom());
+        Rect::new(0.0, PANEL_TOP, PANEL_WIDTH, bottom + MARGIN - PANEL_TOP)
+    }
+}
+
+/// Returns every widget with its area, from the top to the bottom of the panel
+fn layout(widgets: &[Widget]) -> impl Iterator<Item = (&Widget, Rect)> {
+    let mut top = PANEL_TOP + HEADER_HEIGHT;
+    widgets.iter().map(move |widget| {
+        let area = Rect::new(0.0, top, PANEL_WIDTH, widget.height());
+        top += area.h;
+        (widget, area)
+    })
+}
+
+/// Returns the area of the title of the panel
+fn header_rect() -> Rect {
+    Rect::new(0.0, PANEL_TOP, PANEL_WIDTH, HEADER_HEIGHT)
+}
+
+/// Returns the area of the tab of `page` in the title
+fn tab_rect(page: Page) -> Rect {
+    let width = (PANEL_WIDTH - ARROW_WIDTH) / 2.0;
+    let index = match page {
+        Page::Simulation => 0.0,
+        Page::Pendulums => 1.0,
+    };
+    Rect::new(ARROW_WIDTH + index * width, PANEL_TOP, width, HEADER_HEIGHT)
+}
+
+/// Returns the page whose tab is at the horizontal position `x` of the title, if any
+fn tab_at(x: f32) -> Option<Page> {
+    [Page::Simulation, Page::Pendulums]
+        .into_iter()
+        .find(|&page| tab_rect(page).contains([x, PANEL_TOP]))
+}
+
+/// Returns the area of the track of the slider in `area`
+fn track_rect(area: Rect) -> Rect {
+    Rect::new(MARGIN, area.y + 18.0, PANEL_WIDTH - 2.0 * MARGIN, 6.0)
+}
+
+/// Returns the area of the button at `index` among the buttons in `area`
+fn button_rect(area: Rect, index: usize) -> Rect {
+    let count = BUTTONS.len() as f32;
+    let width = (PANEL_WIDTH - MARGIN * (count + 1.0)) / count;
+    Rect::new(
+        MARGIN + index as f32 * (width + MARGIN),
+        area.y + 4.0,
+        width,
+        BUTTON_HEIGHT,
+    )
+}
+
+/// Returns the number of pendulums on each row of the list of pendulums
+fn swatches_per_row() -> usize {
+    ((PANEL_WIDTH - 2.0 * MARGIN) / SWATCH_SIZE) as usize
+}
+
+/// Returns the area of the square of the pendulum at `index` in the list of pendulums in `area`
+fn swatch_rect(area: Rect, index: usize) -> Rect {
+    let row = index / swatches_per_row();
+    let column = index % swatches_per_row();
+    Rect::new(
+        MARGIN + column as f32 * SWATCH_SIZE,
+        area.y + 4.0 + row as f32 * SWATCH_SIZE,
+        SWATCH_SIZE,
+        SWATCH_SIZE,
+    )
+}
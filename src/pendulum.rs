@@ -1,18 +1,33 @@
 use std::collections::VecDeque;
+use std::mem;
+use std::ops::{Deref, DerefMut};
 
-use ggez::graphics;
-use ggez::graphics::Canvas;
-use ggez::graphics::DrawMode;
-use ggez::graphics::Mesh;
-use ggez::Context;
-use ggez::GameResult;
 use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::color::{Color, Palette};
+use crate::integrator::{Dynamics, IntegratorKind, PendulumState, VELOCITY_SCALE};
+use crate::simplify;
 use std::f32::consts::PI;
 
 /// I know gravity is 9.80m/s^2 in real life, but this is a simulation
-const GRAVITY: f32 = 1.0;
-/// The number of previous positions stored for the trail
-const TRAIL_LENGTH: usize = 100;
+pub const GRAVITY: f32 = 1.0;
+/// The distance in pixels travelled by the tip between two points of the trail
+///
+/// Sampling by distance instead of time keeps the trail equally detailed at every simulation speed
+const TRAIL_SPACING: f32 = 3.0;
+/// The number of previous positions stored for the trail of a new pendulum
+///
+/// A length of 0 gives an unlimited trail, which is then periodically simplified
+pub const DEFAULT_TRAIL_LENGTH: usize = 100;
+/// The number of points an unlimited trail can hold before being simplified
+const TRAIL_SIMPLIFY_THRESHOLD: usize = 4000;
+/// The maximum distance in pixels between the simplified trail and the original one
+const TRAIL_SIMPLIFY_EPSILON: f32 = 0.5;
+/// The fastest angular velocity kept by `Rods::recover`, per 1/60 of a second like the angular velocities
+///
+/// No pendulum released by hand comes close, so reaching it means the integrator blew up
+pub const MAX_SPEED: f32 = 10.0;
 
 // Useful resources:
 // https://www.myphysicslab.com/pendulum/double-pendulum-en.html
@@ -20,6 +35,7 @@ const TRAIL_LENGTH: usize = 100;
 // https://en.wikipedia.org/wiki/Euler_method
 
 /// A single pendulum used to store data for its physics calculation
+#[derive(Clone, Copy, Serialize, Deserialize)]
 struct Pendulum {
     /// The mass of the circle (the lines have zero mass)
     /// This also affects the size of the circle
@@ -27,9 +43,14 @@ struct Pendulum {
     /// The length of the rod, in pixels
     radius: f32,
     /// The angle of the pendulum in radians (0 is pointing down, PI/2 is pointing right)
+    ///
+    /// It is kept between -PI (excluded) and PI, the full turns go to `revolutions`
     theta: f32,
     /// The speed at which the pendulum moves
     speed: f32,
+    /// The number of full turns made by the rod, counterclockwise is positive
+    #[serde(default)]
+    revolutions: i32,
 }
 
 impl Pendulum {
@@ -37,11 +58,19 @@ impl Pendulum {
         Self {
             mass,
             radius,
-            theta,
+            theta: wrap_turns(theta).0,
             speed,
+            revolutions: 0,
         }
     }
 
+    /// Bring the angle back between -PI and PI, counting the full turns it made
+    fn normalize(&mut self) {
+        let (theta, turns) = wrap_turns(self.theta);
+        self.theta = theta;
+        self.revolutions = self.revolutions.saturating_add(turns);
+    }
+
     /// Returns the x coordinate of the tip of the rod
     fn x(&self) -> f32 {
         self.radius * self.theta.sin()
@@ -53,37 +82,311 @@ impl Pendulum {
     }
 }
 
-pub struct DoublePendulum {
+/// The physical constants of a double pendulum
+///
+/// Every pendulum has its own copy, so a single simulation can mix
+/// damped, driven and ideal pendulums
+///
+/// It is laid out like the `Physics` struct of `gpu.wgsl`, to be sent to the graphics card as it is
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[cfg_attr(feature = "gpu", derive(bytemuck::Pod, bytemuck::Zeroable))]
+#[repr(C)]
+pub struct Physics {
+    /// The acceleration pulling the masses down
+    pub gravity: f32,
+    /// The friction slowing down the rotation of both rods, 0 means no friction
+    pub damping: f32,
+    /// The amplitude of the periodic angular acceleration driving the first rod
+    pub drive_amplitude: f32,
+    /// The frequency of the driving force, in hertz
+    pub drive_frequency: f32,
+}
+
+impl Default for Physics {
+    fn default() -> Self {
+        Self {
+            gravity: GRAVITY,
+            damping: 0.0,
+            drive_amplitude: 0.0,
+            drive_frequency: 0.0,
+        }
+    }
+}
+
+/// What `Rods::recover` did to a pendulum whose state went wrong
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Recovery {
+    /// The angular velocities were too fast or not finite, they were clamped to `MAX_SPEED`
+    Clamped,
+    /// The angles were not finite, so the pendulum was put back at rest, hanging straight down
+    Reset,
+}
+
+/// The initial state of a double pendulum
+///
+/// Every missing value is chosen randomly
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct InitialConditions {
+    /// The masses of both circles
+    pub m1: Option<f32>,
+    pub m2: Option<f32>,
+    /// The lengths of both rods, in pixels
+    pub l1: Option<f32>,
+    pub l2: Option<f32>,
+    /// The angles of both rods, in radians
+    pub theta1: Option<f32>,
+    pub theta2: Option<f32>,
+    /// The angular velocities of both rods
+    pub v1: Option<f32>,
+    pub v2: Option<f32>,
+}
+
+/// The rods and the circles of a double pendulum, everything but its trails
+///
+/// It is small and `Copy`, so it is what `Pendulums` returns when a single pendulum is read
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Rods {
     /// The first pendulum connected to the origin
     p1: Pendulum,
     /// The second pendulum attached at the tip of p1
     p2: Pendulum,
+    color: Color,
+    physics: Physics,
+    integrator: IntegratorKind,
+}
+
+/// The previous positions of both circles of a double pendulum
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct Trail {
     trail: VecDeque<[f32; 2]>,
-    color: graphics::Color,
+    /// The speed of the second circle at each point of the trail, in the same order as `trail`
+    trail_speeds: VecDeque<f32>,
+    /// The value of `ticks` when each point of the trail was added, in the same order as `trail`
+    #[serde(default)]
+    trail_ticks: VecDeque<u32>,
+    /// The number of physics updates recorded so far, it wraps around
+    #[serde(default)]
+    ticks: u32,
+    /// The number of points added to the trail since the last call to `take_new_trail`
+    unread_trail: usize,
+    /// The previous positions of the first circle
+    joint_trail: VecDeque<[f32; 2]>,
+    /// The number of positions kept in the trails, 0 for unlimited trails
+    #[serde(default = "default_trail_length")]
+    trail_length: usize,
+    /// Whether the trail is drawn in rainbow colors, whatever the style of the other trails
+    #[serde(default)]
+    rainbow: bool,
+}
+
+/// The trails of a double pendulum borrowed as slices, to draw them
+pub struct TrailSlices<'a> {
+    /// The previous positions of the second circle, from the oldest to the newest
+    pub trail: &'a [[f32; 2]],
+    /// The speed of the second circle at each point of `trail`
+    pub speeds: &'a [f32],
+    /// The physics update at which each point of `trail` was added
+    pub ticks: &'a [u32],
+    /// The previous positions of the first circle, from the oldest to the newest
+    pub joint: &'a [[f32; 2]],
+}
+
+/// A double pendulum with its trails
+///
+/// Every method of `Rods` can be called on it directly
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DoublePendulum {
+    #[serde(flatten)]
+    rods: Rods,
+    #[serde(flatten)]
+    trails: Trail,
 }
 
 impl DoublePendulum {
-    /// Create a new DoublePendulum with a random initial state
+    /// Create a new DoublePendulum, the missing initial conditions are chosen randomly
+    /// and its color is picked from `palette`
     ///
-    /// The double pendulum will spawn straight in the top half with no initial speed
-    pub fn new(length: f32) -> Self {
+    /// By default, the double pendulum will spawn straight in the top half with no initial speed
+    pub fn new(
+        rng: &mut impl Rng,
+        length: f32,
+        physics: Physics,
+        initial: &InitialConditions,
+        palette: Palette,
+    ) -> Self {
         let length = length / 2.0;
-        let mut rng = rand::thread_rng();
 
-        let m1 = rng.gen_range(2.0..5.0);
-        let m2 = rng.gen_range(2.0..5.0);
+        let m1 = initial.m1.unwrap_or_else(|| rng.gen_range(2.0..5.0));
+        let m2 = initial.m2.unwrap_or_else(|| rng.gen_range(2.0..5.0));
         let radius = rng.gen_range(-50.0..50.0);
+        let l1 = initial.l1.unwrap_or(length + radius);
+        let l2 = initial.l2.unwrap_or(length - radius);
         let theta = rng.gen_range(0.0..PI) + PI / 2.0;
+        let theta1 = initial.theta1.unwrap_or(theta);
+        let theta2 = initial.theta2.unwrap_or(theta);
+        let v1 = initial.v1.unwrap_or(0.0);
+        let v2 = initial.v2.unwrap_or(0.0);
+
+        let color = palette.color(rng);
+
+        let rods = Rods {
+            p1: Pendulum::new(m1, l1, theta1, v1),
+            p2: Pendulum::new(m2, l2, theta2, v2),
+            color,
+            physics,
+            integrator: IntegratorKind::default(),
+        };
+        Self::from_parts(rods, Trail::with_capacity(DEFAULT_TRAIL_LENGTH))
+    }
 
-        let r = rng.gen_range(0.0..=1.0);
-        let g = rng.gen_range(0.0..=1.0);
-        let b = rng.gen_range(0.0..=1.0);
+    /// Put together a pendulum from its rods and its trails
+    pub(crate) fn from_parts(rods: Rods, trails: Trail) -> Self {
+        Self { rods, trails }
+    }
+
+    /// Returns the rods and the trails of the pendulum, to store them separately
+    pub(crate) fn into_parts(self) -> (Rods, Trail) {
+        (self.rods, self.trails)
+    }
 
+    /// Move the trails out of the pendulum, leaving empty ones
+    pub(crate) fn take_trails(&mut self) -> Trail {
+        mem::take(&mut self.trails)
+    }
+
+    /// Returns the previous positions of the second circle relative to the origin, from the oldest to the newest
+    pub fn trail(&self) -> &VecDeque<[f32; 2]> {
+        &self.trails.trail
+    }
+
+    /// Returns the speed of the second circle at each point of the trail
+    pub fn trail_speeds(&self) -> &VecDeque<f32> {
+        &self.trails.trail_speeds
+    }
+
+    /// Returns the previous positions of the first circle relative to the origin, from the oldest to the newest
+    pub fn joint_trail(&self) -> &VecDeque<[f32; 2]> {
+        &self.trails.joint_trail
+    }
+
+    /// Returns the physics update at which each point of the trail was added, counted by the trail itself
+    ///
+    /// The counter wraps around, so only the differences between the ticks are meaningful
+    pub fn trail_ticks(&self) -> &VecDeque<u32> {
+        &self.trails.trail_ticks
+    }
+
+    /// Returns the trail, the speeds and the ticks along it and the joint trail as slices, to draw them
+    ///
+    /// Their storage is rearranged if needed, which doesn't allocate
+    pub fn contiguous_trails(&mut self) -> TrailSlices<'_> {
+        TrailSlices {
+            trail: self.trails.trail.make_contiguous(),
+            speeds: self.trails.trail_speeds.make_contiguous(),
+            ticks: self.trails.trail_ticks.make_contiguous(),
+            joint: self.trails.joint_trail.make_contiguous(),
+        }
+    }
+
+    /// Returns whether the trail is drawn in rainbow colors, whatever the style of the other trails
+    pub fn rainbow_trail(&self) -> bool {
+        self.trails.rainbow
+    }
+
+    /// Draw the trail in rainbow colors or not, whatever the style of the other trails
+    pub fn set_rainbow_trail(&mut self, rainbow: bool) {
+        self.trails.rainbow = rainbow;
+    }
+
+    /// Put the second circle at `target`, relative to the origin, with no speed
+    ///
+    /// The angle of the first rod is solved by inverse kinematics
+    pub fn place(&mut self, target: [f32; 2]) {
+        let (theta1, theta2) = inverse_kinematics(
+            self.rods.p1.radius,
+            self.rods.p2.radius,
+            target,
+            self.rods.p1.theta,
+        );
+        self.rods.p1 = Pendulum::new(self.rods.p1.mass, self.rods.p1.radius, theta1, 0.0);
+        self.rods.p2 = Pendulum::new(self.rods.p2.mass, self.rods.p2.radius, theta2, 0.0);
+        self.trails.clear();
+    }
+
+    /// Returns the number of positions kept in the trails, 0 for unlimited trails
+    pub fn trail_length(&self) -> usize {
+        self.trails.trail_length
+    }
+
+    /// Change the number of positions kept in the trails, 0 for unlimited trails
+    ///
+    /// The oldest positions are dropped right away if the trails are too long
+    pub fn set_trail_length(&mut self, length: usize) {
+        self.trails.set_length(length);
+    }
+
+    /// Update the trails by popping their oldest point and pushing a new point in them
+    pub fn update_trail(&mut self) {
+        self.trails.record(&self.rods);
+    }
+
+    /// Returns the points added to the trail since the last call, relative to the origin
+    ///
+    /// They are preceded by the newest point returned by the previous call if it is still in the trail,
+    /// so the returned path continues the one drawn before
+    pub fn take_new_trail(&mut self) -> Vec<[f32; 2]> {
+        let count = (self.trails.unread_trail + 1).min(self.trails.trail.len());
+        self.trails.unread_trail = 0;
+        self.trails
+            .trail
+            .range(self.trails.trail.len() - count..)
+            .copied()
+            .collect()
+    }
+
+    /// Update the double pendulum and its trail one step forward
+    ///
+    /// `dt` is the simulated duration of the step in seconds,
+    /// `wind` is the horizontal acceleration applied to both masses,
+    /// and `time` is the simulated time in seconds, used by the driving force
+    pub fn update(&mut self, dt: f32, wind: f32, time: f32) {
+        self.forward(dt, wind, time);
+
+        self.update_trail();
+    }
+}
+
+impl Deref for DoublePendulum {
+    type Target = Rods;
+
+    fn deref(&self) -> &Rods {
+        &self.rods
+    }
+}
+
+impl DerefMut for DoublePendulum {
+    fn deref_mut(&mut self) -> &mut Rods {
+        &mut self.rods
+    }
+}
+
+impl Rods {
+    /// Put together the rods from the values of both of them
+    pub(crate) fn new(
+        masses: [f32; 2],
+        lengths: [f32; 2],
+        angles: [f32; 2],
+        velocities: [f32; 2],
+        color: Color,
+        physics: Physics,
+        integrator: IntegratorKind,
+    ) -> Self {
         Self {
-            p1: Pendulum::new(m1, length + radius, theta, 0.0),
-            p2: Pendulum::new(m2, length - radius, theta, 0.0),
-            trail: VecDeque::with_capacity(TRAIL_LENGTH),
-            color: graphics::Color::new(r, g, b, 1.0),
+            p1: Pendulum::new(masses[0], lengths[0], angles[0], velocities[0]),
+            p2: Pendulum::new(masses[1], lengths[1], angles[1], velocities[1]),
+            color,
+            physics,
+            integrator,
         }
     }
 
@@ -93,17 +396,23 @@ impl DoublePendulum {
     ///
     /// The function returns a1 and a2,
     /// which are the angular acceleration of both pendulums
-    fn compute_acceleration(&self) -> (f32, f32) {
+    /// if their angles and angular velocities were `state`, written as `[θ1, θ2, ω1, ω2]`
+    ///
+    /// A uniform horizontal `wind` acceleration adds up with the gravity into a tilted gravity,
+    /// so it is handled by measuring the angles from the direction of that tilted gravity
+    pub fn compute_acceleration(&self, state: [f32; 4], wind: f32) -> (f32, f32) {
+        let tilt = wind.atan2(self.physics.gravity);
+
         // Name the variables in a similar fashion to the website
         let m1 = self.p1.mass;
         let m2 = self.p2.mass;
         let l1 = self.p1.radius;
         let l2 = self.p2.radius;
-        let t1 = self.p1.theta;
-        let t2 = self.p2.theta;
-        let s1sq = self.p1.speed * self.p1.speed;
-        let s2sq = self.p2.speed * self.p2.speed;
-        let g = GRAVITY;
+        let t1 = state[0] - tilt;
+        let t2 = state[1] - tilt;
+        let s1sq = state[2] * state[2];
+        let s2sq = state[3] * state[3];
+        let g = self.physics.gravity.hypot(wind);
 
         // Make the `sin` and `cos` syntax more natural
         let sin = f32::sin;
@@ -128,120 +437,529 @@ impl DoublePendulum {
 
         let a1 = num1 / (l1 * denom);
         let a2 = num2 / (l2 * denom);
-        return (a1, a2);
+        (a1, a2)
     }
 
-    /// Advance the simulation one step forward
-    fn forward(&mut self, desired_fps: u32) {
-        let step = 60.0 / desired_fps as f32;
-        let (a1, a2) = self.compute_acceleration();
+    /// Advance the simulation one step forward with the integrator of the pendulum, without updating the trail
+    pub fn forward(&mut self, dt: f32, wind: f32, time: f32) {
+        let mut state = PendulumState {
+            theta: [self.p1.theta, self.p2.theta],
+            omega: [self.p1.speed, self.p2.speed],
+            time,
+        };
 
         // TODO Should make sure that we don't start spinning weirdly because of the lack of resistance
-        // ? Maybe add a speed limit
         // ? Maybe make sure to keep the same mechanic energy through the whole simulation
-        // ! Should make sure that theta and speed is a finite f32, or else ggez will crash
-        self.p1.speed += step * a1;
-        self.p2.speed += step * a2;
-        self.p1.theta += step * self.p1.speed;
-        self.p2.theta += step * self.p2.speed;
-
-        // ? Might be useful to uncomment if the pendulum spins a million times
-        // ? and f32 precision starts to be noticeable
-        // self.p1.theta %= PI / 2.0;
-        // self.p2.theta %= PI / 2.0;
-    }
-
-    /// Update self.trail by popping the oldest point and pushing a new point in it
-    fn update_trail(&mut self) {
-        let x = self.p1.x() + self.p2.x();
-        let y = self.p1.y() + self.p2.y();
-        let point = [x, y];
-
-        // Push the current trail position if it's not the same as the previous one
-        if let Some(p) = self.trail.back() {
-            // ? Should check if the distance is smaller than a threshold
-            if p == &point {
-                return;
+        // The angles and the speeds can still blow up, `recover` puts the pendulum back in a drawable state
+        let forces = Forces {
+            pendulum: self,
+            wind,
+        };
+        self.integrator.integrator().step(&mut state, dt, &forces);
+        [self.p1.theta, self.p2.theta] = state.theta;
+        [self.p1.speed, self.p2.speed] = state.omega;
+
+        // Wrapping every step keeps the precision of f32 even after a million turns
+        self.p1.normalize();
+        self.p2.normalize();
+    }
+
+    /// Returns the numerical method advancing this pendulum
+    pub fn integrator(&self) -> IntegratorKind {
+        self.integrator
+    }
+
+    /// Change the numerical method advancing this pendulum
+    pub fn set_integrator(&mut self, integrator: IntegratorKind) {
+        self.integrator = integrator;
+    }
+
+    /// Returns the physical constants of this pendulum
+    pub fn physics(&self) -> &Physics {
+        &self.physics
+    }
+
+    /// Returns the physical constants of this pendulum, which can be changed at any time
+    pub fn physics_mut(&mut self) -> &mut Physics {
+        &mut self.physics
+    }
+
+    /// Returns the positions of both circles, relative to the origin
+    pub fn bobs(&self) -> [[f32; 2]; 2] {
+        let p1 = [self.p1.x(), self.p1.y()];
+        let p2 = [p1[0] + self.p2.x(), p1[1] + self.p2.y()];
+        [p1, p2]
+    }
+
+    /// Returns the angles of both rods, in radians
+    pub fn angles(&self) -> [f32; 2] {
+        [self.p1.theta, self.p2.theta]
+    }
+
+    /// Change the angles of both rods, in radians
+    ///
+    /// Angles outside of -PI and PI are wrapped, and their full turns are added to the revolutions
+    pub fn set_angles(&mut self, angles: [f32; 2]) {
+        self.p1.theta = angles[0];
+        self.p2.theta = angles[1];
+        self.p1.normalize();
+        self.p2.normalize();
+    }
+
+    /// Returns the number of full turns made by both rods since they were placed,
+    /// counterclockwise is positive
+    pub fn revolutions(&self) -> [i32; 2] {
+        [self.p1.revolutions, self.p2.revolutions]
+    }
+
+    /// Returns true if one of the rods went over the top at least once
+    pub fn has_flipped(&self) -> bool {
+        self.revolutions().iter().any(|&turns| turns != 0)
+    }
+
+    /// Returns the angles of both rods with their full turns added back, in radians
+    ///
+    /// They are computed in f64 so the fractional part stays precise after many turns
+    pub fn total_angles(&self) -> [f64; 2] {
+        let total = |p: &Pendulum| p.theta as f64 + p.revolutions as f64 * std::f64::consts::TAU;
+        [total(&self.p1), total(&self.p2)]
+    }
+
+    /// Returns these rods with the given number of full turns, which is how the columns rebuild them
+    pub(crate) fn with_revolutions(mut self, revolutions: [i32; 2]) -> Self {
+        [self.p1.revolutions, self.p2.revolutions] = revolutions;
+        self
+    }
+
+    /// Returns the angular velocities of both rods
+    pub fn angular_velocities(&self) -> [f32; 2] {
+        [self.p1.speed, self.p2.speed]
+    }
+
+    /// Change the angular velocities of both rods
+    pub fn set_angular_velocities(&mut self, velocities: [f32; 2]) {
+        self.p1.speed = velocities[0];
+        self.p2.speed = velocities[1];
+    }
+
+    /// Returns the masses of both circles
+    pub fn masses(&self) -> [f32; 2] {
+        [self.p1.mass, self.p2.mass]
+    }
+
+    /// Returns the lengths of both rods, in pixels
+    pub fn lengths(&self) -> [f32; 2] {
+        [self.p1.radius, self.p2.radius]
+    }
+
+    /// Change the mass of a circle (0 for the first one, 1 for the second one)
+    ///
+    /// The angles and the angular velocities are kept, so the motion goes on from the same state
+    pub fn set_mass(&mut self, bob: usize, mass: f32) {
+        match bob {
+            0 => self.p1.mass = mass,
+            _ => self.p2.mass = mass,
+        }
+    }
+
+    /// Change the length of a rod (0 for the first one, 1 for the second one), in pixels
+    ///
+    /// The angles and the angular velocities are kept, so the motion goes on from the same state
+    pub fn set_length(&mut self, bob: usize, length: f32) {
+        match bob {
+            0 => self.p1.radius = length,
+            _ => self.p2.radius = length,
+        }
+    }
+
+    /// Returns the current state as initial conditions, to create the same pendulum from scratch
+    pub fn conditions(&self) -> InitialConditions {
+        InitialConditions {
+            m1: Some(self.p1.mass),
+            m2: Some(self.p2.mass),
+            l1: Some(self.p1.radius),
+            l2: Some(self.p2.radius),
+            theta1: Some(self.p1.theta),
+            theta2: Some(self.p2.theta),
+            v1: Some(self.p1.speed),
+            v2: Some(self.p2.speed),
+        }
+    }
+
+    /// Returns the kinetic energy of both circles
+    ///
+    /// The angular velocities are measured per 1/60 of a second like in `forward`,
+    /// so the energy is in the same arbitrary units as the gravity
+    pub fn kinetic_energy(&self) -> f32 {
+        let (l1, l2) = (self.p1.radius, self.p2.radius);
+        let (w1, w2) = (self.p1.speed, self.p2.speed);
+        let v1_sq = l1 * l1 * w1 * w1;
+        let v2_sq = v1_sq
+            + l2 * l2 * w2 * w2
+            + 2.0 * l1 * l2 * w1 * w2 * (self.p1.theta - self.p2.theta).cos();
+        0.5 * (self.p1.mass * v1_sq + self.p2.mass * v2_sq)
+    }
+
+    /// Returns the potential energy of both circles, which is 0 at the height of the origin
+    pub fn potential_energy(&self) -> f32 {
+        // The y axis points down, so the height is -y
+        let [[_, y1], [_, y2]] = self.bobs();
+        -self.physics.gravity * (self.p1.mass * y1 + self.p2.mass * y2)
+    }
+
+    /// Returns the potential energy when both rods hang straight down, which is the lowest one
+    pub fn rest_potential_energy(&self) -> f32 {
+        let (l1, l2) = (self.p1.radius, self.p2.radius);
+        -self.physics.gravity * (self.p1.mass * l1 + self.p2.mass * (l1 + l2))
+    }
+
+    /// Returns the index of the circle under `point` (0 for the first one, 1 for the second one)
+    ///
+    /// `point` is relative to the origin, and the second circle is checked first since it is drawn on top
+    pub fn bob_at(&self, point: [f32; 2]) -> Option<usize> {
+        let masses = [self.p1.mass, self.p2.mass];
+        let bobs = self.bobs();
+        (0..2).rev().find(|&i| {
+            let [x, y] = bobs[i];
+            (point[0] - x).hypot(point[1] - y) <= 4.0 * masses[i] + 4.0
+        })
+    }
+
+    /// Move a circle to `target`, relative to the origin, by solving the angles of the rods
+    ///
+    /// The angular velocities are computed from the motion since the previous call `dt` seconds ago,
+    /// so the pendulum keeps the velocity imparted by the drag once it is released
+    pub fn drag(&mut self, bob: usize, target: [f32; 2], dt: f32) {
+        let (theta1, theta2) = if bob == 0 {
+            // The first rod points to the target, and the second one keeps its angle
+            (target[0].atan2(target[1]), self.p2.theta)
+        } else {
+            inverse_kinematics(self.p1.radius, self.p2.radius, target, self.p1.theta)
+        };
+
+        let step = 60.0 * dt;
+        if step > 0.0 {
+            self.p1.speed = wrap_angle(theta1 - self.p1.theta) / step;
+            self.p2.speed = wrap_angle(theta2 - self.p2.theta) / step;
+        }
+        self.p1.theta = theta1;
+        self.p2.theta = theta2;
+    }
+
+    /// Change the color of the pendulum
+    pub fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    /// Returns the color of the pendulum
+    pub fn color(&self) -> Color {
+        self.color
+    }
+
+    /// Returns the color of the trails of the pendulum, its own color with the saturation scaled by `saturation`
+    pub fn trail_color(&self, saturation: f32) -> Color {
+        self.color.desaturated(saturation)
+    }
+
+    /// Returns how far the state of this pendulum is from the state of `other`
+    ///
+    /// This is the euclidean distance between the angles of both rods,
+    /// where each angle difference is wrapped between -PI and PI
+    pub fn divergence(&self, other: &Rods) -> f32 {
+        let d1 = wrap_angle(self.p1.theta - other.p1.theta);
+        let d2 = wrap_angle(self.p2.theta - other.p2.theta);
+        d1.hypot(d2)
+    }
+
+    /// Returns true if the angles are finite and the angular velocities are finite and below `MAX_SPEED`
+    pub fn is_valid(&self) -> bool {
+        let valid = |p: &Pendulum| p.theta.is_finite() && p.speed.abs() <= MAX_SPEED;
+        valid(&self.p1) && valid(&self.p2)
+    }
+
+    /// Put the pendulum back in a valid state if it isn't, and returns what was done
+    ///
+    /// A non-finite angle can't be recovered, so the pendulum is reset at rest hanging down,
+    /// while speeds that are too fast or not finite are clamped and the motion goes on.
+    /// The masses and the lengths are left untouched
+    pub fn recover(&mut self) -> Option<Recovery> {
+        if self.is_valid() {
+            return None;
+        }
+        if !self.p1.theta.is_finite() || !self.p2.theta.is_finite() {
+            for p in [&mut self.p1, &mut self.p2] {
+                p.theta = 0.0;
+                p.speed = 0.0;
+                p.revolutions = 0;
+            }
+            return Some(Recovery::Reset);
+        }
+        for p in [&mut self.p1, &mut self.p2] {
+            // NaN has no direction, so the rod stops
+            p.speed = if p.speed.is_nan() {
+                0.0
+            } else {
+                p.speed.clamp(-MAX_SPEED, MAX_SPEED)
+            };
+        }
+        Some(Recovery::Clamped)
+    }
+
+    /// Reverse the direction of the simulation by negating the angular velocities
+    ///
+    /// The pendulum then retraces its path, up to the errors of the integrator
+    pub fn reverse(&mut self) {
+        self.p1.speed = -self.p1.speed;
+        self.p2.speed = -self.p2.speed;
+    }
+
+    /// Returns the speed of the second circle, in pixels per 1/60 of a second like the angular velocities
+    pub fn tip_speed(&self) -> f32 {
+        let [_, [vx, vy]] = self.velocities();
+        vx.hypot(vy)
+    }
+
+    /// Returns the velocities of both circles, in pixels per 1/60 of a second like the angular velocities
+    pub fn velocities(&self) -> [[f32; 2]; 2] {
+        let v1 = [
+            self.p1.radius * self.p1.theta.cos() * self.p1.speed,
+            -self.p1.radius * self.p1.theta.sin() * self.p1.speed,
+        ];
+        let v2 = [
+            self.p2.radius * self.p2.theta.cos() * self.p2.speed,
+            -self.p2.radius * self.p2.theta.sin() * self.p2.speed,
+        ];
+        [v1, [v1[0] + v2[0], v1[1] + v2[1]]]
+    }
+}
+
+impl Trail {
+    /// Returns empty trails with room for `capacity` positions, keeping `DEFAULT_TRAIL_LENGTH` of them
+    fn with_capacity(capacity: usize) -> Self {
+        Self {
+            trail: VecDeque::with_capacity(capacity),
+            trail_speeds: VecDeque::with_capacity(capacity),
+            trail_ticks: VecDeque::with_capacity(capacity),
+            ticks: 0,
+            unread_trail: 0,
+            joint_trail: VecDeque::with_capacity(capacity),
+            trail_length: DEFAULT_TRAIL_LENGTH,
+            rainbow: false,
+        }
+    }
+
+    /// Remove every position, the trail length is kept
+    fn clear(&mut self) {
+        self.trail.clear();
+        self.trail_speeds.clear();
+        self.trail_ticks.clear();
+        self.unread_trail = 0;
+        self.joint_trail.clear();
+    }
+
+    /// Change the number of positions kept, 0 for unlimited trails, and drop the oldest ones past it
+    fn set_length(&mut self, length: usize) {
+        self.trail_length = length;
+        if length == 0 {
+            return;
+        }
+        if self.trail.len() > length {
+            let excess = self.trail.len() - length;
+            self.trail.drain(..excess);
+            self.trail_speeds.drain(..excess);
+            self.trail_ticks.drain(..excess.min(self.trail_ticks.len()));
+            self.unread_trail = self.unread_trail.min(length);
+        }
+        if self.joint_trail.len() > length {
+            self.joint_trail.drain(..self.joint_trail.len() - length);
+        }
+    }
+
+    /// Push the positions of the circles of `rods` if they moved far enough, dropping the oldest ones if needed
+    pub(crate) fn record(&mut self, rods: &Rods) {
+        let [joint, point] = rods.bobs();
+        self.ticks = self.ticks.wrapping_add(1);
+
+        // Push the current trail positions only once the circles moved far enough from the previous ones
+        if is_far_enough(self.joint_trail.back(), joint) {
+            if self.trail_length == 0 {
+                if self.joint_trail.len() >= TRAIL_SIMPLIFY_THRESHOLD {
+                    self.simplify_joint();
+                }
+            } else if self.joint_trail.len() >= self.trail_length {
+                self.joint_trail.pop_front();
             }
+            self.joint_trail.push_back(joint);
+        }
+
+        if !is_far_enough(self.trail.back(), point) {
+            return;
         }
-        if self.trail.len() >= TRAIL_LENGTH {
+        if self.trail_length == 0 {
+            if self.trail.len() >= TRAIL_SIMPLIFY_THRESHOLD {
+                self.simplify();
+            }
+        } else if self.trail.len() >= self.trail_length {
             self.trail.pop_front();
+            self.trail_speeds.pop_front();
+            self.trail_ticks.pop_front();
         }
         self.trail.push_back(point);
+        self.trail_speeds.push_back(rods.tip_speed());
+        self.trail_ticks.push_back(self.ticks);
+        self.unread_trail += 1;
     }
 
-    fn draw_trail(
-        &mut self,
-        ctx: &mut Context,
-        canvas: &mut Canvas,
-        center: [f32; 2],
-    ) -> GameResult {
-        if self.trail.len() >= 3 {
-            let trail = Mesh::new_line(
-                ctx,
-                self.trail.make_contiguous(),
-                2.0,
-                [0.1, 0.5, 0.1, 1.0].into(),
-            )?;
-            canvas.draw(&trail, center);
-        }
+    /// Shrink the trail with the Ramer–Douglas–Peucker algorithm while keeping its shape
+    ///
+    /// If the path is so tangled that simplifying it isn't enough,
+    /// the oldest points are dropped so the memory usage stays bounded
+    fn simplify(&mut self) {
+        let keep = simplify::rdp_mask(self.trail.make_contiguous(), TRAIL_SIMPLIFY_EPSILON);
+        retain_kept(&mut self.trail, &keep);
+        retain_kept(&mut self.trail_speeds, &keep);
+        retain_kept(&mut self.trail_ticks, &keep);
+        keep_newest(&mut self.trail);
+        keep_newest(&mut self.trail_speeds);
+        keep_newest(&mut self.trail_ticks);
+    }
 
-        Ok(())
+    /// Shrink the trail of the first circles like `simplify`
+    fn simplify_joint(&mut self) {
+        let keep = simplify::rdp_mask(self.joint_trail.make_contiguous(), TRAIL_SIMPLIFY_EPSILON);
+        retain_kept(&mut self.joint_trail, &keep);
+        keep_newest(&mut self.joint_trail);
     }
+}
 
-    /// Update the double pendulum and its trail one step forward
-    pub fn update(&mut self, desired_fps: u32) -> GameResult {
-        self.forward(desired_fps);
+/// Keep the values of `values` whose index is kept in `keep`, the ones past its end are kept
+fn retain_kept<T>(values: &mut VecDeque<T>, keep: &[bool]) {
+    let mut kept = keep.iter();
+    values.retain(|_| *kept.next().unwrap_or(&true));
+}
 
-        self.update_trail();
-        Ok(())
-    }
-
-    /// Draw the two lines, the two circles and the trail if it needs to be drawn
-    pub fn draw(
-        &mut self,
-        ctx: &mut Context,
-        canvas: &mut Canvas,
-        center: [f32; 2],
-        show_trail: bool,
-    ) -> GameResult {
-        let x_1 = self.p1.x();
-        let y_1 = self.p1.y();
-        let x_2 = x_1 + self.p2.x();
-        let y_2 = y_1 + self.p2.y();
-
-        let origin = [0.0, 0.0];
-        let p1 = [x_1, y_1];
-        let p2 = [x_2, y_2];
-
-        // The two lines can be drawn at once
-        let line = Mesh::new_line(ctx, &[origin, p1, p2], 2.0, self.color)?;
-
-        let circle_1 = Mesh::new_circle(
-            ctx,
-            DrawMode::fill(),
-            p1,
-            4.0 * self.p1.mass,
-            2.0,
-            self.color,
-        )?;
-        let circle_2 = Mesh::new_circle(
-            ctx,
-            DrawMode::fill(),
-            p2,
-            4.0 * self.p2.mass,
-            2.0,
-            self.color,
-        )?;
-
-        canvas.draw(&line, center);
-        canvas.draw(&circle_1, center);
-        canvas.draw(&circle_2, center);
-
-        if show_trail {
-            self.draw_trail(ctx, canvas, center)?;
-        }
-
-        Ok(())
+/// Drop the oldest values of a simplified trail that is still too long, so the memory usage stays bounded
+fn keep_newest<T>(values: &mut VecDeque<T>) {
+    let excess = values.len().saturating_sub(TRAIL_SIMPLIFY_THRESHOLD / 2);
+    values.drain(..excess);
+}
+
+/// Empty trails, which don't allocate
+impl Default for Trail {
+    fn default() -> Self {
+        Self::with_capacity(0)
+    }
+}
+
+/// The forces acting on a pendulum during a physics update, with the wind of that update
+struct Forces<'a> {
+    pendulum: &'a Rods,
+    wind: f32,
+}
+
+impl Dynamics for Forces<'_> {
+    fn acceleration(&self, state: &PendulumState) -> [f32; 2] {
+        let [theta1, theta2] = state.theta;
+        let [omega1, omega2] = state.omega;
+        let (mut a1, mut a2) =
+            (self.pendulum).compute_acceleration([theta1, theta2, omega1, omega2], self.wind);
+
+        // The friction opposes the rotation, and the driving force only pushes the first rod
+        let physics = &self.pendulum.physics;
+        a1 -= physics.damping * omega1;
+        a2 -= physics.damping * omega2;
+        a1 += physics.drive_amplitude * (2.0 * PI * physics.drive_frequency * state.time).sin();
+        // The accelerations are per step of 1/60 of a second, like the angular velocities
+        [VELOCITY_SCALE * a1, VELOCITY_SCALE * a2]
+    }
+}
+
+/// Returns the angle wrapped between -PI and PI
+pub fn wrap_angle(angle: f32) -> f32 {
+    (angle + PI).rem_euclid(2.0 * PI) - PI
+}
+
+/// Returns `angle` wrapped between -PI (excluded) and PI, with the number of full turns removed from it
+pub(crate) fn wrap_turns(angle: f32) -> (f32, i32) {
+    let turns = ((angle - PI) / (2.0 * PI)).ceil();
+    if !turns.is_finite() {
+        // A non-finite angle is left for `recover`
+        return (angle, 0);
+    }
+    (angle - turns * 2.0 * PI, turns as i32)
+}
+
+/// Returns the angles of two rods of lengths `l1` and `l2` whose tip is at `target`
+///
+/// The target is moved to the closest reachable point if it is too far or too close,
+/// and of the two possible elbows, the one whose first angle is the closest to `near` is chosen
+pub fn inverse_kinematics(l1: f32, l2: f32, target: [f32; 2], near: f32) -> (f32, f32) {
+    let distance = target[0].hypot(target[1]);
+    let reach = distance.clamp((l1 - l2).abs(), l1 + l2).max(f32::EPSILON);
+    let direction = target[0].atan2(target[1]);
+    let target = [reach * direction.sin(), reach * direction.cos()];
+
+    // The law of cosines gives the angle between the first rod and the direction of the target
+    let cos = (l1 * l1 + reach * reach - l2 * l2) / (2.0 * l1 * reach);
+    let offset = cos.clamp(-1.0, 1.0).acos();
+    let theta1 = [direction + offset, direction - offset]
+        .into_iter()
+        .min_by(|a, b| {
+            let da = wrap_angle(a - near).abs();
+            let db = wrap_angle(b - near).abs();
+            da.total_cmp(&db)
+        })
+        .unwrap_or(direction);
+
+    let elbow = [l1 * theta1.sin(), l1 * theta1.cos()];
+    let theta2 = (target[0] - elbow[0]).atan2(target[1] - elbow[1]);
+    (theta1, theta2)
+}
+
+/// The trail length of the pendulums saved before it could be changed
+fn default_trail_length() -> usize {
+    DEFAULT_TRAIL_LENGTH
+}
+
+/// Returns whether `point` is far enough from the newest point of a trail to be added to it
+fn is_far_enough(newest: Option<&[f32; 2]>, point: [f32; 2]) -> bool {
+    newest.is_none_or(|p| (point[0] - p[0]).hypot(point[1] - p[1]) >= TRAIL_SPACING)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns whether `a` and `b` are the same angle, up to the rounding of `f32`
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-5
+    }
+
+    #[test]
+    fn wrap_turns_keeps_small_angles() {
+        assert_eq!(wrap_turns(0.0), (0.0, 0));
+        assert_eq!(wrap_turns(1.0), (1.0, 0));
+        assert_eq!(wrap_turns(-1.0), (-1.0, 0));
+        assert_eq!(wrap_turns(PI), (PI, 0));
+    }
+
+    #[test]
+    fn wrap_turns_counts_the_full_turns() {
+        let (angle, turns) = wrap_turns(1.5 * PI);
+        assert!(close(angle, -0.5 * PI) && turns == 1, "{angle} {turns}");
+        let (angle, turns) = wrap_turns(-1.5 * PI);
+        assert!(close(angle, 0.5 * PI) && turns == -1, "{angle} {turns}");
+        let (angle, turns) = wrap_turns(0.25 + 6.0 * PI);
+        assert!(close(angle, 0.25) && turns == 3, "{angle} {turns}");
+        // -PI is excluded, it is the same angle as PI
+        let (angle, turns) = wrap_turns(-PI);
+        assert!(close(angle, PI) && turns == -1, "{angle} {turns}");
+    }
+
+    #[test]
+    fn wrap_turns_leaves_non_finite_angles() {
+        let (angle, turns) = wrap_turns(f32::NAN);
+        assert!(angle.is_nan() && turns == 0);
+        assert_eq!(wrap_turns(f32::INFINITY), (f32::INFINITY, 0));
     }
 }
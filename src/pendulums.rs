@@ -0,0 +1,506 @@
+use crate::batch::{self, LANES};
+use crate::color::Color;
+use crate::integrator::IntegratorKind;
+use crate::pendulum::{self, DoublePendulum, Physics, Recovery, Rods, Trail, MAX_SPEED};
+use std::mem;
+use std::ops::{Deref, DerefMut};
+
+/// Many double pendulums stored quantity by quantity, with one vector for each quantity
+///
+/// Advancing the pendulums only touches the angles, the angular velocities and the constants,
+/// which are contiguous, so thousands of pendulums fit better in the cache
+/// and the SIMD lanes are filled straight from the vectors.
+/// A single pendulum is read as `Rods`, or borrowed as a whole `DoublePendulum` with `at_mut`.
+#[derive(Clone, Default)]
+pub struct Pendulums {
+    /// The angles of the first and the second rods of every pendulum, in radians
+    theta: [Vec<f32>; 2],
+    /// The angular velocities of both rods
+    omega: [Vec<f32>; 2],
+    /// The masses of both circles
+    mass: [Vec<f32>; 2],
+    /// The lengths of both rods, in pixels
+    length: [Vec<f32>; 2],
+    /// The full turns made by both rods, which are not needed to advance the pendulums
+    revolutions: Vec<[i32; 2]>,
+    color: Vec<Color>,
+    physics: Vec<Physics>,
+    integrator: Vec<IntegratorKind>,
+    trails: Vec<Trail>,
+}
+
+impl Pendulums {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns an empty list with room for `capacity` pendulums
+    pub fn with_capacity(capacity: usize) -> Self {
+        let columns = || [Vec::with_capacity(capacity), Vec::with_capacity(capacity)];
+        Self {
+            theta: columns(),
+            omega: columns(),
+            mass: columns(),
+            length: columns(),
+            revolutions: Vec::with_capacity(capacity),
+            color: Vec::with_capacity(capacity),
+            physics: Vec::with_capacity(capacity),
+            integrator: Vec::with_capacity(capacity),
+            trails: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Returns the number of pendulums
+    pub fn len(&self) -> usize {
+        self.color.len()
+    }
+
+    /// Returns true if there is no pendulum
+    pub fn is_empty(&self) -> bool {
+        self.color.is_empty()
+    }
+
+    /// Add a pendulum after the other ones
+    pub fn push(&mut self, pendulum: DoublePendulum) {
+        let (rods, trail) = pendulum.into_parts();
+        let columns = [
+            (&mut self.theta, rods.angles()),
+            (&mut self.omega, rods.angular_velocities()),
+            (&mut self.mass, rods.masses()),
+            (&mut self.length, rods.lengths()),
+        ];
+        for ([first, second], [a, b]) in columns {
+            first.push(a);
+            second.push(b);
+        }
+        self.revolutions.push(rods.revolutions());
+        self.color.push(rods.color());
+        self.physics.push(*rods.physics());
+        self.integrator.push(rods.integrator());
+        self.trails.push(trail);
+    }
+
+    /// Remove the pendulum at `index` and returns it, the following ones are shifted
+    ///
+    /// Panics if `index` is out of bounds
+    pub fn remove(&mut self, index: usize) -> DoublePendulum {
+        let rods = self.at(index);
+        for column in self.columns_mut() {
+            column.remove(index);
+        }
+        self.revolutions.remove(index);
+        self.color.remove(index);
+        self.physics.remove(index);
+        self.integrator.remove(index);
+        DoublePendulum::from_parts(rods, self.trails.remove(index))
+    }
+
+    /// Remove the first `count` pendulums, or all of them if there are fewer
+    pub fn remove_first(&mut self, count: usize) {
+        let count = count.min(self.len());
+        for column in self.columns_mut() {
+            column.drain(..count);
+        }
+        self.revolutions.drain(..count);
+        self.color.drain(..count);
+        self.physics.drain(..count);
+        self.integrator.drain(..count);
+        self.trails.drain(..count);
+    }
+
+    /// Remove every pendulum
+    pub fn clear(&mut self) {
+        self.remove_first(self.len());
+    }
+
+    /// Returns the pendulum at `index` without its trails, or `None` if it is out of bounds
+    pub fn get(&self, index: usize) -> Option<Rods> {
+        (index < self.len()).then(|| self.at(index))
+    }
+
+    /// Returns the pendulum at `index` without its trails
+    ///
+    /// Panics if `index` is out of bounds
+    pub fn at(&self, index: usize) -> Rods {
+        Rods::new(
+            [self.mass[0][index], self.mass[1][index]],
+            [self.length[0][index], self.length[1][index]],
+            [self.theta[0][index], self.theta[1][index]],
+            [self.omega[0][index], self.omega[1][index]],
+            self.color[index],
+            self.physics[index],
+            self.integrator[index],
+        )
+        .with_revolutions(self.revolutions[index])
+    }
+
+    /// Replace the rods of the pendulum at `index` with `rods`, its trails are kept
+    ///
+    /// Panics if `index` is out of bounds
+    pub fn set(&mut self, index: usize, rods: Rods) {
+        let columns = [
+            (&mut self.theta, rods.angles()),
+            (&mut self.omega, rods.angular_velocities()),
+            (&mut self.mass, rods.masses()),
+            (&mut self.length, rods.lengths()),
+        ];
+        for ([first, second], [a, b]) in columns {
+            first[index] = a;
+            second[index] = b;
+        }
+        self.revolutions[index] = rods.revolutions();
+        self.color[index] = rods.color();
+        self.physics[index] = *rods.physics();
+        self.integrator[index] = rods.integrator();
+    }
+
+    /// Borrow the pendulum at `index` with its trails, or returns `None` if it is out of bounds
+    pub fn get_mut(&mut self, index: usize) -> Option<PendulumMut<'_>> {
+        (index < self.len()).then(|| self.at_mut(index))
+    }
+
+    /// Borrow the pendulum at `index` with its trails
+    ///
+    /// Panics if `index` is out of bounds
+    pub fn at_mut(&mut self, index: usize) -> PendulumMut<'_> {
+        let [theta1, theta2] = &mut self.theta;
+        let [omega1, omega2] = &mut self.omega;
+        let [m1, m2] = &mut self.mass;
+        let [l1, l2] = &mut self.length;
+        PendulumMut::new(Slot {
+            theta: [&mut theta1[index], &mut theta2[index]],
+            omega: [&mut omega1[index], &mut omega2[index]],
+            mass: [&mut m1[index], &mut m2[index]],
+            length: [&mut l1[index], &mut l2[index]],
+            revolutions: &mut self.revolutions[index],
+            color: &mut self.color[index],
+            physics: &mut self.physics[index],
+            integrator: &mut self.integrator[index],
+            trail: &mut self.trails[index],
+        })
+    }
+
+    /// Borrow the newest pendulum with its trails, or returns `None` if there is no pendulum
+    pub fn last_mut(&mut self) -> Option<PendulumMut<'_>> {
+        let last = self.len().checked_sub(1)?;
+        Some(self.at_mut(last))
+    }
+
+    /// Returns every pendulum without its trails, in order
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = Rods> + ExactSizeIterator + '_ {
+        (0..self.len()).map(|index| self.at(index))
+    }
+
+    /// Borrow every pendulum with its trails, in order
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = PendulumMut<'_>> {
+        (pairs(&mut self.theta).zip(pairs(&mut self.omega)))
+            .zip(pairs(&mut self.mass).zip(pairs(&mut self.length)))
+            .zip(self.revolutions.iter_mut().zip(&mut self.color))
+            .zip(self.physics.iter_mut().zip(&mut self.integrator))
+            .zip(&mut self.trails)
+            .map(
+                |(
+                    (
+                        (((theta, omega), (mass, length)), (revolutions, color)),
+                        (physics, integrator),
+                    ),
+                    trail,
+                )| {
+                    PendulumMut::new(Slot {
+                        theta,
+                        omega,
+                        mass,
+                        length,
+                        revolutions,
+                        color,
+                        physics,
+                        integrator,
+                        trail,
+                    })
+                },
+            )
+    }
+
+    /// Returns the angles of both rods of every pendulum, in order
+    pub fn angles(&self) -> impl ExactSizeIterator<Item = [f32; 2]> + '_ {
+        let [first, second] = &self.theta;
+        first.iter().zip(second).map(|(&a, &b)| [a, b])
+    }
+
+    /// Returns the vectors of the angles and the angular velocities, in the order θ1, θ2, ω1, ω2,
+    /// to read and write them in bulk
+    ///
+    /// The angles are left as they are written, `wrap_angles` brings them back between -PI and PI
+    #[cfg(feature = "gpu")]
+    pub(crate) fn state_columns_mut(&mut self) -> [&mut [f32]; 4] {
+        let [theta1, theta2] = &mut self.theta;
+        let [omega1, omega2] = &mut self.omega;
+        [theta1, theta2, omega1, omega2]
+    }
+
+    /// Returns the vectors of the masses and the lengths, in the order m1, m2, l1, l2
+    #[cfg(feature = "gpu")]
+    pub(crate) fn constant_columns(&self) -> [&[f32]; 4] {
+        let [m1, m2] = &self.mass;
+        let [l1, l2] = &self.length;
+        [m1, m2, l1, l2]
+    }
+
+    /// Returns the physical constants of every pendulum
+    #[cfg(feature = "gpu")]
+    pub(crate) fn physics(&self) -> &[Physics] {
+        &self.physics
+    }
+
+    /// Returns the integrator of every pendulum
+    #[cfg(feature = "gpu")]
+    pub(crate) fn integrators(&self) -> &[IntegratorKind] {
+        &self.integrator
+    }
+
+    /// Bring the angles of every pendulum back between -PI and PI like `Rods::forward`,
+    /// counting the full turns they made since they were last wrapped
+    #[cfg(feature = "gpu")]
+    pub(crate) fn wrap_angles(&mut self) {
+        for (rod, thetas) in self.theta.iter_mut().enumerate() {
+            for (theta, revolutions) in thetas.iter_mut().zip(&mut self.revolutions) {
+                let (wrapped, turns) = pendulum::wrap_turns(*theta);
+                *theta = wrapped;
+                revolutions[rod] = revolutions[rod].saturating_add(turns);
+            }
+        }
+    }
+
+    /// Returns the physical constants of every pendulum, which can be changed at any time
+    pub fn physics_mut(&mut self) -> &mut [Physics] {
+        &mut self.physics
+    }
+
+    /// Reverse the direction of the simulation by negating the angular velocities of every pendulum
+    pub fn reverse(&mut self) {
+        for omega in self.omega.iter_mut().flatten() {
+            *omega = -*omega;
+        }
+    }
+
+    /// Returns a copy of every pendulum with its trails, to store them one after the other
+    pub fn to_vec(&self) -> Vec<DoublePendulum> {
+        (self.iter().zip(&self.trails))
+            .map(|(rods, trail)| DoublePendulum::from_parts(rods, trail.clone()))
+            .collect()
+    }
+
+    /// Advance every pendulum by one step of `dt` seconds like `DoublePendulum::forward`, without the trails
+    ///
+    /// The pendulums using the symplectic Euler method are advanced `LANES` at a time like `batch::forward`,
+    /// with the same results
+    pub fn forward(&mut self, dt: f32, wind: f32, time: f32) {
+        let mut lanes = [0; LANES];
+        let mut count = 0;
+        for index in 0..self.len() {
+            if self.integrator[index] != IntegratorKind::Symplectic {
+                let mut rods = self.at(index);
+                rods.forward(dt, wind, time);
+                self.set(index, rods);
+                continue;
+            }
+            lanes[count] = index;
+            count += 1;
+            if count == LANES {
+                self.step_lanes(&lanes, dt, wind, time);
+                count = 0;
+            }
+        }
+        if count > 0 {
+            self.step_lanes(&lanes[..count], dt, wind, time);
+        }
+    }
+
+    /// Put every pendulum whose state went wrong back in a valid state, like `Rods::recover`
+    ///
+    /// Returns the index of each of them, with its state before it was recovered and what was done
+    pub fn recover(&mut self) -> Vec<(usize, Rods, Recovery)> {
+        let valid = |theta: f32, omega: f32| theta.is_finite() && omega.abs() <= MAX_SPEED;
+        let mut recovered = Vec::new();
+        for index in 0..self.len() {
+            if (0..2).all(|rod| valid(self.theta[rod][index], self.omega[rod][index])) {
+                continue;
+            }
+            let before = self.at(index);
+            let mut rods = before;
+            if let Some(recovery) = rods.recover() {
+                self.set(index, rods);
+                recovered.push((index, before, recovery));
+            }
+        }
+        recovered
+    }
+
+    /// Add the current positions of every pendulum to its trails, like `DoublePendulum::update_trail`
+    pub fn update_trails(&mut self) {
+        for index in 0..self.len() {
+            let rods = self.at(index);
+            self.trails[index].record(&rods);
+        }
+    }
+
+    /// Advance every pendulum and its trails by one step of `dt` seconds, like `DoublePendulum::update`
+    pub fn update(&mut self, dt: f32, wind: f32, time: f32) {
+        self.forward(dt, wind, time);
+        self.update_trails();
+    }
+
+    /// Advance the pendulums at `indices`, at most `LANES` of them, together with SIMD
+    fn step_lanes(&mut self, indices: &[usize], dt: f32, wind: f32, time: f32) {
+        let mut columns = [[0.0; LANES]; 12];
+        for lane in 0..LANES {
+            // The unused lanes hold copies of the first pendulum, so they never produce NaN
+            let index = indices.get(lane).copied().unwrap_or(indices[0]);
+            let physics = &self.physics[index];
+            let values = [
+                self.theta[0][index],
+                self.theta[1][index],
+                self.omega[0][index],
+                self.omega[1][index],
+                self.mass[0][index],
+                self.mass[1][index],
+                self.length[0][index],
+                self.length[1][index],
+                physics.gravity,
+                physics.damping,
+                physics.drive_amplitude,
+                physics.drive_frequency,
+            ];
+            for (column, value) in columns.iter_mut().zip(values) {
+                column[lane] = value;
+            }
+        }
+        let [theta1, theta2, omega1, omega2] = batch::step_columns(columns, dt, wind, time);
+        for (lane, &index) in indices.iter().enumerate() {
+            // The angles are wrapped like in `Rods::forward`
+            for (rod, theta) in [theta1[lane], theta2[lane]].into_iter().enumerate() {
+                let (theta, turns) = pendulum::wrap_turns(theta);
+                self.theta[rod][index] = theta;
+                let revolutions = &mut self.revolutions[index][rod];
+                *revolutions = revolutions.saturating_add(turns);
+            }
+            self.omega[0][index] = omega1[lane];
+            self.omega[1][index] = omega2[lane];
+        }
+    }
+
+    /// Returns every vector holding a number for each pendulum
+    fn columns_mut(&mut self) -> impl Iterator<Item = &mut Vec<f32>> {
+        [
+            &mut self.theta,
+            &mut self.omega,
+            &mut self.mass,
+            &mut self.length,
+        ]
+        .into_iter()
+        .flatten()
+    }
+}
+
+impl From<Vec<DoublePendulum>> for Pendulums {
+    fn from(pendulums: Vec<DoublePendulum>) -> Self {
+        pendulums.into_iter().collect()
+    }
+}
+
+impl FromIterator<DoublePendulum> for Pendulums {
+    fn from_iter<I: IntoIterator<Item = DoublePendulum>>(iter: I) -> Self {
+        let mut pendulums = Self::new();
+        pendulums.extend(iter);
+        pendulums
+    }
+}
+
+impl Extend<DoublePendulum> for Pendulums {
+    fn extend<I: IntoIterator<Item = DoublePendulum>>(&mut self, iter: I) {
+        for pendulum in iter {
+            self.push(pendulum);
+        }
+    }
+}
+
+/// Returns the values of both rods or circles of each pendulum, from the vectors of a quantity
+fn pairs(columns: &mut [Vec<f32>; 2]) -> impl Iterator<Item = [&mut f32; 2]> {
+    let [first, second] = columns;
+    first.iter_mut().zip(second).map(|(a, b)| [a, b])
+}
+
+/// Where every value of a pendulum is stored in `Pendulums`
+struct Slot<'a> {
+    theta: [&'a mut f32; 2],
+    omega: [&'a mut f32; 2],
+    mass: [&'a mut f32; 2],
+    length: [&'a mut f32; 2],
+    revolutions: &'a mut [i32; 2],
+    color: &'a mut Color,
+    physics: &'a mut Physics,
+    integrator: &'a mut IntegratorKind,
+    trail: &'a mut Trail,
+}
+
+/// A pendulum of `Pendulums` borrowed as a whole `DoublePendulum`
+///
+/// The changes are written back into `Pendulums` when it is dropped
+pub struct PendulumMut<'a> {
+    slot: Slot<'a>,
+    pendulum: DoublePendulum,
+}
+
+impl<'a> PendulumMut<'a> {
+    fn new(slot: Slot<'a>) -> Self {
+        let pair = |values: &[&mut f32; 2]| [*values[0], *values[1]];
+        let rods = Rods::new(
+            pair(&slot.mass),
+            pair(&slot.length),
+            pair(&slot.theta),
+            pair(&slot.omega),
+            *slot.color,
+            *slot.physics,
+            *slot.integrator,
+        )
+        .with_revolutions(*slot.revolutions);
+        // The trails are moved rather than copied, they are put back when the pendulum is dropped
+        let pendulum = DoublePendulum::from_parts(rods, mem::take(slot.trail));
+        Self { slot, pendulum }
+    }
+}
+
+impl Deref for PendulumMut<'_> {
+    type Target = DoublePendulum;
+
+    fn deref(&self) -> &DoublePendulum {
+        &self.pendulum
+    }
+}
+
+impl DerefMut for PendulumMut<'_> {
+    fn deref_mut(&mut self) -> &mut DoublePendulum {
+        &mut self.pendulum
+    }
+}
+
+impl Drop for PendulumMut<'_> {
+    fn drop(&mut self) {
+        let p = &mut self.pendulum;
+        let columns = [
+            (&mut self.slot.theta, p.angles()),
+            (&mut self.slot.omega, p.angular_velocities()),
+            (&mut self.slot.mass, p.masses()),
+            (&mut self.slot.length, p.lengths()),
+        ];
+        for ([first, second], [a, b]) in columns {
+            **first = a;
+            **second = b;
+        }
+        *self.slot.revolutions = p.revolutions();
+        *self.slot.color = p.color();
+        *self.slot.physics = *p.physics();
+        *self.slot.integrator = p.integrator();
+        *self.slot.trail = p.take_trails();
+    }
+}
@@ -0,0 +1,114 @@
+use double_pendulum::pendulum::{wrap_angle, Rods};
+use ggez::graphics::{self, Canvas, Color, DrawMode, Mesh, MeshBuilder, Rect};
+use ggez::Context;
+use ggez::GameResult;
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+
+/// The number of physics updates kept in the plot
+const HISTORY_LENGTH: usize = 2000;
+/// The color of the axes of the plots
+const AXIS_COLOR: Color = Color::new(1.0, 1.0, 1.0, 0.3);
+
+/// A live plot of the angle of each rod against its angular velocity, the phase space of the pendulum
+///
+/// Only one pendulum is plotted, and its history is cleared when another one is plotted instead
+pub struct PhasePlot {
+    /// The index of the plotted pendulum
+    index: usize,
+    /// The wrapped angles and the angular velocities after each physics update, as `[θ1, ω1, θ2, ω2]`
+    history: VecDeque<[f32; 4]>,
+}
+
+impl PhasePlot {
+    pub fn new() -> Self {
+        Self {
+            index: 0,
+            history: VecDeque::with_capacity(HISTORY_LENGTH),
+        }
+    }
+
+    /// Add the current state of `pendulum`, which is at `index` in the list of pendulums
+    pub fn record(&mut self, index: usize, pendulum: &Rods) {
+        if index != self.index {
+            self.index = index;
+            self.history.clear();
+        }
+        if self.history.len() >= HISTORY_LENGTH {
+            self.history.pop_front();
+        }
+        let [theta1, theta2] = pendulum.angles().map(wrap_angle);
+        let [omega1, omega2] = pendulum.angular_velocities();
+        self.history.push_back([theta1, omega1, theta2, omega2]);
+    }
+
+    /// Draw the plots of both rods side by side in `area`
+    ///
+    /// The angles go from -PI on the left to PI on the right, and the angular velocities
+    /// are scaled so the fastest one of the history touches the top or the bottom
+    pub fn draw(&self, ctx: &mut Context, canvas: &mut Canvas, area: Rect) -> GameResult {
+        let panel =
+            Mesh::new_rectangle(ctx, DrawMode::fill(), area, Color::new(0.0, 0.0, 0.0, 0.6))?;
+        canvas.draw(&panel, [0.0, 0.0]);
+
+        let width = area.w / 2.0;
+        let mut builder = MeshBuilder::new();
+        for rod in 0..2 {
+            let plot = Rect::new(
+                area.x + rod as f32 * width + 10.0,
+                area.y + 25.0,
+                width - 20.0,
+                area.h - 35.0,
+            );
+            let middle = [plot.x + plot.w / 2.0, plot.y + plot.h / 2.0];
+            builder
+                .line(
+                    &[[plot.x, middle[1]], [plot.right(), middle[1]]],
+                    1.0,
+                    AXIS_COLOR,
+                )?
+                .line(
+                    &[[middle[0], plot.y], [middle[0], plot.bottom()]],
+                    1.0,
+                    AXIS_COLOR,
+                )?;
+
+            let label = format!("θ{0} / ω{0}", rod + 1);
+            canvas.draw(&graphics::Text::new(label), [plot.x, area.y + 6.0]);
+
+            let fastest = (self.history.iter())
+                .map(|state| state[2 * rod + 1].abs())
+                .fold(f32::EPSILON, f32::max);
+            let point = |state: &[f32; 4]| {
+                [
+                    plot.x + (state[2 * rod] + PI) / (2.0 * PI) * plot.w,
+                    middle[1] - state[2 * rod + 1] / fastest * plot.h / 2.0,
+                ]
+            };
+
+            // The line is cut where the angle wraps around, so it doesn't cross the whole plot
+            let mut run: Vec<[f32; 2]> = Vec::new();
+            let mut previous: Option<f32> = None;
+            for state in &self.history {
+                let theta = state[2 * rod];
+                if previous.is_some_and(|p| (theta - p).abs() > PI) {
+                    add_run(&mut builder, &run, Color::WHITE)?;
+                    run.clear();
+                }
+                run.push(point(state));
+                previous = Some(theta);
+            }
+            add_run(&mut builder, &run, Color::WHITE)?;
+        }
+        canvas.draw(&Mesh::from_data(ctx, builder.build()), [0.0, 0.0]);
+        Ok(())
+    }
+}
+
+/// Add a continuous part of a plot to the mesh in `color`, if it has enough points to be a line
+pub fn add_run(builder: &mut MeshBuilder, run: &[[f32; 2]], color: Color) -> GameResult {
+    if run.len() >= 2 {
+        builder.line(run, 1.0, color)?;
+    }
+    Ok(())
+}
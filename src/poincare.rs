@@ -0,0 +1,114 @@
+use double_pendulum::pendulum::{wrap_angle, Rods};
+use ggez::graphics::{self, Canvas, Color, DrawMode, Mesh, MeshData, Rect, Vertex};
+use ggez::Context;
+use ggez::GameResult;
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+
+/// The number of points kept in the section, the oldest ones are dropped past it
+const MAX_POINTS: usize = 20000;
+/// The width in pixels of each point
+const POINT_SIZE: f32 = 1.5;
+
+/// A Poincaré section of a pendulum: the state of the second rod, as `(θ2, ω2)`,
+/// each time the first rod goes through the vertical while swinging counterclockwise
+///
+/// Only one pendulum is recorded, and the section is cleared when another one is recorded instead
+pub struct PoincareSection {
+    /// The index of the recorded pendulum
+    index: usize,
+    /// The wrapped angles and the angular velocities after the previous physics update,
+    /// as `[θ1, ω1, θ2, ω2]`
+    previous: Option<[f32; 4]>,
+    /// The states of the second rod at each crossing, as `[θ2, ω2]`
+    points: VecDeque<[f32; 2]>,
+}
+
+impl PoincareSection {
+    pub fn new() -> Self {
+        Self {
+            index: 0,
+            previous: None,
+            points: VecDeque::new(),
+        }
+    }
+
+    /// Returns the number of crossings recorded
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    /// Check whether `pendulum`, which is at `index` in the list of pendulums, crossed the section
+    /// since the previous physics update
+    ///
+    /// The crossing happened between two updates, so the state at the crossing is interpolated linearly
+    pub fn record(&mut self, index: usize, pendulum: &Rods) {
+        if index != self.index {
+            self.index = index;
+            self.previous = None;
+            self.points.clear();
+        }
+
+        let [theta1, theta2] = pendulum.angles().map(wrap_angle);
+        let [omega1, omega2] = pendulum.angular_velocities();
+        let current = [theta1, omega1, theta2, omega2];
+        let Some(previous) = self.previous.replace(current) else {
+            return;
+        };
+
+        // The angle going from PI to -PI is a wrap around the top, not a crossing at the bottom
+        let crossed = previous[0] < 0.0 && theta1 >= 0.0 && theta1 - previous[0] < PI;
+        if !crossed || omega1 <= 0.0 {
+            return;
+        }
+        let t = -previous[0] / (theta1 - previous[0]);
+        let theta2 = wrap_angle(previous[2] + t * wrap_angle(theta2 - previous[2]));
+        let omega2 = previous[3] + t * (omega2 - previous[3]);
+
+        if self.points.len() >= MAX_POINTS {
+            self.points.pop_front();
+        }
+        self.points.push_back([theta2, omega2]);
+    }
+
+    /// Draw the section in `area`, with θ2 from -PI on the left to PI on the right
+    /// and ω2 scaled so the fastest point touches the top or the bottom
+    pub fn draw(&self, ctx: &mut Context, canvas: &mut Canvas, area: Rect) -> GameResult {
+        let panel =
+            Mesh::new_rectangle(ctx, DrawMode::fill(), area, Color::new(0.0, 0.0, 0.0, 0.6))?;
+        canvas.draw(&panel, [0.0, 0.0]);
+        let label = format!("Poincaré section: {} crossings", self.len());
+        canvas.draw(&graphics::Text::new(label), [area.x + 10.0, area.y + 6.0]);
+        if self.points.is_empty() {
+            return Ok(());
+        }
+
+        let plot = Rect::new(area.x + 10.0, area.y + 25.0, area.w - 20.0, area.h - 35.0);
+        let fastest = (self.points.iter())
+            .map(|point| point[1].abs())
+            .fold(f32::EPSILON, f32::max);
+
+        // Every point is a small square, built by hand since there can be thousands of them
+        let mut vertices = Vec::with_capacity(self.points.len() * 4);
+        let mut indices = Vec::with_capacity(self.points.len() * 6);
+        for [theta, omega] in &self.points {
+            let x = plot.x + (theta + PI) / (2.0 * PI) * plot.w;
+            let y = plot.y + plot.h / 2.0 - omega / fastest * plot.h / 2.0;
+            let first = vertices.len() as u32;
+            for corner in [[0.0, 0.0], [1.0, 0.0], [0.0, 1.0], [1.0, 1.0]] {
+                vertices.push(Vertex {
+                    position: [x + corner[0] * POINT_SIZE, y + corner[1] * POINT_SIZE],
+                    uv: [0.0, 0.0],
+                    color: Color::WHITE.into(),
+                });
+            }
+            indices.extend([0, 1, 2, 1, 3, 2].map(|i| first + i));
+        }
+        let data = MeshData {
+            vertices: &vertices,
+            indices: &indices,
+        };
+        canvas.draw(&Mesh::from_data(ctx, data), [0.0, 0.0]);
+        Ok(())
+    }
+}
@@ -0,0 +1,159 @@
+use crate::bloom::Bloom;
+use crate::config::Config;
+use ggez::graphics::{Canvas, Color, DrawMode, Image, Mesh, Rect};
+use ggez::{Context, GameResult};
+
+/// The fraction of the previous frame kept by the motion blur when it isn't configured
+const DEFAULT_AFTERIMAGE: f32 = 0.8;
+/// How bright the glow is when it isn't configured
+const DEFAULT_GLOW: f32 = 2.0;
+
+/// The post-processing system, which piles the frames up, blurs their motion and makes them glow
+///
+/// Without any of them, the simulation is drawn straight on the frame
+pub struct PostProcessSystem {
+    /// Whether the window is cleared before each frame, otherwise the frames pile up into `layer`
+    clear: bool,
+    /// The image the simulation is drawn into when the frames aren't cleared, it is created with the first frame
+    layer: Option<Image>,
+    /// Whether the previous frames fade out in `layer` instead of being cleared, leaving an afterimage
+    motion_blur: bool,
+    /// The fraction of the previous frame kept by the motion blur, between 0 and 1
+    afterimage: f32,
+    /// Whether the bright bobs and trails glow, the simulation being drawn in `layer` first
+    bloom: bool,
+    /// How bright the glow is compared to the average of the bright colors around a pixel
+    glow: f32,
+    /// The shader making the bright parts glow, created the first time it is drawn
+    bloom_pass: Option<Bloom>,
+}
+
+impl PostProcessSystem {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            clear: config.clear,
+            layer: None,
+            motion_blur: config.motion_blur.is_some(),
+            afterimage: config.motion_blur.unwrap_or(DEFAULT_AFTERIMAGE),
+            bloom: config.bloom.is_some(),
+            glow: config.bloom.unwrap_or(DEFAULT_GLOW),
+            bloom_pass: None,
+        }
+    }
+
+    /// Returns whether the window is cleared before each frame
+    pub fn clear(&self) -> bool {
+        self.clear
+    }
+
+    /// Returns the fraction of the previous frame kept by the motion blur, if it is on
+    pub fn motion_blur(&self) -> Option<f32> {
+        self.motion_blur.then_some(self.afterimage)
+    }
+
+    /// Returns how bright the glow is, if it is on
+    pub fn bloom(&self) -> Option<f32> {
+        self.bloom.then_some(self.glow)
+    }
+
+    /// Turn the motion blur on or off
+    pub fn toggle_motion_blur(&mut self) {
+        self.motion_blur = !self.motion_blur;
+        // The layer is filled with the background again, so the previous drawing doesn't come back
+        self.layer = None;
+    }
+
+    /// Turn the glow on or off
+    pub fn toggle_bloom(&mut self) {
+        self.bloom = !self.bloom;
+    }
+
+    /// Discard the frames piled up in the layer
+    pub fn discard(&mut self) {
+        self.layer = None;
+    }
+
+    /// Apply the post-processing of the configuration file that was just changed
+    pub fn reload(&mut self, config: &Config) {
+        if config.clear != self.clear || config.motion_blur.is_some() != self.motion_blur {
+            self.layer = None;
+        }
+        self.clear = config.clear;
+        self.motion_blur = config.motion_blur.is_some();
+        self.afterimage = config.motion_blur.unwrap_or(self.afterimage);
+        if config.bloom.is_some_and(|glow| glow != self.glow) {
+            self.bloom_pass = None;
+        }
+        self.bloom = config.bloom.is_some();
+        self.glow = config.bloom.unwrap_or(self.glow);
+    }
+
+    /// Returns the canvas the simulation is drawn into before it is post-processed,
+    /// or None if it is drawn straight on the frame
+    ///
+    /// The layer covers the window like the ink image, and it is created again when the window was resized.
+    /// With the motion blur, the previous frames are partially faded out with `background`.
+    pub fn layer(
+        &mut self,
+        ctx: &mut Context,
+        window: [f32; 2],
+        background: Color,
+    ) -> GameResult<Option<Canvas>> {
+        if self.clear && !self.motion_blur && !self.bloom {
+            return Ok(None);
+        }
+        let [width, height] = window.map(|size| size.max(1.0) as u32);
+        let created = match &self.layer {
+            Some(layer) if layer.width() == width && layer.height() == height => None,
+            _ => {
+                let format = ctx.gfx.surface_format();
+                self.layer = Some(Image::new_canvas_image(ctx, format, width, height, 1));
+                Some(background)
+            }
+        };
+        let layer = self.layer.clone().expect("the layer should exist");
+        let clear = if self.clear && !self.motion_blur {
+            Some(background)
+        } else {
+            created
+        };
+        let mut canvas = Canvas::from_image(ctx, layer, clear);
+        if self.motion_blur {
+            // Covering the previous frames with the background partially fades them out
+            let fade = Color {
+                a: 1.0 - self.afterimage,
+                ..background
+            };
+            let rect = Rect::new(0.0, 0.0, width as f32, height as f32);
+            let veil = Mesh::new_rectangle(ctx, DrawMode::fill(), rect, fade)?;
+            canvas.draw(&veil, [0.0, 0.0]);
+        }
+        Ok(Some(canvas))
+    }
+
+    /// Draw the simulation drawn into `layer`, if it wasn't drawn straight on the frame, on `canvas`
+    ///
+    /// The simulation piles up in its own image, so the HUD drawn over it doesn't
+    pub fn draw(
+        &mut self,
+        ctx: &mut Context,
+        canvas: &mut Canvas,
+        layer: Option<Canvas>,
+    ) -> GameResult {
+        let Some(layer_canvas) = layer else {
+            return Ok(());
+        };
+        layer_canvas.finish(ctx)?;
+        let layer = self.layer.as_ref().expect("the layer should exist");
+        if self.bloom {
+            if self.bloom_pass.is_none() {
+                self.bloom_pass = Some(Bloom::new(ctx, self.glow)?);
+            }
+            let bloom = (self.bloom_pass.as_ref()).expect("the bloom pass should exist");
+            bloom.draw(canvas, layer);
+        } else {
+            canvas.draw(layer, [0.0, 0.0]);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,95 @@
+use double_pendulum::pendulum::InitialConditions;
+use std::f32::consts::{PI, SQRT_2};
+
+/// A named set of pendulums with known initial conditions
+pub struct Preset {
+    pub name: &'static str,
+    /// Returns the initial conditions of every pendulum of the preset
+    pub pendulums: fn() -> Vec<InitialConditions>,
+}
+
+/// Every built-in preset, in the order they are cycled through
+pub const PRESETS: &[Preset] = &[
+    Preset {
+        name: "classic",
+        pendulums: classic,
+    },
+    Preset {
+        name: "near-vertical",
+        pendulums: near_vertical,
+    },
+    Preset {
+        name: "heavy-bob",
+        pendulums: heavy_bob,
+    },
+    Preset {
+        name: "chaos-pair",
+        pendulums: chaos_pair,
+    },
+    Preset {
+        name: "synchronized",
+        pendulums: synchronized,
+    },
+];
+
+/// Returns the index of the preset called `name`
+pub fn find(name: &str) -> Option<usize> {
+    PRESETS.iter().position(|p| p.name == name)
+}
+
+/// Both rods start horizontal, at rest
+fn classic() -> Vec<InitialConditions> {
+    vec![conditions(3.0, 3.0, 100.0, 100.0, PI / 2.0, PI / 2.0)]
+}
+
+/// Both rods start almost upright, so the pendulum hesitates before falling
+fn near_vertical() -> Vec<InitialConditions> {
+    vec![conditions(3.0, 3.0, 100.0, 100.0, PI - 0.01, PI)]
+}
+
+/// A light first bob carrying a heavy second bob
+fn heavy_bob() -> Vec<InitialConditions> {
+    vec![conditions(1.0, 5.0, 90.0, 110.0, 2.0, 2.5)]
+}
+
+/// Two pendulums whose initial angles differ by a thousandth of a radian
+fn chaos_pair() -> Vec<InitialConditions> {
+    vec![
+        conditions(3.0, 3.0, 100.0, 100.0, 2.5, 2.5),
+        conditions(3.0, 3.0, 100.0, 100.0, 2.5 + 1e-3, 2.5),
+    ]
+}
+
+/// Pendulums of different amplitudes oscillating in the in-phase normal mode
+///
+/// With equal masses and lengths and small angles, this mode has theta2 = sqrt(2) * theta1,
+/// so every pendulum swings at the same frequency and they stay synchronized
+fn synchronized() -> Vec<InitialConditions> {
+    (1..=5)
+        .map(|i| {
+            let theta1 = 0.05 * i as f32;
+            conditions(3.0, 3.0, 100.0, 100.0, theta1, SQRT_2 * theta1)
+        })
+        .collect()
+}
+
+/// Returns fully specified initial conditions with no initial speed
+pub fn conditions(
+    m1: f32,
+    m2: f32,
+    l1: f32,
+    l2: f32,
+    theta1: f32,
+    theta2: f32,
+) -> InitialConditions {
+    InitialConditions {
+        m1: Some(m1),
+        m2: Some(m2),
+        l1: Some(l1),
+        l2: Some(l2),
+        theta1: Some(theta1),
+        theta2: Some(theta2),
+        v1: Some(0.0),
+        v2: Some(0.0),
+    }
+}
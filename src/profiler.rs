@@ -0,0 +1,136 @@
+use ggez::graphics::{self, Canvas, Color, DrawMode, DrawParam, Mesh, MeshBuilder, Rect};
+use ggez::{Context, GameResult};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// The number of frames shown by the graph
+const FRAMES: usize = 120;
+/// The duration of a frame at 60 fps, in milliseconds, marked by a line on the graph
+const FRAME_BUDGET_MS: f32 = 1000.0 / 60.0;
+/// The colors of the physics, the trails, the drawing and everything else in the graph
+const COLORS: [Color; 4] = [
+    Color::new(1.0, 0.4, 0.3, 1.0),
+    Color::new(1.0, 0.85, 0.3, 1.0),
+    Color::new(0.4, 0.7, 1.0, 1.0),
+    Color::new(0.5, 0.5, 0.5, 1.0),
+];
+/// The names of the parts of a frame, in the same order as `COLORS`
+const LABELS: [&str; 4] = ["Physics", "Trails", "Draw", "Other"];
+
+/// Where the time of one frame went, in milliseconds
+#[derive(Clone, Copy, Default)]
+struct Frame {
+    physics: f32,
+    trails: f32,
+    draw: f32,
+    /// The whole frame, from the end of the previous one, including the time waiting for the screen
+    total: f32,
+}
+
+impl Frame {
+    /// Returns the durations of the parts of the frame, in the same order as `LABELS`
+    fn parts(&self) -> [f32; 4] {
+        let other = (self.total - self.physics - self.trails - self.draw).max(0.0);
+        [self.physics, self.trails, self.draw, other]
+    }
+}
+
+/// Measures how long the physics, the trails and the drawing take in each frame
+///
+/// The durations of the physics updates and of the trails are added up over the frame,
+/// since there are several updates per frame
+pub struct Profiler {
+    /// The frame being measured
+    current: Frame,
+    /// When the previous frame ended
+    last_frame: Instant,
+    /// The last `FRAMES` frames, from the oldest to the newest
+    frames: VecDeque<Frame>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self {
+            current: Frame::default(),
+            last_frame: Instant::now(),
+            frames: VecDeque::with_capacity(FRAMES),
+        }
+    }
+
+    /// Count `duration` as time spent advancing the pendulums in the current frame
+    pub fn add_physics(&mut self, duration: Duration) {
+        self.current.physics += duration.as_secs_f32() * 1000.0;
+    }
+
+    /// Count `duration` as time spent updating the trails in the current frame
+    pub fn add_trails(&mut self, duration: Duration) {
+        self.current.trails += duration.as_secs_f32() * 1000.0;
+    }
+
+    /// Finish the current frame, whose drawing took `draw`
+    pub fn end_frame(&mut self, draw: Duration) {
+        let now = Instant::now();
+        self.current.draw = draw.as_secs_f32() * 1000.0;
+        self.current.total = (now - self.last_frame).as_secs_f32() * 1000.0;
+        self.last_frame = now;
+        if self.frames.len() == FRAMES {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(self.current);
+        self.current = Frame::default();
+    }
+
+    /// Returns the durations of the last frame, written for the HUD
+    pub fn hud(&self) -> String {
+        let last = self.frames.back().copied().unwrap_or_default();
+        format!(
+            "Physics: {:.2} ms  Trails: {:.2} ms\nDraw: {:.2} ms  Total: {:.2} ms",
+            last.physics, last.trails, last.draw, last.total,
+        )
+    }
+
+    /// Draw the last frames in `area` as stacked bars, the newest on the right,
+    /// with a line at the duration of a frame at 60 fps
+    pub fn draw(&self, ctx: &mut Context, canvas: &mut Canvas, area: Rect) -> GameResult {
+        let panel =
+            Mesh::new_rectangle(ctx, DrawMode::fill(), area, Color::new(0.0, 0.0, 0.0, 0.6))?;
+        canvas.draw(&panel, [0.0, 0.0]);
+
+        for (i, (label, color)) in LABELS.into_iter().zip(COLORS).enumerate() {
+            canvas.draw(
+                &graphics::Text::new(label),
+                DrawParam::new()
+                    .dest([area.x + 10.0 + i as f32 * 65.0, area.y + 6.0])
+                    .color(color),
+            );
+        }
+
+        // The scale fits the slowest frame, but never less than two frame budgets
+        let plot = Rect::new(area.x + 10.0, area.y + 25.0, area.w - 20.0, area.h - 35.0);
+        let slowest = (self.frames.iter()).fold(2.0 * FRAME_BUDGET_MS, |max, f| max.max(f.total));
+        let y = |ms: f32| plot.bottom() - ms / slowest * plot.h;
+        let width = plot.w / FRAMES as f32;
+
+        let mut builder = MeshBuilder::new();
+        let offset = FRAMES - self.frames.len();
+        for (i, frame) in self.frames.iter().enumerate() {
+            let x = plot.x + (offset + i) as f32 * width;
+            let mut bottom = 0.0;
+            for (ms, color) in frame.parts().into_iter().zip(COLORS) {
+                if ms * plot.h / slowest >= 0.5 {
+                    let rect = Rect::new(x, y(bottom + ms), width, ms / slowest * plot.h);
+                    builder.rectangle(DrawMode::fill(), rect, color)?;
+                }
+                bottom += ms;
+            }
+        }
+        let budget = y(FRAME_BUDGET_MS);
+        builder.line(
+            &[[plot.x, budget], [plot.right(), budget]],
+            1.0,
+            Color::WHITE,
+        )?;
+        canvas.draw(&Mesh::from_data(ctx, builder.build()), [0.0, 0.0]);
+        Ok(())
+    }
+}
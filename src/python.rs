@@ -0,0 +1,146 @@
+use crate::integrator::IntegratorKind;
+use crate::notebook::Params;
+use crate::pendulum;
+use crate::simulation::DT;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+/// A double pendulum simulated from Python, without any window
+///
+/// The constructor takes the initial state and the constants as keyword arguments,
+/// the missing ones are the ones of the `classic` preset:
+///
+/// ```text
+/// from double_pendulum import DoublePendulum
+/// p = DoublePendulum(theta1=2.0, theta2=2.5, gravity=1.5, integrator="rk4")
+/// p.step(steps=2400)
+/// theta1, theta2, omega1, omega2 = p.state()
+/// ```
+#[pyclass(name = "DoublePendulum", module = "double_pendulum")]
+struct PyDoublePendulum {
+    pendulum: pendulum::DoublePendulum,
+    /// The simulated time in seconds, used by the driving force
+    time: f32,
+}
+
+#[pymethods]
+impl PyDoublePendulum {
+    #[new]
+    #[pyo3(signature = (
+        *, m1=None, m2=None, l1=None, l2=None, theta1=None, theta2=None, omega1=None, omega2=None,
+        gravity=None, damping=None, drive_amplitude=None, drive_frequency=None, integrator=None,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        m1: Option<f32>,
+        m2: Option<f32>,
+        l1: Option<f32>,
+        l2: Option<f32>,
+        theta1: Option<f32>,
+        theta2: Option<f32>,
+        omega1: Option<f32>,
+        omega2: Option<f32>,
+        gravity: Option<f32>,
+        damping: Option<f32>,
+        drive_amplitude: Option<f32>,
+        drive_frequency: Option<f32>,
+        integrator: Option<&str>,
+    ) -> PyResult<Self> {
+        let default = Params::default();
+        let mut params = Params {
+            m1: m1.unwrap_or(default.m1),
+            m2: m2.unwrap_or(default.m2),
+            l1: l1.unwrap_or(default.l1),
+            l2: l2.unwrap_or(default.l2),
+            theta1: theta1.unwrap_or(default.theta1),
+            theta2: theta2.unwrap_or(default.theta2),
+            omega1: omega1.unwrap_or(default.omega1),
+            omega2: omega2.unwrap_or(default.omega2),
+            physics: default.physics,
+        };
+        let physics = &mut params.physics;
+        physics.gravity = gravity.unwrap_or(physics.gravity);
+        physics.damping = damping.unwrap_or(physics.damping);
+        physics.drive_amplitude = drive_amplitude.unwrap_or(physics.drive_amplitude);
+        physics.drive_frequency = drive_frequency.unwrap_or(physics.drive_frequency);
+
+        let mut pendulum = params.pendulum();
+        if let Some(name) = integrator {
+            pendulum.set_integrator(integrator_kind(name)?);
+        }
+        Ok(Self {
+            pendulum,
+            time: 0.0,
+        })
+    }
+
+    /// Advance the simulation by `steps` steps of `dt` seconds, the physics rate of the window by default
+    #[pyo3(signature = (dt=DT, steps=1))]
+    fn step(&mut self, dt: f32, steps: usize) {
+        for _ in 0..steps {
+            // The trails are only drawn by the window, they are skipped
+            self.pendulum.forward(dt, 0.0, self.time);
+            self.time += dt;
+        }
+    }
+
+    /// Returns the angles and the angular velocities of both rods, as `(theta1, theta2, omega1, omega2)`
+    fn state(&self) -> (f32, f32, f32, f32) {
+        let [theta1, theta2] = self.pendulum.angles();
+        let [omega1, omega2] = self.pendulum.angular_velocities();
+        (theta1, theta2, omega1, omega2)
+    }
+
+    /// Returns the positions of both circles relative to the origin, in pixels with y pointing down,
+    /// as `((x1, y1), (x2, y2))`
+    fn positions(&self) -> ((f32, f32), (f32, f32)) {
+        let [[x1, y1], [x2, y2]] = self.pendulum.bobs();
+        ((x1, y1), (x2, y2))
+    }
+
+    /// Returns the number of full turns made by both rods, counterclockwise is positive,
+    /// as `(turns1, turns2)`
+    fn revolutions(&self) -> (i32, i32) {
+        let [turns1, turns2] = self.pendulum.revolutions();
+        (turns1, turns2)
+    }
+
+    /// Returns the kinetic and the potential energy, as `(kinetic, potential)`
+    fn energy(&self) -> (f32, f32) {
+        (
+            self.pendulum.kinetic_energy(),
+            self.pendulum.potential_energy(),
+        )
+    }
+
+    /// The simulated time in seconds
+    #[getter]
+    fn time(&self) -> f32 {
+        self.time
+    }
+
+    fn __repr__(&self) -> String {
+        let (theta1, theta2, omega1, omega2) = self.state();
+        format!(
+            "DoublePendulum(time={}, theta1={theta1}, theta2={theta2}, omega1={omega1}, omega2={omega2})",
+            self.time
+        )
+    }
+}
+
+/// Returns the integrator called `name`, the case is ignored
+fn integrator_kind(name: &str) -> PyResult<IntegratorKind> {
+    name.to_lowercase().parse().map_err(|()| {
+        let ids: Vec<_> = IntegratorKind::ALL.iter().map(|i| i.id()).collect();
+        PyValueError::new_err(format!(
+            "unknown integrator {name}, the available ones are: {}",
+            ids.join(", ")
+        ))
+    })
+}
+
+/// The physics of the double pendulum simulator, for parameter sweeps without the graphics
+#[pymodule]
+fn double_pendulum(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyDoublePendulum>()
+}
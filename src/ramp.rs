@@ -0,0 +1,153 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// A global simulation parameter that can be changed over time
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub enum Parameter {
+    Gravity,
+    Damping,
+    Wind,
+    DriveAmplitude,
+    DriveFrequency,
+}
+
+impl FromStr for Parameter {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gravity" => Ok(Self::Gravity),
+            "damping" => Ok(Self::Damping),
+            "wind" => Ok(Self::Wind),
+            "drive-amplitude" => Ok(Self::DriveAmplitude),
+            "drive-frequency" => Ok(Self::DriveFrequency),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Parameter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Gravity => "gravity",
+            Self::Damping => "damping",
+            Self::Wind => "wind",
+            Self::DriveAmplitude => "drive-amplitude",
+            Self::DriveFrequency => "drive-frequency",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A linear change of a parameter from one value to another over a duration in seconds of simulated time
+///
+/// It is written as `PARAMETER:FROM:TO:SECONDS`, like `gravity:1.0:3.0:60`
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Ramp {
+    pub parameter: Parameter,
+    pub from: f32,
+    pub to: f32,
+    pub duration: f32,
+}
+
+impl Ramp {
+    /// Returns how much of the ramp is done after `elapsed` seconds, between 0 and 1
+    pub fn progress(&self, elapsed: f32) -> f32 {
+        if self.duration <= 0.0 {
+            return 1.0;
+        }
+        (elapsed / self.duration).clamp(0.0, 1.0)
+    }
+
+    /// Returns the value of the parameter after `elapsed` seconds
+    pub fn value_at(&self, elapsed: f32) -> f32 {
+        let t = self.progress(elapsed);
+        self.from + t * (self.to - self.from)
+    }
+}
+
+impl fmt::Display for Ramp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self {
+            parameter,
+            from,
+            to,
+            duration,
+        } = self;
+        write!(f, "{parameter}:{from}:{to}:{duration}")
+    }
+}
+
+impl FromStr for Ramp {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(':');
+        let mut next = || parts.next().ok_or(());
+
+        let parameter = next()?.parse()?;
+        let from = next()?.parse().map_err(|_| ())?;
+        let to = next()?.parse().map_err(|_| ())?;
+        let duration = next()?.parse().map_err(|_| ())?;
+        if next().is_ok() {
+            return Err(());
+        }
+
+        Ok(Self {
+            parameter,
+            from,
+            to,
+            duration,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ramps_are_parsed() {
+        let ramp: Ramp = "gravity:1.0:3.0:60".parse().unwrap();
+        assert!(matches!(ramp.parameter, Parameter::Gravity));
+        assert_eq!((ramp.from, ramp.to, ramp.duration), (1.0, 3.0, 60.0));
+        let ramp: Ramp = "drive-frequency:-0.5:2:0.25".parse().unwrap();
+        assert!(matches!(ramp.parameter, Parameter::DriveFrequency));
+        assert_eq!((ramp.from, ramp.to, ramp.duration), (-0.5, 2.0, 0.25));
+    }
+
+    #[test]
+    fn invalid_ramps_are_rejected() {
+        for s in [
+            "",
+            "gravity",
+            "gravity:1:3",
+            "gravity:1:3:60:5",
+            "friction:1:3:60",
+            "gravity:one:3:60",
+            "gravity:1:3:",
+        ] {
+            assert!(s.parse::<Ramp>().is_err(), "{s:?}");
+        }
+    }
+
+    #[test]
+    fn ramps_are_written_back() {
+        let s = "damping:0:0.02:30";
+        let ramp: Ramp = s.parse().unwrap();
+        assert_eq!(ramp.to_string(), s);
+    }
+
+    #[test]
+    fn values_follow_the_ramp() {
+        let ramp: Ramp = "wind:1:3:10".parse().unwrap();
+        assert_eq!(ramp.value_at(0.0), 1.0);
+        assert_eq!(ramp.value_at(5.0), 2.0);
+        assert_eq!(ramp.value_at(20.0), 3.0);
+        assert_eq!(ramp.progress(-1.0), 0.0);
+        // A ramp without duration jumps to its final value
+        let ramp: Ramp = "wind:1:3:0".parse().unwrap();
+        assert_eq!(ramp.value_at(0.0), 3.0);
+    }
+}
@@ -0,0 +1,113 @@
+use double_pendulum::pendulum::Rods;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// How many sets of parameters are rolled before giving up on satisfying the constraints
+const MAX_ATTEMPTS: usize = 100;
+
+/// A quantity kept unchanged when the parameters of a pendulum are randomized
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Invariant {
+    /// The sum of the lengths of both rods
+    Length,
+    /// The sum of the kinetic and the potential energy, the angular velocities are rescaled to keep it
+    Energy,
+}
+
+impl FromStr for Invariant {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "length" => Ok(Self::Length),
+            "energy" => Ok(Self::Energy),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The bounds the masses and the rod lengths are picked within when a pendulum is randomized
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Constraints {
+    /// The smallest and the largest mass of each circle
+    pub mass: [f32; 2],
+    /// The shortest and the longest length of each rod, in pixels
+    pub length: [f32; 2],
+    /// The quantities kept unchanged
+    pub keep: Vec<Invariant>,
+}
+
+impl Default for Constraints {
+    fn default() -> Self {
+        Self {
+            mass: [2.0, 5.0],
+            length: [50.0, 150.0],
+            keep: Vec::new(),
+        }
+    }
+}
+
+/// Pick new masses and rod lengths for `pendulum` within the `constraints`, the angles are kept
+///
+/// Returns false and leaves the pendulum unchanged if no parameters satisfying the constraints were found,
+/// for instance when the energy can't be kept because the new circles would be too high
+pub fn randomize(pendulum: &mut Rods, rng: &mut impl Rng, constraints: &Constraints) -> bool {
+    let energy = pendulum.kinetic_energy() + pendulum.potential_energy();
+    let total_length = pendulum.lengths().iter().sum::<f32>();
+
+    for _ in 0..MAX_ATTEMPTS {
+        let mut candidate = *pendulum;
+        let [min, max] = constraints.mass;
+        for bob in 0..2 {
+            candidate.set_mass(bob, rng.gen_range(min..=max.max(min)));
+        }
+
+        let [min, max] = constraints.length;
+        let lengths = if constraints.keep.contains(&Invariant::Length) {
+            // Both rods must stay within the bounds, so the first one is limited by the second one
+            let low = min.max(total_length - max);
+            let high = max.min(total_length - min);
+            if low > high {
+                return false;
+            }
+            let l1 = rng.gen_range(low..=high);
+            [l1, total_length - l1]
+        } else {
+            [(); 2].map(|_| rng.gen_range(min..=max.max(min)))
+        };
+        for (rod, length) in lengths.into_iter().enumerate() {
+            candidate.set_length(rod, length);
+        }
+
+        if constraints.keep.contains(&Invariant::Energy) && !keep_energy(&mut candidate, energy) {
+            continue;
+        }
+        *pendulum = candidate;
+        return true;
+    }
+    false
+}
+
+/// Rescale the angular velocities of `pendulum` so its total energy is `energy`
+///
+/// Returns false if the potential energy alone is already above `energy`
+fn keep_energy(pendulum: &mut Rods, energy: f32) -> bool {
+    let kinetic = energy - pendulum.potential_energy();
+    if kinetic < 0.0 {
+        return false;
+    }
+
+    let current = pendulum.kinetic_energy();
+    let velocities = if current > 0.0 {
+        let scale = (kinetic / current).sqrt();
+        pendulum.angular_velocities().map(|v| v * scale)
+    } else {
+        // A pendulum at rest can't be rescaled, so all the kinetic energy goes into the first rod
+        let [m1, m2] = pendulum.masses();
+        let [l1, _] = pendulum.lengths();
+        [(2.0 * kinetic / ((m1 + m2) * l1 * l1)).sqrt(), 0.0]
+    };
+    pendulum.set_angular_velocities(velocities);
+    true
+}
@@ -0,0 +1,177 @@
+use double_pendulum::sink::{ExportSink, Metadata};
+use ggez::graphics::ImageFormat;
+use ggez::{Context, GameError, GameResult};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// The directory where the recordings are saved
+pub const RECORDINGS_DIR: &str = "recordings";
+/// Only one frame every `FRAME_STEP` frames is recorded, to keep the file size reasonable
+const FRAME_STEP: u32 = 2;
+/// The recording stops by itself after this many seconds
+const MAX_DURATION: f32 = 10.0;
+
+/// A frame of the window, in RGBA
+struct Frame {
+    pixels: Vec<u8>,
+    /// How long this frame stays on screen, in milliseconds
+    delay: u16,
+}
+
+/// Records the frames of the window and encodes them into an animated PNG once stopped
+///
+/// Unlike GIF, APNG keeps the full 24-bit colors and the alpha channel,
+/// so the smooth gradients of the trails don't get banded
+pub struct Recorder {
+    /// Where the file is written, a name is picked from the current time if it is missing
+    path: Option<PathBuf>,
+    frames: Vec<Frame>,
+    /// Stored in the text chunks of the file
+    metadata: Metadata,
+    /// The size of the first frame, the frames of a different size are skipped
+    size: Option<(u32, u32)>,
+    /// The number of frames drawn since the start of the recording
+    counter: u32,
+    start: Instant,
+    last_capture: Instant,
+}
+
+impl Recorder {
+    pub fn new(path: Option<&str>, metadata: &Metadata) -> Self {
+        Self {
+            path: path.map(PathBuf::from),
+            frames: Vec::new(),
+            metadata: metadata.clone(),
+            size: None,
+            counter: 0,
+            start: Instant::now(),
+            last_capture: Instant::now(),
+        }
+    }
+}
+
+impl ExportSink for Recorder {
+    fn name(&self) -> &str {
+        "apng"
+    }
+
+    /// Returns true once the recording reached its maximum duration
+    fn is_done(&self) -> bool {
+        self.start.elapsed().as_secs_f32() >= MAX_DURATION
+    }
+
+    /// Read back the last frame of the window
+    fn write_frame(&mut self, ctx: &Context) -> GameResult {
+        self.counter += 1;
+        if !self.counter.is_multiple_of(FRAME_STEP) {
+            return Ok(());
+        }
+
+        let frame = ctx.gfx.frame();
+        let size = (frame.width(), frame.height());
+        if *self.size.get_or_insert(size) != size {
+            return Ok(());
+        }
+        let pixels = read_frame(ctx)?;
+
+        // The delay of the previous frame is only known now,
+        // and the new frame gets the same delay until the next one is captured
+        let elapsed = self.last_capture.elapsed().as_millis();
+        self.last_capture = Instant::now();
+        if let Some(previous) = self.frames.last_mut() {
+            previous.delay = elapsed.clamp(1, u16::MAX as u128) as u16;
+        }
+
+        let delay = self.frames.last().map_or(1000 / 30, |f| f.delay);
+        self.frames.push(Frame { pixels, delay });
+        Ok(())
+    }
+
+    /// Encode every recorded frame into an animated PNG
+    fn finish(self: Box<Self>) -> GameResult {
+        let Some((width, height)) = self.size else {
+            return Ok(());
+        };
+        if self.frames.is_empty() {
+            return Ok(());
+        }
+
+        let path = match &self.path {
+            Some(path) => path.clone(),
+            None => {
+                fs::create_dir_all(RECORDINGS_DIR)?;
+                PathBuf::from(RECORDINGS_DIR).join(format!("{}.png", timestamp()))
+            }
+        };
+        let writer = BufWriter::new(File::create(&path)?);
+
+        let mut encoder = png::Encoder::new(writer, width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        add_text_chunks(&mut encoder, &self.metadata)?;
+        // 0 plays means that the animation loops forever
+        encoder
+            .set_animated(self.frames.len() as u32, 0)
+            .map_err(encoding_error)?;
+
+        let mut writer = encoder.write_header().map_err(encoding_error)?;
+        for frame in &self.frames {
+            writer
+                .set_frame_delay(frame.delay, 1000)
+                .map_err(encoding_error)?;
+            writer
+                .write_image_data(&frame.pixels)
+                .map_err(encoding_error)?;
+        }
+        writer.finish().map_err(encoding_error)?;
+
+        println!("Saved the recording to {}", path.display());
+        Ok(())
+    }
+}
+
+/// Returns the pixels of the last frame of the window, in RGBA
+pub fn read_frame(ctx: &Context) -> GameResult<Vec<u8>> {
+    let frame = ctx.gfx.frame();
+    let mut pixels = frame.to_pixels(ctx)?;
+    match frame.format() {
+        ImageFormat::Rgba8Unorm | ImageFormat::Rgba8UnormSrgb => (),
+        ImageFormat::Bgra8Unorm | ImageFormat::Bgra8UnormSrgb => {
+            pixels.chunks_exact_mut(4).for_each(|p| p.swap(0, 2));
+        }
+        format => {
+            return Err(GameError::RenderError(format!(
+                "cannot read frames in the {format:?} format"
+            )))
+        }
+    }
+    Ok(pixels)
+}
+
+/// Store the metadata in the text chunks of a PNG file
+pub fn add_text_chunks<W: Write>(encoder: &mut png::Encoder<W>, metadata: &Metadata) -> GameResult {
+    // tEXt chunks can only hold Latin-1, so the other values go in UTF-8 iTXt chunks
+    for (key, value) in metadata.entries() {
+        let (key, value) = (key.to_string(), value.clone());
+        if value.is_ascii() {
+            encoder.add_text_chunk(key, value)
+        } else {
+            encoder.add_itxt_chunk(key, value)
+        }
+        .map_err(encoding_error)?;
+    }
+    Ok(())
+}
+
+/// Returns the number of seconds since the Unix epoch, used to give unique names to the files
+pub fn timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+pub fn encoding_error(e: png::EncodingError) -> GameError {
+    GameError::CustomError(format!("couldn't encode the PNG file: {e}"))
+}
@@ -0,0 +1,57 @@
+use ggez::{GameError, GameResult};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+
+/// Tells when the configuration file changes, so its settings are applied to the running simulation
+///
+/// The directory of the file is watched rather than the file itself: many editors save by writing
+/// a new file and renaming it over the old one, and a watch on the old file would stop there.
+pub struct ConfigWatcher {
+    /// Kept alive for as long as the file is watched, dropping it stops the watch
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<notify::Event>>,
+    path: PathBuf,
+}
+
+impl ConfigWatcher {
+    /// Watch the configuration file at `path`, which doesn't have to exist yet
+    pub fn new(path: &Path) -> GameResult<Self> {
+        let error = |e: notify::Error| {
+            GameError::CustomError(format!("couldn't watch {}: {e}", path.display()))
+        };
+        let directory = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender).map_err(error)?;
+        watcher
+            .watch(directory, RecursiveMode::NonRecursive)
+            .map_err(error)?;
+        Ok(Self {
+            _watcher: watcher,
+            events,
+            path: path.to_owned(),
+        })
+    }
+
+    /// Returns the path of the watched file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns whether the file was written since the last call
+    ///
+    /// Every waiting event is read, so a save firing several events is only reported once
+    pub fn changed(&self) -> bool {
+        let name = self.path.file_name();
+        self.events
+            .try_iter()
+            .flatten()
+            .filter(|event| matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)))
+            .filter(|event| event.paths.iter().any(|path| path.file_name() == name))
+            .count()
+            > 0
+    }
+}
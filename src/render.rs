@@ -0,0 +1,482 @@
+use double_pendulum::color;
+use double_pendulum::pendulum::{wrap_angle, DoublePendulum, Rods, TrailSlices};
+use ggez::graphics::{
+    self, Canvas, Color, DrawMode, DrawParam, InstanceArray, Mesh, MeshBuilder, Rect,
+};
+use ggez::{Context, GameResult};
+
+/// The dash and gap lengths of the rods in monochrome mode, None is a solid line
+const DASH_PATTERNS: [Option<[f32; 2]>; 3] = [None, Some([10.0, 6.0]), Some([2.0, 4.0])];
+/// The number of physics updates for the hue of the rainbow trails to go around the color wheel,
+/// 4 seconds of simulated time at the default physics rate
+const RAINBOW_PERIOD: u32 = 960;
+/// The largest change of angle between two physics updates that is interpolated, in radians,
+/// a larger one is a jump, like a reset, drawn at once
+const MAX_INTERPOLATED_ANGLE: f32 = 1.0;
+
+/// How the trail of a double pendulum is colored
+#[derive(Clone, Copy)]
+pub enum TrailColor {
+    /// A single color
+    Fixed(Color),
+    /// The color of the pendulum, with its saturation scaled by `saturation`
+    Pendulum { saturation: f32 },
+    /// A gradient following the speed of the second circle, from blue when it is the slowest
+    /// to red when it is the fastest along the trail
+    Speed,
+    /// A hue going around the color wheel over time, each point keeping the hue it was added with
+    Rainbow,
+}
+
+/// Where the rods of a double pendulum are drawn between the last two physics updates
+#[derive(Clone, Copy)]
+pub struct Interpolation {
+    /// The angles of both rods before the last physics update
+    pub previous: [f32; 2],
+    /// How far the next physics update is, from 0 right after the last one to 1 when it is due
+    pub progress: f32,
+}
+
+/// Which trails of a double pendulum are drawn, and how they are colored
+#[derive(Clone, Copy, Default)]
+pub struct Trails {
+    /// The trail of the second circle, at the tip of the pendulum
+    pub tip: Option<TrailColor>,
+    /// The trail of the first circle, where both rods meet, drawn thinner than the other one
+    pub joint: Option<Color>,
+}
+
+/// How a double pendulum is drawn
+#[derive(Clone, Copy)]
+pub enum Style {
+    /// Use the color of the pendulum
+    Color,
+    /// Only use white shapes, the `index` of the pendulum picks
+    /// a line width, a dash pattern and whether the circles are filled
+    Monochrome { index: usize },
+}
+
+/// Something the pendulums can be drawn on, the window or a file
+///
+/// The shapes are given in the coordinates of the window, so every renderer draws the same picture
+pub trait Renderer {
+    /// Draw a line through `points`, dashed if `dash` gives the lengths of a dash and of a gap
+    fn line(
+        &mut self,
+        points: &[[f32; 2]],
+        width: f32,
+        color: Color,
+        dash: Option<[f32; 2]>,
+    ) -> GameResult;
+
+    /// Draw a line through `points` whose color changes from one point to the next,
+    /// `colors` giving the color of each point in order
+    fn gradient_line(&mut self, points: &[[f32; 2]], width: f32, colors: &[Color]) -> GameResult;
+
+    /// Draw a line through `points` like `gradient_line`, each point coming with its color
+    ///
+    /// The points are collected into slices by default, a renderer can draw them as they come instead
+    fn gradient_path(
+        &mut self,
+        points: impl Iterator<Item = ([f32; 2], Color)>,
+        width: f32,
+    ) -> GameResult {
+        let (points, colors): (Vec<_>, Vec<_>) = points.unzip();
+        self.gradient_line(&points, width, &colors)
+    }
+
+    /// Draw a circle, filled or outlined depending on `mode`
+    fn circle(&mut self, center: [f32; 2], radius: f32, mode: DrawMode, color: Color)
+        -> GameResult;
+
+    /// Draw the shapes kept to be drawn together, before the clipping or the target changes
+    fn flush(&mut self) -> GameResult {
+        Ok(())
+    }
+}
+
+/// Copies of a mesh, each one moved, scaled and tinted, drawn in a single draw call for each flush
+struct Instances {
+    /// A white shape of size 1
+    mesh: Mesh,
+    /// One array for each flush of the current frame, since the instances of an array
+    /// are only read when the canvas is finished
+    arrays: Vec<InstanceArray>,
+    /// The number of arrays already drawn in the current frame
+    used: usize,
+}
+
+impl Instances {
+    fn new(mesh: Mesh) -> Self {
+        Self {
+            mesh,
+            arrays: Vec::new(),
+            used: 0,
+        }
+    }
+
+    /// Forget the instances of the previous frame, the arrays are kept to be filled again
+    fn start_frame(&mut self) {
+        for array in &mut self.arrays {
+            array.clear();
+        }
+        self.used = 0;
+    }
+
+    /// Add an instance to the array of the current flush
+    fn push(&mut self, ctx: &Context, param: DrawParam) {
+        if self.used == self.arrays.len() {
+            self.arrays.push(InstanceArray::new(ctx, None, 1024, false));
+        }
+        self.arrays[self.used].push(param);
+    }
+
+    /// Draw the instances added since the previous flush in one draw call
+    fn flush(&mut self, canvas: &mut Canvas) {
+        match self.arrays.get(self.used) {
+            Some(array) if !array.instances().is_empty() => {
+                canvas.draw_instanced_mesh(self.mesh.clone(), array, DrawParam::new());
+                self.used += 1;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The filled circles and the segments of the trails of the pendulums,
+/// drawn with a single instanced mesh for each scene instead of building a mesh for every shape
+///
+/// The arrays of instances are kept from one frame to the next, so drawing doesn't allocate
+/// once they are large enough
+pub struct Batches {
+    /// A filled circle of radius 1
+    circles: Instances,
+    /// A square of side 1, from the origin to the right, stretched along each segment
+    segments: Instances,
+}
+
+impl Batches {
+    pub fn new(ctx: &Context) -> GameResult<Self> {
+        // The tolerance is relative to the radius of 1, so the circles stay round once scaled
+        let circle = Mesh::new_circle(ctx, DrawMode::fill(), [0.0, 0.0], 1.0, 0.005, Color::WHITE)?;
+        let square = Rect::new(0.0, -0.5, 1.0, 1.0);
+        let segment = Mesh::new_rectangle(ctx, DrawMode::fill(), square, Color::WHITE)?;
+        Ok(Self {
+            circles: Instances::new(circle),
+            segments: Instances::new(segment),
+        })
+    }
+
+    /// Forget the shapes of the previous frame
+    pub fn start_frame(&mut self) {
+        self.circles.start_frame();
+        self.segments.start_frame();
+    }
+
+    /// Add a filled circle
+    fn circle(&mut self, ctx: &Context, center: [f32; 2], radius: f32, color: Color) {
+        let param = DrawParam::new()
+            .dest(center)
+            .scale([radius, radius])
+            .color(color);
+        self.circles.push(ctx, param);
+    }
+
+    /// Add a segment from `a` to `b`
+    fn segment(&mut self, ctx: &Context, a: [f32; 2], b: [f32; 2], width: f32, color: Color) {
+        let length = (b[0] - a[0]).hypot(b[1] - a[1]);
+        let param = DrawParam::new()
+            .dest(a)
+            .rotation((b[1] - a[1]).atan2(b[0] - a[0]))
+            .scale([length, width])
+            .color(color);
+        self.segments.push(ctx, param);
+    }
+
+    /// Draw the shapes added since the previous flush, the circles on top of the segments
+    fn flush(&mut self, canvas: &mut Canvas) {
+        self.segments.flush(canvas);
+        self.circles.flush(canvas);
+    }
+}
+
+/// Draws on a canvas of the window with meshes
+pub struct CanvasRenderer<'a> {
+    pub ctx: &'a Context,
+    pub canvas: &'a mut Canvas,
+    /// Where the filled circles and the trails are batched, each shape gets its own mesh without it
+    pub batches: Option<&'a mut Batches>,
+}
+
+impl Renderer for CanvasRenderer<'_> {
+    fn line(
+        &mut self,
+        points: &[[f32; 2]],
+        width: f32,
+        color: Color,
+        dash: Option<[f32; 2]>,
+    ) -> GameResult {
+        let line = match dash {
+            None => Mesh::new_line(self.ctx, points, width, color)?,
+            Some(dash) => {
+                let mut builder = MeshBuilder::new();
+                for segment in points.windows(2) {
+                    dashed_line(&mut builder, segment[0], segment[1], width, dash, color)?;
+                }
+                Mesh::from_data(self.ctx, builder.build())
+            }
+        };
+        self.canvas.draw(&line, [0.0, 0.0]);
+        Ok(())
+    }
+
+    fn gradient_line(&mut self, points: &[[f32; 2]], width: f32, colors: &[Color]) -> GameResult {
+        let line = gradient_line(self.ctx, points, width, colors);
+        self.canvas.draw(&line, [0.0, 0.0]);
+        Ok(())
+    }
+
+    /// Add a segment between each pair of consecutive points to the batches, without allocating,
+    /// each segment taking the color of its newest end
+    fn gradient_path(
+        &mut self,
+        points: impl Iterator<Item = ([f32; 2], Color)>,
+        width: f32,
+    ) -> GameResult {
+        let Some(batches) = &mut self.batches else {
+            let (points, colors): (Vec<_>, Vec<_>) = points.unzip();
+            return self.gradient_line(&points, width, &colors);
+        };
+        let mut previous = None;
+        for (point, color) in points {
+            if let Some(previous) = previous {
+                batches.segment(self.ctx, previous, point, width, color);
+            }
+            previous = Some(point);
+        }
+        Ok(())
+    }
+
+    fn circle(
+        &mut self,
+        center: [f32; 2],
+        radius: f32,
+        mode: DrawMode,
+        color: Color,
+    ) -> GameResult {
+        if let (Some(batches), DrawMode::Fill(_)) = (&mut self.batches, mode) {
+            batches.circle(self.ctx, center, radius, color);
+            return Ok(());
+        }
+        let circle = Mesh::new_circle(self.ctx, mode, center, radius, 2.0, color)?;
+        self.canvas.draw(&circle, [0.0, 0.0]);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> GameResult {
+        if let Some(batches) = &mut self.batches {
+            batches.flush(self.canvas);
+        }
+        Ok(())
+    }
+}
+
+/// Returns a color of the physics library as a color of ggez
+pub fn to_graphics(color: color::Color) -> Color {
+    Color::new(color.r, color.g, color.b, color.a)
+}
+
+/// Draw the two lines, the two circles and the trails of `p` set in `trails`
+///
+/// The origin is drawn at `center`, and the whole pendulum is scaled by `scale`
+/// so the lengths in pixels of the simulation fit the current window.
+/// The rods are drawn between the last two physics updates with `interpolation`, if given,
+/// so the motion stays smooth when the frames don't fall on the physics updates
+pub fn draw_pendulum(
+    p: &mut DoublePendulum,
+    interpolation: Option<Interpolation>,
+    renderer: &mut impl Renderer,
+    center: [f32; 2],
+    scale: f32,
+    trails: Trails,
+    style: Style,
+) -> GameResult {
+    let mut rods = **p;
+    if let Some(Interpolation { previous, progress }) = interpolation {
+        rods.set_angles(interpolate_angles(previous, p.angles(), progress));
+    }
+    draw_rods(&rods, renderer, center, scale, style)?;
+    draw_trails(p, renderer, center, scale, trails)
+}
+
+/// Returns the angles `progress` of the way from `previous` to `current`, turning the short way around
+///
+/// The angles that changed by more than `MAX_INTERPOLATED_ANGLE` jumped, so they are `current` right away
+fn interpolate_angles(previous: [f32; 2], current: [f32; 2], progress: f32) -> [f32; 2] {
+    let change = [0, 1].map(|rod| wrap_angle(current[rod] - previous[rod]));
+    if change
+        .iter()
+        .any(|change| change.abs() > MAX_INTERPOLATED_ANGLE)
+    {
+        return current;
+    }
+    [0, 1].map(|rod| current[rod] - change[rod] * (1.0 - progress))
+}
+
+/// Draw the two lines and the two circles of `p`, hanging from `center` and scaled by `scale`
+pub fn draw_rods(
+    p: &Rods,
+    renderer: &mut impl Renderer,
+    center: [f32; 2],
+    scale: f32,
+    style: Style,
+) -> GameResult {
+    let to_window = |[x, y]: [f32; 2]| [center[0] + x * scale, center[1] + y * scale];
+    let [p1, p2] = p.bobs().map(to_window);
+    let [m1, m2] = p.masses();
+
+    let (color, width, dash, mode) = match style {
+        Style::Color => (to_graphics(p.color()), 2.0, None, DrawMode::fill()),
+        Style::Monochrome { index } => {
+            let width = 1.0 + (index % 3) as f32 * 1.5;
+            let dash = DASH_PATTERNS[index / 3 % DASH_PATTERNS.len()];
+            let filled = index / 9 % 2 == 0;
+            let mode = if filled {
+                DrawMode::fill()
+            } else {
+                DrawMode::stroke(2.0 * scale)
+            };
+            (Color::WHITE, width, dash, mode)
+        }
+    };
+
+    let dash = dash.map(|dash| dash.map(|length| length * scale));
+    renderer.line(&[center, p1, p2], width * scale, color, dash)?;
+    renderer.circle(p1, 4.0 * m1 * scale, mode, color)?;
+    renderer.circle(p2, 4.0 * m2 * scale, mode, color)
+}
+
+/// Draw the trails of `p` set in `trails`, fading out from the newest point to the oldest one
+pub fn draw_trails(
+    p: &mut DoublePendulum,
+    renderer: &mut impl Renderer,
+    center: [f32; 2],
+    scale: f32,
+    trails: Trails,
+) -> GameResult {
+    let to_window = |[x, y]: [f32; 2]| [center[0] + x * scale, center[1] + y * scale];
+    // The color of the pendulum is read before its trails are borrowed
+    let saturation = match trails.tip {
+        Some(TrailColor::Pendulum { saturation }) => saturation,
+        _ => 1.0,
+    };
+    let own_color = to_graphics(p.trail_color(saturation));
+    let TrailSlices {
+        trail,
+        speeds,
+        ticks,
+        joint: joint_trail,
+    } = p.contiguous_trails();
+    // The trails saved before the ticks were recorded have fewer of them, the missing ones are the oldest
+    let missing_ticks = trail.len().saturating_sub(ticks.len());
+
+    if let (Some(trail_color), true) = (trails.tip, trail.len() >= 3) {
+        let (slowest, fastest) = (speeds.iter()).fold((f32::INFINITY, 0.0f32), |(min, max), &s| {
+            (min.min(s), max.max(s))
+        });
+        let range = (fastest - slowest).max(f32::EPSILON);
+
+        let count = trail.len() as f32;
+        let points = (trail.iter().zip(speeds).enumerate()).map(|(i, (&point, &speed))| {
+            let mut color = match trail_color {
+                TrailColor::Fixed(color) => color,
+                TrailColor::Pendulum { .. } => own_color,
+                TrailColor::Speed => {
+                    to_graphics(color::hsv(240.0 * (fastest - speed) / range, 1.0, 1.0))
+                }
+                TrailColor::Rainbow => {
+                    let tick = i.checked_sub(missing_ticks).map_or(0, |i| ticks[i]);
+                    let turn = (tick % RAINBOW_PERIOD) as f32 / RAINBOW_PERIOD as f32;
+                    to_graphics(color::hsv(360.0 * turn, 0.8, 1.0))
+                }
+            };
+            color.a *= (i + 1) as f32 / count;
+            (to_window(point), color)
+        });
+        renderer.gradient_path(points, 2.0 * scale)?;
+    }
+
+    // The trail of the first circle is thinner than the trail of the tip
+    if let (Some(color), true) = (trails.joint, joint_trail.len() >= 3) {
+        let count = joint_trail.len() as f32;
+        let points = joint_trail.iter().enumerate().map(|(i, &point)| {
+            let mut color = color;
+            color.a *= (i + 1) as f32 / count;
+            (to_window(point), color)
+        });
+        renderer.gradient_path(points, scale)?;
+    }
+    Ok(())
+}
+
+/// Returns a line through `points` whose color changes smoothly from one point to the next
+///
+/// Each segment is a quad whose ends take the colors of its two points,
+/// `colors` giving the color of each point in order
+fn gradient_line(ctx: &Context, points: &[[f32; 2]], width: f32, colors: &[Color]) -> Mesh {
+    let mut vertices = Vec::with_capacity(points.len() * 4);
+    let mut indices = Vec::with_capacity(points.len() * 6);
+    for (i, segment) in points.windows(2).enumerate() {
+        let [a, b] = [segment[0], segment[1]];
+        // Offset both ends by half the width, perpendicular to the segment
+        let length = (b[0] - a[0]).hypot(b[1] - a[1]).max(f32::EPSILON);
+        let normal = [
+            -(b[1] - a[1]) / length * width / 2.0,
+            (b[0] - a[0]) / length * width / 2.0,
+        ];
+
+        let first = vertices.len() as u32;
+        for (point, color) in [(a, colors[i]), (b, colors[i + 1])] {
+            for side in [1.0, -1.0] {
+                vertices.push(graphics::Vertex {
+                    position: [point[0] + side * normal[0], point[1] + side * normal[1]],
+                    uv: [0.0, 0.0],
+                    color: color.into(),
+                });
+            }
+        }
+        indices.extend([0, 1, 2, 1, 3, 2].map(|j| first + j));
+    }
+    Mesh::from_data(
+        ctx,
+        graphics::MeshData {
+            vertices: &vertices,
+            indices: &indices,
+        },
+    )
+}
+
+/// Add a dashed line from `a` to `b` to the mesh, `dash` being the lengths of a dash and a gap
+fn dashed_line(
+    builder: &mut MeshBuilder,
+    a: [f32; 2],
+    b: [f32; 2],
+    width: f32,
+    dash: [f32; 2],
+    color: Color,
+) -> GameResult {
+    let length = (b[0] - a[0]).hypot(b[1] - a[1]);
+    if length == 0.0 {
+        return Ok(());
+    }
+    let direction = [(b[0] - a[0]) / length, (b[1] - a[1]) / length];
+    let point = |d: f32| [a[0] + direction[0] * d, a[1] + direction[1] * d];
+
+    let mut start = 0.0;
+    while start < length {
+        let end = (start + dash[0]).min(length);
+        builder.line(&[point(start), point(end)], width, color)?;
+        start = end + dash[1];
+    }
+    Ok(())
+}
@@ -0,0 +1,380 @@
+use crate::config::DEFAULT_PHYSICS_HZ;
+use crate::history::History;
+use crate::keybindings::Action;
+use crate::ramp::{Parameter, Ramp};
+use crate::randomize::Constraints;
+use crate::save::{json_error, SavedState};
+use crate::seekbar;
+use double_pendulum::color::Color;
+use double_pendulum::integrator::IntegratorKind;
+use double_pendulum::pendulum::InitialConditions;
+use double_pendulum::sink::Metadata;
+use ggez::graphics::Canvas;
+use ggez::{Context, GameResult};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Something that changed the simulation during a recorded run
+///
+/// Every input goes through an event, with the values it ended up with,
+/// so the replay doesn't depend on the mouse, the window or the frame rate
+#[derive(Clone, Serialize, Deserialize)]
+pub enum Event {
+    /// A key or a gamepad button triggered an action
+    Action { action: Action, shift: bool },
+    /// A pendulum was selected with the mouse
+    Select(usize),
+    /// A new pendulum was spawned with its tip at `target`, relative to the origin of `scene`
+    SpawnAt { scene: usize, target: [f32; 2] },
+    /// The wizard spawned a new pendulum
+    SpawnWith {
+        initial: InitialConditions,
+        scene: usize,
+        color: Color,
+    },
+    /// A pendulum was removed with the mouse
+    Remove(usize),
+    /// The mass or the rod of a circle was changed with the mouse wheel, and its pendulum selected
+    Edit {
+        index: usize,
+        bob: usize,
+        steps: f32,
+        length: bool,
+    },
+    /// The mass or the rod of a circle was set to `value` with the control panel
+    Tune {
+        index: usize,
+        bob: usize,
+        value: f32,
+        length: bool,
+    },
+    /// A pendulum was given a new color with the control panel
+    Recolor { index: usize, color: Color },
+    /// A circle was pulled toward `target` during `dt` seconds
+    Drag {
+        index: usize,
+        bob: usize,
+        target: [f32; 2],
+        dt: f32,
+    },
+    /// A parameter was changed with the control panel, the HTTP API or the configuration file
+    Parameter { parameter: Parameter, value: f32 },
+    /// A ramp started, its values follow the simulated time so they aren't recorded
+    Ramp(Ramp),
+    /// A scenario was started from the menu
+    Scenario(usize),
+    /// The next physics updates last `scale` times the usual duration
+    Scale(u32),
+}
+
+/// The number of seconds of simulated time between two keyframes of a replay
+pub const KEYFRAME_INTERVAL: u64 = 10;
+
+/// The state of a recorded run at some point, so the replay can be seeked without playing it from the start
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Keyframe {
+    /// The number of physics updates done before the state, every event up to it included is applied
+    pub tick: u64,
+    /// The scale of the next physics updates
+    pub scale: u32,
+    pub state: SavedState,
+    /// The ramps in progress, with the simulated time they started at
+    #[serde(default)]
+    pub ramps: Vec<(f64, Ramp)>,
+}
+
+/// A recorded run: the state it started from, the settings used by the inputs and every input
+///
+/// Each event is stored with the number of physics updates done before it,
+/// which is enough to reproduce the run exactly since the simulation itself is deterministic
+#[derive(Serialize, Deserialize)]
+pub struct Replay {
+    /// Where the recorded run comes from, like the exports, missing from the replays recorded before
+    #[serde(default)]
+    pub metadata: Metadata,
+    pub state: SavedState,
+    pub spawn_interval: Option<f32>,
+    pub max_pendulums: Option<usize>,
+    pub clones: usize,
+    pub constraints: Constraints,
+    /// The number of physics updates per second, the events being counted in physics updates
+    #[serde(default = "default_physics_hz")]
+    pub physics_hz: u32,
+    /// The integrator of the new pendulums, missing from the replays recorded before
+    #[serde(default)]
+    pub integrator: IntegratorKind,
+    pub events: Vec<(u64, Event)>,
+    /// The states of the run every `KEYFRAME_INTERVAL` seconds, missing from the replays recorded before
+    #[serde(default)]
+    pub keyframes: Vec<Keyframe>,
+    /// The simulated time when the recording stopped, missing from the replays recorded before
+    #[serde(default)]
+    pub end_time: Option<f64>,
+}
+
+impl Replay {
+    /// Write the replay as JSON into `path`
+    pub fn save(&self, path: &Path) -> GameResult {
+        let json = serde_json::to_string(self).map_err(json_error)?;
+        fs::write(path, json)?;
+        Ok(())
+    }
+
+    /// Read a replay written by `save`
+    pub fn load(path: &Path) -> GameResult<Self> {
+        let json = fs::read_to_string(path)?;
+        serde_json::from_str(&json).map_err(json_error)
+    }
+
+    /// Returns the last keyframe at or before the simulated time `time`, the start of the run being the first one
+    pub fn keyframe_before(&self, time: f64) -> Keyframe {
+        let keyframe = (self.keyframes.iter().rev()).find(|keyframe| keyframe.state.time <= time);
+        keyframe.cloned().unwrap_or_else(|| Keyframe {
+            tick: 0,
+            scale: 1,
+            state: self.state.clone(),
+            ramps: Vec::new(),
+        })
+    }
+}
+
+/// The physics rate of the replays recorded before it could be changed
+fn default_physics_hz() -> u32 {
+    DEFAULT_PHYSICS_HZ
+}
+
+/// Whether the inputs are being recorded or replayed
+enum ReplayMode {
+    /// The inputs are added to `replay`, which is written to `path` when the window is closed
+    Recording {
+        path: PathBuf,
+        replay: Box<Replay>,
+        /// The scale of the last physics update, a `Scale` event is added when it changes
+        scale: u32,
+    },
+    /// The inputs come from the replay, the ones of the user are ignored
+    Playing {
+        /// The whole replay, kept to seek it
+        replay: Box<Replay>,
+        /// The index of the next event to apply
+        next: usize,
+        /// The scale of the next physics updates
+        scale: u32,
+    },
+}
+
+/// The replay system, which records the inputs or plays them back, and keeps the history to rewind
+pub struct ReplaySystem {
+    /// Whether the inputs are recorded or replayed, if they are
+    mode: Option<ReplayMode>,
+    /// The number of physics updates done since the start of the replay, the events are stamped with it
+    ticks: u64,
+    /// The simulated time the replay seeks to on the first update, since seeking it needs the context
+    pub pending_seek: Option<f64>,
+    /// The last seconds of the simulation, to rewind it
+    pub history: History,
+}
+
+impl ReplaySystem {
+    pub fn new() -> Self {
+        Self {
+            mode: None,
+            ticks: 0,
+            pending_seek: None,
+            history: History::new(),
+        }
+    }
+
+    /// Returns the number of physics updates done since the start of the replay
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// Returns true while a replay is played
+    pub fn is_playing(&self) -> bool {
+        matches!(self.mode, Some(ReplayMode::Playing { .. }))
+    }
+
+    /// Returns the scale of the next physics update of the replay being played, if one is
+    pub fn scale(&self) -> Option<u32> {
+        match &self.mode {
+            Some(ReplayMode::Playing { scale, .. }) => Some(*scale),
+            _ => None,
+        }
+    }
+
+    /// Start recording the inputs into `replay`, which is written to `path` when the window is closed
+    pub fn start_recording(&mut self, path: PathBuf, replay: Replay) {
+        self.ticks = 0;
+        self.mode = Some(ReplayMode::Recording {
+            path,
+            replay: Box::new(replay),
+            scale: 1,
+        });
+    }
+
+    /// Start playing the inputs of `replay`, whose state was already restored
+    pub fn start_playing(&mut self, replay: Replay) {
+        self.ticks = 0;
+        self.mode = Some(ReplayMode::Playing {
+            replay: Box::new(replay),
+            next: 0,
+            scale: 1,
+        });
+    }
+
+    /// Add `event` to the replay if it is recorded
+    pub fn record(&mut self, event: Event) {
+        if let Some(ReplayMode::Recording { replay, .. }) = &mut self.mode {
+            replay.events.push((self.ticks, event));
+        }
+    }
+
+    /// Returns the next event of the replay due before the next physics update, if any
+    ///
+    /// The scales are applied to the replay itself, the other events are left to the caller
+    pub fn next_event(&mut self) -> Option<Event> {
+        loop {
+            let Some(ReplayMode::Playing {
+                replay,
+                next,
+                scale,
+            }) = &mut self.mode
+            else {
+                return None;
+            };
+            let (_, event) = (replay.events.get(*next)).filter(|&&(tick, _)| tick <= self.ticks)?;
+            let event = event.clone();
+            *next += 1;
+            if *next == replay.events.len() {
+                println!("The replay is over, the simulation goes on from there");
+            }
+            match event {
+                Event::Scale(new) => *scale = new,
+                event => return Some(event),
+            }
+        }
+    }
+
+    /// Returns true if the replay being recorded is due for a keyframe before the next physics update
+    pub fn keyframe_due(&self, physics_hz: u32) -> bool {
+        let interval = KEYFRAME_INTERVAL * physics_hz as u64;
+        self.ticks != 0
+            && self.ticks.is_multiple_of(interval)
+            && matches!(self.mode, Some(ReplayMode::Recording { .. }))
+    }
+
+    /// Count a physics update lasting `scale`, adding `keyframe` to the replay being recorded if there is one
+    ///
+    /// A `Scale` event is recorded whenever the scale changes, so the replay does the same updates
+    pub fn update(&mut self, scale: u32, keyframe: Option<Keyframe>) {
+        if let Some(ReplayMode::Recording {
+            replay,
+            scale: last,
+            ..
+        }) = &mut self.mode
+        {
+            if scale != *last {
+                *last = scale;
+                replay.events.push((self.ticks, Event::Scale(scale)));
+            }
+            replay.keyframes.extend(keyframe);
+        }
+        self.ticks += 1;
+    }
+
+    /// Move the replay being played back to the last keyframe before `time`, and returns it to be restored
+    ///
+    /// Nothing changes when the replay is already at `now`, past the keyframe and before `time`,
+    /// since playing it forward from there is enough
+    pub fn rewind_to(&mut self, time: f64, now: f64) -> Option<Keyframe> {
+        let Some(ReplayMode::Playing {
+            replay,
+            next,
+            scale,
+        }) = &mut self.mode
+        else {
+            return None;
+        };
+        let keyframe = replay.keyframe_before(time);
+        if time >= now && keyframe.state.time <= now {
+            return None;
+        }
+        *next = (replay.events).partition_point(|&(tick, _)| tick <= keyframe.tick);
+        *scale = keyframe.scale;
+        self.ticks = keyframe.tick;
+        Some(keyframe)
+    }
+
+    /// Returns the simulated times at the start and the end of the replay being played, if it has a seek bar
+    ///
+    /// The replays recorded before their end was saved have no seek bar
+    fn seek_bar_range(&self) -> Option<(f64, f64)> {
+        let Some(ReplayMode::Playing { replay, .. }) = &self.mode else {
+            return None;
+        };
+        let start = replay.state.time;
+        let end = replay.end_time.filter(|&end| end > start)?;
+        Some((start, end))
+    }
+
+    /// Returns the simulated time of the replay under `point` on the seek bar, if there is one
+    pub fn seek_bar_time(&self, window: [f32; 2], point: [f32; 2]) -> Option<f64> {
+        let (start, end) = self.seek_bar_range()?;
+        let fraction = seekbar::fraction_at(window, point)?;
+        Some(start + fraction as f64 * (end - start))
+    }
+
+    /// Draw the seek bar of the replay being played at the simulated time `time`, if it has one
+    pub fn draw(
+        &self,
+        ctx: &mut Context,
+        canvas: &mut Canvas,
+        window: [f32; 2],
+        time: f64,
+    ) -> GameResult {
+        let Some((start, end)) = self.seek_bar_range() else {
+            return Ok(());
+        };
+        let fraction = ((time - start) / (end - start)) as f32;
+        let label = format!("{time:.1} / {end:.1} s");
+        seekbar::draw(ctx, canvas, window, fraction, &label)
+    }
+
+    /// Write the replay being recorded to its file, ending at the simulated time `time`
+    pub fn save(&mut self, time: f64) -> GameResult {
+        if let Some(ReplayMode::Recording { path, replay, .. }) = &mut self.mode {
+            replay.end_time = Some(time);
+            replay.save(path)?;
+            println!("Saved the replay to {}", path.display());
+        }
+        Ok(())
+    }
+}
+
+/// Returns true if `action` changes the simulation, so it has to be recorded
+///
+/// The actions writing files, playing sound or opening the wizard or the menu of the scenarios are left out,
+/// the pendulum built with the wizard and the scenario picked in the menu are recorded when they are used
+pub fn is_replayed(action: Action) -> bool {
+    !matches!(
+        action,
+        Action::Record
+            | Action::RecordCsv
+            | Action::RecordGif
+            | Action::RecordVideo
+            | Action::ExportSvg
+            | Action::Screenshot
+            | Action::Save
+            | Action::Repro
+            | Action::Wizard
+            | Action::Scenarios
+            | Action::Sound
+            | Action::StressTest
+            | Action::Louder
+            | Action::Quieter
+            | Action::ControlPanel
+            | Action::Quit
+    )
+}
@@ -0,0 +1,93 @@
+use crate::config::DEFAULT_TRAIL_SATURATION;
+use double_pendulum::pendulum::{DoublePendulum, Physics};
+use double_pendulum::sink::Metadata;
+use ggez::graphics::Color;
+use ggez::{GameError, GameResult};
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// The directory the states are saved in
+pub const SAVES_DIR: &str = "saves";
+
+/// Everything needed to resume a simulation where it was left, written as JSON
+///
+/// The random number generator is saved too, so the pendulums spawned after loading
+/// are the same as in the saved run.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SavedState {
+    /// Where the state comes from, like the exports, missing from the states saved before
+    /// and from the keyframes of the replays
+    #[serde(default, skip_serializing_if = "Metadata::is_empty")]
+    pub metadata: Metadata,
+    /// The seed of the random number generator
+    pub seed: u64,
+    /// The state of the random number generator, missing from the states saved before it could be,
+    /// which start over from `seed`
+    #[serde(default)]
+    pub rng: Option<ChaCha12Rng>,
+    /// The simulated time, in seconds
+    pub time: f64,
+    /// Every pendulum, with its trails
+    pub pendulums: Vec<DoublePendulum>,
+    /// The scene of each pendulum, in the same order as `pendulums`
+    pub pendulum_scenes: Vec<usize>,
+    /// The number of scenes tiled across the window
+    pub scenes: usize,
+    /// The position of the origin of the pendulums in each scene, as a fraction of its width and height
+    pub anchor: [f32; 2],
+    /// The center of the window the lengths of the pendulums are measured in
+    pub reference_center: [f32; 2],
+    /// The physical constants given to every new pendulum
+    pub physics: Physics,
+    /// The constant part of the wind
+    pub wind: f32,
+    /// The amplitude of the gusts of wind
+    pub gust: f32,
+    /// The simulated time since the last automatic spawn, in seconds
+    pub spawn_timer: f32,
+    /// How many physics updates are done for each tick of the update timer
+    pub speed: f32,
+    pub paused: bool,
+    pub slow_motion: bool,
+    pub monochrome: bool,
+    pub show_trail: bool,
+    pub show_joint_trail: bool,
+    pub speed_trail: bool,
+    #[serde(default)]
+    pub rainbow_trail: bool,
+    pub background: Color,
+    /// None if each trail takes the color of its pendulum
+    pub trail_color: Option<Color>,
+    #[serde(default = "default_trail_saturation")]
+    pub trail_saturation: f32,
+    pub joint_trail_color: Color,
+    /// The index of the pendulum shown in the inspector panel, if any
+    pub selected: Option<usize>,
+}
+
+impl SavedState {
+    /// Write the state as JSON into `path`
+    pub fn save(&self, path: &Path) -> GameResult {
+        let json = serde_json::to_string(self).map_err(json_error)?;
+        fs::write(path, json)?;
+        Ok(())
+    }
+
+    /// Read a state written by `save`
+    pub fn load(path: &Path) -> GameResult<Self> {
+        let json = fs::read_to_string(path)?;
+        serde_json::from_str(&json).map_err(json_error)
+    }
+}
+
+/// The saturation of the trails of the states saved before it could be changed
+fn default_trail_saturation() -> f32 {
+    DEFAULT_TRAIL_SATURATION
+}
+
+/// Returns the error of a JSON file that couldn't be written or read
+pub fn json_error(e: serde_json::Error) -> GameError {
+    GameError::CustomError(format!("invalid JSON file: {e}"))
+}
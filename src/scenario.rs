@@ -0,0 +1,142 @@
+mod chaos;
+mod driven;
+mod fractal_tour;
+
+use chaos::Chaos;
+use double_pendulum::pendulum::{InitialConditions, Physics};
+use double_pendulum::pendulums::Pendulums;
+use driven::Driven;
+use fractal_tour::FractalTour;
+use ggez::graphics::{self, Canvas, Color, DrawMode, Mesh, Rect};
+use ggez::winit::event::VirtualKeyCode;
+use ggez::Context;
+use ggez::GameResult;
+
+/// The vertical space in pixels taken by each entry of the menu
+const ENTRY_SPACING: f32 = 40.0;
+
+/// A self-contained demo: the pendulums it starts with, what it does after every physics update
+/// and what it draws on top of the simulation
+///
+/// The physics are reset to their defaults before `setup`, so a scenario doesn't depend on the previous one.
+/// Each scenario has its own module, and is listed in `SCENARIOS` to appear in the menu
+pub trait Scenario {
+    /// Change the physics given to the pendulums if needed, and returns the initial conditions of every pendulum
+    fn setup(&mut self, physics: &mut Physics) -> Vec<InitialConditions>;
+
+    /// Called after every physics update
+    fn update(&mut self, stage: &mut Stage);
+
+    /// Draw the overlay of the scenario, `window` is the size of the window
+    fn draw(&self, _ctx: &mut Context, _canvas: &mut Canvas, _window: [f32; 2]) -> GameResult {
+        Ok(())
+    }
+}
+
+/// The simulation as seen by a scenario after a physics update
+pub struct Stage<'a> {
+    pub pendulums: &'a mut Pendulums,
+    /// The simulated time after the update, in seconds
+    pub time: f32,
+    /// The duration of the update, in seconds
+    pub dt: f32,
+    /// Set by the scenario to replace every pendulum with new ones
+    pub restart: Option<Vec<InitialConditions>>,
+}
+
+/// A scenario that can be picked in the menu
+pub struct ScenarioEntry {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Returns the scenario, ready to be set up
+    pub create: fn() -> Box<dyn Scenario>,
+}
+
+/// Every built-in scenario, in the order of the menu
+pub const SCENARIOS: &[ScenarioEntry] = &[
+    ScenarioEntry {
+        name: "chaos",
+        description: "50 pendulums a ten-thousandth of a radian apart",
+        create: || Box::new(Chaos::new()),
+    },
+    ScenarioEntry {
+        name: "driven",
+        description: "A damped pendulum pushed back and forth",
+        create: || Box::new(Driven::new()),
+    },
+    ScenarioEntry {
+        name: "fractal-tour",
+        description: "Zoom into the map of the pendulums that flip",
+        create: || Box::new(FractalTour::new()),
+    },
+];
+
+/// Write `text` in the bottom left corner of the window
+pub fn caption(canvas: &mut Canvas, window: [f32; 2], text: String) {
+    canvas.draw(&graphics::Text::new(text), [10.0, window[1] - 30.0]);
+}
+
+/// What happens after a key is pressed in the menu of the scenarios
+pub enum MenuAction {
+    /// The menu is still open
+    Continue,
+    /// The menu was closed without picking a scenario
+    Cancel,
+    /// The scenario at this index in `SCENARIOS` was picked
+    Start(usize),
+}
+
+/// The list of the scenarios, Up and Down move the cursor, Enter starts a scenario and Escape closes the menu
+pub struct ScenarioMenu {
+    /// The index of the highlighted scenario
+    cursor: usize,
+}
+
+impl ScenarioMenu {
+    pub fn new() -> Self {
+        Self { cursor: 0 }
+    }
+
+    /// Handle a key press, and returns whether the menu is still open or what was picked
+    pub fn key_down(&mut self, key: VirtualKeyCode) -> MenuAction {
+        match key {
+            VirtualKeyCode::Up => self.cursor = self.cursor.saturating_sub(1),
+            VirtualKeyCode::Down => self.cursor = (self.cursor + 1).min(SCENARIOS.len() - 1),
+            VirtualKeyCode::Return => return MenuAction::Start(self.cursor),
+            VirtualKeyCode::Escape => return MenuAction::Cancel,
+            _ => (),
+        }
+        MenuAction::Continue
+    }
+
+    /// Draw the list of the scenarios over the darkened simulation
+    pub fn draw(&self, ctx: &mut Context, canvas: &mut Canvas, window: [f32; 2]) -> GameResult {
+        let shade = Mesh::new_rectangle(
+            ctx,
+            DrawMode::fill(),
+            Rect::new(0.0, 0.0, window[0], window[1]),
+            Color::new(0.0, 0.0, 0.0, 0.7),
+        )?;
+        canvas.draw(&shade, [0.0, 0.0]);
+
+        for (i, scenario) in SCENARIOS.iter().enumerate() {
+            let highlight = if i == self.cursor {
+                Color::YELLOW
+            } else {
+                Color::WHITE
+            };
+            let text =
+                graphics::Text::new(format!("{}\n  {}", scenario.name, scenario.description));
+            canvas.draw(
+                &text,
+                graphics::DrawParam::new()
+                    .dest([10.0, 40.0 + i as f32 * ENTRY_SPACING])
+                    .color(highlight),
+            );
+        }
+
+        let help = graphics::Text::new("Scenarios  Up/Down: choose  Enter: start  Esc: cancel");
+        canvas.draw(&help, [10.0, 10.0]);
+        Ok(())
+    }
+}
@@ -0,0 +1,60 @@
+use super::{caption, Scenario, Stage};
+use crate::preset::conditions;
+use double_pendulum::color;
+use double_pendulum::pendulum::{InitialConditions, Physics};
+use ggez::graphics::Canvas;
+use ggez::Context;
+use ggez::GameResult;
+
+/// The number of pendulums released together
+const PENDULUMS: usize = 50;
+/// The difference between the second angles of two neighbouring pendulums, in radians
+const OFFSET: f32 = 1e-4;
+
+/// Many pendulums released from almost the same angles, rainbow colored, that drift apart after a few seconds
+pub struct Chaos {
+    /// The standard deviation of the second angles after the last update, in radians
+    spread: f32,
+}
+
+impl Chaos {
+    pub fn new() -> Self {
+        Self { spread: 0.0 }
+    }
+}
+
+impl Scenario for Chaos {
+    fn setup(&mut self, _physics: &mut Physics) -> Vec<InitialConditions> {
+        (0..PENDULUMS)
+            .map(|i| conditions(3.0, 3.0, 100.0, 100.0, 2.5, 2.5 + OFFSET * i as f32))
+            .collect()
+    }
+
+    fn update(&mut self, stage: &mut Stage) {
+        let count = stage.pendulums.len();
+        for (i, mut p) in stage.pendulums.iter_mut().enumerate() {
+            p.set_color(color::hsv(360.0 * i as f32 / count as f32, 0.8, 1.0));
+        }
+
+        // The full turns are counted, so the pendulums that flipped spread further apart
+        let n = count.max(1) as f64;
+        let mean = (stage.pendulums.iter())
+            .map(|p| p.total_angles()[1])
+            .sum::<f64>()
+            / n;
+        let variance = (stage.pendulums.iter())
+            .map(|p| (p.total_angles()[1] - mean).powi(2))
+            .sum::<f64>()
+            / n;
+        self.spread = variance.sqrt() as f32;
+    }
+
+    fn draw(&self, _ctx: &mut Context, canvas: &mut Canvas, window: [f32; 2]) -> GameResult {
+        caption(
+            canvas,
+            window,
+            format!("Spread of θ2: {:.4} rad", self.spread),
+        );
+        Ok(())
+    }
+}
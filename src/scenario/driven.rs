@@ -0,0 +1,57 @@
+use super::{caption, Scenario, Stage};
+use crate::preset::conditions;
+use double_pendulum::pendulum::{InitialConditions, Physics};
+use ggez::graphics::{Canvas, Color, DrawMode, Mesh, Rect};
+use ggez::Context;
+use ggez::GameResult;
+use std::f32::consts::PI;
+
+/// A damped pendulum whose first rod is pushed periodically, with a gauge of the driving force
+pub struct Driven {
+    /// The driving acceleration after the last update, between -1 and 1
+    drive: f32,
+}
+
+impl Driven {
+    /// The amplitude and the frequency of the driving force
+    const AMPLITUDE: f32 = 0.005;
+    const FREQUENCY: f32 = 0.5;
+
+    pub fn new() -> Self {
+        Self { drive: 0.0 }
+    }
+}
+
+impl Scenario for Driven {
+    fn setup(&mut self, physics: &mut Physics) -> Vec<InitialConditions> {
+        physics.damping = 0.002;
+        physics.drive_amplitude = Self::AMPLITUDE;
+        physics.drive_frequency = Self::FREQUENCY;
+        vec![conditions(3.0, 3.0, 100.0, 100.0, 0.3, 0.0)]
+    }
+
+    fn update(&mut self, stage: &mut Stage) {
+        self.drive = (2.0 * PI * Self::FREQUENCY * stage.time).sin();
+    }
+
+    fn draw(&self, ctx: &mut Context, canvas: &mut Canvas, window: [f32; 2]) -> GameResult {
+        // The gauge grows from its middle toward the side the first rod is pushed to
+        let (width, middle) = (200.0, window[0] / 2.0);
+        let top = window[1] - 30.0;
+        let track = Rect::new(middle - width / 2.0, top, width, 10.0);
+        let track = Mesh::new_rectangle(ctx, DrawMode::stroke(1.0), track, Color::WHITE)?;
+        canvas.draw(&track, [0.0, 0.0]);
+        let filled = width / 2.0 * self.drive;
+        if filled.abs() > 0.5 {
+            let rect = Rect::new(middle.min(middle + filled), top, filled.abs(), 10.0);
+            let bar = Mesh::new_rectangle(ctx, DrawMode::fill(), rect, Color::YELLOW)?;
+            canvas.draw(&bar, [0.0, 0.0]);
+        }
+        caption(
+            canvas,
+            window,
+            format!("Drive: {} at {} Hz", Self::AMPLITUDE, Self::FREQUENCY),
+        );
+        Ok(())
+    }
+}
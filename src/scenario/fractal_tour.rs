@@ -0,0 +1,142 @@
+use super::{caption, Scenario, Stage};
+use crate::preset::conditions;
+use crate::render;
+use double_pendulum::color;
+use double_pendulum::pendulum::{InitialConditions, Physics};
+use ggez::graphics::{self, Canvas, Color, DrawMode, Mesh, Rect};
+use ggez::Context;
+use ggez::GameResult;
+use std::f32::consts::PI;
+
+/// The number of rows and columns of the grid of pendulums
+const GRID: usize = 12;
+/// The simulated time spent at each stop, in seconds
+const STOP_DURATION: f32 = 10.0;
+/// The side in pixels of a cell of the map
+const CELL: f32 = 8.0;
+/// The center and the half width, in radians, of the square of initial angles shown at each stop
+const STOPS: [([f32; 2], f32); 4] = [
+    ([0.0, 0.0], PI),
+    ([1.5, -1.5], 0.8),
+    ([2.0, 0.5], 0.4),
+    ([-1.0, 2.2], 0.2),
+];
+
+/// A grid of pendulums covering a square of initial angles, colored when they flip,
+/// which moves to a smaller square every `STOP_DURATION` seconds
+///
+/// The map of the grid is the flip fractal drawn by the headless mode, one stop at a time
+pub struct FractalTour {
+    /// The index of the current stop in `STOPS`
+    stop: usize,
+    /// The simulated time at which the current stop started, known after its first update
+    started: Option<f32>,
+    /// The time after which each pendulum of the grid first flipped, if it did
+    flips: Vec<Option<f32>>,
+}
+
+impl FractalTour {
+    pub fn new() -> Self {
+        Self {
+            stop: 0,
+            started: None,
+            flips: Vec::new(),
+        }
+    }
+
+    /// Returns the initial conditions of the grid of the current stop
+    fn grid(&self) -> Vec<InitialConditions> {
+        let ([theta1, theta2], half_width) = STOPS[self.stop];
+        let offset = |i: usize| half_width * (2.0 * (i as f32 + 0.5) / GRID as f32 - 1.0);
+        (0..GRID * GRID)
+            .map(|i| {
+                // Like in the fractal, the first angle grows to the right and the second one upward
+                let (row, column) = (i / GRID, i % GRID);
+                conditions(
+                    3.0,
+                    3.0,
+                    100.0,
+                    100.0,
+                    theta1 + offset(column),
+                    theta2 - offset(row),
+                )
+            })
+            .collect()
+    }
+
+    /// Returns the color of a pendulum that flipped after `time` seconds, on the palette of the fractal
+    fn flip_color(time: f32) -> color::Color {
+        let t = (1.0 + time).ln() / (1.0 + STOP_DURATION).ln();
+        color::hsv(300.0 * t.min(1.0), 1.0, 1.0 - 0.6 * t.min(1.0))
+    }
+}
+
+impl Scenario for FractalTour {
+    fn setup(&mut self, _physics: &mut Physics) -> Vec<InitialConditions> {
+        self.stop = 0;
+        self.started = None;
+        self.flips.clear();
+        self.grid()
+    }
+
+    fn update(&mut self, stage: &mut Stage) {
+        let started = *self.started.get_or_insert(stage.time - stage.dt);
+        let elapsed = stage.time - started;
+        if elapsed > STOP_DURATION {
+            self.stop = (self.stop + 1) % STOPS.len();
+            self.started = None;
+            self.flips.clear();
+            stage.restart = Some(self.grid());
+            return;
+        }
+
+        self.flips.resize(stage.pendulums.len(), None);
+        for (mut p, flip) in stage.pendulums.iter_mut().zip(&mut self.flips) {
+            if flip.is_none() && p.has_flipped() {
+                *flip = Some(elapsed);
+            }
+            p.set_color(match flip {
+                Some(time) => Self::flip_color(*time),
+                None => color::Color::new(0.5, 0.5, 0.5, 1.0),
+            });
+        }
+    }
+
+    fn draw(&self, ctx: &mut Context, canvas: &mut Canvas, window: [f32; 2]) -> GameResult {
+        let side = CELL * GRID as f32;
+        let top = window[1] - 40.0 - side;
+        let mut builder = graphics::MeshBuilder::new();
+        builder.rectangle(
+            DrawMode::fill(),
+            Rect::new(10.0, top, side, side),
+            Color::BLACK,
+        )?;
+        for (i, flip) in self.flips.iter().take(GRID * GRID).enumerate() {
+            if let Some(time) = flip {
+                let (row, column) = (i / GRID, i % GRID);
+                let cell = Rect::new(
+                    10.0 + column as f32 * CELL,
+                    top + row as f32 * CELL,
+                    CELL,
+                    CELL,
+                );
+                let color = render::to_graphics(Self::flip_color(*time));
+                builder.rectangle(DrawMode::fill(), cell, color)?;
+            }
+        }
+        canvas.draw(&Mesh::from_data(ctx, builder.build()), [0.0, 0.0]);
+
+        let ([theta1, theta2], half_width) = STOPS[self.stop];
+        let flipped = self.flips.iter().filter(|flip| flip.is_some()).count();
+        caption(
+            canvas,
+            window,
+            format!(
+                "Stop {}/{}: θ1 {theta1:+.2} θ2 {theta2:+.2} ± {half_width:.2} rad, {flipped} flipped",
+                self.stop + 1,
+                STOPS.len(),
+            ),
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,82 @@
+use crate::recorder::{add_text_chunks, encoding_error, read_frame, timestamp};
+use double_pendulum::sink::{ExportSink, Metadata};
+use ggez::{Context, GameResult};
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::PathBuf;
+
+/// The directory where the screenshots are saved
+pub const SCREENSHOTS_DIR: &str = "screenshots";
+
+/// Captures the next frame of the window into a PNG file, then stops by itself
+pub struct Screenshot {
+    /// Where the file is written, a name is picked from the current time if it is missing
+    path: Option<PathBuf>,
+    /// Stored in the text chunks of the file
+    metadata: Metadata,
+    /// The width, the height and the RGBA pixels of the frame, once it is captured
+    image: Option<(u32, u32, Vec<u8>)>,
+}
+
+impl Screenshot {
+    pub fn new(path: Option<&str>, metadata: &Metadata) -> Self {
+        Self {
+            path: path.map(PathBuf::from),
+            metadata: metadata.clone(),
+            image: None,
+        }
+    }
+}
+
+impl ExportSink for Screenshot {
+    fn name(&self) -> &str {
+        "screenshot"
+    }
+
+    /// Returns true once the frame is captured
+    fn is_done(&self) -> bool {
+        self.image.is_some()
+    }
+
+    fn write_frame(&mut self, ctx: &Context) -> GameResult {
+        if self.image.is_none() {
+            let frame = ctx.gfx.frame();
+            self.image = Some((frame.width(), frame.height(), read_frame(ctx)?));
+        }
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> GameResult {
+        let Some((width, height, pixels)) = &self.image else {
+            return Ok(());
+        };
+
+        let path = match &self.path {
+            Some(path) => path.clone(),
+            None => {
+                fs::create_dir_all(SCREENSHOTS_DIR)?;
+                // Several screenshots can be taken in the same second
+                let name = |n: u32| match n {
+                    0 => format!("{}.png", timestamp()),
+                    n => format!("{}-{n}.png", timestamp()),
+                };
+                (0..)
+                    .map(|n| PathBuf::from(SCREENSHOTS_DIR).join(name(n)))
+                    .find(|path| !path.exists())
+                    .unwrap_or_default()
+            }
+        };
+        let writer = BufWriter::new(File::create(&path)?);
+
+        let mut encoder = png::Encoder::new(writer, *width, *height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        add_text_chunks(&mut encoder, &self.metadata)?;
+        let mut writer = encoder.write_header().map_err(encoding_error)?;
+        writer.write_image_data(pixels).map_err(encoding_error)?;
+        writer.finish().map_err(encoding_error)?;
+
+        println!("Saved the screenshot to {}", path.display());
+        Ok(())
+    }
+}
@@ -0,0 +1,184 @@
+use double_pendulum::color::Color;
+use double_pendulum::pendulum::{InitialConditions, Rods};
+use double_pendulum::pendulums::Pendulums;
+use ggez::{GameError, GameResult};
+use rhai::{Array, Dynamic, Engine, Map, Scope, AST, FLOAT};
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// The largest number of operations of a single run, so an endless loop stops with an error
+/// instead of freezing the simulation
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// A Rhai script run after every physics update, to experiment with the simulation without recompiling
+///
+/// The whole script is run each time, with these variables:
+/// - `time` and `dt`: the simulated time after the update and its duration, in seconds
+/// - `pendulums`: a map for each pendulum with `theta1`, `theta2`, `omega1`, `omega2`, `m1`, `m2`, `l1`, `l2`,
+///   `color` as `[r, g, b]` and the positions `x1`, `y1`, `x2`, `y2` of the circles relative to the origin
+/// - `state`: a map kept from one run to the next, for the values of the script itself
+///
+/// The changes made to the pendulums are applied to the simulation, except for the positions,
+/// and `add_pendulum(#{ theta1: 1.0 })` adds a new pendulum whose missing values are random
+pub struct Script {
+    path: PathBuf,
+    engine: Engine,
+    ast: AST,
+    /// The variables given to the script, `state` is the only one kept between the runs
+    scope: Scope<'static>,
+    /// The initial conditions of the pendulums spawned by the script during the current run
+    spawned: Rc<RefCell<Vec<InitialConditions>>>,
+}
+
+impl Script {
+    /// Compile the script written in `path`
+    pub fn load(path: &Path) -> GameResult<Self> {
+        let source = fs::read_to_string(path)?;
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        let spawned = Rc::new(RefCell::new(Vec::new()));
+        let queue = Rc::clone(&spawned);
+        engine.register_fn("add_pendulum", move |map: Map| {
+            queue.borrow_mut().push(conditions(&map));
+        });
+        let ast = engine
+            .compile(source)
+            .map_err(|e| GameError::CustomError(format!("{}: {e}", path.display())))?;
+
+        let mut scope = Scope::new();
+        scope.push("state", Map::new());
+        Ok(Self {
+            path: path.to_owned(),
+            engine,
+            ast,
+            scope,
+            spawned,
+        })
+    }
+
+    /// Returns the file the script was loaded from
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Run the script on `pendulums`, `dt` seconds after the previous physics update,
+    /// and returns the initial conditions of the pendulums it spawned
+    pub fn run(
+        &mut self,
+        pendulums: &mut Pendulums,
+        time: f32,
+        dt: f32,
+    ) -> GameResult<Vec<InitialConditions>> {
+        let array: Array = pendulums.iter().map(|p| to_map(&p).into()).collect();
+        // The variables declared by the script are dropped after each run
+        let kept = self.scope.len();
+        self.scope.push("time", time as FLOAT);
+        self.scope.push("dt", dt as FLOAT);
+        self.scope.push("pendulums", array);
+        let result = self.engine.run_ast_with_scope(&mut self.scope, &self.ast);
+        let array = self.scope.get_value::<Array>("pendulums");
+        self.scope.rewind(kept);
+        result.map_err(|e| GameError::CustomError(format!("{}: {e}", self.path.display())))?;
+
+        for (index, map) in array.unwrap_or_default().into_iter().enumerate() {
+            if let (Some(mut p), Some(map)) = (pendulums.get(index), map.try_cast::<Map>()) {
+                apply(&map, &mut p);
+                pendulums.set(index, p);
+            }
+        }
+        Ok(self.spawned.borrow_mut().drain(..).collect())
+    }
+}
+
+/// Returns the state of `pendulum` as given to the script
+fn to_map(pendulum: &Rods) -> Map {
+    let [theta1, theta2] = pendulum.angles();
+    let [omega1, omega2] = pendulum.angular_velocities();
+    let [m1, m2] = pendulum.masses();
+    let [l1, l2] = pendulum.lengths();
+    let [[x1, y1], [x2, y2]] = pendulum.bobs();
+    let color = pendulum.color();
+
+    let mut map = Map::new();
+    for (key, value) in [
+        ("theta1", theta1),
+        ("theta2", theta2),
+        ("omega1", omega1),
+        ("omega2", omega2),
+        ("m1", m1),
+        ("m2", m2),
+        ("l1", l1),
+        ("l2", l2),
+        ("x1", x1),
+        ("y1", y1),
+        ("x2", x2),
+        ("y2", y2),
+    ] {
+        map.insert(key.into(), Dynamic::from_float(value as FLOAT));
+    }
+    let color: Array = [color.r, color.g, color.b]
+        .into_iter()
+        .map(|c| Dynamic::from_float(c as FLOAT))
+        .collect();
+    map.insert("color".into(), color.into());
+    map
+}
+
+/// Change `pendulum` to match the state the script left in `map`, the missing or invalid values are kept
+fn apply(map: &Map, pendulum: &mut Rods) {
+    let [theta1, theta2] = pendulum.angles();
+    let [omega1, omega2] = pendulum.angular_velocities();
+    pendulum.set_angles([
+        number(map, "theta1").unwrap_or(theta1),
+        number(map, "theta2").unwrap_or(theta2),
+    ]);
+    pendulum.set_angular_velocities([
+        number(map, "omega1").unwrap_or(omega1),
+        number(map, "omega2").unwrap_or(omega2),
+    ]);
+    for (bob, (mass, length)) in [("m1", "l1"), ("m2", "l2")].into_iter().enumerate() {
+        if let Some(mass) = number(map, mass) {
+            pendulum.set_mass(bob, mass);
+        }
+        if let Some(length) = number(map, length) {
+            pendulum.set_length(bob, length);
+        }
+    }
+
+    let color = map.get("color").and_then(|c| c.clone().try_cast::<Array>());
+    let components: Option<Vec<f32>> = color.map(|c| c.iter().filter_map(as_f32).collect());
+    if let Some(&[r, g, b]) = components.as_deref() {
+        pendulum.set_color(Color::new(r, g, b, pendulum.color().a));
+    }
+}
+
+/// Returns the initial conditions written in the map given to `add_pendulum`
+fn conditions(map: &Map) -> InitialConditions {
+    InitialConditions {
+        m1: number(map, "m1"),
+        m2: number(map, "m2"),
+        l1: number(map, "l1"),
+        l2: number(map, "l2"),
+        theta1: number(map, "theta1"),
+        theta2: number(map, "theta2"),
+        v1: number(map, "omega1"),
+        v2: number(map, "omega2"),
+    }
+}
+
+/// Returns the number at `key` in `map`, if there is one and it is finite
+fn number(map: &Map, key: &str) -> Option<f32> {
+    map.get(key)
+        .and_then(as_f32)
+        .filter(|value| value.is_finite())
+}
+
+/// Returns `value` as a float, the integers are converted
+fn as_f32(value: &Dynamic) -> Option<f32> {
+    match value.as_float() {
+        Ok(value) => Some(value as f32),
+        Err(_) => value.as_int().ok().map(|value| value as f32),
+    }
+}
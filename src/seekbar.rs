@@ -0,0 +1,50 @@
+use ggez::graphics::{self, Canvas, Color, DrawMode, Drawable, Mesh, Rect};
+use ggez::Context;
+use ggez::GameResult;
+
+/// The distance between the bar and the edges of the window
+const MARGIN: f32 = 20.0;
+/// The distance between the bar and the bottom of the window, leaving room for the wind arrow
+const BOTTOM: f32 = 45.0;
+/// The height of the bar, which is also how far from it a click still counts
+const HEIGHT: f32 = 8.0;
+/// The color of the part of the replay left to play
+const TRACK_COLOR: Color = Color::new(1.0, 1.0, 1.0, 0.25);
+/// The color of the part of the replay already played
+const PLAYED_COLOR: Color = Color::new(1.0, 1.0, 1.0, 0.8);
+
+/// Returns the area of the bar in a window of size `window`
+fn rect(window: [f32; 2]) -> Rect {
+    Rect::new(MARGIN, window[1] - BOTTOM, window[0] - 2.0 * MARGIN, HEIGHT)
+}
+
+/// Returns the fraction of the replay under `point`, or `None` if it is not on the bar
+pub fn fraction_at(window: [f32; 2], point: [f32; 2]) -> Option<f32> {
+    let bar = rect(window);
+    let area = Rect::new(bar.x, bar.y - HEIGHT, bar.w, bar.h + 2.0 * HEIGHT);
+    area.contains(point)
+        .then(|| ((point[0] - bar.x) / bar.w).clamp(0.0, 1.0))
+}
+
+/// Draw the bar at the bottom of a window of size `window`, filled up to `fraction`,
+/// with `label` above its right end
+pub fn draw(
+    ctx: &mut Context,
+    canvas: &mut Canvas,
+    window: [f32; 2],
+    fraction: f32,
+    label: &str,
+) -> GameResult {
+    let bar = rect(window);
+    let track = Mesh::new_rectangle(ctx, DrawMode::fill(), bar, TRACK_COLOR)?;
+    canvas.draw(&track, [0.0, 0.0]);
+    let played = Rect::new(bar.x, bar.y, bar.w * fraction.clamp(0.0, 1.0), bar.h);
+    if played.w > 0.0 {
+        let played = Mesh::new_rectangle(ctx, DrawMode::fill(), played, PLAYED_COLOR)?;
+        canvas.draw(&played, [0.0, 0.0]);
+    }
+    let text = graphics::Text::new(label);
+    let width = text.dimensions(ctx).map_or(0.0, |rect| rect.w);
+    canvas.draw(&text, [bar.right() - width, bar.y - 20.0]);
+    Ok(())
+}
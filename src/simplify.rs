@@ -0,0 +1,137 @@
+/// Simplify a polyline with the Ramer–Douglas–Peucker algorithm
+///
+/// Every point that is less than `epsilon` pixels away from the simplified path is dropped,
+/// so the shape of the path is preserved while most of the redundant points disappear.
+/// The first and the last points are always kept.
+///
+/// https://en.wikipedia.org/wiki/Ramer%E2%80%93Douglas%E2%80%93Peucker_algorithm
+pub fn rdp(points: &[[f32; 2]], epsilon: f32) -> Vec<[f32; 2]> {
+    points
+        .iter()
+        .zip(rdp_mask(points, epsilon))
+        .filter_map(|(p, k)| k.then_some(*p))
+        .collect()
+}
+
+/// Returns which points of the polyline are kept by `rdp`, in the same order as `points`
+///
+/// This is useful to simplify data stored alongside the points the same way
+pub fn rdp_mask(points: &[[f32; 2]], epsilon: f32) -> Vec<bool> {
+    if points.len() < 3 {
+        return vec![true; points.len()];
+    }
+
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+
+    // Use an explicit stack instead of recursion, since long trails could overflow the stack
+    let mut stack = vec![(0, points.len() - 1)];
+    while let Some((start, end)) = stack.pop() {
+        let mut max_distance = 0.0;
+        let mut index = start;
+        for i in start + 1..end {
+            let distance = distance_to_segment(points[i], points[start], points[end]);
+            if distance > max_distance {
+                max_distance = distance;
+                index = i;
+            }
+        }
+
+        if max_distance > epsilon {
+            keep[index] = true;
+            stack.push((start, index));
+            stack.push((index, end));
+        }
+    }
+
+    keep
+}
+
+/// Returns the distance between the point `p` and the segment going from `a` to `b`
+fn distance_to_segment(p: [f32; 2], a: [f32; 2], b: [f32; 2]) -> f32 {
+    let ab = [b[0] - a[0], b[1] - a[1]];
+    let ap = [p[0] - a[0], p[1] - a[1]];
+    let length_sq = ab[0] * ab[0] + ab[1] * ab[1];
+
+    // The segment is a single point
+    if length_sq == 0.0 {
+        return ap[0].hypot(ap[1]);
+    }
+
+    // Project p on the segment and clamp the projection between a and b
+    let t = ((ap[0] * ab[0] + ap[1] * ab[1]) / length_sq).clamp(0.0, 1.0);
+    let dx = ap[0] - t * ab[0];
+    let dy = ap[1] - t * ab[1];
+    dx.hypot(dy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_polylines_are_kept() {
+        assert!(rdp(&[], 1.0).is_empty());
+        assert_eq!(rdp(&[[0.0, 0.0]], 1.0), [[0.0, 0.0]]);
+        assert_eq!(
+            rdp(&[[0.0, 0.0], [1.0, 1.0]], 1.0),
+            [[0.0, 0.0], [1.0, 1.0]]
+        );
+    }
+
+    #[test]
+    fn straight_line_keeps_its_ends() {
+        let points: Vec<_> = (0..10).map(|i| [i as f32, 2.0 * i as f32]).collect();
+        assert_eq!(rdp(&points, 0.1), [[0.0, 0.0], [9.0, 18.0]]);
+    }
+
+    #[test]
+    fn corners_are_kept_and_noise_is_dropped() {
+        let points = [
+            [0.0, 0.0],
+            [5.0, 0.5],
+            [10.0, 0.0],
+            [10.0, 5.0],
+            [10.0, 10.0],
+        ];
+        assert_eq!(rdp(&points, 1.0), [[0.0, 0.0], [10.0, 0.0], [10.0, 10.0]]);
+        // The bump is farther from the path than a smaller epsilon
+        assert_eq!(
+            rdp(&points, 0.1),
+            [[0.0, 0.0], [5.0, 0.5], [10.0, 0.0], [10.0, 10.0]],
+        );
+    }
+
+    #[test]
+    fn mask_matches_the_simplified_points() {
+        let points: Vec<_> = (0..50)
+            .map(|i| {
+                let t = i as f32 / 5.0;
+                [t * 10.0, t.sin() * 20.0]
+            })
+            .collect();
+        let mask = rdp_mask(&points, 0.5);
+        assert_eq!(mask.len(), points.len());
+        assert!(mask[0] && mask[points.len() - 1]);
+        let kept: Vec<_> = (points.iter().zip(&mask))
+            .filter_map(|(p, &k)| k.then_some(*p))
+            .collect();
+        assert_eq!(kept, rdp(&points, 0.5));
+        assert!(kept.len() < points.len());
+    }
+
+    #[test]
+    fn distance_to_a_degenerate_segment_is_the_distance_to_its_point() {
+        assert_eq!(distance_to_segment([3.0, 4.0], [0.0, 0.0], [0.0, 0.0]), 5.0);
+        assert_eq!(
+            distance_to_segment([5.0, 2.0], [0.0, 0.0], [10.0, 0.0]),
+            2.0
+        );
+        // Past the end of the segment, the distance is to the end
+        assert_eq!(
+            distance_to_segment([13.0, 4.0], [0.0, 0.0], [10.0, 0.0]),
+            5.0
+        );
+    }
+}
@@ -0,0 +1,144 @@
+use crate::color::Palette;
+use crate::pendulum::{DoublePendulum, InitialConditions, Physics};
+use crate::pendulums::Pendulums;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+/// The duration of a step in seconds, the same as the physics rate of the window
+pub const DT: f32 = 1.0 / 240.0;
+/// The total length of both rods of the random pendulums, the height of the default window
+const LENGTH: f32 = 200.0;
+
+/// The angles and angular velocities of both rods of a double pendulum
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct State {
+    /// The angles of both rods, in radians
+    pub theta: [f32; 2],
+    /// The angular velocities of both rods
+    pub omega: [f32; 2],
+}
+
+/// A simulation of several double pendulums without any window,
+/// meant to be driven step by step from tests and other programs
+///
+/// Two simulations built from the same seed and stepped the same way always have the same state
+pub struct Simulation {
+    pendulums: Pendulums,
+    /// The simulated time in seconds, used by the driving force
+    time: f64,
+    /// The horizontal wind applied to every pendulum
+    wind: f32,
+}
+
+impl Simulation {
+    /// Create `count` random pendulums, picked from the random number generator seeded with `seed`
+    pub fn new(seed: u64, count: usize) -> Self {
+        Self::with_conditions(seed, &vec![InitialConditions::default(); count])
+    }
+
+    /// Create a pendulum for each of the `initial` conditions,
+    /// the missing values are picked from the random number generator seeded with `seed`
+    pub fn with_conditions(seed: u64, initial: &[InitialConditions]) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let pendulums = initial
+            .iter()
+            .map(|initial| {
+                DoublePendulum::new(
+                    &mut rng,
+                    LENGTH,
+                    Physics::default(),
+                    initial,
+                    Palette::default(),
+                )
+            })
+            .collect();
+        Self {
+            pendulums,
+            time: 0.0,
+            wind: 0.0,
+        }
+    }
+
+    /// Advance every pendulum by `n` steps of `DT` seconds
+    pub fn step_n(&mut self, n: usize) {
+        for _ in 0..n {
+            self.pendulums.forward(DT, self.wind, self.time as f32);
+            self.time += DT as f64;
+        }
+    }
+
+    /// Returns the state of every pendulum, in the order they were created
+    pub fn state(&self) -> Vec<State> {
+        self.pendulums
+            .iter()
+            .map(|pendulum| State {
+                theta: pendulum.angles(),
+                omega: pendulum.angular_velocities(),
+            })
+            .collect()
+    }
+
+    /// Change the physical constants of every pendulum and the wind, the states are kept
+    pub fn set_params(&mut self, physics: Physics, wind: f32) {
+        self.pendulums.physics_mut().fill(physics);
+        self.wind = wind;
+    }
+
+    /// Returns the simulated time in seconds
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    /// Returns the positions of both circles of every pendulum, relative to the origin
+    pub fn positions(&self) -> Vec<[[f32; 2]; 2]> {
+        self.pendulums.iter().map(|p| p.bobs()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_gives_the_same_run() {
+        let mut a = Simulation::new(42, 5);
+        let mut b = Simulation::new(42, 5);
+        assert_eq!(a.state(), b.state());
+        a.step_n(1000);
+        b.step_n(1000);
+        assert_eq!(a.state(), b.state());
+        assert_eq!(a.positions(), b.positions());
+        assert_eq!(a.time(), b.time());
+    }
+
+    #[test]
+    fn stepping_in_chunks_gives_the_same_run() {
+        let mut a = Simulation::new(3, 4);
+        let mut b = Simulation::new(3, 4);
+        a.step_n(300);
+        for _ in 0..3 {
+            b.step_n(100);
+        }
+        assert_eq!(a.state(), b.state());
+    }
+
+    #[test]
+    fn different_seeds_give_different_pendulums() {
+        assert_ne!(Simulation::new(1, 3).state(), Simulation::new(2, 3).state());
+    }
+
+    #[test]
+    fn parameters_change_the_run_but_not_the_state() {
+        let mut a = Simulation::new(9, 2);
+        let mut b = Simulation::new(9, 2);
+        let physics = Physics {
+            gravity: 2.0,
+            ..Physics::default()
+        };
+        b.set_params(physics, 0.5);
+        assert_eq!(a.state(), b.state());
+        a.step_n(100);
+        b.step_n(100);
+        assert_ne!(a.state(), b.state());
+    }
+}
@@ -0,0 +1,183 @@
+use crate::pendulums::Pendulums;
+use ggez::{Context, GameError, GameResult};
+use serde::de::{MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Cow;
+use std::fmt;
+
+/// The number of physics updates between two rows of the energy log when no interval is given
+pub const DEFAULT_ENERGY_INTERVAL: u32 = 10;
+
+/// The state of the simulation given to the export sinks after every physics update
+pub struct Sample<'a> {
+    /// The simulated time in seconds
+    pub time: f32,
+    pub pendulums: &'a Pendulums,
+}
+
+/// Where some exported data comes from, so the simulation can be reproduced months later
+///
+/// Every sink is given the metadata when it starts, and stores it alongside its data.
+/// It is written in JSON as an object whose keys are in the order they were added.
+#[derive(Clone, Default)]
+pub struct Metadata {
+    /// The keys read back from a file aren't known in advance, the other ones are
+    entries: Vec<(Cow<'static, str>, String)>,
+}
+
+impl Metadata {
+    /// Add an entry, the keys are written as is in the files
+    pub fn push(&mut self, key: &'static str, value: impl ToString) {
+        self.entries.push((Cow::Borrowed(key), value.to_string()));
+    }
+
+    /// Returns every entry, in the order they were added
+    pub fn entries(&self) -> &[(Cow<'static, str>, String)] {
+        &self.entries
+    }
+
+    /// Returns true if there isn't any entry
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl Serialize for Metadata {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.entries.iter().map(|(key, value)| (key, value)))
+    }
+}
+
+impl<'de> Deserialize<'de> for Metadata {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(MetadataVisitor)
+    }
+}
+
+/// Reads the entries of the metadata in the order they were written
+struct MetadataVisitor;
+
+impl<'de> Visitor<'de> for MetadataVisitor {
+    type Value = Metadata;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a map of strings")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut entries = Vec::new();
+        while let Some((key, value)) = map.next_entry::<String, String>()? {
+            entries.push((Cow::Owned(key), value));
+        }
+        Ok(Metadata { entries })
+    }
+}
+
+/// Something that receives the simulation data and writes it somewhere
+///
+/// Every method has a default implementation doing nothing,
+/// so a sink only implements the kind of data it is interested in.
+/// Several sinks can run at the same time.
+pub trait ExportSink {
+    /// The name the sink was registered with
+    fn name(&self) -> &str;
+
+    /// Receive the state of the simulation after a physics update
+    fn write_sample(&mut self, _sample: &Sample) -> GameResult {
+        Ok(())
+    }
+
+    /// Receive the frame that was just drawn, which can be read back with `ctx.gfx.frame()`
+    fn write_frame(&mut self, _ctx: &Context) -> GameResult {
+        Ok(())
+    }
+
+    /// Returns true once the sink doesn't want any more data, it is then finished and removed
+    fn is_done(&self) -> bool {
+        false
+    }
+
+    /// Flush everything that wasn't written yet, called once when the sink is removed
+    fn finish(self: Box<Self>) -> GameResult {
+        Ok(())
+    }
+}
+
+/// The options of the sinks, the same for every sink of a kind
+#[derive(Clone)]
+pub struct ExportSettings {
+    /// The number of physics updates between two rows of the energy log
+    pub energy_interval: u32,
+    /// Only one frame every `gif_frame_step` frames is recorded in the GIFs
+    pub gif_frame_step: u32,
+    /// The duration of the GIFs, in seconds
+    pub gif_duration: f32,
+    /// The frame rate of the videos
+    pub video_fps: u32,
+    /// The largest number of times per second the OSC messages are sent
+    pub osc_rate: f32,
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        Self {
+            energy_interval: DEFAULT_ENERGY_INTERVAL,
+            gif_frame_step: 3,
+            gif_duration: 5.0,
+            video_fps: 60,
+            osc_rate: 60.0,
+        }
+    }
+}
+
+/// Creates a sink, writing to `path` if it is given or to a default location otherwise
+pub type SinkFactory = fn(
+    path: Option<&str>,
+    metadata: &Metadata,
+    settings: &ExportSettings,
+) -> GameResult<Box<dyn ExportSink>>;
+
+/// Every kind of sink that can be created by name
+pub struct SinkRegistry {
+    factories: Vec<(&'static str, SinkFactory)>,
+    /// Given to every sink created
+    settings: ExportSettings,
+}
+
+impl SinkRegistry {
+    /// Create an empty registry, whose sinks are configured with `settings`
+    pub fn new(settings: ExportSettings) -> Self {
+        Self {
+            factories: Vec::new(),
+            settings,
+        }
+    }
+
+    /// Add a new kind of sink, replacing the one with the same name if any
+    pub fn register(&mut self, name: &'static str, factory: SinkFactory) {
+        self.factories.retain(|(n, _)| *n != name);
+        self.factories.push((name, factory));
+    }
+
+    /// Create a sink from a specification written as `NAME` or `NAME:PATH`
+    pub fn create(&self, spec: &str, metadata: &Metadata) -> GameResult<Box<dyn ExportSink>> {
+        let (name, path) = match spec.split_once(':') {
+            Some((name, path)) => (name, Some(path)),
+            None => (spec, None),
+        };
+
+        let factory = self
+            .factories
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, factory)| factory)
+            .ok_or_else(|| {
+                let names: Vec<_> = self.factories.iter().map(|(n, _)| *n).collect();
+                GameError::CustomError(format!(
+                    "unknown export sink {name}, the available ones are: {}",
+                    names.join(", ")
+                ))
+            })?;
+        factory(path, metadata, &self.settings)
+    }
+}
@@ -0,0 +1,165 @@
+use double_pendulum::pendulum::Rods;
+use ggez::graphics::{self, Canvas, Color, DrawMode, Mesh, MeshBuilder, Rect};
+use ggez::Context;
+use ggez::GameResult;
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+
+/// The number of samples of simulated time per second
+const SAMPLE_RATE: f32 = 32.0;
+/// The number of samples in the sliding window, a power of two for the FFT
+const WINDOW: usize = 512;
+/// The number of dominant frequencies shown in the chart
+const PEAKS: usize = 6;
+/// The color of the bars of the chart
+const BAR_COLOR: Color = Color::new(0.4, 0.8, 1.0, 0.9);
+
+/// The frequency spectrum of the second rod of a pendulum, over the last `WINDOW` samples
+///
+/// The sine of θ2 is sampled instead of θ2 itself, so the wrap around the top doesn't show up as a jump.
+/// A regular motion has a few sharp peaks, a chaotic one spreads over many frequencies.
+/// Only one pendulum is recorded, and the window is cleared when another one is recorded instead
+pub struct Spectrum {
+    /// The index of the recorded pendulum
+    index: usize,
+    /// The simulated time since the last sample, in seconds
+    since_sample: f32,
+    /// The sine of the second angle at each sample
+    samples: VecDeque<f32>,
+}
+
+impl Spectrum {
+    pub fn new() -> Self {
+        Self {
+            index: 0,
+            since_sample: 0.0,
+            samples: VecDeque::with_capacity(WINDOW),
+        }
+    }
+
+    /// Sample `pendulum`, which is at `index` in the list of pendulums, if it is time to,
+    /// `dt` seconds after the last physics update
+    pub fn record(&mut self, index: usize, pendulum: &Rods, dt: f32) {
+        if index != self.index {
+            self.index = index;
+            self.since_sample = 0.0;
+            self.samples.clear();
+        }
+        self.since_sample += dt;
+        if self.since_sample < 1.0 / SAMPLE_RATE {
+            return;
+        }
+        self.since_sample -= 1.0 / SAMPLE_RATE;
+        if self.samples.len() >= WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(pendulum.angles()[1].sin());
+    }
+
+    /// Returns the amplitude of every frequency from 0 to the Nyquist frequency,
+    /// the i-th one being `i * SAMPLE_RATE / WINDOW` Hz, or `None` until the window is full
+    fn amplitudes(&self) -> Option<Vec<f32>> {
+        if self.samples.len() < WINDOW {
+            return None;
+        }
+
+        // The mean is removed and a Hann window is applied, so the edges of the window don't leak everywhere
+        let mean = self.samples.iter().sum::<f32>() / WINDOW as f32;
+        let mut values: Vec<[f32; 2]> = (self.samples.iter().enumerate())
+            .map(|(i, sample)| {
+                let hann = 0.5 - 0.5 * (2.0 * PI * i as f32 / (WINDOW - 1) as f32).cos();
+                [(sample - mean) * hann, 0.0]
+            })
+            .collect();
+        fft(&mut values);
+        let amplitudes = values[..=WINDOW / 2]
+            .iter()
+            .map(|[re, im]| re.hypot(*im))
+            .collect();
+        Some(amplitudes)
+    }
+
+    /// Draw the dominant frequencies as a bar chart in `area`, sorted from the lowest to the highest,
+    /// each bar being as high as its amplitude compared to the strongest one
+    pub fn draw(&self, ctx: &mut Context, canvas: &mut Canvas, area: Rect) -> GameResult {
+        let mut builder = MeshBuilder::new();
+        builder.rectangle(DrawMode::fill(), area, Color::new(0.0, 0.0, 0.0, 0.6))?;
+
+        let Some(amplitudes) = self.amplitudes() else {
+            canvas.draw(&Mesh::from_data(ctx, builder.build()), [0.0, 0.0]);
+            let label = format!("Spectrum: sampling {}/{WINDOW}", self.samples.len());
+            canvas.draw(&graphics::Text::new(label), [area.x + 10.0, area.y + 6.0]);
+            return Ok(());
+        };
+
+        // The peaks are the local maxima, the constant part is left out
+        let mut peaks: Vec<usize> = (1..amplitudes.len() - 1)
+            .filter(|&i| amplitudes[i] > amplitudes[i - 1] && amplitudes[i] >= amplitudes[i + 1])
+            .collect();
+        peaks.sort_by(|&a, &b| amplitudes[b].total_cmp(&amplitudes[a]));
+        peaks.truncate(PEAKS);
+        peaks.sort_unstable();
+        let strongest = (peaks.iter())
+            .map(|&i| amplitudes[i])
+            .fold(f32::EPSILON, f32::max);
+
+        let plot = Rect::new(area.x + 10.0, area.y + 25.0, area.w - 20.0, area.h - 50.0);
+        let slot = plot.w / PEAKS as f32;
+        let mut labels = Vec::with_capacity(peaks.len());
+        for (n, &i) in peaks.iter().enumerate() {
+            let height = amplitudes[i] / strongest * plot.h;
+            let x = plot.x + n as f32 * slot;
+            let bar = Rect::new(x + slot * 0.2, plot.bottom() - height, slot * 0.6, height);
+            builder.rectangle(DrawMode::fill(), bar, BAR_COLOR)?;
+            let frequency = i as f32 * SAMPLE_RATE / WINDOW as f32;
+            labels.push((format!("{frequency:.2}"), [x + 2.0, plot.bottom() + 4.0]));
+        }
+        canvas.draw(&Mesh::from_data(ctx, builder.build()), [0.0, 0.0]);
+
+        let title = "Spectrum of θ2 (Hz)";
+        canvas.draw(&graphics::Text::new(title), [area.x + 10.0, area.y + 6.0]);
+        for (label, position) in labels {
+            canvas.draw(&graphics::Text::new(label), position);
+        }
+        Ok(())
+    }
+}
+
+/// Replace `values`, complex numbers written as `[re, im]`, with their discrete Fourier transform
+///
+/// This is the iterative radix-2 Cooley-Tukey algorithm, the length must be a power of two
+fn fft(values: &mut [[f32; 2]]) {
+    let n = values.len();
+    debug_assert!(n.is_power_of_two());
+
+    // Sort the values in bit-reversed order of their indices
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            values.swap(i, j);
+        }
+    }
+
+    // Merge the transforms of the halves, doubling the length every time
+    let mut length = 2;
+    while length <= n {
+        let angle = -2.0 * PI / length as f32;
+        for start in (0..n).step_by(length) {
+            for k in 0..length / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let [re, im] = values[start + k + length / 2];
+                let odd = [re * cos - im * sin, re * sin + im * cos];
+                let even = values[start + k];
+                values[start + k] = [even[0] + odd[0], even[1] + odd[1]];
+                values[start + k + length / 2] = [even[0] - odd[0], even[1] - odd[1]];
+            }
+        }
+        length *= 2;
+    }
+}
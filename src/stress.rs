@@ -0,0 +1,104 @@
+/// The number of frames averaged before deciding whether the pendulums fit in the frame budget
+const WINDOW: usize = 60;
+/// The longest average frame that still fits in the budget, in milliseconds:
+/// a frame at 60 fps with some room for the jitter of the clock
+const FRAME_BUDGET_MS: f32 = 1000.0 / 60.0 * 1.1;
+/// The slowest the simulation can run compared to real time while the count is still sustainable
+const MIN_REALTIME_RATIO: f32 = 0.95;
+/// The fraction of the pendulums added after each window that fit in the budget
+const GROWTH: f32 = 0.1;
+/// The fewest pendulums added after each window that fit in the budget
+const MIN_SPAWNS: usize = 10;
+
+/// Spawns pendulums until the frames don't fit in the budget anymore, to find how many the machine sustains
+///
+/// The first time the frames get too slow, the level of detail is lowered:
+/// only the newest pendulums keep their trails and their velocity arrows, half as many as were sustained
+/// with every detail, and the spawning goes on. The second time, the test is over
+/// and the largest count that fit in the budget is reported.
+pub struct StressTest {
+    /// The number of frames measured in the current window
+    frames: usize,
+    /// The total duration of the frames of the current window, in milliseconds
+    elapsed: f32,
+    /// The largest number of pendulums that fit in the budget with every detail
+    full_detail: usize,
+    /// The largest number of pendulums that fit in the budget
+    sustained: usize,
+    /// How many of the newest pendulums are drawn with every detail, once the level of detail is lowered
+    detailed: Option<usize>,
+    /// Whether the largest count was found
+    finished: bool,
+}
+
+impl StressTest {
+    pub fn new() -> Self {
+        Self {
+            frames: 0,
+            elapsed: 0.0,
+            full_detail: 0,
+            sustained: 0,
+            detailed: None,
+            finished: false,
+        }
+    }
+
+    /// Measure a frame lasting `duration` seconds, with `count` pendulums running at `realtime_ratio`
+    /// times real time, and returns how many pendulums should be spawned
+    pub fn frame(&mut self, duration: f32, count: usize, realtime_ratio: f32) -> usize {
+        if self.finished {
+            return 0;
+        }
+        self.frames += 1;
+        self.elapsed += duration * 1000.0;
+        if self.frames < WINDOW {
+            return 0;
+        }
+        let average = self.elapsed / self.frames as f32;
+        self.frames = 0;
+        self.elapsed = 0.0;
+
+        if average <= FRAME_BUDGET_MS && realtime_ratio >= MIN_REALTIME_RATIO {
+            self.sustained = self.sustained.max(count);
+            if self.detailed.is_none() {
+                self.full_detail = count;
+            }
+            return ((count as f32 * GROWTH) as usize).max(MIN_SPAWNS);
+        }
+        match self.detailed {
+            // The same count is measured again with less detail
+            None => self.detailed = Some(self.full_detail / 2),
+            Some(_) => {
+                self.finished = true;
+                println!(
+                    "Stress test: {} pendulums sustained at 60 fps, {} with every detail",
+                    self.sustained, self.full_detail,
+                );
+            }
+        }
+        0
+    }
+
+    /// Returns how many of the newest pendulums are drawn with every detail,
+    /// or `None` if the level of detail wasn't lowered
+    pub fn detailed(&self) -> Option<usize> {
+        self.detailed
+    }
+
+    /// Returns the progress of the test, written for the HUD
+    pub fn hud(&self) -> String {
+        if self.finished {
+            format!(
+                "Stress test: {} pendulums sustained, {} with every detail",
+                self.sustained, self.full_detail,
+            )
+        } else if self.detailed.is_some() {
+            format!(
+                "Stress test: spawning with less detail, {} sustained",
+                self.sustained,
+            )
+        } else {
+            format!("Stress test: spawning, {} sustained", self.sustained)
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use crate::render::Renderer;
+use double_pendulum::simplify;
+use double_pendulum::sink::Metadata;
+use ggez::graphics::{Color, DrawMode};
+use ggez::GameResult;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+/// The directory where the SVG drawings are saved
+pub const DRAWINGS_DIR: &str = "drawings";
+/// The maximum distance in pixels between the lines written and the trails,
+/// plotters and laser cutters are much slower with many tiny segments
+const SIMPLIFY_EPSILON: f32 = 0.5;
+/// The colors of a line whose red, green and blue components are within this step of each other are merged
+const COLOR_STEP: u8 = 16;
+
+/// A vector drawing of the window, built shape by shape and written as an SVG file
+///
+/// The lines are simplified and drawn without the fading of the trails on screen,
+/// so they can be plotted or cut
+pub struct Svg {
+    width: f32,
+    height: f32,
+    /// The shapes, in the order they are drawn
+    body: String,
+}
+
+impl Svg {
+    /// Start a drawing of `width` by `height` pixels filled with `background`
+    pub fn new(width: f32, height: f32, background: Color) -> Self {
+        let mut svg = Self {
+            width,
+            height,
+            body: String::new(),
+        };
+        // The background is a shape too, so it can be removed easily before plotting
+        let _ = writeln!(
+            svg.body,
+            r#"<rect width="100%" height="100%" fill="{}"/>"#,
+            hex(background)
+        );
+        svg
+    }
+
+    /// Write the drawing into `path`, with `metadata` in its description
+    pub fn save(&self, path: &Path, metadata: &Metadata) -> GameResult {
+        let description: Vec<String> = (metadata.entries().iter())
+            .map(|(key, value)| escape(&format!("{key}: {value}")))
+            .collect();
+        let svg = format!(
+            concat!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+                "\n",
+                r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+                "\n<desc>\n{desc}\n</desc>\n{body}</svg>\n"
+            ),
+            w = self.width,
+            h = self.height,
+            desc = description.join("\n"),
+            body = self.body,
+        );
+        fs::write(path, svg)?;
+        Ok(())
+    }
+}
+
+impl Renderer for Svg {
+    /// Add a line through `points`, simplified while keeping its shape
+    fn line(
+        &mut self,
+        points: &[[f32; 2]],
+        width: f32,
+        color: Color,
+        dash: Option<[f32; 2]>,
+    ) -> GameResult {
+        if points.len() < 2 {
+            return Ok(());
+        }
+        let points: Vec<String> = (simplify::rdp(points, SIMPLIFY_EPSILON).iter())
+            .map(|[x, y]| format!("{x:.2},{y:.2}"))
+            .collect();
+        let dash = dash.map_or(String::new(), |[dash, gap]| {
+            format!(r#" stroke-dasharray="{dash:.2} {gap:.2}""#)
+        });
+        let _ = writeln!(
+            self.body,
+            r#"<polyline points="{}" fill="none" stroke="{}" stroke-opacity="{}" stroke-width="{width}" stroke-linejoin="round" stroke-linecap="round"{dash}/>"#,
+            points.join(" "),
+            hex(color),
+            color.a,
+        );
+        Ok(())
+    }
+
+    /// Add a line through `points` split into pieces of similar colors
+    ///
+    /// The alpha is left out, since plotters can't fade a line,
+    /// and the colors are rounded so there are only a few pieces
+    fn gradient_line(&mut self, points: &[[f32; 2]], width: f32, colors: &[Color]) -> GameResult {
+        let rounded = |color: Color| {
+            let (r, g, b) = color.to_rgb();
+            [r, g, b].map(|c| c / COLOR_STEP)
+        };
+        let mut start = 0;
+        for end in 1..=points.len() {
+            if end == points.len() || rounded(colors[end]) != rounded(colors[start]) {
+                // The pieces share their end points so the line stays connected
+                let last = end.min(points.len() - 1);
+                let color = Color {
+                    a: 1.0,
+                    ..colors[start]
+                };
+                self.line(&points[start..=last], width, color, None)?;
+                start = end;
+            }
+        }
+        Ok(())
+    }
+
+    fn circle(
+        &mut self,
+        center: [f32; 2],
+        radius: f32,
+        mode: DrawMode,
+        color: Color,
+    ) -> GameResult {
+        let paint = match mode {
+            DrawMode::Fill(_) => format!(r#"fill="{}" fill-opacity="{}""#, hex(color), color.a),
+            DrawMode::Stroke(stroke) => format!(
+                r#"fill="none" stroke="{}" stroke-opacity="{}" stroke-width="{}""#,
+                hex(color),
+                color.a,
+                stroke.line_width,
+            ),
+        };
+        let _ = writeln!(
+            self.body,
+            r#"<circle cx="{:.2}" cy="{:.2}" r="{radius:.2}" {paint}/>"#,
+            center[0], center[1],
+        );
+        Ok(())
+    }
+}
+
+/// Returns the color as `#rrggbb`, without its alpha
+fn hex(color: Color) -> String {
+    let (r, g, b) = color.to_rgb();
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+/// Returns the text with the characters reserved by XML escaped
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -0,0 +1,188 @@
+use double_pendulum::pendulum::Rods;
+use ggez::{GameError, GameResult};
+use rodio::{OutputStream, Sink, Source};
+use std::f32::consts::PI;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The number of samples per second of the generated sound
+const SAMPLE_RATE: u32 = 44_100;
+/// The pitch of the oscillator of each rod when it is at rest, in hertz
+const BASE_FREQUENCIES: [f32; 2] = [220.0, 330.0];
+/// The angular velocity raising the pitch by one octave
+const OMEGA_PER_OCTAVE: f32 = 0.1;
+/// The highest pitch is this many octaves above the base one
+const MAX_OCTAVES: f32 = 3.0;
+/// The angular velocity at which an oscillator is the loudest, it is silent when its rod is at rest
+const LOUDEST_OMEGA: f32 = 0.2;
+/// The fraction of the way to the target pitch and amplitude covered by each sample,
+/// so the changes between two frames glide instead of clicking
+const GLIDE: f32 = 0.002;
+
+/// The pitch and the amplitude wanted for each oscillator, written by the simulation and read by the audio thread
+///
+/// The floats are stored as their bits, so they can be shared without a lock
+#[derive(Default)]
+struct Targets {
+    frequencies: [AtomicU32; 2],
+    amplitudes: [AtomicU32; 2],
+}
+
+/// A sine oscillator for each rod, gliding toward the targets
+struct Oscillators {
+    targets: Arc<Targets>,
+    frequencies: [f32; 2],
+    amplitudes: [f32; 2],
+    /// The phase of each oscillator, between 0 and 1
+    phases: [f32; 2],
+}
+
+impl Iterator for Oscillators {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let mut sample = 0.0;
+        for rod in 0..2 {
+            let frequency = f32::from_bits(self.targets.frequencies[rod].load(Ordering::Relaxed));
+            let amplitude = f32::from_bits(self.targets.amplitudes[rod].load(Ordering::Relaxed));
+            self.frequencies[rod] += (frequency - self.frequencies[rod]) * GLIDE;
+            self.amplitudes[rod] += (amplitude - self.amplitudes[rod]) * GLIDE;
+
+            self.phases[rod] =
+                (self.phases[rod] + self.frequencies[rod] / SAMPLE_RATE as f32).fract();
+            sample += self.amplitudes[rod] * (2.0 * PI * self.phases[rod]).sin();
+        }
+        Some(sample)
+    }
+}
+
+impl Source for Oscillators {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// Plays the motion of a pendulum on the default audio output
+///
+/// Each rod has its own oscillator, whose pitch and loudness grow with its angular velocity,
+/// so a calm pendulum hums and a wild one screams. The sound stops when the synthesizer is dropped
+pub struct Synth {
+    /// Kept alive for the whole playback, the sound stops when it is dropped
+    _stream: OutputStream,
+    sink: Sink,
+    targets: Arc<Targets>,
+}
+
+impl Synth {
+    /// Start playing on the default output device, silent until a pendulum is followed
+    pub fn new(volume: f32) -> GameResult<Self> {
+        let (stream, handle) = OutputStream::try_default().map_err(audio_error)?;
+        let sink = Sink::try_new(&handle).map_err(audio_error)?;
+        let targets = Arc::new(Targets::default());
+        sink.set_volume(volume);
+        sink.append(Oscillators {
+            targets: Arc::clone(&targets),
+            frequencies: BASE_FREQUENCIES,
+            amplitudes: [0.0; 2],
+            phases: [0.0; 2],
+        });
+        Ok(Self {
+            _stream: stream,
+            sink,
+            targets,
+        })
+    }
+
+    /// Change the volume, 1 is the loudest without distortion
+    pub fn set_volume(&self, volume: f32) {
+        self.sink.set_volume(volume);
+    }
+
+    /// Play the motion of `pendulum`, or fade to silence if it is `None`
+    pub fn follow(&self, pendulum: Option<&Rods>) {
+        let omegas = pendulum.map_or([0.0; 2], Rods::angular_velocities);
+        for (rod, omega) in omegas.into_iter().enumerate() {
+            let octaves = (omega.abs() / OMEGA_PER_OCTAVE).min(MAX_OCTAVES);
+            let frequency = BASE_FREQUENCIES[rod] * octaves.exp2();
+            // Both oscillators together never go over 1
+            let amplitude = 0.5 * (omega.abs() / LOUDEST_OMEGA).min(1.0);
+            self.targets.frequencies[rod].store(frequency.to_bits(), Ordering::Relaxed);
+            self.targets.amplitudes[rod].store(amplitude.to_bits(), Ordering::Relaxed);
+        }
+    }
+}
+
+/// The sound system, which plays the motion of a pendulum while the sound is on
+pub struct SoundSystem {
+    /// The synthesizer playing the sound, if it is on
+    synth: Option<Synth>,
+    /// The volume of the sound, between 0 and 1
+    volume: f32,
+}
+
+impl SoundSystem {
+    pub fn new(volume: f32) -> Self {
+        Self {
+            synth: None,
+            volume,
+        }
+    }
+
+    /// Returns the volume of the sound, between 0 and 1
+    pub fn volume(&self) -> f32 {
+        self.volume
+    }
+
+    /// Start playing the motion of the pendulums as sound, or stop it
+    pub fn toggle(&mut self) {
+        if self.synth.take().is_some() {
+            return;
+        }
+        // Without an audio device the simulation goes on silently
+        match Synth::new(self.volume) {
+            Ok(synth) => self.synth = Some(synth),
+            Err(e) => eprintln!("Couldn't play the sound: {e}"),
+        }
+    }
+
+    /// Change the volume of the sound, kept between 0 and 1
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume.clamp(0.0, 1.0);
+        if let Some(synth) = &self.synth {
+            synth.set_volume(self.volume);
+        }
+    }
+
+    /// Play the motion of `pendulum`, or fade to silence if it is `None`
+    pub fn update(&self, pendulum: Option<&Rods>) {
+        if let Some(synth) = &self.synth {
+            synth.follow(pendulum);
+        }
+    }
+
+    /// Returns the line of the HUD showing the volume, starting with a newline, if the sound is on
+    pub fn hud(&self) -> String {
+        match self.synth {
+            Some(_) => format!("\nSound: {:.0}%", self.volume * 100.0),
+            None => String::new(),
+        }
+    }
+}
+
+/// Returns the error of an audio output that couldn't be opened
+fn audio_error(error: impl std::error::Error) -> GameError {
+    GameError::CustomError(format!("audio error: {error}"))
+}
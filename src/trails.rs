@@ -0,0 +1,164 @@
+use crate::config::Config;
+use crate::render::{self, TrailColor, Trails};
+use crate::world::{Layout, PendulumSystem};
+use double_pendulum::pendulums::Pendulums;
+use ggez::graphics::{Canvas, Color, DrawParam, Image, Mesh};
+use ggez::{Context, GameResult};
+
+/// The trail system, which extends the trails of the pendulums and decides how they are drawn
+///
+/// The trails themselves are stored with their pendulum, since they move with it
+pub struct TrailSystem {
+    /// Stores whether the trail of each pendulum should be drawn or not
+    ///
+    /// Note that the trail is still updated at each frame
+    pub show: bool,
+    /// The color of the trails, or None if each trail takes the color of its pendulum
+    pub color: Option<Color>,
+    /// How saturated the trails taking the color of their pendulum are, compared to the pendulum
+    pub saturation: f32,
+    /// The color of the trails of the first circles
+    pub joint_color: Color,
+    /// Whether the trails of the first circles are drawn
+    pub show_joint: bool,
+    /// Whether the trails are colored by the speed of the second circle instead of `color`
+    pub speed: bool,
+    /// Whether the trails are drawn in colors cycling over time, instead of by speed or in `color`
+    pub rainbow: bool,
+    /// Whether the trails are also drawn into `ink`, where they accumulate forever
+    ink_mode: bool,
+    /// The image the trails are drawn into in ink mode, it is never cleared until it is discarded
+    ink: Option<Image>,
+    /// The number of positions kept in the trails of the new pendulums, 0 for unlimited trails
+    length: usize,
+}
+
+impl TrailSystem {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            show: config.show_trail,
+            color: config.trail_color.map(Color::from),
+            saturation: config.trail_saturation,
+            joint_color: config.joint_trail_color.into(),
+            show_joint: false,
+            speed: false,
+            rainbow: false,
+            ink_mode: false,
+            ink: None,
+            length: config.trail_length,
+        }
+    }
+
+    /// Returns the number of positions kept in the trails, 0 for unlimited trails
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    /// Keep `length` positions in the trails of `pendulums` and of the new pendulums, 0 for unlimited trails
+    pub fn set_length(&mut self, pendulums: &mut Pendulums, length: usize) {
+        self.length = length;
+        for mut p in pendulums.iter_mut() {
+            p.set_trail_length(length);
+        }
+    }
+
+    /// Turn the ink mode on or off, the ink is discarded either way
+    pub fn toggle_ink(&mut self) {
+        self.ink_mode = !self.ink_mode;
+        self.ink = None;
+    }
+
+    /// Discard the trails accumulated in the ink
+    pub fn clear_ink(&mut self) {
+        self.ink = None;
+    }
+
+    /// Returns which trails should be drawn and how they are colored
+    pub fn styles(&self, monochrome: bool) -> Trails {
+        let color = if monochrome {
+            TrailColor::Fixed(Color::WHITE)
+        } else if self.rainbow {
+            TrailColor::Rainbow
+        } else if self.speed {
+            TrailColor::Speed
+        } else {
+            match self.color {
+                Some(color) => TrailColor::Fixed(color),
+                None => TrailColor::Pendulum {
+                    saturation: self.saturation,
+                },
+            }
+        };
+        let joint = if monochrome {
+            Color::WHITE
+        } else {
+            self.joint_color
+        };
+        Trails {
+            tip: self.show.then_some(color),
+            joint: self.show_joint.then_some(joint),
+        }
+    }
+
+    /// Add the current position of the circles of `pendulums` to their trails
+    pub fn update(&self, pendulums: &mut Pendulums) {
+        pendulums.update_trails();
+    }
+
+    /// Draw the trail points added since the last frame into the ink image, in ink mode
+    ///
+    /// The image covers the window without zoom nor pan, and it is created again,
+    /// thus cleared, when it is missing or the window was resized
+    pub fn draw_ink(
+        &mut self,
+        ctx: &mut Context,
+        world: &mut PendulumSystem,
+        layout: &Layout,
+        monochrome: bool,
+    ) -> GameResult {
+        if !self.ink_mode {
+            return Ok(());
+        }
+        let [width, height] = layout.window().map(|size| size.max(1.0) as u32);
+        let clear = match &self.ink {
+            Some(ink) if ink.width() == width && ink.height() == height => None,
+            _ => {
+                let format = ctx.gfx.surface_format();
+                self.ink = Some(Image::new_canvas_image(ctx, format, width, height, 1));
+                Some(Color::new(0.0, 0.0, 0.0, 0.0))
+            }
+        };
+        let ink = self.ink.clone().expect("the ink image should exist");
+        let mut canvas = Canvas::from_image(ctx, ink, clear);
+
+        let scale = layout.scale();
+        for index in 0..world.pendulums.len() {
+            let points = world.pendulums.at_mut(index).take_new_trail();
+            if points.len() < 2 {
+                continue;
+            }
+            let color = match self.color {
+                _ if monochrome => Color::WHITE,
+                Some(color) => color,
+                None => {
+                    let color = world.pendulums.at(index).trail_color(self.saturation);
+                    render::to_graphics(color)
+                }
+            };
+            // Clip each scene to its tile, so they don't overlap
+            let scene = world.pendulum_scenes[index];
+            canvas.set_scissor_rect(layout.scene_rect(scene))?;
+            let line = Mesh::new_line(ctx, &points, 2.0, color)?;
+            let origin = layout.origin(scene);
+            canvas.draw(&line, DrawParam::new().dest(origin).scale([scale, scale]));
+        }
+        canvas.finish(ctx)
+    }
+
+    /// Draw the trails accumulated in the ink, if any, under the pendulums
+    pub fn draw(&self, canvas: &mut Canvas) {
+        if let Some(ink) = &self.ink {
+            canvas.draw(ink, [0.0, 0.0]);
+        }
+    }
+}
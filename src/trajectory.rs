@@ -0,0 +1,65 @@
+use crate::recorder::{timestamp, RECORDINGS_DIR};
+use double_pendulum::sink::{ExportSink, Metadata, Sample};
+use ggez::GameResult;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+/// Writes the state of every pendulum after each physics update into a CSV file,
+/// for analysis in Python or in a spreadsheet
+///
+/// Every row holds the angles and the angular velocities of both rods,
+/// and the positions of both circles relative to the origin, in pixels with y pointing down.
+pub struct TrajectoryExporter {
+    writer: BufWriter<File>,
+    path: PathBuf,
+}
+
+impl TrajectoryExporter {
+    pub fn new(path: Option<&str>, metadata: &Metadata) -> GameResult<Self> {
+        let path = match path {
+            Some(path) => PathBuf::from(path),
+            None => {
+                fs::create_dir_all(RECORDINGS_DIR)?;
+                PathBuf::from(RECORDINGS_DIR).join(format!("{}-trajectory.csv", timestamp()))
+            }
+        };
+        let mut writer = BufWriter::new(File::create(&path)?);
+        // The metadata is written as comments before the header
+        for (key, value) in metadata.entries() {
+            writeln!(writer, "# {key}: {value}")?;
+        }
+        writeln!(
+            writer,
+            "time,pendulum,theta1,theta2,omega1,omega2,x1,y1,x2,y2"
+        )?;
+        println!("Exporting the trajectories to {}", path.display());
+        Ok(Self { writer, path })
+    }
+}
+
+impl ExportSink for TrajectoryExporter {
+    fn name(&self) -> &str {
+        "csv"
+    }
+
+    fn write_sample(&mut self, sample: &Sample) -> GameResult {
+        for (index, pendulum) in sample.pendulums.iter().enumerate() {
+            let [theta1, theta2] = pendulum.angles();
+            let [omega1, omega2] = pendulum.angular_velocities();
+            let [[x1, y1], [x2, y2]] = pendulum.bobs();
+            writeln!(
+                self.writer,
+                "{},{index},{theta1},{theta2},{omega1},{omega2},{x1},{y1},{x2},{y2}",
+                sample.time,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> GameResult {
+        self.writer.flush()?;
+        println!("Saved the trajectories to {}", self.path.display());
+        Ok(())
+    }
+}
@@ -0,0 +1,143 @@
+use crate::recorder::{read_frame, timestamp, RECORDINGS_DIR};
+use double_pendulum::sink::{ExportSettings, ExportSink, Metadata};
+use ggez::{Context, GameError, GameResult};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::time::Instant;
+
+/// An ffmpeg process encoding the frames written to its standard input
+struct Encoder {
+    child: Child,
+    stdin: ChildStdin,
+    /// The size of the frames, the frames of a different size are skipped
+    size: (u32, u32),
+}
+
+/// Records a full-quality video of the window by piping every frame to ffmpeg
+///
+/// ffmpeg has to be installed and in the `PATH`. The video has a constant frame rate,
+/// so the frames are repeated or dropped to keep the timing of the window.
+/// Unlike the APNG and GIF recorders, nothing is kept in memory and there is no maximum duration.
+pub struct VideoRecorder {
+    path: PathBuf,
+    /// Stored in the comment of the video
+    metadata: Metadata,
+    fps: u32,
+    /// Started with the first frame, once the size of the window is known
+    encoder: Option<Encoder>,
+    /// The number of frames written to the video
+    written: u64,
+    start: Instant,
+}
+
+impl VideoRecorder {
+    pub fn new(
+        path: Option<&str>,
+        metadata: &Metadata,
+        settings: &ExportSettings,
+    ) -> GameResult<Self> {
+        let path = match path {
+            Some(path) => PathBuf::from(path),
+            None => {
+                fs::create_dir_all(RECORDINGS_DIR)?;
+                PathBuf::from(RECORDINGS_DIR).join(format!("{}.mp4", timestamp()))
+            }
+        };
+        println!("Recording a video to {}", path.display());
+        Ok(Self {
+            path,
+            metadata: metadata.clone(),
+            fps: settings.video_fps.max(1),
+            encoder: None,
+            written: 0,
+            start: Instant::now(),
+        })
+    }
+
+    /// Start ffmpeg, reading raw RGBA frames of `size` from its standard input
+    fn spawn(&self, (width, height): (u32, u32)) -> GameResult<Encoder> {
+        let comment: Vec<String> = (self.metadata.entries().iter())
+            .map(|(key, value)| format!("{key}: {value}"))
+            .collect();
+        let mut child = Command::new("ffmpeg")
+            .args(["-loglevel", "error", "-y"])
+            .args(["-f", "rawvideo", "-pix_fmt", "rgba"])
+            .args(["-s", &format!("{width}x{height}")])
+            .args(["-r", &self.fps.to_string()])
+            .args(["-i", "-"])
+            // yuv420p is the only pixel format every player supports, and it needs an even size
+            .args(["-vf", "pad=ceil(iw/2)*2:ceil(ih/2)*2"])
+            .args(["-c:v", "libx264", "-pix_fmt", "yuv420p"])
+            .args(["-metadata", &format!("comment={}", comment.join("\n"))])
+            .arg(&self.path)
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(|e| ffmpeg_error(format!("couldn't start it, is it installed? {e}")))?;
+        let stdin = child.stdin.take().expect("the standard input is piped");
+        Ok(Encoder {
+            child,
+            stdin,
+            size: (width, height),
+        })
+    }
+}
+
+impl ExportSink for VideoRecorder {
+    fn name(&self) -> &str {
+        "video"
+    }
+
+    /// Send the last frame of the window to ffmpeg, as many times as the frame rate of the video needs
+    fn write_frame(&mut self, ctx: &Context) -> GameResult {
+        let frame = ctx.gfx.frame();
+        let size = (frame.width(), frame.height());
+        let encoder = match &mut self.encoder {
+            Some(encoder) => encoder,
+            None => {
+                self.start = Instant::now();
+                self.encoder.insert(self.spawn(size)?)
+            }
+        };
+        if encoder.size != size {
+            return Ok(());
+        }
+
+        // The frame is shown until the next one is drawn
+        let due = (self.start.elapsed().as_secs_f64() * self.fps as f64) as u64 + 1;
+        if due <= self.written {
+            return Ok(());
+        }
+        let pixels = read_frame(ctx)?;
+        for _ in self.written..due {
+            encoder
+                .stdin
+                .write_all(&pixels)
+                .map_err(|e| ffmpeg_error(format!("it stopped reading the frames: {e}")))?;
+        }
+        self.written = due;
+        Ok(())
+    }
+
+    /// Close the standard input of ffmpeg and wait for it to finish the file
+    fn finish(self: Box<Self>) -> GameResult {
+        let Some(Encoder {
+            mut child, stdin, ..
+        }) = self.encoder
+        else {
+            return Ok(());
+        };
+        drop(stdin);
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(ffmpeg_error(format!("it exited with {status}")));
+        }
+        println!("Saved the video to {}", self.path.display());
+        Ok(())
+    }
+}
+
+fn ffmpeg_error(message: String) -> GameError {
+    GameError::CustomError(format!("couldn't record the video with ffmpeg: {message}"))
+}
@@ -0,0 +1,161 @@
+use crate::save::json_error;
+use double_pendulum::pendulum::Rods;
+use double_pendulum::sink::{ExportSink, Sample};
+use ggez::GameResult;
+use serde::Serialize;
+use std::io::ErrorKind;
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tungstenite::protocol::WebSocketConfig;
+use tungstenite::{Error, Message, WebSocket};
+
+/// Where the server listens when no address is given
+const DEFAULT_ADDRESS: &str = "127.0.0.1:9001";
+/// The largest number of frames sent per second of wall-clock time
+const FRAME_RATE: f32 = 60.0;
+/// The largest size of the messages waiting to be sent to a client, it is dropped past it
+const MAX_BUFFERED_BYTES: usize = 4 << 20;
+
+/// The state of a pendulum in a frame
+#[derive(Serialize)]
+struct PendulumFrame {
+    theta1: f32,
+    theta2: f32,
+    omega1: f32,
+    omega2: f32,
+    x1: f32,
+    y1: f32,
+    x2: f32,
+    y2: f32,
+    color: [f32; 3],
+}
+
+impl From<&Rods> for PendulumFrame {
+    fn from(pendulum: &Rods) -> Self {
+        let [theta1, theta2] = pendulum.angles();
+        let [omega1, omega2] = pendulum.angular_velocities();
+        let [[x1, y1], [x2, y2]] = pendulum.bobs();
+        let color = pendulum.color();
+        Self {
+            theta1,
+            theta2,
+            omega1,
+            omega2,
+            x1,
+            y1,
+            x2,
+            y2,
+            color: [color.r, color.g, color.b],
+        }
+    }
+}
+
+/// The message sent to every client
+#[derive(Serialize)]
+struct Frame {
+    time: f32,
+    pendulums: Vec<PendulumFrame>,
+}
+
+/// Broadcasts the state of every pendulum as JSON text messages to every connected WebSocket client,
+/// so dashboards or web visualizers can mirror the simulation live
+///
+/// The clients are accepted by a background thread, and the ones too slow to keep up or gone are dropped
+pub struct WebSocketServer {
+    clients: Arc<Mutex<Vec<WebSocket<TcpStream>>>>,
+    /// When the last frame was sent, if it was
+    last_sent: Option<Instant>,
+}
+
+impl WebSocketServer {
+    /// Listen on `address`, written as `HOST:PORT`
+    pub fn new(address: Option<&str>) -> GameResult<Self> {
+        let listener = TcpListener::bind(address.unwrap_or(DEFAULT_ADDRESS))?;
+        println!("Serving the simulation on ws://{}", listener.local_addr()?);
+        let clients = Arc::new(Mutex::new(Vec::new()));
+        let accepted = Arc::downgrade(&clients);
+        thread::spawn(move || {
+            let config = WebSocketConfig {
+                max_write_buffer_size: MAX_BUFFERED_BYTES,
+                ..Default::default()
+            };
+            for stream in listener.incoming().flatten() {
+                // The thread stops at the first connection after the sink is removed
+                let Some(clients) = accepted.upgrade() else {
+                    break;
+                };
+                let Ok(client) = tungstenite::accept_with_config(stream, Some(config)) else {
+                    continue;
+                };
+                // A slow client mustn't block the simulation, its messages are buffered instead
+                if client.get_ref().set_nonblocking(true).is_ok() {
+                    if let Ok(mut clients) = clients.lock() {
+                        clients.push(client);
+                    }
+                }
+            }
+        });
+        Ok(Self {
+            clients,
+            last_sent: None,
+        })
+    }
+}
+
+impl ExportSink for WebSocketServer {
+    fn name(&self) -> &str {
+        "websocket"
+    }
+
+    fn write_sample(&mut self, sample: &Sample) -> GameResult {
+        let now = Instant::now();
+        let interval = Duration::from_secs_f32(1.0 / FRAME_RATE);
+        if self
+            .last_sent
+            .is_some_and(|last| now.duration_since(last) < interval)
+        {
+            return Ok(());
+        }
+        self.last_sent = Some(now);
+
+        let Ok(mut clients) = self.clients.lock() else {
+            return Ok(());
+        };
+        if clients.is_empty() {
+            return Ok(());
+        }
+        let frame = Frame {
+            time: sample.time,
+            pendulums: sample
+                .pendulums
+                .iter()
+                .map(|p| PendulumFrame::from(&p))
+                .collect(),
+        };
+        let json = serde_json::to_string(&frame).map_err(json_error)?;
+        clients.retain_mut(|client| {
+            // The messages of the client are read so its pings are answered and its closing is noticed
+            while client.read().is_ok() {}
+            match client.send(Message::text(json.clone())) {
+                Ok(()) => true,
+                // The message was buffered, it is sent with the next ones
+                Err(Error::Io(e)) if e.kind() == ErrorKind::WouldBlock => true,
+                Err(_) => false,
+            }
+        });
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> GameResult {
+        if let Ok(mut clients) = self.clients.lock() {
+            for mut client in clients.drain(..) {
+                let _ = client.close(None);
+                let _ = client.flush();
+            }
+        }
+        println!("Stopped serving the simulation");
+        Ok(())
+    }
+}
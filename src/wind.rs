@@ -0,0 +1,93 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+/// How many gusts per second there are, roughly
+const GUST_FREQUENCY: f32 = 0.5;
+
+/// A horizontal force field blowing on both masses of every pendulum
+///
+/// The wind is expressed as an acceleration, in the same unit as the gravity
+pub struct Wind {
+    /// The constant part of the wind, positive values blow to the right
+    strength: f32,
+    /// The amplitude of the gusts added on top of the constant wind
+    gust: f32,
+    noise: Perlin,
+}
+
+impl Wind {
+    pub fn new(rng: &mut impl Rng, strength: f32, gust: f32) -> Self {
+        Self {
+            strength,
+            gust,
+            noise: Perlin::new(rng),
+        }
+    }
+
+    /// Change the constant part of the wind
+    pub fn set_strength(&mut self, strength: f32) {
+        self.strength = strength;
+    }
+
+    /// Returns the constant part of the wind
+    pub fn strength(&self) -> f32 {
+        self.strength
+    }
+
+    /// Returns the amplitude of the gusts
+    pub fn gust(&self) -> f32 {
+        self.gust
+    }
+
+    /// Returns true if the wind can blow at some point
+    pub fn is_enabled(&self) -> bool {
+        self.strength != 0.0 || self.gust != 0.0
+    }
+
+    /// Returns the horizontal acceleration caused by the wind at `time` seconds
+    pub fn at(&self, time: f32) -> f32 {
+        if self.gust == 0.0 {
+            return self.strength;
+        }
+        self.strength + self.gust * self.noise.get(time * GUST_FREQUENCY)
+    }
+}
+
+/// One dimensional Perlin noise, used to make the gusts smooth
+///
+/// https://en.wikipedia.org/wiki/Perlin_noise
+struct Perlin {
+    /// A random permutation of 0..256, used to hash the lattice points
+    permutation: [u8; 256],
+}
+
+impl Perlin {
+    fn new(rng: &mut impl Rng) -> Self {
+        let mut permutation = [0; 256];
+        for (i, p) in permutation.iter_mut().enumerate() {
+            *p = i as u8;
+        }
+        permutation.shuffle(rng);
+        Self { permutation }
+    }
+
+    /// Returns the gradient at the lattice point `i`, between -1 and 1
+    fn gradient(&self, i: i64) -> f32 {
+        self.permutation[(i & 255) as usize] as f32 / 127.5 - 1.0
+    }
+
+    /// Returns the noise value at `x`, roughly between -1 and 1
+    fn get(&self, x: f32) -> f32 {
+        let x0 = x.floor();
+        let t = x - x0;
+        let i = x0 as i64;
+
+        let n0 = self.gradient(i) * t;
+        let n1 = self.gradient(i + 1) * (t - 1.0);
+
+        // Smootherstep, so the noise has a continuous second derivative
+        let fade = t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+        // The raw noise is between -0.5 and 0.5
+        2.0 * (n0 + fade * (n1 - n0))
+    }
+}
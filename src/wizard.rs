@@ -0,0 +1,185 @@
+use crate::render::{self, CanvasRenderer, Style};
+use double_pendulum::color::{self, Palette};
+use double_pendulum::pendulum::{DoublePendulum, InitialConditions, Physics};
+use ggez::graphics::{self, Canvas, Color, DrawMode, Mesh, Rect};
+use ggez::winit::event::VirtualKeyCode;
+use ggez::Context;
+use ggez::GameResult;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+/// The width in pixels of the sliders
+const SLIDER_WIDTH: f32 = 120.0;
+/// The vertical space in pixels taken by each slider and its label
+const SLIDER_SPACING: f32 = 36.0;
+
+/// A value chosen with a slider in the wizard
+struct Field {
+    name: &'static str,
+    min: f32,
+    max: f32,
+    /// How much the value changes for each key press
+    step: f32,
+    value: f32,
+}
+
+impl Field {
+    const fn new(name: &'static str, min: f32, max: f32, step: f32, value: f32) -> Self {
+        Self {
+            name,
+            min,
+            max,
+            step,
+            value,
+        }
+    }
+
+    /// Move the value by `steps` steps, without leaving the range of the slider
+    fn nudge(&mut self, steps: f32) {
+        self.value = (self.value + steps * self.step).clamp(self.min, self.max);
+    }
+
+    /// Returns where the value is in the range of the slider, between 0 and 1
+    fn fraction(&self) -> f32 {
+        (self.value - self.min) / (self.max - self.min)
+    }
+}
+
+/// What happens after a key is pressed in the wizard
+pub enum WizardAction {
+    /// The wizard is still open
+    Continue,
+    /// The wizard was closed without adding the pendulum
+    Cancel,
+    /// The wizard was completed, the pendulum should be added
+    Done,
+}
+
+/// A step-by-step overlay to choose every parameter of a new pendulum with a preview
+///
+/// Up and Down change the current value, Enter and Backspace go to the next and previous steps,
+/// and Escape closes the wizard
+pub struct Wizard {
+    fields: [Field; 7],
+    /// The index of the field being edited
+    current: usize,
+}
+
+impl Wizard {
+    pub fn new() -> Self {
+        Self {
+            fields: [
+                Field::new("Mass 1", 1.0, 10.0, 0.5, 3.0),
+                Field::new("Mass 2", 1.0, 10.0, 0.5, 3.0),
+                Field::new("Length 1", 10.0, 200.0, 5.0, 100.0),
+                Field::new("Length 2", 10.0, 200.0, 5.0, 100.0),
+                Field::new("Angle 1", -180.0, 180.0, 5.0, 90.0),
+                Field::new("Angle 2", -180.0, 180.0, 5.0, 90.0),
+                Field::new("Hue", 0.0, 360.0, 10.0, 120.0),
+            ],
+            current: 0,
+        }
+    }
+
+    /// Handle a key press, and returns whether the wizard is still open or was closed
+    pub fn key_down(&mut self, key: VirtualKeyCode) -> WizardAction {
+        match key {
+            VirtualKeyCode::Up | VirtualKeyCode::Right => self.fields[self.current].nudge(1.0),
+            VirtualKeyCode::Down | VirtualKeyCode::Left => self.fields[self.current].nudge(-1.0),
+            VirtualKeyCode::Return if self.current + 1 == self.fields.len() => {
+                return WizardAction::Done;
+            }
+            VirtualKeyCode::Return => self.current += 1,
+            VirtualKeyCode::Back => self.current = self.current.saturating_sub(1),
+            VirtualKeyCode::Escape => return WizardAction::Cancel,
+            _ => (),
+        }
+        WizardAction::Continue
+    }
+
+    /// Returns the initial conditions chosen so far
+    pub fn initial_conditions(&self) -> InitialConditions {
+        let value = |i: usize| Some(self.fields[i].value);
+        InitialConditions {
+            m1: value(0),
+            m2: value(1),
+            l1: value(2),
+            l2: value(3),
+            theta1: value(4).map(f32::to_radians),
+            theta2: value(5).map(f32::to_radians),
+            v1: Some(0.0),
+            v2: Some(0.0),
+        }
+    }
+
+    /// Returns the color chosen so far
+    pub fn color(&self) -> color::Color {
+        color::hsv(self.fields[6].value, 0.8, 1.0)
+    }
+
+    /// Draw the sliders on the left of the screen and a preview of the pendulum at `center`
+    pub fn draw(&self, ctx: &mut Context, canvas: &mut Canvas, center: [f32; 2]) -> GameResult {
+        // Darken the simulation behind the wizard
+        let size = [center[0] * 2.0, center[1] * 2.0];
+        let shade = Mesh::new_rectangle(
+            ctx,
+            DrawMode::fill(),
+            Rect::new(0.0, 0.0, size[0], size[1]),
+            Color::new(0.0, 0.0, 0.0, 0.7),
+        )?;
+        canvas.draw(&shade, [0.0, 0.0]);
+
+        // The random number generator isn't used since every initial condition is given
+        let mut rng = StdRng::seed_from_u64(0);
+        let initial = self.initial_conditions();
+        let mut preview = DoublePendulum::new(
+            &mut rng,
+            0.0,
+            Physics::default(),
+            &initial,
+            Palette::default(),
+        );
+        preview.set_color(self.color());
+        let mut renderer = CanvasRenderer {
+            ctx,
+            canvas,
+            batches: None,
+        };
+        render::draw_rods(&preview, &mut renderer, center, 1.0, Style::Color)?;
+
+        for (i, field) in self.fields.iter().enumerate() {
+            let top = 40.0 + i as f32 * SLIDER_SPACING;
+            let highlight = if i == self.current {
+                Color::YELLOW
+            } else {
+                Color::WHITE
+            };
+
+            let label = graphics::Text::new(format!("{}: {:.1}", field.name, field.value));
+            canvas.draw(
+                &label,
+                graphics::DrawParam::new()
+                    .dest([10.0, top])
+                    .color(highlight),
+            );
+
+            let track = Rect::new(10.0, top + 18.0, SLIDER_WIDTH, 6.0);
+            let track = Mesh::new_rectangle(ctx, DrawMode::stroke(1.0), track, highlight)?;
+            canvas.draw(&track, [0.0, 0.0]);
+
+            let filled = Rect::new(10.0, top + 18.0, SLIDER_WIDTH * field.fraction(), 6.0);
+            if filled.w > 0.0 {
+                let filled = Mesh::new_rectangle(ctx, DrawMode::fill(), filled, highlight)?;
+                canvas.draw(&filled, [0.0, 0.0]);
+            }
+        }
+
+        let help = graphics::Text::new(format!(
+            "Step {}/{}  Up/Down: change  Enter: next  Backspace: back  Esc: cancel",
+            self.current + 1,
+            self.fields.len(),
+        ));
+        canvas.draw(&help, [10.0, 10.0]);
+        Ok(())
+    }
+}
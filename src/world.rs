@@ -0,0 +1,493 @@
+use crate::camera::Camera;
+use crate::heatmap::Heatmap;
+use crate::render::{
+    self, Batches, CanvasRenderer, Interpolation, Renderer, Style, TrailColor, Trails,
+};
+use double_pendulum::gpu::GpuPhysics;
+use double_pendulum::pendulum::{DoublePendulum, Recovery};
+use double_pendulum::pendulums::{PendulumMut, Pendulums};
+use ggez::graphics::{self, Canvas, Color, DrawMode, DrawParam, Mesh, MeshBuilder, Rect};
+use ggez::{Context, GameResult};
+
+/// How fast the left stick of a gamepad moves the origin, in fractions of the window per second
+const STICK_SPEED: f32 = 0.5;
+/// The length in pixels of the velocity arrows for a circle moving one pixel every 1/60 of a second
+const VELOCITY_ARROW_SCALE: f32 = 4.0;
+/// The color of the velocity arrows
+const VELOCITY_COLOR: Color = Color::new(1.0, 1.0, 1.0, 0.8);
+/// The color of the rings around the circles of the selected pendulum
+const SELECTION_COLOR: Color = Color::new(1.0, 0.85, 0.2, 1.0);
+
+/// Where the scenes are in the window, and where the pendulums hang in each of them
+pub struct Layout {
+    /// The coordinates of the center of the screen
+    pub center: [f32; 2],
+    /// The coordinates of the center of the screen at startup
+    ///
+    /// The lengths of the pendulums are in pixels of that window, and scaled to the current one when drawn
+    pub reference_center: [f32; 2],
+    /// The position of the origin of the pendulums in each scene, as a fraction of its width and height
+    pub anchor: [f32; 2],
+    /// The number of independent scenes tiled across the window
+    pub scenes: usize,
+}
+
+impl Layout {
+    /// Returns the size of the window
+    pub fn window(&self) -> [f32; 2] {
+        self.center.map(|c| c * 2.0)
+    }
+
+    /// Returns the number of columns and rows of the grid of scenes
+    fn grid(&self) -> (usize, usize) {
+        let columns = (self.scenes as f32).sqrt().ceil() as usize;
+        (columns, self.scenes.div_ceil(columns))
+    }
+
+    /// Returns the area of the window covered by `scene`
+    pub fn scene_rect(&self, scene: usize) -> Rect {
+        let (columns, rows) = self.grid();
+        let width = self.center[0] * 2.0 / columns as f32;
+        let height = self.center[1] * 2.0 / rows as f32;
+        let column = (scene % columns) as f32;
+        let row = (scene / columns) as f32;
+        Rect::new(column * width, row * height, width, height)
+    }
+
+    /// Returns the position of the origin of the pendulums of `scene` in the window
+    pub fn origin(&self, scene: usize) -> [f32; 2] {
+        let rect = self.scene_rect(scene);
+        [
+            rect.x + rect.w * self.anchor[0],
+            rect.y + rect.h * self.anchor[1],
+        ]
+    }
+
+    /// Returns the scene under the point (`x`, `y`) of the window
+    pub fn scene_at(&self, x: f32, y: f32) -> usize {
+        let (columns, rows) = self.grid();
+        let column = (x / (self.center[0] * 2.0) * columns as f32) as usize;
+        let row = (y / (self.center[1] * 2.0) * rows as f32) as usize;
+        let scene = row.min(rows - 1) * columns + column.min(columns - 1);
+        scene.min(self.scenes - 1)
+    }
+
+    /// Returns the position of the mouse relative to the origin of the pendulums of `scene`,
+    /// in the units of the pendulums
+    pub fn to_origin(&self, x: f32, y: f32, scene: usize) -> [f32; 2] {
+        let origin = self.origin(scene);
+        let scale = self.scale();
+        [(x - origin[0]) / scale, (y - origin[1]) / scale]
+    }
+
+    /// Returns how many pixels of the current window a pixel of the window at startup covers,
+    /// so the pendulums always fit in the window
+    pub fn scale(&self) -> f32 {
+        let [x, y] = self.center;
+        let [reference_x, reference_y] = self.reference_center;
+        (x / reference_x).min(y / reference_y)
+    }
+
+    /// Move the origin of the pendulums with the left stick of a gamepad at `stick`, `dt` seconds after the last frame
+    pub fn move_anchor(&mut self, stick: [f32; 2], dt: f32) {
+        for (anchor, stick) in self.anchor.iter_mut().zip(stick) {
+            *anchor = (*anchor + stick * STICK_SPEED * dt).clamp(0.0, 1.0);
+        }
+    }
+}
+
+/// What is drawn along with the pendulums, and how
+pub struct DrawOptions<'a> {
+    /// Which trails are drawn and how they are colored
+    pub trails: Trails,
+    /// How far the next physics update is, to draw the rods between the last two updates,
+    /// or None if they are drawn as they are
+    pub progress: Option<f32>,
+    /// The index of the oldest pendulum drawn with its trails and its velocity arrows, if not all of them are
+    pub detailed: Option<usize>,
+    /// The places visited by the tips of the pendulums, drawn under each scene, if any
+    pub heatmap: Option<&'a Heatmap>,
+    /// Whether an arrow shows the velocity of each circle
+    pub velocities: bool,
+    /// The index of the pendulum whose circles are ringed, if any
+    pub selected: Option<usize>,
+    /// Whether everything is drawn in white on black, with patterns instead of colors
+    pub monochrome: bool,
+}
+
+/// The pendulum system, which holds every double pendulum with its scene, advances them and draws them
+pub struct PendulumSystem {
+    /// Every double pendulum on the screen, stored quantity by quantity
+    pub pendulums: Pendulums,
+    /// The scene of each pendulum, in the same order as `pendulums`
+    pub pendulum_scenes: Vec<usize>,
+    /// The angles of the rods of every pendulum before the last physics update, to draw them in between
+    previous_angles: Vec<[f32; 2]>,
+    /// The filled circles and the trails of the pendulums, drawn together, it is created with the first frame
+    batches: Option<Batches>,
+}
+
+impl PendulumSystem {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            pendulums: Pendulums::with_capacity(capacity),
+            pendulum_scenes: Vec::with_capacity(capacity),
+            previous_angles: Vec::with_capacity(capacity),
+            batches: None,
+        }
+    }
+
+    /// Add `pendulum` to `scene`
+    pub fn push(&mut self, pendulum: DoublePendulum, scene: usize) {
+        self.pendulums.push(pendulum);
+        self.pendulum_scenes.push(scene);
+    }
+
+    /// Remove the pendulum at `index`
+    pub fn remove(&mut self, index: usize) {
+        self.pendulums.remove(index);
+        self.pendulum_scenes.remove(index);
+    }
+
+    /// Remove the `count` oldest pendulums
+    pub fn remove_first(&mut self, count: usize) {
+        self.pendulums.remove_first(count);
+        self.pendulum_scenes.drain(..count);
+    }
+
+    /// Remove every pendulum
+    pub fn clear(&mut self) {
+        self.pendulums.clear();
+        self.pendulum_scenes.clear();
+    }
+
+    /// Returns the scene of `layout` with the fewest pendulums, where new pendulums are added
+    pub fn emptiest_scene(&self, layout: &Layout) -> usize {
+        (0..layout.scenes)
+            .min_by_key(|&scene| self.pendulum_scenes.iter().filter(|&&s| s == scene).count())
+            .unwrap_or(0)
+    }
+
+    /// Returns the index of the pendulum and of its circle under the point (`x`, `y`) of the window
+    ///
+    /// Only the scene under the point is searched, and the pendulum drawn on top,
+    /// which is the last one, is picked
+    pub fn bob_at(&self, layout: &Layout, x: f32, y: f32) -> Option<(usize, usize)> {
+        let scene = layout.scene_at(x, y);
+        let target = layout.to_origin(x, y, scene);
+        (self.pendulums.iter().enumerate().rev())
+            .filter(|(index, _)| self.pendulum_scenes[*index] == scene)
+            .find_map(|(index, p)| p.bob_at(target).map(|bob| (index, bob)))
+    }
+
+    /// Advance every pendulum by `substeps` steps of `dt` seconds from the simulated time `time`,
+    /// with the GPU if it is given
+    ///
+    /// The pendulum at the index `held`, if any, stays where it is since it follows the mouse instead
+    pub fn update(
+        &mut self,
+        gpu: Option<&mut GpuPhysics>,
+        dt: f32,
+        substeps: u32,
+        wind: f32,
+        time: f32,
+        held: Option<usize>,
+    ) {
+        self.previous_angles.clear();
+        self.previous_angles.extend(self.pendulums.angles());
+
+        let held = held.and_then(|index| Some((index, self.pendulums.get(index)?)));
+        match gpu {
+            Some(gpu) => gpu.forward(&mut self.pendulums, dt, substeps, wind, time),
+            None => {
+                for substep in 0..substeps {
+                    self.pendulums.forward(dt, wind, time + substep as f32 * dt);
+                }
+            }
+        }
+        if let Some((index, rods)) = held {
+            self.pendulums.set(index, rods);
+        }
+        // A pendulum whose state blew up would crash the drawing, so it is fixed right away
+        for (index, before, recovery) in self.pendulums.recover() {
+            let [theta1, theta2] = before.angles();
+            let [omega1, omega2] = before.angular_velocities();
+            let action = match recovery {
+                Recovery::Clamped => "its speed was clamped",
+                Recovery::Reset => "it was reset at rest",
+            };
+            eprintln!(
+                "Pendulum {} reached θ1: {theta1}, θ2: {theta2}, ω1: {omega1}, ω2: {omega2}, {action}",
+                index + 1,
+            );
+        }
+    }
+
+    /// Returns whether the rods can be drawn between their last two updates,
+    /// which isn't the case after pendulums were added or removed since the last update
+    pub fn can_interpolate(&self) -> bool {
+        self.previous_angles.len() == self.pendulums.len()
+    }
+
+    /// Returns where the rods of the pendulum at each index are drawn, `progress` of the way
+    /// from their `previous` angles to their current ones, or None if they are drawn as they are
+    fn interpolation(
+        previous: &[[f32; 2]],
+        progress: Option<f32>,
+    ) -> impl Fn(usize) -> Option<Interpolation> + Copy + '_ {
+        move |index| {
+            let previous = *previous.get(index)?;
+            Some(Interpolation {
+                previous,
+                progress: progress?,
+            })
+        }
+    }
+
+    /// Forget the shapes batched in the previous frame, the batches are created with the first frame
+    fn start_frame(&mut self, ctx: &Context) -> GameResult {
+        match &mut self.batches {
+            Some(batches) => batches.start_frame(),
+            None => self.batches = Some(Batches::new(ctx)?),
+        }
+        Ok(())
+    }
+
+    /// Draw the pendulums of every scene of `layout` seen through `camera`, with the heatmap under them
+    ///
+    /// `canvas` shows the world through the camera, in the window area `screen`
+    pub fn draw(
+        &mut self,
+        ctx: &mut Context,
+        canvas: &mut Canvas,
+        layout: &Layout,
+        camera: &Camera,
+        screen: Rect,
+        options: &DrawOptions,
+    ) -> GameResult {
+        self.start_frame(ctx)?;
+        let view = camera.view(screen);
+        let scale = layout.scale();
+        let interpolation = Self::interpolation(&self.previous_angles, options.progress);
+        let heatmap = options.heatmap.map(|heatmap| {
+            // The grid is centered on the origin of each scene
+            let corner = heatmap.extent() * scale;
+            let param = DrawParam::new().scale([heatmap.cell_size() * scale; 2]);
+            (heatmap.image(ctx), corner, param)
+        });
+        for scene in 0..layout.scenes {
+            // Clip each scene to its tile, so they don't overlap
+            let Some(rect) = camera.screen_rect(layout.scene_rect(scene), screen) else {
+                continue;
+            };
+            canvas.set_scissor_rect(rect)?;
+            let origin = layout.origin(scene);
+            if let Some((image, corner, param)) = &heatmap {
+                let dest = [origin[0] - corner, origin[1] - corner];
+                canvas.draw(image, param.dest(dest));
+            }
+            // Once the stress test lowers the level of detail, the older pendulums lose their trails
+            // and their velocity arrows, and so do the scenes whose origin is out of the view
+            let detailed = options.detailed.is_none() || view.contains(origin);
+            let scene_trails = |index| match options.detailed {
+                Some(oldest) if !detailed || index < oldest => Trails::default(),
+                _ => options.trails,
+            };
+            let pendulums = (self.pendulums.iter_mut().enumerate())
+                .filter(|(index, _)| self.pendulum_scenes[*index] == scene);
+            let mut renderer = CanvasRenderer {
+                ctx,
+                canvas,
+                batches: self.batches.as_mut(),
+            };
+            Self::draw_scene(
+                pendulums,
+                &mut renderer,
+                origin,
+                scale,
+                scene_trails,
+                interpolation,
+                options.monochrome,
+            )?;
+            if options.velocities && detailed {
+                let oldest = options.detailed.unwrap_or(0);
+                self.draw_velocities(ctx, canvas, layout, scene, oldest)?;
+            }
+        }
+        canvas.set_default_scissor_rect();
+        if let Some(index) = options.selected {
+            self.draw_selection(ctx, canvas, layout, index)?;
+        }
+        Ok(())
+    }
+
+    /// Draw the live pendulums on the left half of the window and `snapshot` on the right half
+    pub fn draw_comparison(
+        &mut self,
+        ctx: &mut Context,
+        canvas: &mut Canvas,
+        layout: &Layout,
+        snapshot: &mut Pendulums,
+        options: &DrawOptions,
+    ) -> GameResult {
+        self.start_frame(ctx)?;
+        let [half, center_y] = layout.center;
+        let height = center_y * 2.0;
+        let scale = layout.scale();
+        let interpolation = Self::interpolation(&self.previous_angles, options.progress);
+
+        // The snapshot stands still, so it is drawn as it is
+        let halves = [
+            (&mut self.pendulums, 0.0, "Live", true),
+            (snapshot, half, "Snapshot", false),
+        ];
+        for (pendulums, left, label, live) in halves {
+            // Clip each scene to its half, so they don't overlap
+            canvas.set_scissor_rect(Rect::new(left, 0.0, half, height))?;
+            let center = [left + half / 2.0, center_y];
+            let pendulums = pendulums.iter_mut().enumerate();
+            let mut renderer = CanvasRenderer {
+                ctx,
+                canvas,
+                batches: self.batches.as_mut(),
+            };
+            Self::draw_scene(
+                pendulums,
+                &mut renderer,
+                center,
+                scale,
+                |_| options.trails,
+                |index| interpolation(index).filter(|_| live),
+                options.monochrome,
+            )?;
+
+            let text = graphics::Text::new(label);
+            canvas.draw(&text, [left + 10.0, height - 30.0]);
+        }
+        canvas.set_default_scissor_rect();
+
+        let divider = Mesh::new_line(ctx, &[[half, 0.0], [half, height]], 2.0, Color::WHITE)?;
+        canvas.draw(&divider, [0.0, 0.0]);
+        Ok(())
+    }
+
+    /// Draw the pendulums hanging from `center` and scaled by `scale`, and a white circle at `center`
+    ///
+    /// The pendulums come with their index, which picks their style in monochrome mode,
+    /// the trails drawn with `trails` and where their rods are drawn with `interpolation`.
+    /// The trails of the pendulums set to rainbow trails are drawn in rainbow colors,
+    /// unless they are hidden or in monochrome mode
+    fn draw_scene<'a>(
+        pendulums: impl Iterator<Item = (usize, PendulumMut<'a>)>,
+        renderer: &mut impl Renderer,
+        center: [f32; 2],
+        scale: f32,
+        trails: impl Fn(usize) -> Trails,
+        interpolation: impl Fn(usize) -> Option<Interpolation>,
+        monochrome: bool,
+    ) -> GameResult {
+        for (index, mut p) in pendulums {
+            let style = if monochrome {
+                Style::Monochrome { index }
+            } else {
+                Style::Color
+            };
+            let mut trails = trails(index);
+            if p.rainbow_trail() && !monochrome && trails.tip.is_some() {
+                trails.tip = Some(TrailColor::Rainbow);
+            }
+            let interpolation = interpolation(index);
+            render::draw_pendulum(
+                &mut p,
+                interpolation,
+                renderer,
+                center,
+                scale,
+                trails,
+                style,
+            )?;
+        }
+        renderer.circle(center, 10.0, DrawMode::fill(), Color::WHITE)?;
+        renderer.flush()
+    }
+
+    /// Draw an arrow at each circle of the pendulums of `scene`, showing the direction and the magnitude of its velocity
+    ///
+    /// The pendulums before the index `oldest` are skipped
+    fn draw_velocities(
+        &self,
+        ctx: &mut Context,
+        canvas: &mut Canvas,
+        layout: &Layout,
+        scene: usize,
+        oldest: usize,
+    ) -> GameResult {
+        let mut builder = MeshBuilder::new();
+        let mut empty = true;
+        let pendulums = (self.pendulums.iter().enumerate().skip(oldest))
+            .filter(|(index, _)| self.pendulum_scenes[*index] == scene);
+        for (_, p) in pendulums {
+            for (bob, velocity) in p.bobs().into_iter().zip(p.velocities()) {
+                let speed = velocity[0].hypot(velocity[1]);
+                let length = speed * VELOCITY_ARROW_SCALE;
+                // The arrow would be degenerate if the circle is almost still
+                if length < 1.0 {
+                    continue;
+                }
+                let direction = [velocity[0] / speed, velocity[1] / speed];
+                let tip = [
+                    bob[0] + direction[0] * length,
+                    bob[1] + direction[1] * length,
+                ];
+                let head = 6.0f32.min(length / 2.0);
+                let base = [tip[0] - direction[0] * head, tip[1] - direction[1] * head];
+                let side = [-direction[1] * head / 2.0, direction[0] * head / 2.0];
+                builder.line(&[bob, base], 2.0, VELOCITY_COLOR)?.polygon(
+                    DrawMode::fill(),
+                    &[
+                        tip,
+                        [base[0] + side[0], base[1] + side[1]],
+                        [base[0] - side[0], base[1] - side[1]],
+                    ],
+                    VELOCITY_COLOR,
+                )?;
+                empty = false;
+            }
+        }
+        if empty {
+            return Ok(());
+        }
+
+        let arrows = Mesh::from_data(ctx, builder.build());
+        let scale = layout.scale();
+        canvas.draw(
+            &arrows,
+            DrawParam::new()
+                .dest(layout.origin(scene))
+                .scale([scale, scale]),
+        );
+        Ok(())
+    }
+
+    /// Draw a ring around both circles of the pendulum at `index`
+    fn draw_selection(
+        &self,
+        ctx: &mut Context,
+        canvas: &mut Canvas,
+        layout: &Layout,
+        index: usize,
+    ) -> GameResult {
+        let p = self.pendulums.at(index);
+
+        let mut builder = MeshBuilder::new();
+        for (bob, mass) in p.bobs().into_iter().zip(p.masses()) {
+            let radius = 4.0 * mass + 4.0;
+            builder.circle(DrawMode::stroke(2.0), bob, radius, 1.0, SELECTION_COLOR)?;
+        }
+        let rings = Mesh::from_data(ctx, builder.build());
+        let origin = layout.origin(self.pendulum_scenes[index]);
+        let scale = layout.scale();
+        canvas.draw(&rings, DrawParam::new().dest(origin).scale([scale, scale]));
+        Ok(())
+    }
+}